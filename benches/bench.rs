@@ -81,13 +81,19 @@ fn run_tricky(c: &mut Criterion) {
     c.bench_function("run_tricky", |b| b.iter(|| run_default(&p, &s, 0).unwrap()));
 }
 
+fn find_missing_suffix(c: &mut Criterion) {
+    let re = fancy_regex::Regex::new(r".*\.tar\.gz(?<!partial\.tar\.gz)").unwrap();
+    let s = "x".repeat(10_000);
+    c.bench_function("find_missing_suffix", |b| b.iter(|| re.find(&s).unwrap()));
+}
+
 fn run_backtrack_limit(c: &mut Criterion) {
     let tree = Expr::parse_tree("(?i)(a|b|ab)*(?=c)").unwrap();
     let a = analyze(&tree).unwrap();
     let p = compile(&a).unwrap();
     let s = "abababababababababababababababababababababababababababab";
     c.bench_function("run_backtrack_limit", |b| {
-        b.iter(|| run_default(&p, &s, 0).unwrap_err())
+        b.iter(|| run_default(&p, s, 0).unwrap_err())
     });
 }
 
@@ -101,6 +107,7 @@ criterion_group!(
     analyze_literal_re,
     run_backtrack,
     run_tricky,
+    find_missing_suffix,
 );
 criterion_group!(
     name = slow_benches;
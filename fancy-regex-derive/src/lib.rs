@@ -0,0 +1,163 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! The `FromCaptures` derive macro of fancy-regex. Use it through the `derive` feature of
+//! fancy-regex, which re-exports it, instead of depending on this crate directly.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Field, Fields, GenericArgument, Lit, Meta, NestedMeta,
+    PathArguments, Type,
+};
+
+/// Implements `fancy_regex::FromCaptures` for a struct.
+///
+/// Each field of a struct with named fields is converted from the capture group with the same
+/// name, and each field of a tuple struct from the group with its position (the first field from
+/// group 1). Use `#[capture(name = "...")]` on a field for a group with another name. The text of
+/// the group is converted with `FromStr`. Fields with an `Option` type are `None` if the group
+/// didn't match, for other fields that's an error.
+#[proc_macro_derive(FromCaptures, attributes(capture))]
+pub fn derive_from_captures(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "FromCaptures can only be derived for structs",
+            ))
+        }
+    };
+    let value = match fields {
+        Fields::Named(fields) => {
+            let inits = fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.as_ref().expect("named field");
+                    let default = ident.to_string().trim_start_matches("r#").to_string();
+                    let group = group_name(field)?.unwrap_or(default);
+                    let value = convert(field, quote!(caps.name(#group)), &group);
+                    Ok(quote!(#ident: #value))
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote!(#name { #(#inits,)* })
+        }
+        Fields::Unnamed(fields) => {
+            let values = fields
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let index = i + 1;
+                    Ok(match group_name(field)? {
+                        Some(group) => convert(field, quote!(caps.name(#group)), &group),
+                        None => convert(field, quote!(caps.get(#index)), &index.to_string()),
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote!(#name(#(#values,)*))
+        }
+        Fields::Unit => quote!(#name),
+    };
+    Ok(quote! {
+        impl #impl_generics ::fancy_regex::FromCaptures for #name #ty_generics #where_clause {
+            fn from_captures(
+                caps: &::fancy_regex::Captures<'_>,
+            ) -> ::fancy_regex::Result<Self> {
+                ::std::result::Result::Ok(#value)
+            }
+        }
+    })
+}
+
+/// Returns the expression that converts the match of a group, `None` if the group didn't match.
+fn convert(field: &Field, group_match: TokenStream2, group: &str) -> TokenStream2 {
+    if is_option(&field.ty) {
+        quote!(::fancy_regex::__private::convert_optional(#group_match, #group)?)
+    } else {
+        quote!(::fancy_regex::__private::convert(#group_match, #group)?)
+    }
+}
+
+/// Returns the group name from a `#[capture(name = "...")]` attribute of the field.
+fn group_name(field: &Field) -> syn::Result<Option<String>> {
+    let mut name = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("capture"))
+    {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new_spanned(meta, "expected #[capture(...)]")),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.path.is_ident("name") => {
+                    match pair.lit {
+                        Lit::Str(ref s) => name = Some(s.value()),
+                        ref lit => {
+                            return Err(syn::Error::new_spanned(lit, "expected a string"));
+                        }
+                    }
+                }
+                nested => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "unknown capture attribute, expected `name = \"...\"`",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(name)
+}
+
+/// Whether the type is written as `Option<T>` (or with a path like `std::option::Option<T>`).
+fn is_option(ty: &Type) -> bool {
+    let path = match ty {
+        Type::Path(ty) if ty.qself.is_none() => &ty.path,
+        _ => return false,
+    };
+    match path.segments.last() {
+        Some(segment) if segment.ident == "Option" => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => {
+                args.args.len() == 1 && matches!(args.args.first(), Some(GenericArgument::Type(_)))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
@@ -21,19 +21,30 @@
 //! Analysis of regex expressions.
 
 use bit_set::BitSet;
-use std::cmp::min;
+use regex::Regex;
+use std::cmp::{max, min};
+use std::ops::Range;
 use std::usize;
 
-use crate::parse::{ExprTree, NamedGroups};
+use crate::parse::{repeat_count, ExprTree, NamedGroups};
+use crate::CompileError;
 use crate::Error;
 use crate::Expr;
+use crate::LookAround::*;
 use crate::Result;
+use crate::{LargeRepeatPolicy, RegexOptions, RepeatedGroups};
 
 #[derive(Debug)]
 pub struct Info<'a> {
     pub(crate) start_group: usize,
     pub(crate) end_group: usize,
     pub(crate) min_size: usize,
+    /// Maximum number of characters that matching can inspect to the right of the start position,
+    /// including look-ahead. `None` if unbounded.
+    pub(crate) max_size: Option<usize>,
+    /// Maximum number of characters that matching can inspect to the left of the start position,
+    /// e.g. for look-behind or `\b`.
+    pub(crate) max_lookbehind: usize,
     pub(crate) const_size: bool,
     pub(crate) hard: bool,
 
@@ -43,6 +54,9 @@ pub struct Info<'a> {
     /// The matching of `\b` depends on the previous character.
     pub(crate) looks_left: bool,
 
+    /// The span of the expression in the pattern, if it's a group
+    pub(crate) span: Option<Range<usize>>,
+
     pub(crate) expr: &'a Expr,
     pub(crate) children: Vec<Info<'a>>,
 }
@@ -56,6 +70,15 @@ impl<'a> Info<'a> {
         }
     }
 
+    /// Whether the expression is a case-insensitive literal, or a concatenation of them.
+    pub(crate) fn is_literal_casei(&self) -> bool {
+        match *self.expr {
+            Expr::Literal { casei, .. } => casei,
+            Expr::Concat(_) => self.children.iter().all(|child| child.is_literal_casei()),
+            _ => false,
+        }
+    }
+
     pub(crate) fn push_literal(&self, buf: &mut String) {
         match *self.expr {
             // could be more paranoid about checking casei
@@ -68,12 +91,168 @@ impl<'a> Info<'a> {
             _ => panic!("push_literal called on non-literal"),
         }
     }
+
+    /// Returns the set of bytes that a non-empty match of the expression can start with, or
+    /// `None` if that's not known (or if the expression can match the empty string).
+    pub(crate) fn first_bytes(&self) -> Option<BitSet> {
+        if self.min_size == 0 {
+            return None;
+        }
+        let mut set = BitSet::with_capacity(256);
+        if self.add_first_bytes(&mut set) {
+            Some(set)
+        } else {
+            None
+        }
+    }
+
+    // Adds the possible first bytes to `set`, returns false if they are not known.
+    fn add_first_bytes(&self, set: &mut BitSet) -> bool {
+        match *self.expr {
+            Expr::Literal { ref val, casei } => {
+                let b = val.as_bytes()[0];
+                // Simple case folding maps `k` and `s` to non-ASCII characters too
+                if casei && (b >= 0x80 || b"KkSs".contains(&b)) {
+                    false
+                } else if casei && b.is_ascii_alphabetic() {
+                    set.insert(b.to_ascii_lowercase() as usize);
+                    set.insert(b.to_ascii_uppercase() as usize);
+                    true
+                } else {
+                    set.insert(b as usize);
+                    true
+                }
+            }
+            Expr::Concat(_) => {
+                for child in &self.children {
+                    if child.min_size > 0 {
+                        return child.add_first_bytes(set);
+                    }
+                    let zero_width = match child.expr {
+                        Expr::LookAround(..) => true,
+                        _ => child.max_size == Some(0),
+                    };
+                    if !zero_width && !child.add_first_bytes(set) {
+                        return false;
+                    }
+                }
+                false
+            }
+            Expr::Alt(_) => self.children.iter().all(|child| child.add_first_bytes(set)),
+            Expr::Group(_) | Expr::AtomicGroup(_) | Expr::ScriptRun(_) | Expr::Repeat { .. } => {
+                self.children[0].add_first_bytes(set)
+            }
+            Expr::Delegate {
+                ref inner,
+                size: 1,
+                casei,
+            } => add_delegate_first_bytes(inner, casei, set),
+            // Only reached for optional parts of a concatenation, nothing to add
+            Expr::Empty => true,
+            _ => false,
+        }
+    }
+
+    /// Returns a set of literals such that every match of the expression starts with one of them,
+    /// or `None` if there's no such set (e.g. if the expression can match the empty string).
+    #[cfg(feature = "perf-literal")]
+    pub(crate) fn literal_prefixes(&self) -> Option<Vec<String>> {
+        match *self.expr {
+            Expr::Literal {
+                ref val,
+                casei: false,
+            } => Some(vec![val.clone()]),
+            Expr::Concat(_) => {
+                let mut prefix = String::new();
+                for child in &self.children {
+                    let zero_width = match child.expr {
+                        Expr::LookAround(..) => true,
+                        _ => child.max_size == Some(0),
+                    };
+                    if zero_width {
+                        continue;
+                    } else if child.is_literal() {
+                        child.push_literal(&mut prefix);
+                    } else if prefix.is_empty() {
+                        return child.literal_prefixes();
+                    } else {
+                        break;
+                    }
+                }
+                if prefix.is_empty() {
+                    None
+                } else {
+                    Some(vec![prefix])
+                }
+            }
+            Expr::Alt(_) => {
+                let mut prefixes = Vec::new();
+                for child in &self.children {
+                    prefixes.extend(child.literal_prefixes()?);
+                }
+                Some(prefixes)
+            }
+            Expr::Group(_) | Expr::AtomicGroup(_) => self.children[0].literal_prefixes(),
+            Expr::Repeat { lo, .. } if lo > 0 => self.children[0].literal_prefixes(),
+            _ => None,
+        }
+    }
+
+    /// Returns literal text that every match of the expression ends with, if any.
+    pub(crate) fn literal_suffix(&self) -> Option<String> {
+        match *self.expr {
+            Expr::Literal {
+                ref val,
+                casei: false,
+            } => Some(val.clone()),
+            Expr::Concat(_) => {
+                let mut suffix = String::new();
+                for child in self.children.iter().rev() {
+                    let zero_width = match child.expr {
+                        Expr::LookAround(..) => true,
+                        _ => child.max_size == Some(0),
+                    };
+                    if zero_width {
+                        // Doesn't consume text, so what's before it is part of the suffix too
+                        continue;
+                    }
+                    match child.literal_suffix() {
+                        Some(child_suffix) => {
+                            suffix.insert_str(0, &child_suffix);
+                            if !child.is_literal() {
+                                break;
+                            }
+                        }
+                        None => break,
+                    }
+                }
+                if suffix.is_empty() {
+                    None
+                } else {
+                    Some(suffix)
+                }
+            }
+            Expr::Group(_) | Expr::AtomicGroup(_) => self.children[0].literal_suffix(),
+            Expr::Repeat { lo, .. } if lo > 0 => self.children[0].literal_suffix(),
+            _ => None,
+        }
+    }
 }
 
 struct Analyzer<'a> {
     backrefs: &'a BitSet,
     group_ix: usize,
     group_names: &'a NamedGroups,
+    group_spans: &'a [Range<usize>],
+    /// Whether groups inside repeats are reset at the start of each iteration, which can't be
+    /// delegated
+    reset_repeated_groups: bool,
+    /// Whether every iteration of groups is recorded in the capture history, which the VM does
+    /// for the groups it matches
+    capture_history: bool,
+    /// Repeats with a larger count are counted by the VM instead of being expanded by the regex
+    /// crate, see `LargeRepeatPolicy::Count`
+    max_expanded_repeat: Option<usize>,
 }
 
 impl<'a> Analyzer<'a> {
@@ -81,6 +260,8 @@ impl<'a> Analyzer<'a> {
         let start_group = self.group_ix;
         let mut children = Vec::new();
         let mut min_size = 0;
+        let mut max_size = Some(0);
+        let mut max_lookbehind = 0;
         let mut const_size = false;
         let mut hard = false;
         let mut looks_left = false;
@@ -90,16 +271,26 @@ impl<'a> Analyzer<'a> {
             }
             Expr::Any { .. } => {
                 min_size = 1;
+                max_size = Some(1);
                 const_size = true;
             }
-            Expr::Literal { ref val, casei } => {
-                // right now each character in a literal gets its own node, that might change
+            Expr::AnyByte => {
+                // Only the VM can match single bytes, and in a byte string their number isn't
+                // the number of characters
                 min_size = 1;
+                max_size = Some(1);
+                hard = true;
+            }
+            Expr::Literal { ref val, casei } => {
+                // Literals of several characters are the result of simplification
+                min_size = val.chars().count();
+                max_size = Some(min_size);
                 const_size = literal_const_size(val, casei);
             }
             Expr::StartText | Expr::StartLine => {
                 const_size = true;
                 looks_left = true;
+                max_lookbehind = 1;
             }
             Expr::Concat(ref v) => {
                 const_size = true;
@@ -107,6 +298,8 @@ impl<'a> Analyzer<'a> {
                     let child_info = self.visit(child)?;
                     looks_left |= child_info.looks_left && min_size == 0;
                     min_size += child_info.min_size;
+                    max_size = add_max_size(max_size, child_info.max_size);
+                    max_lookbehind = max(max_lookbehind, child_info.max_lookbehind);
                     const_size &= child_info.const_size;
                     hard |= child_info.hard;
                     children.push(child_info);
@@ -115,6 +308,8 @@ impl<'a> Analyzer<'a> {
             Expr::Alt(ref v) => {
                 let child_info = self.visit(&v[0])?;
                 min_size = child_info.min_size;
+                max_size = child_info.max_size;
+                max_lookbehind = child_info.max_lookbehind;
                 const_size = child_info.const_size;
                 hard = child_info.hard;
                 looks_left = child_info.looks_left;
@@ -123,6 +318,11 @@ impl<'a> Analyzer<'a> {
                     let child_info = self.visit(child)?;
                     const_size &= child_info.const_size && min_size == child_info.min_size;
                     min_size = min(min_size, child_info.min_size);
+                    max_size = match (max_size, child_info.max_size) {
+                        (Some(a), Some(b)) => Some(max(a, b)),
+                        _ => None,
+                    };
+                    max_lookbehind = max(max_lookbehind, child_info.max_lookbehind);
                     hard |= child_info.hard;
                     looks_left |= child_info.looks_left;
                     children.push(child_info);
@@ -133,17 +333,31 @@ impl<'a> Analyzer<'a> {
                 self.group_ix += 1;
                 let child_info = self.visit(child)?;
                 min_size = child_info.min_size;
+                max_size = child_info.max_size;
+                max_lookbehind = child_info.max_lookbehind;
                 const_size = child_info.const_size;
                 looks_left = child_info.looks_left;
                 // If there's a backref to this group, we potentially have to backtrack within the
                 // group. E.g. with `(x|xy)\1` and input `xyxy`, `x` matches but then the backref
                 // doesn't, so we have to backtrack and try `xy`.
-                hard = child_info.hard | self.backrefs.contains(group);
+                hard = child_info.hard
+                    || self.backrefs.contains(group)
+                    || (self.capture_history && group > 0);
                 children.push(child_info);
             }
-            Expr::LookAround(ref child, _) => {
+            Expr::LookAround(ref child, la) => {
                 let child_info = self.visit(child)?;
                 // min_size = 0
+                // Conservative: look-ahead inspects as far as its body can, look-behind inspects
+                // the text it steps back over.
+                max_size = child_info.max_size;
+                max_lookbehind = match la {
+                    LookAhead | LookAheadNeg => child_info.max_lookbehind,
+                    LookBehind | LookBehindNeg => match child_info.max_size {
+                        Some(size) => size.saturating_add(child_info.max_lookbehind),
+                        None => usize::MAX,
+                    },
+                };
                 const_size = true;
                 hard = true;
                 looks_left = child_info.looks_left;
@@ -154,37 +368,72 @@ impl<'a> Analyzer<'a> {
             } => {
                 let child_info = self.visit(child)?;
                 min_size = child_info.min_size * lo;
+                max_size = match child_info.max_size {
+                    Some(0) => Some(0),
+                    Some(size) if hi != usize::MAX => size.checked_mul(hi),
+                    _ => None,
+                };
+                max_lookbehind = child_info.max_lookbehind;
                 const_size = child_info.const_size && lo == hi;
-                hard = child_info.hard;
+                let has_groups = child_info.end_group > child_info.start_group;
+                let counted = self
+                    .max_expanded_repeat
+                    .map_or(false, |max| repeat_count(lo, hi) > max);
+                hard = child_info.hard
+                    || (self.reset_repeated_groups && hi > 1 && has_groups)
+                    || counted;
                 looks_left = child_info.looks_left;
                 children.push(child_info);
             }
             Expr::Delegate { size, .. } => {
                 // currently only used for empty and single-char matches
                 min_size = size;
+                max_size = Some(size);
                 const_size = true;
                 looks_left = size == 0; // TODO: conservative for \z
+                if looks_left {
+                    max_lookbehind = 1;
+                }
             }
-            Expr::Backref(group) => {
+            Expr::Backref { group, .. } => {
                 if group >= self.group_ix {
-                    return Err(Error::InvalidBackref);
+                    return Err(Error::CompileError(CompileError::InvalidBackref));
                 }
+                max_size = None;
                 hard = true;
             }
             Expr::NamedBackref(ref name) => {
                 if !self.group_names.contains_key(name) {
-                    return Err(Error::InvalidBackref);
+                    return Err(Error::CompileError(CompileError::InvalidBackref));
                 }
+                max_size = None;
                 hard = true;
             }
             Expr::AtomicGroup(ref child) => {
                 let child_info = self.visit(child)?;
                 min_size = child_info.min_size;
+                max_size = child_info.max_size;
+                max_lookbehind = child_info.max_lookbehind;
                 const_size = child_info.const_size;
                 looks_left = child_info.looks_left;
                 hard = true; // TODO: possibly could weaken
                 children.push(child_info);
             }
+            Expr::ScriptRun(ref child) => {
+                let child_info = self.visit(child)?;
+                min_size = child_info.min_size;
+                max_size = child_info.max_size;
+                max_lookbehind = child_info.max_lookbehind;
+                const_size = child_info.const_size;
+                looks_left = child_info.looks_left;
+                hard = true;
+                children.push(child_info);
+            }
+        };
+
+        let span = match *expr {
+            Expr::Group(_) if start_group > 0 => self.group_spans.get(start_group - 1).cloned(),
+            _ => None,
         };
 
         Ok(Info {
@@ -193,13 +442,45 @@ impl<'a> Analyzer<'a> {
             start_group,
             end_group: self.group_ix,
             min_size,
+            max_size,
+            max_lookbehind,
             const_size,
             hard,
             looks_left,
+            span,
         })
     }
 }
 
+fn add_max_size(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => a.checked_add(b),
+        _ => None,
+    }
+}
+
+/// Adds the first bytes of the characters that the delegated regex `inner` (matching a single
+/// character) can match. ASCII characters are tried one by one, for other characters all the
+/// bytes that can start a multi-byte UTF-8 sequence are added. Returns false if the regex
+/// couldn't be compiled.
+fn add_delegate_first_bytes(inner: &str, casei: bool, set: &mut BitSet) -> bool {
+    let flags = if casei { "(?i)" } else { "" };
+    let re = match Regex::new(&format!("^{}(?:{})$", flags, inner)) {
+        Ok(re) => re,
+        Err(_) => return false,
+    };
+    let mut buf = [0; 4];
+    for b in 0..0x80u8 {
+        if re.is_match(char::from(b).encode_utf8(&mut buf)) {
+            set.insert(b as usize);
+        }
+    }
+    for b in 0xc2..0xf5 {
+        set.insert(b);
+    }
+    true
+}
+
 fn literal_const_size(_: &str, _: bool) -> bool {
     // Right now, regex doesn't do sophisticated case folding,
     // test below will fail when that changes, then we need to
@@ -209,10 +490,26 @@ fn literal_const_size(_: &str, _: bool) -> bool {
 
 /// Analyze the parsed expression to determine whether it requires fancy features.
 pub fn analyze<'a>(tree: &'a ExprTree) -> Result<Info<'a>> {
+    analyze_with_options(tree, &RegexOptions::default())
+}
+
+/// Analyze the parsed expression like `analyze`, taking into account the options that need fancy
+/// features, e.g. `RepeatedGroups::JavaScript`.
+pub(crate) fn analyze_with_options<'a>(
+    tree: &'a ExprTree,
+    options: &RegexOptions,
+) -> Result<Info<'a>> {
     let mut analyzer = Analyzer {
         backrefs: &tree.backrefs,
         group_ix: 0,
         group_names: &tree.named_groups,
+        group_spans: &tree.group_spans,
+        reset_repeated_groups: options.repeated_groups == RepeatedGroups::JavaScript,
+        capture_history: options.capture_history,
+        max_expanded_repeat: match options.large_repeat_policy {
+            LargeRepeatPolicy::Count => options.max_repeat,
+            LargeRepeatPolicy::Reject => None,
+        },
     };
 
     analyzer.visit(&tree.expr)
@@ -223,7 +520,6 @@ mod tests {
     use super::analyze;
     use super::literal_const_size;
     use crate::Expr;
-    use regex;
 
     #[test]
     fn case_folding_safe() {
@@ -241,7 +537,7 @@ mod tests {
 
     #[test]
     fn invalid_backref_1() {
-        assert!(analyze(&Expr::parse_tree(".\\0").unwrap()).is_err());
+        assert!(analyze(&Expr::parse_tree("(?:.)\\1").unwrap()).is_err());
     }
 
     #[test]
@@ -258,13 +554,107 @@ mod tests {
     fn is_literal() {
         let tree = Expr::parse_tree("abc").unwrap();
         let info = analyze(&tree).unwrap();
-        assert_eq!(info.is_literal(), true);
+        assert!(info.is_literal());
+    }
+
+    #[test]
+    fn max_size() {
+        fn max_size(re: &str) -> Option<usize> {
+            let tree = Expr::parse_tree(re).unwrap();
+            analyze(&tree).unwrap().max_size
+        }
+        assert_eq!(max_size("abc"), Some(3));
+        assert_eq!(max_size("a|bcd"), Some(3));
+        assert_eq!(max_size("(?:ab){2,3}"), Some(6));
+        assert_eq!(max_size("a(?=bcd)"), Some(4));
+        assert_eq!(max_size("a+"), None);
+        assert_eq!(max_size("a(?:bc)*"), None);
+    }
+
+    #[test]
+    fn max_lookbehind() {
+        let tree = Expr::parse_tree(r"(?<=abc)d|\bx").unwrap();
+        assert_eq!(analyze(&tree).unwrap().max_lookbehind, 3);
+        let tree = Expr::parse_tree(r"\bx").unwrap();
+        assert_eq!(analyze(&tree).unwrap().max_lookbehind, 1);
+    }
+
+    #[test]
+    fn first_bytes() {
+        fn first_bytes(re: &str) -> Option<Vec<u8>> {
+            let tree = Expr::parse_tree(re).unwrap();
+            let info = analyze(&tree).unwrap();
+            info.first_bytes()
+                .map(|set| set.iter().map(|b| b as u8).collect())
+        }
+        assert_eq!(first_bytes("abc"), Some(b"a".to_vec()));
+        assert_eq!(first_bytes("b|a+"), Some(b"ab".to_vec()));
+        assert_eq!(first_bytes("(?i)x"), Some(b"Xx".to_vec()));
+        assert_eq!(first_bytes("a?(?=x)b"), Some(b"ab".to_vec()));
+        assert_eq!(first_bytes(r"\b(?:a|)c"), Some(b"ac".to_vec()));
+        assert_eq!(first_bytes("(?i)s"), None);
+        assert_eq!(first_bytes("a?"), None);
+        assert_eq!(first_bytes(".b"), None);
+
+        // Classes are checked for the ASCII characters, other characters could start with any
+        // lead byte
+        let ascii_first_bytes = |re: &str| {
+            let bytes = first_bytes(re)?;
+            assert!(bytes.contains(&0xc3) && bytes.contains(&0xf4) && !bytes.contains(&0x80));
+            Some(bytes.into_iter().filter(u8::is_ascii).collect::<Vec<_>>())
+        };
+        assert_eq!(ascii_first_bytes("[ab]"), Some(b"ab".to_vec()));
+        assert_eq!(
+            ascii_first_bytes(r"\d|(?i)x"),
+            Some(b"0123456789Xx".to_vec())
+        );
+        assert_eq!(ascii_first_bytes(r"(?i:[k-l])+\b"), Some(b"KLkl".to_vec()));
+    }
+
+    #[test]
+    fn literal_suffix() {
+        fn literal_suffix(re: &str) -> Option<String> {
+            let tree = Expr::parse_tree(re).unwrap();
+            analyze(&tree).unwrap().literal_suffix()
+        }
+        assert_eq!(literal_suffix("abc"), Some("abc".to_string()));
+        assert_eq!(literal_suffix(r"\w+bc"), Some("bc".to_string()));
+        assert_eq!(literal_suffix(r"\d(x+)\b(?=y)"), Some("x".to_string()));
+        assert_eq!(literal_suffix(r"a\bb$"), Some("ab".to_string()));
+        assert_eq!(literal_suffix("a(?i)b"), None);
+        assert_eq!(literal_suffix("ab?"), None);
+        assert_eq!(literal_suffix("a|b"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "perf-literal")]
+    fn literal_prefixes() {
+        fn literal_prefixes(re: &str) -> Option<Vec<String>> {
+            let tree = Expr::parse_tree(re).unwrap();
+            analyze(&tree).unwrap().literal_prefixes()
+        }
+        assert_eq!(literal_prefixes("abc"), Some(vec!["abc".to_string()]));
+        assert_eq!(
+            literal_prefixes(r"(?<=\$)(foo|bar|baz\d)"),
+            Some(vec![
+                "foo".to_string(),
+                "bar".to_string(),
+                "baz".to_string()
+            ])
+        );
+        assert_eq!(
+            literal_prefixes(r"\b(?:ab|c)+x"),
+            Some(vec!["ab".to_string(), "c".to_string()])
+        );
+        assert_eq!(literal_prefixes("a|b?"), None);
+        assert_eq!(literal_prefixes("(?i)ab"), None);
+        assert_eq!(literal_prefixes(r"\w+"), None);
     }
 
     #[test]
     fn is_literal_with_repeat() {
         let tree = Expr::parse_tree("abc*").unwrap();
         let info = analyze(&tree).unwrap();
-        assert_eq!(info.is_literal(), false);
+        assert!(!info.is_literal());
     }
 }
@@ -0,0 +1,263 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Property-based testing support with quickcheck, enabled with the `quickcheck` feature.
+//!
+//! `Expr` implements `Arbitrary` by generating expressions in the subset that the regex crate
+//! supports too, so that a generated expression can be formatted with `Expr::to_str` and the
+//! result compared with the regex crate as an oracle. `SampleText` generates texts over a small
+//! alphabet that is shared with the literals of generated expressions, so that they actually
+//! match now and then.
+
+use std::usize;
+
+use quickcheck::{Arbitrary, Gen};
+
+use crate::Expr;
+
+/// The characters used for literals and sample texts.
+const ALPHABET: &[char] = &['a', 'b', 'c', 'A', 'B', '1', ' ', '\n', '-', 'é'];
+
+/// Character classes and assertions that are used for delegates, with their size.
+const DELEGATES: &[(&str, usize)] = &[
+    ("[a-c]", 1),
+    ("[^a]", 1),
+    (r"\d", 1),
+    (r"\w", 1),
+    (r"\s", 1),
+    (r"\b", 0),
+    (r"\B", 0),
+];
+
+/// Maximum nesting depth of generated expressions.
+const MAX_DEPTH: usize = 4;
+
+/// A text for matching generated expressions against, see the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SampleText(pub String);
+
+impl Arbitrary for SampleText {
+    fn arbitrary(g: &mut Gen) -> SampleText {
+        let len = below(g, g.size() / 4 + 1);
+        SampleText((0..len).map(|_| *g.choose(ALPHABET).unwrap()).collect())
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = SampleText>> {
+        let chars: Vec<char> = self.0.chars().collect();
+        Box::new(
+            chars
+                .shrink()
+                .map(|chars| SampleText(chars.into_iter().collect())),
+        )
+    }
+}
+
+impl Arbitrary for Expr {
+    fn arbitrary(g: &mut Gen) -> Expr {
+        arbitrary_expr(g, 0)
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Expr>> {
+        let mut simpler = Vec::new();
+        match *self {
+            Expr::Empty => return Box::new(std::iter::empty()),
+            Expr::Concat(ref children) | Expr::Alt(ref children) => {
+                simpler.extend(children.iter().cloned());
+                let concat = matches!(*self, Expr::Concat(_));
+                for i in 0..children.len() {
+                    let mut fewer = children.clone();
+                    fewer.remove(i);
+                    simpler.push(if concat {
+                        Expr::Concat(fewer)
+                    } else {
+                        Expr::Alt(fewer)
+                    });
+                }
+            }
+            Expr::Group(ref child) => simpler.push((**child).clone()),
+            Expr::Repeat {
+                ref child,
+                lo,
+                hi,
+                greedy,
+            } => {
+                simpler.push((**child).clone());
+                if lo > 0 || hi != usize::MAX || !greedy {
+                    simpler.push(Expr::Repeat {
+                        child: child.clone(),
+                        lo: 0,
+                        hi: usize::MAX,
+                        greedy: true,
+                    });
+                }
+            }
+            Expr::Literal { ref val, casei } => {
+                if casei {
+                    simpler.push(Expr::Literal {
+                        val: val.clone(),
+                        casei: false,
+                    });
+                }
+                if val != "a" {
+                    simpler.push(literal("a"));
+                }
+            }
+            _ => simpler.push(literal("a")),
+        }
+        simpler.insert(0, Expr::Empty);
+        Box::new(simpler.into_iter())
+    }
+}
+
+fn arbitrary_expr(g: &mut Gen, depth: usize) -> Expr {
+    let leaf = depth >= MAX_DEPTH || below(g, MAX_DEPTH + 1) <= depth;
+    if leaf {
+        return arbitrary_leaf(g);
+    }
+    match below(g, 4) {
+        0 => Expr::Concat(arbitrary_children(g, depth)),
+        1 => Expr::Alt(arbitrary_children(g, depth)),
+        2 => Expr::Group(Box::new(arbitrary_expr(g, depth + 1))),
+        _ => {
+            let lo = below(g, 3);
+            let hi = match below(g, 3) {
+                0 => usize::MAX,
+                n => lo + n - 1,
+            };
+            Expr::Repeat {
+                child: Box::new(arbitrary_repeatable(g, depth + 1)),
+                lo,
+                hi,
+                greedy: bool::arbitrary(g),
+            }
+        }
+    }
+}
+
+fn arbitrary_children(g: &mut Gen, depth: usize) -> Vec<Expr> {
+    let len = 2 + below(g, 3);
+    (0..len).map(|_| arbitrary_expr(g, depth + 1)).collect()
+}
+
+/// Generate an expression that can be repeated, i.e. one that isn't empty or an assertion, also
+/// not after removing the non-capturing group that `Expr::to_str` puts around it.
+fn arbitrary_repeatable(g: &mut Gen, depth: usize) -> Expr {
+    loop {
+        let expr = arbitrary_expr(g, depth);
+        let mut inner = &expr;
+        while let Expr::Concat(ref children) = *inner {
+            let mut non_empty = children.iter().filter(|child| **child != Expr::Empty);
+            match (non_empty.next(), non_empty.next()) {
+                (None, _) => inner = &Expr::Empty,
+                (Some(child), None) => inner = child,
+                _ => break,
+            }
+        }
+        let repeatable = match *inner {
+            Expr::Empty | Expr::StartText | Expr::EndText | Expr::StartLine | Expr::EndLine => {
+                false
+            }
+            Expr::Delegate { size, .. } => size > 0,
+            _ => true,
+        };
+        if repeatable {
+            return expr;
+        }
+    }
+}
+
+fn arbitrary_leaf(g: &mut Gen) -> Expr {
+    match below(g, 10) {
+        0 => Expr::Empty,
+        1 => Expr::Any {
+            newline: bool::arbitrary(g),
+        },
+        2 => g
+            .choose(&[
+                Expr::StartText,
+                Expr::EndText,
+                Expr::StartLine,
+                Expr::EndLine,
+            ])
+            .unwrap()
+            .clone(),
+        3 | 4 => {
+            let &(inner, size) = g.choose(DELEGATES).unwrap();
+            Expr::Delegate {
+                inner: inner.to_string(),
+                size,
+                casei: bool::arbitrary(g),
+            }
+        }
+        _ => Expr::Literal {
+            val: g.choose(ALPHABET).unwrap().to_string(),
+            casei: bool::arbitrary(g),
+        },
+    }
+}
+
+fn literal(val: &str) -> Expr {
+    Expr::Literal {
+        val: val.to_string(),
+        casei: false,
+    }
+}
+
+/// Random number in `0..n`.
+fn below(g: &mut Gen, n: usize) -> usize {
+    usize::arbitrary(g) % n
+}
+
+#[cfg(all(test, feature = "compiler"))]
+mod tests {
+    use super::*;
+    use crate::Regex;
+    use quickcheck::{quickcheck, TestResult};
+
+    fn to_pattern(expr: &Expr) -> String {
+        let mut pattern = String::new();
+        expr.to_str(&mut pattern, 0);
+        pattern
+    }
+
+    #[test]
+    fn generated_expressions_compile() {
+        let mut g = Gen::new(100);
+        for _ in 0..200 {
+            let pattern = to_pattern(&Expr::arbitrary(&mut g));
+            assert!(Regex::new(&pattern).is_ok(), "{:?}", pattern);
+            assert!(regex::Regex::new(&pattern).is_ok(), "{:?}", pattern);
+        }
+    }
+
+    quickcheck! {
+        fn same_matches_as_regex_crate(expr: Expr, text: SampleText) -> TestResult {
+            let pattern = to_pattern(&expr);
+            let (fancy, oracle) = match (Regex::new(&pattern), regex::Regex::new(&pattern)) {
+                (Ok(fancy), Ok(oracle)) => (fancy, oracle),
+                _ => return TestResult::discard(),
+            };
+            let text = &text.0;
+            let expected = oracle.find(text).map(|m| m.range());
+            let actual = fancy.find(text).unwrap().map(|m| m.start()..m.end());
+            TestResult::from_bool(expected == actual)
+        }
+    }
+}
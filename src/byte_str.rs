@@ -0,0 +1,375 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Searching byte strings that are conventionally UTF-8, enabled with the `bstr` feature.
+//!
+//! Like with bstr's own iterators over characters, each invalid UTF-8 sequence is treated as one
+//! U+FFFD replacement character: it's matched by `.` and `[^a]` but not by `\w`, so there are word
+//! boundaries between it and word characters, and `^` and `$` don't match next to it. The text
+//! that is searched only has to be copied if the byte string isn't valid UTF-8.
+//!
+//! `\O` matches single bytes instead of characters (`Insn::AnyByte`). Positions inside a character
+//! are the same in the text and in the haystack, and the positions inside a U+FFFD stand for the
+//! bytes it replaces after the first one. The VM is told where the replacements are, see
+//! `RunConfig::byte_string`.
+
+use std::borrow::Cow;
+use std::ops::Range;
+use std::str;
+use std::sync::Arc;
+
+use bstr::BStr;
+
+use crate::parse::NamedGroups;
+use crate::{Matches, Regex, Result, RunConfig};
+
+/// A single match of a regex in a byte string.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BStrMatch<'t> {
+    haystack: &'t BStr,
+    start: usize,
+    end: usize,
+}
+
+impl<'t> BStrMatch<'t> {
+    /// Returns the starting byte offset of the match in the haystack.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending byte offset of the match in the haystack.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the match in the haystack.
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the matched bytes, which can contain invalid UTF-8.
+    #[inline]
+    pub fn as_bstr(&self) -> &'t BStr {
+        &self.haystack[self.start..self.end]
+    }
+}
+
+/// An iterator over the non-overlapping matches of a regex in a byte string, see
+/// [`Regex::find_iter_bstr`](struct.Regex.html#method.find_iter_bstr).
+///
+/// The iterator yields a `Result<BStrMatch>` and stops after an error.
+#[derive(Debug)]
+pub struct BStrMatches<'r, 't> {
+    regex: &'r Regex,
+    text: Text<'t>,
+    last_end: usize,
+    last_match: Option<usize>,
+    config: RunConfig,
+}
+
+impl<'r, 't> Iterator for BStrMatches<'r, 't> {
+    type Item = Result<BStrMatch<'t>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut matches = Matches {
+            re: self.regex,
+            text: &self.text.text,
+            last_end: self.last_end,
+            last_match: self.last_match,
+            config: self.config.clone(),
+        };
+        let result = matches.next();
+        self.last_end = matches.last_end;
+        self.last_match = matches.last_match;
+        // A match can end inside a character with `\O`, but the next one starts after it
+        let text = &self.text.text;
+        while self.last_end < text.len() && !text.is_char_boundary(self.last_end) {
+            self.last_end += 1;
+        }
+        match result? {
+            Ok(m) => Some(Ok(self.text.to_match(m.range()))),
+            Err(error) => {
+                // Stop after an error
+                self.last_end = self.text.text.len() + 1;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// The capture groups of a match of a regex in a byte string, see
+/// [`Regex::captures_bstr`](struct.Regex.html#method.captures_bstr).
+#[derive(Clone, Debug)]
+pub struct BStrCaptures<'t> {
+    haystack: &'t BStr,
+    groups: Vec<Option<Range<usize>>>,
+    named_groups: Arc<NamedGroups>,
+}
+
+#[allow(clippy::len_without_is_empty)] // follow regex's API
+impl<'t> BStrCaptures<'t> {
+    /// Get the capture group by its index in the regex.
+    ///
+    /// If there is no match for that group or the index does not correspond to a group, `None`
+    /// is returned. The index 0 returns the whole match.
+    pub fn get(&self, i: usize) -> Option<BStrMatch<'t>> {
+        self.groups
+            .get(i)
+            .cloned()
+            .flatten()
+            .map(|range| BStrMatch {
+                haystack: self.haystack,
+                start: range.start,
+                end: range.end,
+            })
+    }
+
+    /// Returns the match for a named capture group. Returns `None` if the capture group did not
+    /// match or if there is no group with the given name.
+    pub fn name(&self, name: &str) -> Option<BStrMatch<'t>> {
+        self.named_groups.get(name).and_then(|i| self.get(*i))
+    }
+
+    /// How many groups were captured. This is always at least 1 because group 0 returns the whole
+    /// match.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+/// Returns an iterator over the matches of `regex` in `haystack`.
+pub(crate) fn find_iter<'r, 't>(regex: &'r Regex, haystack: &'t BStr) -> BStrMatches<'r, 't> {
+    let text = Text::new(haystack);
+    let config = text.config();
+    BStrMatches {
+        regex,
+        text,
+        last_end: 0,
+        last_match: None,
+        config,
+    }
+}
+
+/// Returns the captures of the first match of `regex` in `haystack`.
+pub(crate) fn captures<'t>(regex: &Regex, haystack: &'t BStr) -> Result<Option<BStrCaptures<'t>>> {
+    let text = Text::new(haystack);
+    let caps = match regex.captures_with_config(&text.text, &text.config())? {
+        Some(caps) => caps,
+        None => return Ok(None),
+    };
+    let groups = caps
+        .iter()
+        .map(|group| group.map(|m| text.to_match(m.range()).range()))
+        .collect();
+    Ok(Some(BStrCaptures {
+        haystack,
+        groups,
+        named_groups: regex.named_groups.clone(),
+    }))
+}
+
+/// A byte string as text, with each invalid UTF-8 sequence replaced by U+FFFD.
+#[derive(Debug)]
+struct Text<'t> {
+    haystack: &'t BStr,
+    text: Cow<'t, str>,
+    /// Positions in `text` with the corresponding positions in the haystack, at the start and
+    /// after the end of each replacement. Empty if the haystack is valid UTF-8.
+    anchors: Vec<(usize, usize)>,
+}
+
+impl<'t> Text<'t> {
+    fn new(haystack: &'t BStr) -> Text<'t> {
+        let bytes: &'t [u8] = haystack;
+        let mut text = match str::from_utf8(bytes) {
+            Ok(text) => {
+                return Text {
+                    haystack,
+                    text: Cow::Borrowed(text),
+                    anchors: Vec::new(),
+                }
+            }
+            Err(_) => String::with_capacity(bytes.len() + 2),
+        };
+        let mut anchors = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let rest = &bytes[pos..];
+            match str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let valid = &rest[..e.valid_up_to()];
+                    text.push_str(str::from_utf8(valid).expect("checked to be valid"));
+                    pos += valid.len();
+                    anchors.push((text.len(), pos));
+                    text.push('\u{FFFD}');
+                    // An incomplete sequence at the end is invalid too
+                    pos += e.error_len().unwrap_or(rest.len() - valid.len());
+                    anchors.push((text.len(), pos));
+                }
+            }
+        }
+        Text {
+            haystack,
+            text: Cow::Owned(text),
+            anchors,
+        }
+    }
+
+    /// The configuration for running the VM on the text, with where the replacements are for
+    /// matching single bytes.
+    fn config(&self) -> RunConfig {
+        // The anchors come in pairs, at the start and after the end of each replacement
+        let replacements: Vec<(usize, usize)> = self
+            .anchors
+            .chunks(2)
+            .map(|pair| (pair[0].0, pair[1].1 - pair[0].1))
+            .collect();
+        let mut config = RunConfig::new();
+        config.byte_string = Some(replacements.into());
+        config
+    }
+
+    /// Translates a position in the text to one in the haystack. Inside a replacement, the
+    /// positions after its start stand for the bytes it replaces after the first one.
+    fn to_haystack_pos(&self, pos: usize) -> usize {
+        match self
+            .anchors
+            .binary_search_by_key(&pos, |&(text_pos, _)| text_pos)
+        {
+            Ok(i) => self.anchors[i].1,
+            Err(0) => pos,
+            Err(i) => {
+                let (text_pos, haystack_pos) = self.anchors[i - 1];
+                haystack_pos + (pos - text_pos)
+            }
+        }
+    }
+
+    fn to_match(&self, range: Range<usize>) -> BStrMatch<'t> {
+        BStrMatch {
+            haystack: self.haystack,
+            start: self.to_haystack_pos(range.start),
+            end: self.to_haystack_pos(range.end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RegexBuilder;
+    use bstr::ByteSlice;
+
+    fn found<'t>(re: &str, haystack: &'t [u8]) -> Vec<(usize, &'t [u8])> {
+        Regex::new(re)
+            .unwrap()
+            .find_iter_bstr(haystack.as_bstr())
+            .map(|m| {
+                let m = m.unwrap();
+                (m.start(), m.as_bstr().as_bytes())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_utf8() {
+        assert_eq!(found(r"\w+", b"ab cd"), vec![(0, &b"ab"[..]), (3, b"cd")]);
+    }
+
+    #[test]
+    fn invalid_utf8_is_one_character() {
+        assert_eq!(
+            found(r"a.b", b"a\xffb a\xe2\x82b"),
+            vec![(0, &b"a\xffb"[..]), (4, b"a\xe2\x82b")]
+        );
+        assert_eq!(
+            found(r"\w+", b"ab\xffcd"),
+            vec![(0, &b"ab"[..]), (3, b"cd")]
+        );
+        assert_eq!(found(r"\b\w", b"\xffa"), vec![(1, &b"a"[..])]);
+        assert!(found(r"^a", b"\xffa").is_empty());
+        assert_eq!(found(r"[^a]+$", b"a\xff\xfe"), vec![(1, &b"\xff\xfe"[..])]);
+    }
+
+    #[test]
+    fn any_byte() {
+        // Inside a character, in an invalid sequence of one byte and in one of two bytes
+        assert_eq!(
+            found(
+                r"\x02\O\O\x03",
+                b"\x02\xc3\xa9\x03\x02\xff\x41\x03\x02\xe2\x82\x03"
+            ),
+            vec![
+                (0, &b"\x02\xc3\xa9\x03"[..]),
+                (4, b"\x02\xff\x41\x03"),
+                (8, b"\x02\xe2\x82\x03")
+            ]
+        );
+        assert_eq!(found(r"\O", b"\xe2\x82"), vec![(0, &b"\xe2"[..])]);
+        assert_eq!(found(r"\O+", b"\xe2\x82"), vec![(0, &b"\xe2\x82"[..])]);
+        // Only `\O` matches inside a character
+        assert!(found(r"\O.", b"\xc3\xa9").is_empty());
+        assert!(found(r"\O\w", b"\xc3\xa9").is_empty());
+        assert_eq!(found(r"(?<=\O\O)x", b"\xc3\xa9x"), vec![(2, &b"x"[..])]);
+        assert_eq!(found(r"(?<=\O\O)x", b"\xe2\x82x"), vec![(2, &b"x"[..])]);
+        assert!(found(r"(?<=\O\O)x", b"\xffx").is_empty());
+    }
+
+    #[test]
+    fn any_byte_captures() {
+        let re = Regex::new(r"(\O)(\O)\2").unwrap();
+        let caps = re
+            .captures_bstr(b"\xe2\x82\x82".as_bstr())
+            .unwrap()
+            .unwrap();
+        assert_eq!(caps.get(0).unwrap().range(), 0..3);
+        assert_eq!(caps.get(1).unwrap().as_bstr().as_bytes(), b"\xe2");
+        assert_eq!(caps.get(2).unwrap().range(), 1..2);
+
+        // The groups are found by running the regex again, on bytes too
+        let re = RegexBuilder::new(r"(\O)+\x03")
+            .capture_history(true)
+            .build()
+            .unwrap();
+        let caps = re
+            .captures_bstr(b"\xe2\x82\x03".as_bstr())
+            .unwrap()
+            .unwrap();
+        assert_eq!(caps.get(1).unwrap().range(), 1..2);
+    }
+
+    #[test]
+    fn captures() {
+        let re = Regex::new(r"(?<key>\w+)=(.*)").unwrap();
+        let caps = re.captures_bstr(b"\xffk=v\xff".as_bstr()).unwrap().unwrap();
+        assert_eq!(caps.len(), 3);
+        assert_eq!(caps.get(0).unwrap().range(), 1..5);
+        assert_eq!(caps.name("key").unwrap().as_bstr(), "k");
+        assert_eq!(caps.get(2).unwrap().as_bstr().as_bytes(), b"v\xff");
+        assert!(re.captures_bstr(b"\xff".as_bstr()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Process-wide cache of compiled regexes, see `Regex::cached`.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use once_cell::sync::OnceCell;
+
+use crate::{Regex, RegexOptions, Result};
+
+/// Maximum number of regexes kept in the global cache.
+const CAPACITY: usize = 256;
+
+/// A least-recently-used cache of compiled regexes, keyed by pattern and options.
+#[derive(Debug)]
+struct Cache {
+    capacity: usize,
+    entries: HashMap<RegexOptions, (Regex, u64)>,
+    /// Incremented on every access, used to find the least recently used entry
+    tick: u64,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Cache {
+        Cache {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn get(&mut self, options: &RegexOptions) -> Option<Regex> {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(options).map(|entry| {
+            entry.1 = tick;
+            entry.0.clone()
+        })
+    }
+
+    fn insert(&mut self, options: RegexOptions, regex: Regex) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&options) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, &(_, tick))| tick)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(options, (regex, self.tick));
+    }
+}
+
+fn global() -> &'static Mutex<Cache> {
+    static CACHE: OnceCell<Mutex<Cache>> = OnceCell::new();
+    CACHE.get_or_init(|| Mutex::new(Cache::new(CAPACITY)))
+}
+
+/// Get the regex for `options` from the global cache, compiling and adding it if necessary.
+pub(crate) fn get_or_compile(options: &RegexOptions) -> Result<Regex> {
+    if let Some(regex) = lock().get(options) {
+        return Ok(regex);
+    }
+    // Compile without holding the lock, so other threads aren't blocked by a slow compile.
+    let regex = Regex::new_options(options.clone())?;
+    lock().insert(options.clone(), regex.clone());
+    Ok(regex)
+}
+
+fn lock() -> MutexGuard<'static, Cache> {
+    // The cache is always in a consistent state, even if another thread panicked.
+    global().lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(pattern: &str) -> RegexOptions {
+        RegexOptions {
+            pattern: pattern.to_string(),
+            ..RegexOptions::default()
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = Cache::new(2);
+        cache.insert(options("a"), Regex::new("a").unwrap());
+        cache.insert(options("b"), Regex::new("b").unwrap());
+        assert!(cache.get(&options("a")).is_some());
+        cache.insert(options("c"), Regex::new("c").unwrap());
+        assert!(cache.get(&options("a")).is_some());
+        assert!(cache.get(&options("b")).is_none());
+        assert!(cache.get(&options("c")).is_some());
+        assert_eq!(cache.entries.len(), 2);
+    }
+
+    #[test]
+    fn keyed_by_options() {
+        let mut cache = Cache::new(2);
+        cache.insert(options("a"), Regex::new("a").unwrap());
+        let mut other = options("a");
+        other.backtrack_limit = 10;
+        assert!(cache.get(&other).is_none());
+    }
+}
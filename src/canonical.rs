@@ -0,0 +1,224 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Canonical forms of patterns for deduplicating them, see `Regex::canonical_pattern`.
+
+use std::fmt;
+
+use crate::{push_quantifier, push_usize, Expr, LookAround};
+
+/// The canonical form of a pattern and a fingerprint of it, returned by
+/// [`Regex::canonical_pattern`](struct.Regex.html#method.canonical_pattern).
+///
+/// The `Display` implementation writes the canonical pattern.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CanonicalPattern {
+    pattern: String,
+    fingerprint: u64,
+}
+
+impl CanonicalPattern {
+    pub(crate) fn new(expr: &Expr) -> CanonicalPattern {
+        let mut pattern = String::new();
+        push_expr(&mut pattern, expr, 0);
+        let fingerprint = fingerprint(&pattern);
+        CanonicalPattern {
+            pattern,
+            fingerprint,
+        }
+    }
+
+    /// The canonical pattern, which can be used to build a regex that matches the same.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// A 64-bit hash of the canonical pattern. Unlike with `Hash`, the value is the same on all
+    /// platforms and Rust versions, so it can be stored, e.g. in a database of rules.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+}
+
+impl fmt::Display for CanonicalPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+/// FNV-1a hash of `s`.
+fn fingerprint(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Like `Expr::to_str`, but also for the expressions that the regex crate can't handle.
+fn push_expr(buf: &mut String, expr: &Expr, precedence: u8) {
+    match *expr {
+        Expr::Concat(ref children) => {
+            if precedence > 1 {
+                buf.push_str("(?:");
+            }
+            let mut backref_start = None;
+            for child in children {
+                let start = buf.len();
+                push_expr(buf, child, 2);
+                // `\1` followed by `0` would be read as `\10`
+                if let Some(backref_start) = backref_start {
+                    if buf[start..].starts_with(|c: char| c.is_ascii_digit()) {
+                        buf.insert(start, ')');
+                        buf.insert_str(backref_start, "(?:");
+                    }
+                }
+                backref_start = match *child {
+                    Expr::Backref { casei: false, .. } => Some(start),
+                    _ => None,
+                };
+            }
+            if precedence > 1 {
+                buf.push(')');
+            }
+        }
+        Expr::Alt(ref children) => {
+            if precedence > 0 {
+                buf.push_str("(?:");
+            }
+            for (i, child) in children.iter().enumerate() {
+                if i != 0 {
+                    buf.push('|');
+                }
+                push_expr(buf, child, 1);
+            }
+            if precedence > 0 {
+                buf.push(')');
+            }
+        }
+        Expr::Group(ref child) => {
+            buf.push('(');
+            push_expr(buf, child, 0);
+            buf.push(')');
+        }
+        Expr::Repeat {
+            ref child,
+            lo,
+            hi,
+            greedy,
+        } => {
+            if precedence > 2 {
+                buf.push_str("(?:");
+            }
+            push_expr(buf, child, 3);
+            push_quantifier(buf, lo, hi, greedy);
+            if precedence > 2 {
+                buf.push(')');
+            }
+        }
+        Expr::LookAround(ref child, la) => {
+            buf.push_str(match la {
+                LookAround::LookAhead => "(?=",
+                LookAround::LookAheadNeg => "(?!",
+                LookAround::LookBehind => "(?<=",
+                LookAround::LookBehindNeg => "(?<!",
+            });
+            push_expr(buf, child, 0);
+            buf.push(')');
+        }
+        Expr::Backref { group, casei } => {
+            if casei {
+                buf.push_str("(?i:");
+            }
+            buf.push('\\');
+            push_usize(buf, group);
+            if casei {
+                buf.push(')');
+            }
+        }
+        Expr::NamedBackref(ref name) => {
+            buf.push_str("\\k<");
+            buf.push_str(name);
+            buf.push('>');
+        }
+        Expr::AtomicGroup(ref child) => {
+            buf.push_str("(?>");
+            push_expr(buf, child, 0);
+            buf.push(')');
+        }
+        Expr::ScriptRun(ref child) => {
+            buf.push_str("(*sr:");
+            push_expr(buf, child, 0);
+            buf.push(')');
+        }
+        ref expr => expr.to_str(buf, precedence),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Regex;
+
+    fn canonical(re: &str) -> String {
+        Regex::new(re).unwrap().canonical_pattern().to_string()
+    }
+
+    #[test]
+    fn flags_and_whitespace() {
+        assert_eq!(canonical("(?i)ab"), "(?i:ab)");
+        assert_eq!(canonical("(?x) a b # comment"), "ab");
+        assert_eq!(canonical("(?s:.)(?m)^."), "(?s:.)(?m:^).");
+        assert_eq!(canonical("(?U)a*b*?"), "a*?b*");
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(
+            canonical(r"(?<first>\w)(?P<second>.)\k<first>(?P=second)"),
+            r"(\w)(.)\1\2"
+        );
+        assert_eq!(canonical("(?:ab)+(?:c)"), "(?:ab)+c");
+        assert_eq!(canonical("(?>a|bc)(?=d)(?<!e)"), "(?>a|bc)(?=d)(?<!e)");
+    }
+
+    #[test]
+    fn backref_followed_by_digit() {
+        let pattern = canonical(r"(a)(b)\1\x30");
+        assert_eq!(pattern, r"(a)(b)(?:\1)0");
+        let re = Regex::new(&pattern).unwrap();
+        assert!(re.is_match("aba0").unwrap());
+    }
+
+    #[test]
+    fn same_fingerprint() {
+        let re = |pattern| Regex::new(pattern).unwrap().canonical_pattern();
+        let a = re(r"(?<x>a+) \k<x>");
+        let b = re(r"(?x) ( a+ ) \  \1 # same");
+        assert_eq!(a, b);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(a.fingerprint(), re(r"(a+) \1 ").fingerprint());
+    }
+
+    #[test]
+    fn canonical_pattern_is_stable() {
+        for re in &[r"^(\w+)\s+\1$", "(?i)(?<=a)b|c{2,}?", r"(*sr:\w+)(?!x)"] {
+            let pattern = canonical(re);
+            assert_eq!(canonical(&pattern), pattern);
+        }
+    }
+}
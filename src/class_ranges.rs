@@ -0,0 +1,131 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Resolving character classes to the ranges of characters that they match, see
+//! `Expr::class_ranges`.
+
+use std::ops::RangeInclusive;
+
+use regex_syntax::hir::{Class, Group, GroupKind, HirKind, Literal};
+use regex_syntax::ParserBuilder;
+
+use crate::Expr;
+
+impl Expr {
+    /// Returns the characters that the expression matches as sorted, non-overlapping ranges, if
+    /// it always matches a single character: a character class like `[a-z&&[^aeiou]]`, `\d` or
+    /// `\p{Greek}`, `.`, or a literal of one character. Returns `None` for other expressions.
+    ///
+    /// The ranges are resolved like the regex crate does it when matching, with Unicode tables,
+    /// set operations and the flags that apply to the expression, e.g. case-insensitivity. This
+    /// is useful for showing what a class matches, or for writing an equivalent class for
+    /// another regex engine. Requires the `class-ranges` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Expr;
+    /// let tree = Expr::parse_tree(r"(?i)[a-d&&[^b]]").unwrap();
+    /// assert_eq!(
+    ///     tree.expr.class_ranges(),
+    ///     Some(vec!['A'..='A', 'C'..='D', 'a'..='a', 'c'..='d'])
+    /// );
+    /// ```
+    pub fn class_ranges(&self) -> Option<Vec<RangeInclusive<char>>> {
+        let single_char = match self {
+            Expr::Any { .. } => true,
+            Expr::Literal { val, .. } => val.chars().count() == 1,
+            Expr::Delegate { size, .. } => *size == 1,
+            _ => false,
+        };
+        if !single_char {
+            return None;
+        }
+        let mut re = String::new();
+        self.to_str(&mut re, 0);
+        let parsed = ParserBuilder::new().build().parse(&re).ok()?;
+        // Flags are written as non-capturing groups, which regex-syntax keeps
+        let mut hir = &parsed;
+        while let HirKind::Group(Group {
+            kind: GroupKind::NonCapturing,
+            hir: inner,
+        }) = hir.kind()
+        {
+            hir = inner;
+        }
+        match hir.kind() {
+            HirKind::Class(Class::Unicode(class)) => Some(
+                class
+                    .ranges()
+                    .iter()
+                    .map(|range| range.start()..=range.end())
+                    .collect(),
+            ),
+            HirKind::Literal(Literal::Unicode(c)) => Some(vec![*c..=*c]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expr;
+
+    fn ranges(re: &str) -> Option<Vec<(char, char)>> {
+        let expr = Expr::parse_tree(re).unwrap().expr;
+        expr.class_ranges().map(|ranges| {
+            ranges
+                .into_iter()
+                .map(|range| (*range.start(), *range.end()))
+                .collect()
+        })
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(ranges(r"[a-fx]"), Some(vec![('a', 'f'), ('x', 'x')]));
+        assert_eq!(ranges(r"[a-z&&[^b-y]]"), Some(vec![('a', 'a'), ('z', 'z')]));
+        assert_eq!(ranges(r"[[:digit:]--[5-9]]"), Some(vec![('0', '4')]));
+        assert_eq!(ranges(r"\p{Greek}"), ranges(r"[\p{Greek}]"));
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(
+            ranges(r"(?i)k"),
+            Some(vec![('K', 'K'), ('k', 'k'), ('\u{212A}', '\u{212A}')])
+        );
+        assert_eq!(ranges(r"(?i)[x-z]"), Some(vec![('X', 'Z'), ('x', 'z')]));
+        assert_eq!(
+            ranges(r"."),
+            Some(vec![('\0', '\u{9}'), ('\u{B}', '\u{10FFFF}')])
+        );
+        assert_eq!(ranges(r"(?s)."), Some(vec![('\0', '\u{10FFFF}')]));
+    }
+
+    #[test]
+    fn not_single_characters() {
+        assert_eq!(ranges(r"a"), Some(vec![('a', 'a')]));
+        assert_eq!(ranges(r"ab"), None);
+        assert_eq!(ranges(r"\b"), None);
+        assert_eq!(ranges(r"a|b"), None);
+        assert_eq!(ranges(r"(a)"), None);
+    }
+}
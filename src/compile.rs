@@ -20,15 +20,26 @@
 
 //! Compilation of regexes to VM.
 
+use bit_set::BitSet;
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::slice;
+use std::sync::Arc;
 use std::usize;
 
 use crate::analyze::Info;
+use crate::case_fold::simple_fold_str;
 use crate::vm::{Insn, Prog};
+use crate::CompileError;
 use crate::Error;
 use crate::Expr;
 use crate::LookAround;
 use crate::LookAround::*;
 use crate::RegexOptions;
+use crate::RepeatedGroups;
 use crate::Result;
 
 // I'm thinking it probably doesn't make a lot of sense having this split
@@ -36,6 +47,10 @@ use crate::Result;
 struct VMBuilder {
     prog: Vec<Insn>,
     n_saves: usize,
+    /// The span in the pattern of the expression being compiled, which is recorded for each
+    /// instruction that is added
+    span: Option<Range<usize>>,
+    spans: Vec<Option<Range<usize>>>,
 }
 
 impl VMBuilder {
@@ -43,11 +58,21 @@ impl VMBuilder {
         VMBuilder {
             prog: Vec::new(),
             n_saves: max_group * 2,
+            span: None,
+            spans: Vec::new(),
         }
     }
 
     fn build(self) -> Prog {
-        Prog::new(self.prog, self.n_saves)
+        let mut prog = Prog::new(self.prog, self.n_saves);
+        prog.spans = self.spans;
+        #[cfg(debug_assertions)]
+        {
+            if let Err(error) = prog.validate() {
+                panic!("Compiled an invalid program: {}\n{:?}", error, prog.body);
+            }
+        }
+        prog
     }
 
     fn newsave(&mut self) -> usize {
@@ -63,6 +88,7 @@ impl VMBuilder {
     // would "emit" be a better name?
     fn add(&mut self, insn: Insn) {
         self.prog.push(insn);
+        self.spans.push(self.span.clone());
     }
 
     fn set_jmp_target(&mut self, jmp_pc: usize, target: usize) {
@@ -91,20 +117,158 @@ impl VMBuilder {
     }
 }
 
+/// Delegate regexes by their pattern, for sharing them between programs.
+pub(crate) type Delegates = HashMap<String, Arc<LazyRegex>>;
+
 struct Compiler {
     b: VMBuilder,
     options: RegexOptions,
+    /// Delegate regexes so far, so that identical ones are shared and only compiled once
+    delegates: Delegates,
+    /// Whether we're compiling the body of a look-behind that is matched right-to-left
+    reverse: bool,
+    /// The groups whose positions are saved, `None` for all of them. Only the groups that are
+    /// referenced by backrefs are needed when it's only checked whether there's a match.
+    ///
+    /// The saved groups get consecutive slots, see `group_index`.
+    saved_groups: Option<BitSet>,
+    /// Where to continue looking for the span of the next child of the expression being compiled
+    cursor: usize,
+    /// Whether all the ways of matching are to be explored, so that nothing which can match in
+    /// more than one way may be delegated
+    exhaustive: bool,
+    /// The slot with the length of the capture history, once a group has been recorded in it
+    history_slot: Option<usize>,
 }
 
 impl Compiler {
-    fn new(max_group: usize) -> Compiler {
+    fn new(max_group: usize, options: &RegexOptions) -> Compiler {
         Compiler {
             b: VMBuilder::new(max_group),
-            options: Default::default(),
+            options: options.clone(),
+            delegates: HashMap::new(),
+            reverse: false,
+            saved_groups: None,
+            cursor: 0,
+            exhaustive: false,
+            history_slot: None,
         }
     }
 
+    fn saves_group(&self, group: usize) -> bool {
+        self.saved_groups
+            .as_ref()
+            .map_or(true, |groups| groups.contains(group))
+    }
+
+    /// The index of `group` among the saved groups, which is the group number if all groups are
+    /// saved. The slots of the group are `2 * index` and `2 * index + 1`. For a group that isn't
+    /// saved, it's the index of the next saved group.
+    fn group_index(&self, group: usize) -> usize {
+        match self.saved_groups {
+            Some(ref groups) => groups.iter().take_while(|&saved| saved < group).count(),
+            None => group,
+        }
+    }
+
+    /// Record the positions of `group` that were just saved in the capture history, if the
+    /// history is kept. It's not needed for programs that don't save all the groups, and the
+    /// whole match only matches once.
+    fn record_group(&mut self, group: usize) {
+        if !self.options.capture_history || self.saved_groups.is_some() || group == 0 {
+            return;
+        }
+        let history = match self.history_slot {
+            Some(slot) => slot,
+            None => {
+                let slot = self.b.newsave();
+                self.history_slot = Some(slot);
+                slot
+            }
+        };
+        let slot = self.group_index(group) * 2;
+        self.b.add(Insn::RecordGroup { slot, history });
+    }
+
     fn visit(&mut self, info: &Info<'_>, hard: bool) -> Result<()> {
+        if self.reverse {
+            return self.visit_reverse(info);
+        }
+        self.with_span(slice::from_ref(info), |compiler| {
+            compiler.compile_expr(info, hard)
+        })
+    }
+
+    /// Compile `infos`, a sequence of expressions, with their span in the pattern as the span of
+    /// the instructions, if it can be found.
+    fn with_span(
+        &mut self,
+        infos: &[Info<'_>],
+        f: impl FnOnce(&mut Compiler) -> Result<()>,
+    ) -> Result<()> {
+        let outer_span = self.b.span.clone();
+        let outer_cursor = self.cursor;
+        let mut spans = infos.iter().map(|info| {
+            let span = self.find_span(info);
+            if let Some(ref span) = span {
+                self.cursor = span.end;
+            }
+            span
+        });
+        let first = spans.next().flatten();
+        let last = spans.last().unwrap_or_else(|| first.clone());
+        let span = match (first, last) {
+            (Some(first), Some(last)) if first.start <= last.end => Some(first.start..last.end),
+            _ => None,
+        };
+        if let Some(ref span) = span {
+            self.b.span = Some(span.clone());
+            self.cursor = span.start;
+        } else {
+            self.cursor = outer_cursor;
+        }
+        let result = f(self);
+        self.b.span = outer_span;
+        self.cursor = span.map_or(outer_cursor, |span| span.end);
+        result
+    }
+
+    /// Find the span of `info` in the pattern. Groups know their span, other expressions are
+    /// looked up by their text, within the span of the enclosing expression and after the
+    /// previous one that was found. Expressions that are written differently in the pattern
+    /// (e.g. after simplification) and hard ones other than groups and backrefs are not found.
+    fn find_span(&self, info: &Info<'_>) -> Option<Range<usize>> {
+        let pattern = &self.options.pattern;
+        if pattern.is_empty() {
+            // E.g. compiling an expression tree that wasn't parsed from a pattern
+            return None;
+        }
+        let text = match *info.expr {
+            Expr::Group(_) if info.start_group == 0 => return Some(0..pattern.len()),
+            Expr::Group(_) => return info.span.clone(),
+            Expr::Backref { group, .. } => format!("\\{}", group),
+            _ if info.hard => return None,
+            _ => {
+                let mut text = String::new();
+                info.expr.to_str(&mut text, 0);
+                text
+            }
+        };
+        if text.is_empty() {
+            return None;
+        }
+        let within = self.b.span.clone().unwrap_or(0..pattern.len());
+        // Expressions are found in order, except when compiling right-to-left
+        let starts = [self.cursor.max(within.start).min(within.end), within.start];
+        starts.iter().find_map(|&start| {
+            pattern
+                .get(start..within.end)?
+                .find(&text)
+                .map(|ix| start + ix..start + ix + text.len())
+        })
+    }
+
+    fn compile_expr(&mut self, info: &Info<'_>, hard: bool) -> Result<()> {
         if !hard && !info.hard {
             // easy case, delegate entire subexpr
             return self.compile_delegate(info);
@@ -115,7 +279,7 @@ impl Compiler {
                 if !casei {
                     self.b.add(Insn::Lit(val.clone()));
                 } else {
-                    self.compile_delegate(info)?;
+                    self.b.add(Insn::LitCasei(simple_fold_str(val)));
                 }
             }
             Expr::Any { newline: true } => {
@@ -124,6 +288,9 @@ impl Compiler {
             Expr::Any { newline: false } => {
                 self.b.add(Insn::AnyNoNL);
             }
+            Expr::AnyByte => {
+                self.b.add(Insn::AnyByte);
+            }
             Expr::Concat(_) => {
                 self.compile_concat(info, hard)?;
             }
@@ -133,9 +300,24 @@ impl Compiler {
             }
             Expr::Group(_) => {
                 let group = info.start_group;
-                self.b.add(Insn::Save(group * 2));
-                self.visit(&info.children[0], hard)?;
-                self.b.add(Insn::Save(group * 2 + 1));
+                let slot = self.group_index(group) * 2;
+                if self.saves_group(group) && has_backref(&info.children[0], group) {
+                    // Only set the start when the group ends, so that backrefs inside the group
+                    // see what it captured before, not a mix of that and the current start
+                    let start = self.b.newsave();
+                    self.b.add(Insn::Save(start));
+                    self.visit(&info.children[0], hard)?;
+                    self.b.add(Insn::SaveFrom { slot, from: start });
+                    self.b.add(Insn::Save(slot + 1));
+                    self.record_group(group);
+                } else if self.saves_group(group) {
+                    self.b.add(Insn::Save(slot));
+                    self.visit(&info.children[0], hard)?;
+                    self.b.add(Insn::Save(slot + 1));
+                    self.record_group(group);
+                } else {
+                    self.visit(&info.children[0], hard)?;
+                }
             }
             Expr::Repeat { lo, hi, greedy, .. } => {
                 self.compile_repeat(info, lo, hi, greedy, hard)?;
@@ -143,8 +325,20 @@ impl Compiler {
             Expr::LookAround(_, la) => {
                 self.compile_lookaround(info, la)?;
             }
-            Expr::Backref(group) => {
-                self.b.add(Insn::Backref(group * 2));
+            Expr::Backref { group, casei } => {
+                let slot = self.group_index(group) * 2;
+                #[cfg(feature = "unicode-normalization")]
+                {
+                    if self.options.canonical_backrefs {
+                        self.b.add(Insn::BackrefCanonical { slot, casei });
+                        return Ok(());
+                    }
+                }
+                if casei {
+                    self.b.add(Insn::BackrefCasei(slot));
+                } else {
+                    self.b.add(Insn::Backref(slot));
+                }
             }
             Expr::AtomicGroup(_) => {
                 // TODO optimization: atomic insns are not needed if the
@@ -153,6 +347,13 @@ impl Compiler {
                 self.visit(&info.children[0], false)?;
                 self.b.add(Insn::EndAtomic);
             }
+            Expr::ScriptRun(_) => {
+                let start = self.b.newsave();
+                self.b.add(Insn::Save(start));
+                // Not delegated, so that shorter matches can be tried if the check fails
+                self.visit(&info.children[0], true)?;
+                self.b.add(Insn::ScriptRun(start));
+            }
             Expr::Delegate { .. }
             | Expr::StartText
             | Expr::EndText
@@ -168,6 +369,79 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compile an expression that is matched right-to-left, ending at the current position.
+    fn visit_reverse(&mut self, info: &Info<'_>) -> Result<()> {
+        self.with_span(slice::from_ref(info), |compiler| {
+            compiler.compile_expr_reverse(info)
+        })
+    }
+
+    fn compile_expr_reverse(&mut self, info: &Info<'_>) -> Result<()> {
+        if !info.hard && info.const_size {
+            // Step back over the text the expression matches, match it forwards, then step back
+            // again to where it starts
+            let size = info.min_size;
+            if size > 0 {
+                self.b.add(Insn::GoBack(size));
+            }
+            self.compile_delegate(info)?;
+            if size > 0 {
+                self.b.add(Insn::GoBack(size));
+            }
+            return Ok(());
+        }
+        match *info.expr {
+            Expr::AnyByte => {
+                self.b.add(Insn::GoBackByte);
+            }
+            Expr::Concat(_) => {
+                for child in info.children.iter().rev() {
+                    self.visit_reverse(child)?;
+                }
+            }
+            Expr::Alt(_) => {
+                let count = info.children.len();
+                self.compile_alt(count, |compiler, i| {
+                    compiler.visit_reverse(&info.children[i])
+                })?;
+            }
+            Expr::Group(_) => {
+                // The end of the group is reached first
+                let group = info.start_group;
+                if self.saves_group(group) {
+                    let slot = self.group_index(group) * 2;
+                    self.b.add(Insn::Save(slot + 1));
+                    self.visit_reverse(&info.children[0])?;
+                    self.b.add(Insn::Save(slot));
+                    self.record_group(group);
+                } else {
+                    self.visit_reverse(&info.children[0])?;
+                }
+            }
+            Expr::Repeat { lo, hi, greedy, .. } => {
+                self.compile_repeat(info, lo, hi, greedy, true)?;
+            }
+            Expr::AtomicGroup(_) => {
+                self.b.add(Insn::BeginAtomic);
+                self.visit_reverse(&info.children[0])?;
+                self.b.add(Insn::EndAtomic);
+            }
+            Expr::ScriptRun(_) => {
+                let end = self.b.newsave();
+                self.b.add(Insn::Save(end));
+                self.visit_reverse(&info.children[0])?;
+                self.b.add(Insn::ScriptRun(end));
+            }
+            Expr::LookAround(_, la) => {
+                // Doesn't move the position, the body sets its own direction
+                self.compile_lookaround(info, la)?;
+            }
+            // Backrefs would need to be matched backwards
+            _ => return Err(Error::CompileError(CompileError::LookBehindNotConst)),
+        }
+        Ok(())
+    }
+
     fn compile_alt<F>(&mut self, count: usize, mut handle_alternative: F) -> Result<()>
     where
         F: FnMut(&mut Compiler, usize) -> Result<()>,
@@ -204,6 +478,14 @@ impl Compiler {
     }
 
     fn compile_concat(&mut self, info: &Info<'_>, hard: bool) -> Result<()> {
+        if self.exhaustive {
+            // Even constant size children can match in several ways, e.g. `(a)|(a)`
+            for child in &info.children {
+                self.visit(child, true)?;
+            }
+            return Ok(());
+        }
+
         // First: determine a prefix which is constant size and not hard.
         let prefix_end = info
             .children
@@ -229,13 +511,27 @@ impl Compiler {
         };
         let suffix_begin = info.children.len() - suffix_len;
 
-        self.compile_delegates(&info.children[..prefix_end])?;
+        let prefix = &info.children[..prefix_end];
+        self.with_span(prefix, |compiler| compiler.compile_delegates(prefix))?;
 
         for child in info.children[prefix_end..suffix_begin].iter() {
             self.visit(child, true)?;
         }
 
-        self.compile_delegates(&info.children[suffix_begin..])
+        let suffix = &info.children[suffix_begin..];
+        self.with_span(suffix, |compiler| compiler.compile_delegates(suffix))
+    }
+
+    /// Compile an iteration of a repeat with at most `hi` iterations. For
+    /// `RepeatedGroups::JavaScript`, the groups inside it are reset first.
+    fn visit_iteration(&mut self, info: &Info<'_>, hi: usize, hard: bool) -> Result<()> {
+        let start = self.group_index(info.start_group);
+        let end = self.group_index(info.end_group);
+        let reset = self.options.repeated_groups == RepeatedGroups::JavaScript;
+        if reset && hi > 1 && start < end {
+            self.b.add(Insn::ResetGroups { start, end });
+        }
+        self.visit(info, hard)
     }
 
     fn compile_repeat(
@@ -247,6 +543,14 @@ impl Compiler {
         hard: bool,
     ) -> Result<()> {
         let child = &info.children[0];
+        let (lo, hi) = match *child.expr {
+            // Repeating an assertion doesn't change whether it matches, so like PCRE, a repeated
+            // look-around is matched once, or is optional when the repeat allows zero times
+            Expr::LookAround(..) if hi == 0 => return Ok(()),
+            Expr::LookAround(..) if lo > 0 => return self.visit(child, hard),
+            Expr::LookAround(..) => (0, 1),
+            _ => (lo, hi),
+        };
         if lo == 0 && hi == 1 {
             // e?
             let pc = self.b.pc();
@@ -281,7 +585,7 @@ impl Compiler {
                     check,
                 });
             }
-            self.visit(child, hard)?;
+            self.visit_iteration(child, hi, hard)?;
             self.b.add(Insn::Jmp(pc));
             let next_pc = self.b.pc();
             self.b.set_repeat_target(pc, next_pc);
@@ -289,14 +593,14 @@ impl Compiler {
             // e*
             let pc = self.b.pc();
             self.b.add(Insn::Split(pc + 1, pc + 1));
-            self.visit(child, hard)?;
+            self.visit_iteration(child, hi, hard)?;
             self.b.add(Insn::Jmp(pc));
             let next_pc = self.b.pc();
             self.b.set_split_target(pc, next_pc, greedy);
         } else if lo == 1 && hi == usize::MAX {
             // e+
             let pc = self.b.pc();
-            self.visit(child, hard)?;
+            self.visit_iteration(child, hi, hard)?;
             let next = self.b.pc() + 1;
             let (x, y) = if greedy { (pc, next) } else { (next, pc) };
             self.b.add(Insn::Split(x, y));
@@ -319,7 +623,7 @@ impl Compiler {
                     repeat,
                 });
             }
-            self.visit(child, hard)?;
+            self.visit_iteration(child, hi, hard)?;
             self.b.add(Insn::Jmp(pc));
             let next_pc = self.b.pc();
             self.b.set_repeat_target(pc, next_pc);
@@ -390,19 +694,26 @@ impl Compiler {
     fn compile_lookaround_inner(&mut self, inner: &Info<'_>, la: LookAround) -> Result<()> {
         if la == LookBehind || la == LookBehindNeg {
             if !inner.const_size {
-                return Err(Error::LookBehindNotConst);
+                // Match the body right-to-left, starting at the current position
+                let reverse = self.reverse;
+                self.reverse = true;
+                let result = self.visit(inner, false);
+                self.reverse = reverse;
+                return result;
             }
             self.b.add(Insn::GoBack(inner.min_size));
         }
-        self.visit(inner, false)
+        let reverse = self.reverse;
+        self.reverse = false;
+        let result = self.visit(inner, false);
+        self.reverse = reverse;
+        result
     }
 
     fn compile_delegates(&mut self, infos: &[Info<'_>]) -> Result<()> {
         if infos.is_empty() {
             return Ok(());
         }
-        // TODO: might want to do something similar for case insensitive literals
-        // (have is_literal return an additional bool for casei)
         if infos.iter().all(|e| e.is_literal()) {
             let mut val = String::new();
             for info in infos {
@@ -411,12 +722,20 @@ impl Compiler {
             self.b.add(Insn::Lit(val));
             return Ok(());
         }
+        if infos.iter().all(|e| e.is_literal_casei()) {
+            let mut val = String::new();
+            for info in infos {
+                info.push_literal(&mut val);
+            }
+            self.b.add(Insn::LitCasei(simple_fold_str(&val)));
+            return Ok(());
+        }
 
         let mut delegate_builder = DelegateBuilder::new();
         for info in infos {
             delegate_builder.push(info);
         }
-        let delegate = delegate_builder.build(&self.options)?;
+        let delegate = delegate_builder.build(self)?;
 
         self.b.add(delegate);
         Ok(())
@@ -427,34 +746,260 @@ impl Compiler {
             let mut val = String::new();
             info.push_literal(&mut val);
             Insn::Lit(val)
+        } else if info.is_literal_casei() {
+            let mut val = String::new();
+            info.push_literal(&mut val);
+            Insn::LitCasei(simple_fold_str(&val))
         } else {
-            DelegateBuilder::new().push(info).build(&self.options)?
+            DelegateBuilder::new().push(info).build(self)?
         };
         self.b.add(insn);
         Ok(())
     }
+
+    /// Make a lazily compiled regex for `inner_re`, or reuse it if it was made before. `source` is
+    /// the part of the pattern it was made from.
+    fn compile_shared(&mut self, inner_re: &str, source: &str) -> Arc<LazyRegex> {
+        if let Some(compiled) = self.delegates.get(inner_re) {
+            return compiled.clone();
+        }
+        let compiled = Arc::new(LazyRegex::new(inner_re, source, &self.options));
+        self.delegates
+            .insert(inner_re.to_string(), compiled.clone());
+        compiled
+    }
 }
 
-pub(crate) fn compile_inner(inner_re: &str, options: &RegexOptions) -> Result<regex::Regex> {
-    let mut builder = regex::RegexBuilder::new(inner_re);
-    if let Some(size_limit) = options.delegate_size_limit {
-        builder.size_limit(size_limit);
+/// Whether `info` contains a backref to `group`.
+fn has_backref(info: &Info<'_>, group: usize) -> bool {
+    match *info.expr {
+        Expr::Backref { group: target, .. } => target == group,
+        _ => info.children.iter().any(|child| has_backref(child, group)),
     }
-    if let Some(dfa_size_limit) = options.delegate_dfa_size_limit {
-        builder.dfa_size_limit(dfa_size_limit);
+}
+
+/// Compile a regex that is delegated to the regex crate, with the delegate limits of `options`.
+///
+/// If that fails, the error has the span of `source` in the pattern, if it occurs in it as
+/// written. That's usually the case for the parts of the pattern that are delegated, because
+/// they don't contain any fancy syntax.
+pub(crate) fn compile_inner(
+    inner_re: &str,
+    source: &str,
+    options: &RegexOptions,
+) -> Result<regex::Regex> {
+    LazyRegex::new(inner_re, source, options).build()
+}
+
+/// A regex that is delegated to the regex crate, which is only compiled when it's first used
+/// for matching (or by `Regex::compile_all`). Building a regex with many delegates is cheap
+/// that way, which helps when lots of regexes are built but only a few of them are used.
+pub struct LazyRegex {
+    pattern: String,
+    /// The span in the fancy pattern of the part that this was made from, for errors
+    span: Option<Range<usize>>,
+    size_limit: Option<usize>,
+    dfa_size_limit: Option<usize>,
+    compiled: OnceCell<regex::Regex>,
+}
+
+impl LazyRegex {
+    fn new(inner_re: &str, source: &str, options: &RegexOptions) -> LazyRegex {
+        LazyRegex {
+            pattern: inner_re.to_string(),
+            span: options
+                .pattern
+                .find(source)
+                .map(|start| start..start + source.len()),
+            size_limit: options.delegate_size_limit,
+            dfa_size_limit: options.delegate_dfa_size_limit,
+            compiled: OnceCell::new(),
+        }
+    }
+
+    /// Make a regex for a delegate instruction of a program built with
+    /// [`Prog::from_insns`](../prog/struct.Prog.html#method.from_insns). The pattern is matched
+    /// against the text from the current position on, so it usually starts with `^`.
+    pub fn from_pattern(pattern: &str) -> LazyRegex {
+        LazyRegex {
+            pattern: pattern.to_string(),
+            span: None,
+            size_limit: None,
+            dfa_size_limit: None,
+            compiled: OnceCell::new(),
+        }
+    }
+
+    /// The pattern of the regex.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The span in the fancy pattern of the part that this was made from, if it could be found.
+    pub(crate) fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    /// Whether the regex has been compiled already.
+    pub(crate) fn is_compiled(&self) -> bool {
+        self.compiled.get().is_some()
+    }
+
+    /// Get the compiled regex, compiling it first if it's used for the first time. If compiling
+    /// fails, the error is returned, and the next call tries again.
+    pub(crate) fn get(&self) -> Result<&regex::Regex> {
+        self.compiled.get_or_try_init(|| self.build())
     }
 
-    builder.build().map_err(Error::InnerError)
+    fn build(&self) -> Result<regex::Regex> {
+        let mut builder = regex::RegexBuilder::new(&self.pattern);
+        if let Some(size_limit) = self.size_limit {
+            builder.size_limit(size_limit);
+        }
+        if let Some(dfa_size_limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(dfa_size_limit);
+        }
+
+        builder.build().map_err(|error| {
+            Error::CompileError(CompileError::DelegateError {
+                delegated: self.pattern.clone(),
+                span: self.span.clone(),
+                error,
+            })
+        })
+    }
+}
+
+impl fmt::Debug for LazyRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Same as for `regex::Regex`, which is what this was before it was compiled lazily
+        f.write_str(&self.pattern)
+    }
+}
+
+impl fmt::Display for LazyRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
 }
 
 /// Compile the analyzed expressions into a program.
 pub fn compile(info: &Info<'_>) -> Result<Prog> {
-    let mut c = Compiler::new(info.end_group);
+    compile_with_options(info, &RegexOptions::default())
+}
+
+/// Compile the analyzed expressions into a program, passing the delegate limits of `options` to
+/// the regex crate.
+pub(crate) fn compile_with_options(info: &Info<'_>, options: &RegexOptions) -> Result<Prog> {
+    compile_sharing(info, options, &mut Delegates::new())
+}
+
+/// Compile the analyzed expressions into a program like `compile_with_options`, reusing the
+/// regexes in `delegates` and adding the new ones to it. They must have been made with the same
+/// delegate limits.
+pub(crate) fn compile_sharing(
+    info: &Info<'_>,
+    options: &RegexOptions,
+    delegates: &mut Delegates,
+) -> Result<Prog> {
+    let mut c = Compiler::new(info.end_group, options);
+    c.delegates = mem::take(delegates);
+    let result = c.visit(info, false);
+    *delegates = c.delegates;
+    result?;
+    c.b.add(Insn::End);
+    Ok(c.b.build())
+}
+
+/// Compile the analyzed expressions into a program that explores all the ways of matching: only
+/// single characters and classes are delegated, everything else is backtracked.
+pub(crate) fn compile_exhaustive(info: &Info<'_>, options: &RegexOptions) -> Result<Prog> {
+    let mut c = Compiler::new(info.end_group, options);
+    c.exhaustive = true;
+    c.visit(info, true)?;
+    c.b.add(Insn::End);
+    Ok(c.b.build())
+}
+
+/// Compile the analyzed expressions into a program that only saves the positions of `groups`,
+/// e.g. for only checking whether there's a match, where just the groups referenced by backrefs
+/// are needed. Only the groups in `groups` get slots, in order, so e.g. if it's just group 3,
+/// its slots are 0 and 1.
+///
+/// The delegate regexes are shared with `prog`, the full program for the same expressions, so
+/// they're only compiled once.
+pub(crate) fn compile_saving_groups(
+    info: &Info<'_>,
+    groups: &BitSet,
+    prog: &Prog,
+    options: &RegexOptions,
+) -> Result<Prog> {
+    let mut c = Compiler::new(groups.len(), options);
+    c.saved_groups = Some(groups.clone());
+    for insn in &prog.body {
+        let (inner, inner1) = match *insn {
+            Insn::Delegate {
+                ref inner,
+                ref inner1,
+                ..
+            }
+            | Insn::DelegateSized {
+                ref inner,
+                ref inner1,
+                ..
+            } => (inner, inner1.as_ref()),
+            _ => continue,
+        };
+        for re in Some(inner).into_iter().chain(inner1) {
+            c.delegates.insert(re.as_str().to_string(), re.clone());
+        }
+    }
     c.visit(info, false)?;
     c.b.add(Insn::End);
     Ok(c.b.build())
 }
 
+/// Compile the analyzed expressions into a program like `compile`, which also records which
+/// alternative of the top-level alternation matched. Returns the program and the save slot
+/// with the index of the alternative (`0` if the expression is not an alternation).
+pub(crate) fn compile_recording_branch(
+    info: &Info<'_>,
+    options: &RegexOptions,
+    delegates: &mut Delegates,
+) -> Result<(Prog, usize)> {
+    let mut c = Compiler::new(info.end_group, options);
+    c.delegates = mem::take(delegates);
+    let result = c.compile_recording_branch(info);
+    *delegates = c.delegates;
+    let slot = result?;
+    Ok((c.b.build(), slot))
+}
+
+impl Compiler {
+    fn compile_recording_branch(&mut self, info: &Info<'_>) -> Result<usize> {
+        let slot = self.b.newsave();
+        // The wrapper from `Regex::new_options`: `.*?` for searching, then group 0 for the match
+        let (search, group) = (&info.children[0], &info.children[1]);
+        self.visit(search, true)?;
+        self.b.add(Insn::Save(0));
+        let inner = &group.children[0];
+        // Nothing after the alternation can fail, so the alternatives don't have to be hard
+        match *inner.expr {
+            Expr::Alt(_) => self.compile_alt(inner.children.len(), |compiler, i| {
+                compiler.b.add(Insn::SaveValue { slot, value: i });
+                compiler.visit(&inner.children[i], false)
+            })?,
+            _ => {
+                self.b.add(Insn::SaveValue { slot, value: 0 });
+                self.visit(inner, false)?;
+            }
+        }
+        self.b.add(Insn::Save(1));
+        self.b.add(Insn::End);
+        Ok(slot)
+    }
+}
+
 struct DelegateBuilder {
     re: String,
     min_size: usize,
@@ -502,30 +1047,35 @@ impl DelegateBuilder {
         self
     }
 
-    fn build(&self, options: &RegexOptions) -> Result<Insn> {
+    fn build(&self, compiler: &mut Compiler) -> Result<Insn> {
         let start_group = self.start_group.expect("Expected at least one expression");
-        let end_group = self.end_group;
+        let groups: Vec<(usize, usize)> = (start_group..self.end_group)
+            .enumerate()
+            .filter(|&(_, group)| compiler.saves_group(group))
+            .map(|(i, group)| (i + 1, compiler.group_index(group) * 2))
+            .collect();
 
-        let compiled = compile_inner(&self.re, options)?;
-        if self.looks_left {
+        // Without the `^` it's the expressions as they would be written in the pattern
+        let source = &self.re[1..];
+        let compiled = compiler.compile_shared(&self.re, source);
+        let compiled1 = if self.looks_left {
             // The "s" flag is for allowing `.` to match `\n`
-            let inner1 = ["^(?s:.)", &self.re[1..]].concat();
-            let compiled1 = compile_inner(&inner1, options)?;
-            Ok(Insn::Delegate {
-                inner: Box::new(compiled),
-                inner1: Some(Box::new(compiled1)),
-                start_group,
-                end_group,
+            let inner1 = ["^(?s:.)", source].concat();
+            Some(compiler.compile_shared(&inner1, source))
+        } else {
+            None
+        };
+        if self.const_size && groups.is_empty() {
+            Ok(Insn::DelegateSized {
+                inner: compiled,
+                inner1: compiled1,
+                size: self.min_size,
             })
-        } else if self.const_size && start_group == end_group {
-            let size = self.min_size;
-            Ok(Insn::DelegateSized(Box::new(compiled), size))
         } else {
             Ok(Insn::Delegate {
-                inner: Box::new(compiled),
-                inner1: None,
-                start_group,
-                end_group,
+                inner: compiled,
+                inner1: compiled1,
+                groups,
             })
         }
     }
@@ -560,10 +1110,11 @@ mod tests {
             ]),
             backrefs: BitSet::new(),
             named_groups: Default::default(),
+            group_spans: Vec::new(),
         };
         let info = analyze(&tree).unwrap();
 
-        let mut c = Compiler::new(0);
+        let mut c = Compiler::new(0, &RegexOptions::default());
         // Force "hard" so that compiler doesn't just delegate
         c.visit(&info, true).unwrap();
         c.b.add(Insn::End);
@@ -634,6 +1185,135 @@ mod tests {
         assert_matches!(prog[8], End);
     }
 
+    #[test]
+    fn sized_delegate_looking_left() {
+        let prog = compile_prog(r"(?=a)\ba");
+
+        let delegate = prog
+            .iter()
+            .find(|insn| matches!(insn, DelegateSized { .. }))
+            .unwrap_or_else(|| panic!("prog: {:?}", prog));
+        match delegate {
+            DelegateSized {
+                inner1: Some(inner1),
+                size,
+                ..
+            } => {
+                assert!(inner1.as_str().starts_with("^(?s:.)"), "{}", inner1);
+                assert_eq!(*size, 1);
+            }
+            _ => panic!("Expected inner1 for looking left: {:?}", delegate),
+        }
+    }
+
+    #[test]
+    fn identical_delegates_are_shared() {
+        let prog = compile_prog(r"\s*(?=a)[a-z]\s*(?=b)[a-z]");
+
+        let delegates: Vec<_> = prog
+            .iter()
+            .filter_map(|insn| match insn {
+                Insn::DelegateSized { inner, .. } => Some(inner),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(delegates.len(), 4);
+        assert_eq!(delegates[0].as_str(), r"^\s");
+        assert!(Arc::ptr_eq(delegates[0], delegates[2]));
+        assert_eq!(delegates[1].as_str(), "^[a-z]");
+        assert!(Arc::ptr_eq(delegates[1], delegates[3]));
+    }
+
+    #[test]
+    fn repeated_backref_checks_for_empty_iterations() {
+        // The group can match empty, so the loop must not repeat without making progress
+        let prog = compile_anchored(r"(a*)\1{2,}").body;
+
+        let pc = prog
+            .iter()
+            .position(|insn| matches!(insn, RepeatEpsilonGr { lo: 2, .. }))
+            .unwrap_or_else(|| panic!("prog: {:?}", prog));
+        assert_matches!(prog[pc + 1], Backref(2));
+        assert_matches!(prog[pc + 2], Jmp(target) if target == pc);
+        assert_matches!(prog[pc + 3], Save(1));
+        assert_matches!(prog[pc + 4], End);
+    }
+
+    #[test]
+    fn match_only_skips_unreferenced_groups() {
+        let tree = Expr::parse_tree(r"(a)(?=(b+)c)(\w)\2").unwrap();
+        let info = analyze(&tree).unwrap();
+        let full = compile(&info).unwrap();
+        let prog =
+            compile_saving_groups(&info, &tree.backrefs, &full, &RegexOptions::default()).unwrap();
+
+        // Only the referenced group has slots, and they come first
+        assert_eq!(prog.n_saves, 3, "prog: {:?}", prog.body);
+        assert_eq!(prog.body.len(), 9, "prog: {:?}", prog.body);
+        assert_delegate_sized(&prog.body[0], "^(a)");
+        assert_matches!(prog.body[1], Save(2));
+        assert_matches!(prog.body[2], Delegate { ref groups, .. } if groups.is_empty());
+        assert_matches!(prog.body[3], Restore(2));
+        assert_matches!(prog.body[4], Save(0));
+        assert_delegate_sized(&prog.body[5], r"^\w");
+        assert_matches!(prog.body[6], Save(1));
+        assert_matches!(prog.body[7], Backref(0));
+        assert_matches!(prog.body[8], End);
+    }
+
+    #[test]
+    fn match_only_without_backrefs_has_no_group_slots() {
+        let tree = Expr::parse_tree(r"(a+)(?=b)(?<g>c)").unwrap();
+        let info = analyze(&tree).unwrap();
+        let full = compile(&info).unwrap();
+        let prog =
+            compile_saving_groups(&info, &tree.backrefs, &full, &RegexOptions::default()).unwrap();
+
+        assert_eq!(prog.n_saves, 1, "prog: {:?}", prog.body);
+        // The only slot is for the position before the look-ahead
+        assert_eq!(prog.body.len(), 7, "prog: {:?}", prog.body);
+        assert_matches!(prog.body[2], Save(0));
+        assert_matches!(prog.body[4], Restore(0));
+        assert_delegate_sized(&prog.body[5], "^(c)");
+    }
+
+    #[test]
+    fn one_pass_start() {
+        assert_eq!(compile_anchored(r"(a)(?=b)(\w)\1").one_pass_start, 0);
+        // Only after the alternation
+        let prog = compile_anchored(r"(a)(?:b(?=c)|d)\1");
+        assert_matches!(
+            prog.body[prog.one_pass_start..],
+            [Lit(_), Backref(2), Save(1), End]
+        );
+        // Only the end after the loop
+        let prog = compile_anchored(r"(a)\1+");
+        assert_matches!(prog.body[prog.one_pass_start..], [Save(1), End]);
+    }
+
+    #[test]
+    fn tree_without_pattern_has_no_spans() {
+        let tree = Expr::parse_tree(r"(\w)\1").unwrap();
+        let tree = ExprTree {
+            expr: Expr::Group(Box::new(tree.expr)),
+            ..tree
+        };
+        let prog = compile(&analyze(&tree).unwrap()).unwrap();
+        assert!(prog.spans.iter().all(Option::is_none), "{:?}", prog.spans);
+    }
+
+    #[test]
+    fn casei_literal_is_not_delegated() {
+        let prog = compile_prog("(?!x)(?i:Ab\u{1E9E}\u{212A})");
+
+        assert_eq!(prog.len(), 5, "prog: {:?}", prog);
+        assert_matches!(prog[0], Split(1, 3));
+        assert_matches!(prog[1], Lit(ref l) if l == "x");
+        assert_matches!(prog[2], FailNegativeLookAround);
+        assert_matches!(prog[3], LitCasei(ref l) if l == "abßk");
+        assert_matches!(prog[4], End);
+    }
+
     fn compile_prog(re: &str) -> Vec<Insn> {
         let tree = Expr::parse_tree(re).unwrap();
         let info = analyze(&tree).unwrap();
@@ -641,6 +1321,16 @@ mod tests {
         prog.body
     }
 
+    /// Compile like a regex that only matches at the start, with group 0 for the match, so that
+    /// backrefs refer to the right groups.
+    fn compile_anchored(re: &str) -> Prog {
+        let options = RegexOptions {
+            pattern: re.to_string(),
+            ..RegexOptions::default()
+        };
+        crate::compile_anchored(&options).unwrap()
+    }
+
     fn assert_delegate(insn: &Insn, re: &str) {
         match insn {
             Insn::Delegate { inner, .. } => {
@@ -654,7 +1344,7 @@ mod tests {
 
     fn assert_delegate_sized(insn: &Insn, re: &str) {
         match insn {
-            Insn::DelegateSized(inner, ..) => {
+            Insn::DelegateSized { inner, .. } => {
                 assert_eq!(inner.as_str(), re);
             }
             _ => {
@@ -0,0 +1,191 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Limits and tracing for a single run of a regex, see `RunConfig`.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Receives the lines of a trace of the backtracking VM.
+pub(crate) type TraceSink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Configuration for running a regex, for the `*_with_config` methods of
+/// [`Regex`](struct.Regex.html).
+///
+/// Unlike the options of [`RegexBuilder`](struct.RegexBuilder.html), these can be different for
+/// each call. So the same `Regex` can be used with strict limits on untrusted input and with the
+/// defaults elsewhere.
+///
+/// The limits only apply to regexes that need backtracking. Regexes that are delegated to the
+/// regex crate entirely run in linear time and ignore them.
+///
+/// # Example
+///
+/// ```rust
+/// use fancy_regex::{Error, Regex, RunConfig, RuntimeError};
+///
+/// let re = Regex::new(r"(?i)(a|b|ab)*(?=c)").unwrap();
+/// let mut config = RunConfig::new();
+/// config.step_limit(1_000);
+///
+/// let untrusted = "ab".repeat(20);
+/// match re.is_match_with_config(&untrusted, &config) {
+///     Err(Error::RuntimeError(RuntimeError::StepLimitExceeded)) => {}
+///     result => panic!("{:?}", result),
+/// }
+/// assert!(re.is_match_with_config("abc", &config).unwrap());
+/// ```
+#[derive(Clone)]
+pub struct RunConfig {
+    pub(crate) max_stack: usize,
+    pub(crate) step_limit: Option<usize>,
+    pub(crate) auto_step_factor: Option<usize>,
+    pub(crate) trace_sink: Option<TraceSink>,
+    pub(crate) deadline: Option<Instant>,
+    /// Only matches that end at or before the position are accepted, or exactly at it if the
+    /// flag is set, see `Regex::find_in`
+    pub(crate) end_bound: Option<(usize, bool)>,
+    /// Set when searching a byte string: the position in the text of each U+FFFD that replaces
+    /// invalid UTF-8, with the number of bytes it replaces. `AnyByte` matches single bytes then,
+    /// see `byte_str::Text`
+    pub(crate) byte_string: Option<Arc<[(usize, usize)]>>,
+}
+
+impl RunConfig {
+    /// Create a configuration with the default limits, no deadline and no tracing.
+    pub fn new() -> RunConfig {
+        RunConfig {
+            max_stack: std::usize::MAX,
+            step_limit: None,
+            auto_step_factor: None,
+            trace_sink: None,
+            deadline: None,
+            end_bound: None,
+            byte_string: None,
+        }
+    }
+
+    /// Limit the number of backtrack branches that can be pending at the same time. If it's
+    /// exceeded, [`RuntimeError::StackOverflow`](enum.RuntimeError.html#variant.StackOverflow) is returned.
+    ///
+    /// By default, the number of branches is not limited by itself, only the memory they take up
+    /// is, see [`RegexBuilder::memory_limit`](struct.RegexBuilder.html#method.memory_limit).
+    pub fn max_stack(&mut self, limit: usize) -> &mut Self {
+        self.max_stack = limit;
+        self
+    }
+
+    /// Limit the number of instructions the VM executes in a run. If it's exceeded,
+    /// [`RuntimeError::StepLimitExceeded`](enum.RuntimeError.html#variant.StepLimitExceeded) is returned.
+    ///
+    /// Searching runs the VM at each position where a match can start, each of those runs has
+    /// its own count. By default, the number of steps is unlimited.
+    pub fn step_limit(&mut self, limit: usize) -> &mut Self {
+        self.step_limit = Some(limit);
+        self
+    }
+
+    /// Limit the number of instructions the VM executes in a run to `factor` times the length of
+    /// the text (plus one) times the number of instructions of the program. If it's exceeded,
+    /// [`RuntimeError::AutoStepLimitExceeded`](enum.RuntimeError.html#variant.AutoStepLimitExceeded)
+    /// is returned, with the limit and the numbers it was computed from.
+    ///
+    /// Unlike [`step_limit`](#method.step_limit), this doesn't need a number picked for each
+    /// pattern and input size: a match that runs in time linear in the text stays within the
+    /// limit, while catastrophic backtracking on untrusted patterns is cut off. The length is
+    /// counted from the position where the run starts. If both limits are set, the smaller one
+    /// applies.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use fancy_regex::{Error, Regex, RunConfig, RuntimeError};
+    ///
+    /// let re = Regex::new(r"(?i)(a|b|ab)*(?=c)").unwrap();
+    /// let mut config = RunConfig::new();
+    /// config.auto_step_limit(4);
+    ///
+    /// assert!(re.is_match_with_config("ababc", &config).unwrap());
+    /// match re.is_match_with_config(&"ab".repeat(20), &config) {
+    ///     Err(Error::RuntimeError(RuntimeError::AutoStepLimitExceeded { text_len, .. })) => {
+    ///         assert_eq!(text_len, 40);
+    ///     }
+    ///     result => panic!("{:?}", result),
+    /// }
+    /// ```
+    pub fn auto_step_limit(&mut self, factor: usize) -> &mut Self {
+        self.auto_step_factor = Some(factor);
+        self
+    }
+
+    /// The limit set by `auto_step_limit` for a text of `text_len` bytes and a program of
+    /// `instructions` instructions.
+    pub(crate) fn auto_step_limit_for(
+        &self,
+        text_len: usize,
+        instructions: usize,
+    ) -> Option<usize> {
+        self.auto_step_factor.map(|factor| {
+            factor
+                .saturating_mul(text_len.saturating_add(1))
+                .saturating_mul(instructions)
+        })
+    }
+
+    /// Stop running the VM at `deadline`, returning
+    /// [`RuntimeError::DeadlineExceeded`](enum.RuntimeError.html#variant.DeadlineExceeded).
+    ///
+    /// The time is only checked every few hundred steps, so the run can take slightly longer.
+    pub fn deadline(&mut self, deadline: Instant) -> &mut Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Trace the instructions that the VM executes, and its backtrack stack and saves, by calling
+    /// `sink` with each line of output. This is meant for debugging.
+    pub fn trace_sink<F>(&mut self, sink: F) -> &mut Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.trace_sink = Some(Arc::new(sink));
+        self
+    }
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        RunConfig::new()
+    }
+}
+
+impl fmt::Debug for RunConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunConfig")
+            .field("max_stack", &self.max_stack)
+            .field("step_limit", &self.step_limit)
+            .field("auto_step_factor", &self.auto_step_factor)
+            .field("trace_sink", &self.trace_sink.is_some())
+            .field("deadline", &self.deadline)
+            .field("end_bound", &self.end_bound)
+            .field("byte_string", &self.byte_string.is_some())
+            .finish()
+    }
+}
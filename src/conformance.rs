@@ -0,0 +1,614 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Running conformance tests in the format of PCRE2's test files, enabled with the `conformance`
+//! feature.
+//!
+//! A corpus is in the format of PCRE2's `testoutput` files: a pattern between delimiters with
+//! optional modifiers, followed by subject lines, each followed by the expected captures or
+//! `No match`:
+//!
+//! ```text
+//! /(a+)(b)?/i
+//!     xAAy
+//!  0: AA
+//!  1: AA
+//!     xyz
+//! No match
+//! ```
+//!
+//! Groups that didn't match are written as `<unset>`, and groups after the last one that matched
+//! are left out. Subjects and captures can use escapes like `\n` and `\x{e9}`. Lines starting
+//! with `#` and `\=` comments are ignored, and so are patterns that PCRE2 fails to compile
+//! (`Failed: ...`).
+//!
+//! # Example
+//!
+//! ```rust
+//! use fancy_regex::conformance::Corpus;
+//!
+//! let corpus = Corpus::parse("/(\\w)\\1/\n    aabb\n 0: aa\n 1: a\n").unwrap();
+//! let report = corpus.run();
+//! assert_eq!(report.passed, 1);
+//! assert!(report.failures.is_empty());
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use crate::Regex;
+
+/// A set of test cases, parsed from PCRE2's test format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Corpus {
+    cases: Vec<Case>,
+}
+
+/// A pattern with a subject and the expected result of matching it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Case {
+    /// The line of the subject in the corpus, starting at 1
+    pub line: usize,
+    /// The pattern, without delimiters
+    pub pattern: String,
+    /// The modifiers after the pattern, e.g. `i` or `x`
+    pub modifiers: String,
+    /// The subject, with escapes resolved
+    pub subject: String,
+    /// The expected result
+    pub expected: Outcome,
+}
+
+/// The result of matching a subject.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The subject matched, with the text of each group (`None` if a group didn't match). Trailing
+    /// groups that didn't match are left out.
+    Match(Vec<Option<String>>),
+    /// The subject didn't match
+    NoMatch,
+    /// The pattern couldn't be compiled or matching failed, with the error message
+    Error(String),
+}
+
+/// The results of running a corpus.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Number of cases with the expected result
+    pub passed: usize,
+    /// Cases with a different result than expected
+    pub failures: Vec<Failure>,
+    /// Cases that weren't run, with the reason, e.g. a modifier that isn't supported
+    pub skipped: Vec<(Case, String)>,
+}
+
+/// A case with a different result than expected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    /// The case that failed
+    pub case: Case,
+    /// The result of fancy-regex
+    pub actual: Outcome,
+}
+
+/// An error in the format of a corpus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// The line of the error, starting at 1
+    pub line: usize,
+    /// What's wrong
+    pub message: String,
+}
+
+impl Corpus {
+    /// Parse a corpus in the format of PCRE2's `testoutput` files.
+    ///
+    /// Modifiers after a subject (`\=...`) are added to the modifiers of the pattern for that
+    /// subject.
+    pub fn parse(input: &str) -> Result<Corpus, SyntaxError> {
+        let mut cases = Vec::new();
+        let mut lines = input.lines().enumerate().map(|(ix, line)| (ix + 1, line));
+        // The current pattern with its modifiers, the subject waiting for its result and the
+        // captures of the subject so far
+        let mut pattern: Option<(String, String)> = None;
+        let mut subject: Option<Subject> = None;
+        let mut groups: Vec<Option<String>> = Vec::new();
+
+        while let Some((line_number, line)) = lines.next() {
+            let error = |message: &str| SyntaxError {
+                line: line_number,
+                message: message.to_string(),
+            };
+            let trimmed = line.trim();
+            let group = parse_group_line(trimmed);
+            if group.is_none() && !groups.is_empty() {
+                let expected = Outcome::Match(std::mem::take(&mut groups));
+                cases.push(subject.take().unwrap().into_case(&pattern, expected));
+            }
+
+            if let Some((index, text)) = group {
+                if subject.is_none() {
+                    return Err(error("captures without a subject"));
+                }
+                if index != groups.len() {
+                    return Err(error("captures out of order"));
+                }
+                groups.push(match text {
+                    "<unset>" => None,
+                    text => Some(unescape(text).map_err(error)?),
+                });
+            } else if trimmed == "No match" {
+                match subject.take() {
+                    Some(subject) => cases.push(subject.into_case(&pattern, Outcome::NoMatch)),
+                    None => return Err(error("result without a subject")),
+                }
+            } else if let Some(subject) = &subject {
+                return Err(subject.missing_result());
+            } else if trimmed.is_empty() {
+                pattern = None;
+            } else if trimmed.starts_with('#') || trimmed.starts_with("\\=") {
+                // Directive or comment
+            } else if pattern.is_none() {
+                pattern = Some(parse_pattern(line, line_number, &mut lines)?);
+            } else if trimmed.starts_with("Failed: ") {
+                // PCRE2 couldn't compile the pattern, so there are no subjects
+            } else {
+                let (text, modifiers) = match trimmed.find("\\=") {
+                    Some(ix) => (&trimmed[..ix], &trimmed[ix + 2..]),
+                    None => (trimmed, ""),
+                };
+                subject = Some(Subject {
+                    line: line_number,
+                    text: unescape(text).map_err(error)?,
+                    modifiers: modifiers.to_string(),
+                });
+            }
+        }
+        match subject {
+            Some(subject) if groups.is_empty() => Err(subject.missing_result()),
+            Some(subject) => {
+                cases.push(subject.into_case(&pattern, Outcome::Match(groups)));
+                Ok(Corpus { cases })
+            }
+            None => Ok(Corpus { cases }),
+        }
+    }
+
+    /// The cases of the corpus, in the order of the input.
+    pub fn cases(&self) -> &[Case] {
+        &self.cases
+    }
+
+    /// Run all cases and report which ones have the expected result.
+    ///
+    /// Each pattern is compiled once for all of its subjects.
+    pub fn run(&self) -> Report {
+        let mut report = Report::default();
+        let mut compiled: Option<(String, Result<Regex, String>)> = None;
+        for case in &self.cases {
+            let flags = match inline_flags(&case.modifiers) {
+                Ok(flags) => flags,
+                Err(reason) => {
+                    report.skipped.push((case.clone(), reason));
+                    continue;
+                }
+            };
+            let source = format!("{}{}", flags, case.pattern);
+            if compiled.as_ref().map_or(true, |(s, _)| *s != source) {
+                let regex = Regex::new(&source).map_err(|e| e.to_string());
+                compiled = Some((source, regex));
+            }
+            let regex = &compiled.as_ref().unwrap().1;
+            let actual = match regex {
+                Ok(regex) => outcome(regex, &case.subject),
+                Err(e) => Outcome::Error(e.clone()),
+            };
+            if actual == case.expected {
+                report.passed += 1;
+            } else {
+                report.failures.push(Failure {
+                    case: case.clone(),
+                    actual,
+                });
+            }
+        }
+        report
+    }
+}
+
+impl Case {
+    /// Run the case, returning the result of fancy-regex.
+    pub fn run(&self) -> Outcome {
+        match inline_flags(&self.modifiers) {
+            Ok(flags) => match Regex::new(&format!("{}{}", flags, self.pattern)) {
+                Ok(regex) => outcome(&regex, &self.subject),
+                Err(e) => Outcome::Error(e.to_string()),
+            },
+            Err(reason) => Outcome::Error(reason),
+        }
+    }
+}
+
+/// A subject line, waiting for its expected result.
+struct Subject {
+    line: usize,
+    text: String,
+    modifiers: String,
+}
+
+impl Subject {
+    fn into_case(self, pattern: &Option<(String, String)>, expected: Outcome) -> Case {
+        // A subject is only parsed after a pattern
+        let (pattern, pattern_modifiers) = pattern.clone().unwrap();
+        let modifiers = match (pattern_modifiers.is_empty(), self.modifiers.is_empty()) {
+            (_, true) => pattern_modifiers,
+            (true, false) => self.modifiers,
+            (false, false) => format!("{},{}", pattern_modifiers, self.modifiers),
+        };
+        Case {
+            line: self.line,
+            pattern,
+            modifiers,
+            subject: self.text,
+            expected,
+        }
+    }
+
+    fn missing_result(&self) -> SyntaxError {
+        SyntaxError {
+            line: self.line,
+            message: "subject without expected result".to_string(),
+        }
+    }
+}
+
+impl Report {
+    /// Whether all cases that were run had the expected result.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+fn outcome(regex: &Regex, subject: &str) -> Outcome {
+    match regex.captures(subject) {
+        Ok(Some(caps)) => {
+            let mut groups: Vec<Option<String>> = caps
+                .iter()
+                .map(|m| m.map(|m| m.as_str().to_string()))
+                .collect();
+            while let Some(None) = groups.last() {
+                groups.pop();
+            }
+            Outcome::Match(groups)
+        }
+        Ok(None) => Outcome::NoMatch,
+        Err(e) => Outcome::Error(e.to_string()),
+    }
+}
+
+/// Parse a line like ` 1: abc`, returning the group number and the text.
+fn parse_group_line(line: &str) -> Option<(usize, &str)> {
+    let colon = line.find(':')?;
+    let index = line[..colon].parse().ok()?;
+    let text = &line[colon + 1..];
+    if text.is_empty() {
+        Some((index, text))
+    } else if text.starts_with(' ') {
+        Some((index, &text[1..]))
+    } else {
+        None
+    }
+}
+
+/// Parse a pattern with its delimiters and modifiers, which can continue on the following lines
+/// if the closing delimiter is missing.
+fn parse_pattern<'a>(
+    line: &str,
+    line_number: usize,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Result<(String, String), SyntaxError> {
+    let mut chars = line.trim_start().chars();
+    let delimiter = chars.next().unwrap();
+    if delimiter.is_alphanumeric() || delimiter == '\\' || delimiter.is_whitespace() {
+        return Err(SyntaxError {
+            line: line_number,
+            message: format!("invalid pattern delimiter {:?}", delimiter),
+        });
+    }
+    let mut pattern = String::new();
+    let mut rest = chars.as_str().to_string();
+    loop {
+        let mut chars = rest.char_indices();
+        while let Some((ix, c)) = chars.next() {
+            if c == delimiter {
+                pattern.push_str(&rest[..ix]);
+                let modifiers = rest[ix + c.len_utf8()..].trim().to_string();
+                return Ok((pattern, modifiers));
+            } else if c == '\\' {
+                chars.next();
+            }
+        }
+        pattern.push_str(&rest);
+        pattern.push('\n');
+        rest = match lines.next() {
+            Some((_, line)) => line.to_string(),
+            None => {
+                return Err(SyntaxError {
+                    line: line_number,
+                    message: "pattern without closing delimiter".to_string(),
+                })
+            }
+        };
+    }
+}
+
+/// Translate modifiers to inline flags, or return why they aren't supported.
+fn inline_flags(modifiers: &str) -> Result<String, String> {
+    let mut flags = String::new();
+    for modifier in modifiers.split(',').map(str::trim) {
+        match modifier {
+            "" => {}
+            "caseless" => flags.push('i'),
+            "multiline" => flags.push('m'),
+            "dotall" => flags.push('s'),
+            "extended" => flags.push('x'),
+            _ if modifier.chars().all(|c| "imsx".contains(c)) => flags.push_str(modifier),
+            _ => return Err(format!("unsupported modifier `{}`", modifier)),
+        }
+    }
+    if flags.is_empty() {
+        Ok(flags)
+    } else {
+        Ok(format!("(?{})", flags))
+    }
+}
+
+/// Resolve the escapes that PCRE2 uses in subjects and in its output.
+fn unescape(s: &str) -> Result<String, &'static str> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let c = match chars.next() {
+            Some('a') => '\x07',
+            Some('b') => '\x08',
+            Some('e') => '\x1b',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\x0b',
+            Some('x') => {
+                let mut hex = String::new();
+                if chars.peek() == Some(&'{') {
+                    chars.next();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => hex.push(c),
+                            None => return Err("unclosed \\x{...} escape"),
+                        }
+                    }
+                } else {
+                    while hex.len() < 2 && chars.peek().map_or(false, char::is_ascii_hexdigit) {
+                        hex.push(chars.next().unwrap());
+                    }
+                }
+                u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or("invalid \\x escape")?
+            }
+            Some(c @ '0'..='7') => {
+                let mut octal = c.to_string();
+                while octal.len() < 3 && chars.peek().map_or(false, |c| ('0'..='7').contains(c)) {
+                    octal.push(chars.next().unwrap());
+                }
+                std::char::from_u32(u32::from_str_radix(&octal, 8).unwrap()).unwrap()
+            }
+            Some(c) => c,
+            None => return Err("trailing backslash"),
+        };
+        result.push(c);
+    }
+    Ok(result)
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Match(groups) => {
+                for (i, group) in groups.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match group {
+                        Some(text) => write!(f, "{}: {:?}", i, text)?,
+                        None => write!(f, "{}: <unset>", i)?,
+                    }
+                }
+                Ok(())
+            }
+            Outcome::NoMatch => write!(f, "No match"),
+            Outcome::Error(message) => write!(f, "Error: {}", message),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: pattern {:?}",
+            self.case.line, self.case.pattern
+        )?;
+        if !self.case.modifiers.is_empty() {
+            write!(f, " ({})", self.case.modifiers)?;
+        }
+        write!(
+            f,
+            " on subject {:?}: expected {}, got {}",
+            self.case.subject, self.case.expected, self.actual
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} passed, {} failed, {} skipped",
+            self.passed,
+            self.failures.len(),
+            self.skipped.len()
+        )?;
+        for failure in &self.failures {
+            writeln!(f, "{}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid corpus at line {}: {}", self.line, self.message)
+    }
+}
+
+impl StdError for SyntaxError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS: &str = r"# A comment
+/(a+)(b)?/i
+    xAAy
+ 0: AA
+ 1: AA
+    xyz
+No match
+\= Expect no match
+    b
+No match
+
+/(a)|(b)/
+    b
+ 0: b
+ 1: <unset>
+ 2: b
+    \x{e9}a\n
+ 0: a
+ 1: a
+
+/[/
+Failed: error 106 at offset 1: missing terminating ] for character class
+
+/a
+b/x
+    ab
+ 0: ab
+";
+
+    #[test]
+    fn parse() {
+        let corpus = Corpus::parse(CORPUS).unwrap();
+        let cases = corpus.cases();
+        assert_eq!(cases.len(), 6);
+        assert_eq!(
+            cases[0],
+            Case {
+                line: 3,
+                pattern: "(a+)(b)?".to_string(),
+                modifiers: "i".to_string(),
+                subject: "xAAy".to_string(),
+                expected: Outcome::Match(vec![Some("AA".to_string()), Some("AA".to_string())]),
+            }
+        );
+        assert_eq!(cases[1].expected, Outcome::NoMatch);
+        assert_eq!(cases[2].subject, "b");
+        assert_eq!(
+            cases[3].expected,
+            Outcome::Match(vec![Some("b".to_string()), None, Some("b".to_string())])
+        );
+        assert_eq!(cases[4].subject, "\u{e9}a\n");
+        assert_eq!(cases[5].pattern, "a\nb");
+        assert_eq!(cases[5].modifiers, "x");
+    }
+
+    #[test]
+    fn run() {
+        let report = Corpus::parse(CORPUS).unwrap().run();
+        assert!(report.is_success(), "{}", report);
+        assert_eq!(report.passed, 6);
+        assert_eq!(report.to_string(), "6 passed, 0 failed, 0 skipped\n");
+    }
+
+    #[test]
+    fn failures_and_skipped() {
+        let corpus = Corpus::parse("/a(?=b)/\n    ac\n 0: a\n\n/a/g\n    a\n 0: a\n").unwrap();
+        let report = corpus.run();
+        assert_eq!(report.passed, 0);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].actual, Outcome::NoMatch);
+        assert_eq!(
+            report.failures[0].to_string(),
+            r#"line 2: pattern "a(?=b)" on subject "ac": expected 0: "a", got No match"#
+        );
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].1, "unsupported modifier `g`");
+    }
+
+    #[test]
+    fn compile_error() {
+        let corpus = Corpus::parse("/(?/\n    a\nNo match\n").unwrap();
+        assert!(matches!(corpus.cases()[0].run(), Outcome::Error(_)));
+        assert_eq!(corpus.run().failures.len(), 1);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |input| Corpus::parse(input).unwrap_err();
+        assert_eq!(error("/a/\n    a\n").line, 2);
+        assert_eq!(error(" 0: a\n").message, "captures without a subject");
+        assert_eq!(
+            error("/a/\n    a\n 1: a\n").message,
+            "captures out of order"
+        );
+        assert_eq!(error("/a\n").message, "pattern without closing delimiter");
+        assert_eq!(
+            error("/a/\n    a\n").to_string(),
+            "Invalid corpus at line 2: subject without expected result"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            unescape(r"a\tb\x41\x{1F600}\101\\").unwrap(),
+            "a\tbA\u{1F600}A\\"
+        );
+        assert!(unescape(r"\x{110000}").is_err());
+        assert!(unescape("a\\").is_err());
+    }
+}
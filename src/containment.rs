@@ -0,0 +1,214 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Checking whether the texts that one regex matches are a subset of the texts that another
+//! regex matches, see `Regex::is_subset_of`.
+//!
+//! Both regexes are compiled to DFAs with regex-automata, and the product of the two DFAs and a
+//! small automaton for valid UTF-8 is searched breadth-first for a text that the first regex
+//! matches but the second doesn't. This only works for regexes that don't need backtracking.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use regex_automata::dfa::{dense, Automaton};
+use regex_automata::util::primitives::StateID;
+use regex_automata::{Anchored, Input, MatchKind};
+
+use crate::analyze::analyze_with_options;
+use crate::{parse_pattern, CompileError, Error, Expr, ExprTree, RegexOptions, Result};
+
+/// A state of the search: the states of the UTF-8 automaton and of both DFAs. The state of the
+/// first DFA is `None` once it has matched, because then it matches any continuation.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct State {
+    utf8: u8,
+    sub: Option<StateID>,
+    sup: StateID,
+}
+
+/// Returns the shortest text that the regex of `sub` matches (anywhere in the text, like
+/// `is_match`) but the regex of `sup` doesn't, or `None` if there is no such text.
+pub(crate) fn counterexample(sub: &RegexOptions, sup: &RegexOptions) -> Result<Option<String>> {
+    let sub_dfa = build_dfa(sub)?;
+    let sup_dfa = build_dfa(sup)?;
+    let input = Input::new("").anchored(Anchored::No);
+    let start = State {
+        utf8: 0,
+        sub: Some(sub_dfa.start_state_forward(&input).map_err(dfa_error)?),
+        sup: sup_dfa.start_state_forward(&input).map_err(dfa_error)?,
+    };
+
+    // The state each state was first reached from and with which byte, for building the text
+    let mut parents: HashMap<State, Option<(State, u8)>> = HashMap::new();
+    parents.insert(start, None);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(state) = queue.pop_front() {
+        if state.utf8 == 0 {
+            let sub_matches = state.sub.map_or(true, |id| {
+                sub_dfa.is_match_state(sub_dfa.next_eoi_state(id))
+            });
+            if sub_matches && !sup_dfa.is_match_state(sup_dfa.next_eoi_state(state.sup)) {
+                return Ok(Some(text_to(state, &parents)));
+            }
+        }
+        for b in 0..=255u8 {
+            let utf8 = match next_utf8(state.utf8, b) {
+                Some(utf8) => utf8,
+                None => continue,
+            };
+            // Match states are delayed by one byte: they mean that the text before `b` matched
+            let sup = sup_dfa.next_state(state.sup, b);
+            if sup_dfa.is_match_state(sup) {
+                // every continuation is matched by `sup`, so there's no counterexample here
+                continue;
+            }
+            let sub = match state.sub {
+                Some(id) => {
+                    let next = sub_dfa.next_state(id, b);
+                    if sub_dfa.is_match_state(next) {
+                        None
+                    } else if sub_dfa.is_dead_state(next) {
+                        // no continuation is matched by `sub`
+                        continue;
+                    } else {
+                        Some(next)
+                    }
+                }
+                None => None,
+            };
+            let next = State { utf8, sub, sup };
+            if let Entry::Vacant(entry) = parents.entry(next) {
+                entry.insert(Some((state, b)));
+                queue.push_back(next);
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn build_dfa(options: &RegexOptions) -> Result<dense::DFA<Vec<u32>>> {
+    let raw_tree = parse_pattern(options)?;
+    let tree = ExprTree {
+        expr: Expr::Group(Box::new(raw_tree.expr)),
+        ..raw_tree
+    };
+    let info = analyze_with_options(&tree, options)?;
+    if info.hard {
+        return Err(Error::CompileError(CompileError::NotRegular));
+    }
+    let mut pattern = String::new();
+    tree.expr.to_str(&mut pattern, 0);
+    dense::Builder::new()
+        .configure(dense::Config::new().match_kind(MatchKind::All))
+        .build(&pattern)
+        .map_err(dfa_error)
+}
+
+fn dfa_error<E: ToString>(error: E) -> Error {
+    Error::CompileError(CompileError::DfaError(error.to_string()))
+}
+
+/// Follow the parents back to the start to get the text that reaches `state`.
+fn text_to(mut state: State, parents: &HashMap<State, Option<(State, u8)>>) -> String {
+    let mut bytes = Vec::new();
+    while let Some(&Some((parent, b))) = parents.get(&state) {
+        bytes.push(b);
+        state = parent;
+    }
+    bytes.reverse();
+    String::from_utf8(bytes).expect("the UTF-8 automaton only accepts valid UTF-8")
+}
+
+/// Transition of an automaton that accepts valid UTF-8, see the table of well-formed byte
+/// sequences in the Unicode standard (section 3.9). State 0 is between characters, the others
+/// are in the middle of a character.
+fn next_utf8(state: u8, b: u8) -> Option<u8> {
+    match (state, b) {
+        (0, 0x00..=0x7F) => Some(0),
+        (0, 0xC2..=0xDF) => Some(1),
+        (0, 0xE0) => Some(4),
+        (0, 0xE1..=0xEC) | (0, 0xEE..=0xEF) => Some(2),
+        (0, 0xED) => Some(5),
+        (0, 0xF0) => Some(6),
+        (0, 0xF1..=0xF3) => Some(3),
+        (0, 0xF4) => Some(7),
+        (1, 0x80..=0xBF) => Some(0),
+        (2, 0x80..=0xBF) => Some(1),
+        (3, 0x80..=0xBF) => Some(2),
+        (4, 0xA0..=0xBF) => Some(1),
+        (5, 0x80..=0x9F) => Some(1),
+        (6, 0x90..=0xBF) => Some(2),
+        (7, 0x80..=0x8F) => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CompileError, Error, Regex};
+
+    fn re(pattern: &str) -> Regex {
+        Regex::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn subsets() {
+        assert!(re("ab").is_subset_of(&re("a")).unwrap());
+        assert!(re("^a+$").is_subset_of(&re("a")).unwrap());
+        assert!(re(r"\d{3}").is_subset_of(&re(r"\d+")).unwrap());
+        assert!(re("cat|dog").is_subset_of(&re("[a-z]{3}")).unwrap());
+        assert!(!re("a").is_subset_of(&re("ab")).unwrap());
+        assert!(!re(r"\w").is_subset_of(&re("[a-z]")).unwrap());
+    }
+
+    #[test]
+    fn equivalent() {
+        assert!(re("a|b|ab").is_equivalent_to(&re("[ab]")).unwrap());
+        assert!(re("(?i)ab").is_equivalent_to(&re("[aA][bB]")).unwrap());
+        assert!(re(r"(?:x\d)+").is_equivalent_to(&re(r"x\d")).unwrap());
+        assert!(!re("^a").is_equivalent_to(&re("a")).unwrap());
+    }
+
+    #[test]
+    fn counterexamples() {
+        let example = |sub: &str, sup: &str| re(sub).subset_counterexample(&re(sup)).unwrap();
+        assert_eq!(example("a", "ab"), Some("a".to_string()));
+        assert_eq!(example("a", "^a"), Some("\u{0}a".to_string()));
+        assert_eq!(example("^[a-z]+$", "^[a-y]"), Some("z".to_string()));
+        // Only valid UTF-8 is considered
+        assert_eq!(
+            example(r"^\w$", r"^[a-zA-Z0-9_]$"),
+            Some("\u{aa}".to_string())
+        );
+        assert_eq!(example("a+", "a"), None);
+    }
+
+    #[test]
+    fn not_regular() {
+        let result = re(r"(a)\1").is_subset_of(&re("a"));
+        assert!(matches!(
+            result,
+            Err(Error::CompileError(CompileError::NotRegular))
+        ));
+        assert!(re("a").is_subset_of(&re("(?=a)")).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Differential testing against the regex crate, enabled with the `differential` feature.
+//!
+//! For patterns that both fancy-regex and the regex crate support, the results must be the same.
+//! [`Differential`] runs a pattern through both on generated texts and reports the first
+//! divergence, with the text minimized so that it's easy to see what went wrong.
+//!
+//! # Example
+//!
+//! ```rust
+//! use fancy_regex::differential::Differential;
+//!
+//! let mut differential = Differential::new(r"(\w+)\s*=\s*(\d+)").unwrap();
+//! differential.samples(50).seed(1);
+//! assert!(differential.run().unwrap().is_none());
+//! ```
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::generate::Rng;
+use crate::{CompileError, Error, Generator, Regex, Result};
+
+/// Characters that are inserted into generated texts, in addition to the ones of the text.
+const NOISE: &[char] = &['a', 'Z', '1', ' ', '\n', '-', 'é'];
+
+/// Runs a pattern through fancy-regex and the regex crate and compares the results.
+#[derive(Debug)]
+pub struct Differential {
+    fancy: Regex,
+    oracle: regex::Regex,
+    samples: usize,
+    seed: Option<u64>,
+}
+
+/// A text for which fancy-regex and the regex crate disagree.
+///
+/// The results are the positions of the groups of the first match, or empty if there was no
+/// match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The pattern for fancy-regex
+    pub pattern: String,
+    /// The pattern for the regex crate
+    pub oracle_pattern: String,
+    /// The minimized text
+    pub text: String,
+    /// The result of fancy-regex
+    pub fancy: Vec<Option<Range<usize>>>,
+    /// The result of the regex crate
+    pub oracle: Vec<Option<Range<usize>>>,
+}
+
+impl Differential {
+    /// Compare fancy-regex and the regex crate for `pattern`.
+    ///
+    /// Returns an error if either of them can't compile the pattern.
+    pub fn new(pattern: &str) -> Result<Differential> {
+        Differential::with_oracle(pattern, pattern)
+    }
+
+    /// Compare `pattern` in fancy-regex with `oracle_pattern` in the regex crate, e.g. when the
+    /// syntax differs but the patterns are meant to be equivalent.
+    pub fn with_oracle(pattern: &str, oracle_pattern: &str) -> Result<Differential> {
+        Ok(Differential {
+            fancy: Regex::new(pattern)?,
+            oracle: regex::Regex::new(oracle_pattern)
+                .map_err(|e| Error::CompileError(CompileError::InnerError(e)))?,
+            samples: 100,
+            seed: None,
+        })
+    }
+
+    /// Number of texts that are generated by `run`.
+    ///
+    /// Default is 100.
+    pub fn samples(&mut self, samples: usize) -> &mut Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Seed the random number generator, to get the same texts each time.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Compare the results for generated texts and return the first divergence, if any.
+    ///
+    /// Texts are generated by taking strings that match the pattern (see [`Generator`]) and
+    /// mutating them, so that both matches and near misses are tested.
+    pub fn run(&self) -> Result<Option<Divergence>> {
+        let mut generator = Generator::new(&self.fancy);
+        let mut rng = match self.seed {
+            Some(seed) => {
+                generator.seed(seed);
+                Rng::new(seed)
+            }
+            None => Rng::random(),
+        };
+        for _ in 0..self.samples {
+            let matching = generator.generate()?.unwrap_or_default();
+            let text = mutate(&matching, &mut rng);
+            for text in &[matching, text] {
+                if let Some(divergence) = self.check(text)? {
+                    return Ok(Some(divergence));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Compare the results for `text`, returning the divergence with a minimized text if they
+    /// differ.
+    pub fn check(&self, text: &str) -> Result<Option<Divergence>> {
+        if !self.diverges(text)? {
+            return Ok(None);
+        }
+        let text = self.minimize(text)?;
+        Ok(Some(Divergence {
+            pattern: self.fancy.as_str().to_string(),
+            oracle_pattern: self.oracle.as_str().to_string(),
+            fancy: self.fancy_groups(&text)?,
+            oracle: self.oracle_groups(&text),
+            text,
+        }))
+    }
+
+    fn diverges(&self, text: &str) -> Result<bool> {
+        Ok(self.fancy_groups(text)? != self.oracle_groups(text))
+    }
+
+    fn fancy_groups(&self, text: &str) -> Result<Vec<Option<Range<usize>>>> {
+        Ok(match self.fancy.captures(text)? {
+            Some(caps) => caps.iter().map(|m| m.map(|m| m.start()..m.end())).collect(),
+            None => Vec::new(),
+        })
+    }
+
+    fn oracle_groups(&self, text: &str) -> Vec<Option<Range<usize>>> {
+        match self.oracle.captures(text) {
+            Some(caps) => caps.iter().map(|m| m.map(|m| m.range())).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Remove as much of `text` as possible while it still diverges, first in big chunks, then
+    /// single characters.
+    fn minimize(&self, text: &str) -> Result<String> {
+        let mut chars: Vec<char> = text.chars().collect();
+        let mut chunk = chars.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start + chunk <= chars.len() {
+                let candidate: String = chars[..start]
+                    .iter()
+                    .chain(&chars[start + chunk..])
+                    .collect();
+                if self.diverges(&candidate)? {
+                    chars.drain(start..start + chunk);
+                } else {
+                    start += chunk;
+                }
+            }
+            chunk /= 2;
+        }
+        Ok(chars.into_iter().collect())
+    }
+}
+
+/// Insert, delete or replace characters of `text`, or repeat it.
+fn mutate(text: &str, rng: &mut Rng) -> String {
+    let mut chars: Vec<char> = text.chars().collect();
+    let mutations = 1 + rng.below(3);
+    for _ in 0..mutations {
+        let noise = if !chars.is_empty() && rng.below(2) == 0 {
+            chars[rng.below(chars.len())]
+        } else {
+            NOISE[rng.below(NOISE.len())]
+        };
+        let pos = rng.below(chars.len() + 1);
+        match rng.below(4) {
+            0 => chars.insert(pos, noise),
+            1 if pos < chars.len() => {
+                chars.remove(pos);
+            }
+            2 if pos < chars.len() => chars[pos] = noise,
+            _ => {
+                let copy = chars.clone();
+                chars.extend(copy);
+            }
+        }
+    }
+    chars.into_iter().collect()
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pattern {:?}", self.pattern)?;
+        if self.oracle_pattern != self.pattern {
+            write!(f, " (regex crate: {:?})", self.oracle_pattern)?;
+        }
+        write!(
+            f,
+            " on text {:?}: fancy-regex found {:?}, regex crate found {:?}",
+            self.text, self.fancy, self.oracle
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_divergence() {
+        for pattern in &[
+            r"a+b",
+            r"(\w+)@(\w+)\.com",
+            r"(?i)[a-z]+\d?",
+            r"^(a|ab)(c|bcd)(d*)$",
+            r"(?m)^\s*$",
+            r"\bx.y\b",
+        ] {
+            let mut differential = Differential::new(pattern).unwrap();
+            differential.seed(1);
+            assert_eq!(differential.run().unwrap(), None, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn minimized_divergence() {
+        let mut differential = Differential::with_oracle(r"(a+)b", r"(a+)c|(a+)b").unwrap();
+        differential.seed(1);
+        let divergence = differential.run().unwrap().unwrap();
+        assert_eq!(divergence.text, "ab");
+        assert_eq!(divergence.fancy, vec![Some(0..2), Some(0..1)]);
+        assert_eq!(divergence.oracle, vec![Some(0..2), None, Some(0..1)]);
+        assert_eq!(
+            divergence.to_string(),
+            r#"pattern "(a+)b" (regex crate: "(a+)c|(a+)b") on text "ab": fancy-regex found [Some(0..2), Some(0..1)], regex crate found [Some(0..2), None, Some(0..1)]"#
+        );
+    }
+
+    #[test]
+    fn check() {
+        let differential = Differential::with_oracle(r"\d+", r"[0-8]+").unwrap();
+        assert_eq!(differential.check("123").unwrap(), None);
+        let divergence = differential.check("x 19 y").unwrap().unwrap();
+        assert_eq!(divergence.text, "9");
+    }
+
+    #[test]
+    fn oracle_error() {
+        assert!(matches!(
+            Differential::new(r"(a)\1"),
+            Err(Error::CompileError(CompileError::InnerError(_)))
+        ));
+    }
+}
@@ -1,22 +1,55 @@
+use std::error::Error as StdError;
 use std::fmt;
+use std::ops::Range;
 
 /// Result type for this crate with specific error enum.
 pub type Result<T> = ::std::result::Result<T, Error>;
 
 /// An error for the result of compiling or running a regex.
+///
+/// The errors are grouped by when they happen. Use
+/// [`source`](https://doc.rust-lang.org/std/error/trait.Error.html#method.source) to get the error
+/// of the regex crate for parts of the pattern that it failed to compile.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
-    // Compile time errors
-    /// General parsing error
-    ParseError,
+    /// An error while parsing the pattern, with the byte position in the pattern where it was
+    /// found
+    ParseError(usize, ParseError),
+    /// An error while compiling the parsed pattern
+    CompileError(CompileError),
+    /// An error while running the regex, the text could be matched or not
+    RuntimeError(RuntimeError),
+    /// A capture group could not be converted to a field, see
+    /// [`Regex::captures_parse`](struct.Regex.html#method.captures_parse).
+    CaptureConversion {
+        /// Name or number of the group
+        group: String,
+        /// Why the conversion failed, e.g. the error of `FromStr`
+        reason: String,
+    },
+    /// A replacement template refers to a group that is not in the regex or is malformed, see
+    /// [`Regex::validate_template`](struct.Regex.html#method.validate_template).
+    InvalidTemplate {
+        /// Byte range in the template of the invalid part, e.g. `${name}`
+        span: Range<usize>,
+        /// What is wrong with it
+        reason: String,
+    },
+}
+
+/// An error for the syntax of a pattern, see `Error::ParseError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// General parsing error, e.g. an unexpected character
+    GeneralParseError(String),
     /// Opening parenthesis without closing parenthesis, e.g. `(a|b`
     UnclosedOpenParen,
     /// Invalid repeat syntax
     InvalidRepeat,
     /// Pattern too deeply nested
     RecursionExceeded,
-    /// Look-behind assertion without constant size
-    LookBehindNotConst,
     /// Backslash without following character
     TrailingBackslash,
     /// Invalid escape
@@ -35,65 +68,337 @@ pub enum Error {
     NonUnicodeUnsupported,
     /// Invalid back reference
     InvalidBackref,
-    /// Regex crate error
-    InnerError(regex::Error),
     /// Couldn't parse group name
     InvalidGroupName,
+    /// The name of a named group is used by another group too
+    DuplicateGroupName(String),
     /// Invalid group id in escape sequence
     InvalidGroupNameBackref(String),
     /// Once named groups are used you cannot refer to groups by number
     NamedBackrefOnly,
-
     /// Quantifier on lookaround or other zero-width assertion
     TargetNotRepeatable,
+    /// Closing bracket without opening bracket, e.g. `a]`, which is only an error with
+    /// [`RegexBuilder::strict`](struct.RegexBuilder.html#method.strict)
+    UnmatchedBracket,
+    /// A repeat with a count above
+    /// [`RegexBuilder::max_repeat`](struct.RegexBuilder.html#method.max_repeat), e.g. `a{1000}`
+    /// with a bound of 100
+    RepeatTooLarge {
+        /// The count of the repeat
+        count: usize,
+        /// The bound
+        max: usize,
+    },
+    /// A fragment call like `(?&name)` for a name that is not in the
+    /// [`Fragments`](struct.Fragments.html) of the regex
+    UnknownFragment(String),
+    /// A fragment that includes itself, directly or through other fragments
+    RecursiveFragment(String),
+    /// A fragment call with a different number of arguments than the fragment has parameters,
+    /// e.g. `(?&list)` for a fragment `list` with one parameter
+    FragmentArguments {
+        /// The name of the fragment or parameter
+        name: String,
+        /// The number of parameters
+        expected: usize,
+        /// The number of arguments of the call
+        found: usize,
+    },
+    /// Syntax of another regex flavor that is not supported, e.g. `\K` from PCRE
+    UnsupportedSyntax {
+        /// The start of the construct in the pattern, e.g. `\K` or `(?|`
+        construct: String,
+        /// What the construct is
+        name: &'static str,
+        /// The flavors that support it
+        flavor: &'static str,
+        /// What to use instead, if there is an equivalent
+        suggestion: Option<&'static str>,
+    },
+}
 
-    // Run time errors
+/// An error for compiling a parsed pattern, see `Error::CompileError`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CompileError {
+    /// Regex crate error, the error is the
+    /// [`source`](https://doc.rust-lang.org/std/error/trait.Error.html#method.source)
+    #[cfg(feature = "compiler")]
+    InnerError(regex::Error),
+    /// A part of the pattern that is delegated to the regex crate could not be compiled, e.g.
+    /// because it exceeds the size limit set with
+    /// [`RegexBuilder::delegate_size_limit`](struct.RegexBuilder.html#method.delegate_size_limit).
+    /// The error of the regex crate is the
+    /// [`source`](https://doc.rust-lang.org/std/error/trait.Error.html#method.source).
+    #[cfg(feature = "compiler")]
+    DelegateError {
+        /// The regex that was passed to the regex crate
+        delegated: String,
+        /// Byte range of the delegated part in the pattern, if it could be located
+        span: Option<Range<usize>>,
+        /// The error of the regex crate
+        error: regex::Error,
+    },
+    /// Look-behind assertion without constant size that contains a backref, which is not supported
+    LookBehindNotConst,
+    /// Back reference to a group that is not in the pattern
+    InvalidBackref,
+    /// Group name or number that is not in the pattern, see
+    /// [`RegexBuilder::captured_groups`](struct.RegexBuilder.html#method.captured_groups)
+    UnknownGroup(String),
+    /// The pattern needs backtracking, e.g. because of backrefs or look-around, so it can't be
+    /// compiled to a DFA, see [`Regex::is_subset_of`](struct.Regex.html#method.is_subset_of)
+    NotRegular,
+    /// A DFA for the pattern could not be built, e.g. because the pattern contains a Unicode word
+    /// boundary, see [`Regex::is_subset_of`](struct.Regex.html#method.is_subset_of)
+    DfaError(String),
+    /// A program built from instructions is malformed, see
+    /// [`Prog::from_insns`](prog/struct.Prog.html#method.from_insns)
+    InvalidProgram {
+        /// The index of the instruction that is invalid
+        pc: usize,
+        /// What is wrong with it
+        reason: String,
+    },
+}
+
+/// An error for running a regex, see `Error::RuntimeError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RuntimeError {
     /// Max stack size exceeded for backtracking while executing regex.
+    /// Configure using [`RunConfig::max_stack`](struct.RunConfig.html#method.max_stack).
     StackOverflow,
     /// Max limit for backtracking count exceeded while executing the regex.
     /// Configure using
     /// [`RegexBuilder::backtrack_limit`](struct.RegexBuilder.html#method.backtrack_limit).
     BacktrackLimitExceeded,
+    /// Max memory for the backtracking state exceeded while executing the regex.
+    /// Configure using
+    /// [`RegexBuilder::memory_limit`](struct.RegexBuilder.html#method.memory_limit).
+    MemoryLimitExceeded,
+    /// Max number of steps of the VM exceeded while executing the regex.
+    /// Configure using [`RunConfig::step_limit`](struct.RunConfig.html#method.step_limit).
+    StepLimitExceeded,
+    /// Max number of steps of the VM exceeded, where the limit scales with the length of the
+    /// text and the size of the program.
+    /// Configure using [`RunConfig::auto_step_limit`](struct.RunConfig.html#method.auto_step_limit).
+    AutoStepLimitExceeded {
+        /// The number of steps that was allowed for the run
+        limit: usize,
+        /// The length in bytes of the text from the position where the run started
+        text_len: usize,
+        /// The number of instructions of the program
+        instructions: usize,
+        /// The number of times the VM backtracked before the limit was hit
+        backtracks: usize,
+    },
+    /// The deadline passed while executing the regex.
+    /// Configure using [`RunConfig::deadline`](struct.RunConfig.html#method.deadline).
+    DeadlineExceeded,
+    /// A match of a streamed replacement reached the end of the text that was retained, so it
+    /// could need more text than allowed.
+    /// Configure using
+    /// [`Regex::replace_all_stream_limited`](struct.Regex.html#method.replace_all_stream_limited).
+    StreamBufferExceeded {
+        /// The maximum number of bytes retained after the start of a match
+        limit: usize,
+    },
+    /// The program for the regex was malformed, which is a bug in fancy-regex. Running the regex
+    /// returns this error instead of panicking.
+    InternalBug {
+        /// Index of the instruction that was executed when the problem was detected
+        pc: usize,
+        /// Description of the problem
+        reason: String,
+    },
+}
 
-    /// This enum may grow additional variants, so this makes sure clients don't count on exhaustive
-    /// matching. Otherwise, adding a new variant could break existing code.
-    #[doc(hidden)]
-    __Nonexhaustive,
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::CompileError(error) => error.source(),
+            _ => None,
+        }
+    }
 }
 
-impl ::std::error::Error for Error {}
+impl StdError for ParseError {}
+
+impl StdError for CompileError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            #[cfg(feature = "compiler")]
+            CompileError::InnerError(error) | CompileError::DelegateError { error, .. } => {
+                Some(error)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl StdError for RuntimeError {}
 
 impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ParseError(position, error) => {
+                write!(f, "Parsing error at position {}: {}", position, error)
+            }
+            Error::CompileError(error) => write!(f, "Error compiling regex: {}", error),
+            Error::RuntimeError(error) => write!(f, "Error executing regex: {}", error),
+            Error::CaptureConversion { group, reason } => {
+                write!(f, "Could not convert group {}: {}", group, reason)
+            }
+            Error::InvalidTemplate { span, reason } => write!(
+                f,
+                "Invalid replacement template at {}..{}: {}",
+                span.start, span.end, reason
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // We should make these more helpful, e.g. by including the parts of the regex that lead to
         // the error.
         match self {
-            Error::ParseError => write!(f, "General parsing error"),
-            Error::UnclosedOpenParen => {
+            ParseError::GeneralParseError(s) => write!(f, "General parsing error: {}", s),
+            ParseError::UnclosedOpenParen => {
                 write!(f, "Opening parenthesis without closing parenthesis")
             }
-            Error::InvalidRepeat => write!(f, "Invalid repeat syntax"),
-            Error::RecursionExceeded => write!(f, "Pattern too deeply nested"),
-            Error::LookBehindNotConst => write!(f, "Look-behind assertion without constant size"),
-            Error::TrailingBackslash => write!(f, "Backslash without following character"),
-            Error::InvalidEscape(s) => write!(f, "Invalid escape: {}", s),
-            Error::UnclosedUnicodeName => write!(f, "Unicode escape not closed"),
-            Error::InvalidHex => write!(f, "Invalid hex escape"),
-            Error::InvalidCodepointValue => {
+            ParseError::InvalidRepeat => write!(f, "Invalid repeat syntax"),
+            ParseError::RecursionExceeded => write!(f, "Pattern too deeply nested"),
+            ParseError::TrailingBackslash => write!(f, "Backslash without following character"),
+            ParseError::InvalidEscape(s) => write!(f, "Invalid escape: {}", s),
+            ParseError::UnclosedUnicodeName => write!(f, "Unicode escape not closed"),
+            ParseError::InvalidHex => write!(f, "Invalid hex escape"),
+            ParseError::InvalidCodepointValue => {
                 write!(f, "Invalid codepoint for hex or unicode escape")
             }
-            Error::InvalidClass => write!(f, "Invalid character class"),
-            Error::UnknownFlag(s) => write!(f, "Unknown group flag: {}", s),
-            Error::NonUnicodeUnsupported => write!(f, "Disabling Unicode not supported"),
-            Error::InvalidBackref => write!(f, "Invalid back reference"),
-            Error::InnerError(e) => write!(f, "Regex error: {}", e),
-            Error::StackOverflow => write!(f, "Max stack size exceeded for backtracking"),
-            Error::BacktrackLimitExceeded => write!(f, "Max limit for backtracking count exceeded"),
-            Error::__Nonexhaustive => unreachable!(),
-            Error::InvalidGroupName => write!(f, "Could not parse group name"),
-            Error::InvalidGroupNameBackref(s) => write!(f, "Invalid group name in back reference: {}", s),
-            Error::TargetNotRepeatable => write!(f, "Target of repeat operator is invalid"),
-            Error::NamedBackrefOnly => write!(f, "Numbered backref/call not allowed because named group was used, use a named backref instead"),
+            ParseError::InvalidClass => write!(f, "Invalid character class"),
+            ParseError::UnknownFlag(s) => write!(f, "Unknown group flag: {}", s),
+            ParseError::NonUnicodeUnsupported => write!(f, "Disabling Unicode not supported"),
+            ParseError::InvalidBackref => write!(f, "Invalid back reference"),
+            ParseError::UnmatchedBracket => write!(f, "Closing bracket without opening bracket"),
+            ParseError::RepeatTooLarge { count, max } => write!(
+                f,
+                "Repeat count {} is larger than the maximum of {}",
+                count, max
+            ),
+            ParseError::InvalidGroupName => write!(f, "Could not parse group name"),
+            ParseError::DuplicateGroupName(name) => {
+                write!(f, "Duplicate group name: {}", name)
+            }
+            ParseError::InvalidGroupNameBackref(s) => {
+                write!(f, "Invalid group name in back reference: {}", s)
+            }
+            ParseError::NamedBackrefOnly => write!(f, "Numbered backref/call not allowed because named group was used, use a named backref instead"),
+            ParseError::TargetNotRepeatable => write!(f, "Target of repeat operator is invalid"),
+            ParseError::UnknownFragment(name) => write!(f, "Unknown fragment: {}", name),
+            ParseError::RecursiveFragment(name) => write!(f, "Recursive fragment: {}", name),
+            ParseError::FragmentArguments {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Fragment {} takes {} arguments, found {}",
+                name, expected, found
+            ),
+            ParseError::UnsupportedSyntax {
+                construct,
+                name,
+                flavor,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "Unsupported {} `{}` from {}, use {} instead",
+                name, construct, flavor, suggestion
+            ),
+            ParseError::UnsupportedSyntax {
+                construct,
+                name,
+                flavor,
+                suggestion: None,
+            } => write!(
+                f,
+                "Unsupported {} `{}` from {}, there is no equivalent",
+                name, construct, flavor
+            ),
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "compiler")]
+            CompileError::InnerError(e) => write!(f, "Regex error: {}", e),
+            #[cfg(feature = "compiler")]
+            CompileError::DelegateError {
+                delegated,
+                span: Some(span),
+                error,
+            } => write!(
+                f,
+                "Regex error in delegated part at {}..{} (as `{}`): {}",
+                span.start, span.end, delegated, error
+            ),
+            #[cfg(feature = "compiler")]
+            CompileError::DelegateError {
+                delegated, error, ..
+            } => write!(f, "Regex error in delegated `{}`: {}", delegated, error),
+            CompileError::LookBehindNotConst => {
+                write!(f, "Look-behind assertion without constant size")
+            }
+            CompileError::InvalidBackref => write!(f, "Invalid back reference"),
+            CompileError::UnknownGroup(group) => write!(f, "Unknown group: {}", group),
+            CompileError::NotRegular => {
+                write!(
+                    f,
+                    "Pattern needs backtracking and can't be compiled to a DFA"
+                )
+            }
+            CompileError::DfaError(s) => write!(f, "Could not build DFA: {}", s),
+            CompileError::InvalidProgram { pc, reason } => {
+                write!(f, "Invalid program at instruction {}: {}", pc, reason)
+            }
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::StackOverflow => write!(f, "Max stack size exceeded for backtracking"),
+            RuntimeError::BacktrackLimitExceeded => {
+                write!(f, "Max limit for backtracking count exceeded")
+            }
+            RuntimeError::MemoryLimitExceeded => write!(f, "Max memory for backtracking exceeded"),
+            RuntimeError::StepLimitExceeded => write!(f, "Max number of steps exceeded"),
+            RuntimeError::AutoStepLimitExceeded {
+                limit,
+                text_len,
+                instructions,
+                backtracks,
+            } => write!(
+                f,
+                "Max number of steps ({}) exceeded for {} bytes of text and {} instructions, \
+                 after {} backtracks",
+                limit, text_len, instructions, backtracks
+            ),
+            RuntimeError::DeadlineExceeded => write!(f, "Deadline exceeded"),
+            RuntimeError::StreamBufferExceeded { limit } => write!(
+                f,
+                "Match needs more than the {} bytes of text retained for streaming",
+                limit
+            ),
+            RuntimeError::InternalBug { pc, reason } => {
+                write!(f, "Internal bug at instruction {}: {}", pc, reason)
+            }
         }
     }
 }
@@ -1,8 +1,9 @@
 use crate::parse::{parse_decimal, parse_id};
-use crate::{Captures, Error, Regex};
+use crate::{Captures, Error, ParseError, Regex};
 use std::borrow::Cow;
 use std::io;
 use std::mem;
+use std::ops::Range;
 
 /// A set of options for expanding a template string using the contents
 /// of capture groups.
@@ -67,30 +68,101 @@ impl Expander {
     /// - An opening group name delimiter without a closing delimiter
     /// - Using an empty string as a group name
     pub fn check(&self, template: &str, regex: &Regex) -> crate::Result<()> {
-        let on_group_num = |num| {
+        let on_group_num = |pos, num: usize| {
             if num == 0 {
                 Ok(())
             } else if !regex.named_groups.is_empty() {
-                Err(Error::NamedBackrefOnly)
+                Err(Error::ParseError(pos, ParseError::NamedBackrefOnly))
             } else if num < regex.captures_len() {
                 Ok(())
             } else {
-                Err(Error::InvalidBackref)
+                Err(Error::ParseError(pos, ParseError::InvalidBackref))
             }
         };
-        self.exec(template, |step| match step {
+        self.exec(template, |span, step| match step {
             Step::Char(_) => Ok(()),
             Step::GroupName(name) => {
+                let pos = span.start;
                 if regex.named_groups.contains_key(name) {
                     Ok(())
                 } else if let Ok(num) = name.parse() {
-                    on_group_num(num)
+                    on_group_num(pos, num)
                 } else {
-                    Err(Error::InvalidBackref)
+                    Err(Error::ParseError(pos, ParseError::InvalidBackref))
+                }
+            }
+            Step::GroupNum(num) => on_group_num(span.start, num),
+            Step::Error => {
+                let message = "group reference without a group".to_string();
+                Err(Error::ParseError(
+                    span.start,
+                    ParseError::GeneralParseError(message),
+                ))
+            }
+        })
+    }
+
+    /// Checks the group references in `template` against `regex` the way they are expanded, see
+    /// `Regex::validate_template`. Unlike `check`, numbered references to groups are allowed
+    /// when there are named groups.
+    pub(crate) fn validate(&self, template: &str, regex: &Regex) -> crate::Result<()> {
+        let invalid = |span, reason| Err(Error::InvalidTemplate { span, reason });
+        let unknown_number = |num: &str| {
+            format!(
+                "no group {} in the regex, which has groups 0 to {}",
+                num,
+                regex.captures_len() - 1
+            )
+        };
+        self.exec(template, |span, step| match step {
+            Step::Char(_) => Ok(()),
+            Step::GroupName(name) => {
+                if regex.named_groups.contains_key(name) {
+                    return Ok(());
+                }
+                let digits = name.bytes().take_while(u8::is_ascii_digit).count();
+                if digits == name.len() {
+                    match name.parse::<usize>() {
+                        Ok(num) if num < regex.captures_len() => Ok(()),
+                        _ => invalid(span, unknown_number(name)),
+                    }
+                } else if digits > 0 {
+                    // E.g. `$1st`, which is the group named `1st` rather than group 1
+                    let reason = format!(
+                        "no group named `{}` in the regex, use `{}{}{}{}{}` for group {} \
+                         followed by `{}`",
+                        name,
+                        self.sub_char,
+                        self.open,
+                        &name[..digits],
+                        self.close,
+                        &name[digits..],
+                        &name[..digits],
+                        &name[digits..]
+                    );
+                    invalid(span, reason)
+                } else {
+                    invalid(span, format!("no group named `{}` in the regex", name))
+                }
+            }
+            Step::GroupNum(num) if num < regex.captures_len() => Ok(()),
+            Step::GroupNum(num) => invalid(span, unknown_number(&num.to_string())),
+            Step::Error => {
+                let rest = &template[span.end..];
+                if rest.starts_with(self.open) {
+                    let end = match rest.find(self.close) {
+                        Some(ix) => span.end + ix + self.close.len(),
+                        None => template.len(),
+                    };
+                    invalid(span.start..end, "invalid group name".to_string())
+                } else {
+                    let reason = format!(
+                        "`{0}` without a group, use `{0}{0}` for a literal `{0}`",
+                        self.sub_char
+                    );
+                    invalid(span, reason)
                 }
             }
-            Step::GroupNum(num) => on_group_num(num),
-            Step::Error => Err(Error::ParseError),
         })
     }
 
@@ -133,7 +205,7 @@ impl Expander {
     /// than calling `expansion` directly and appending to an existing string.
     pub fn append_expansion(&self, dst: &mut String, template: &str, captures: &Captures<'_>) {
         let pos = dst.len();
-        let mut cursor = io::Cursor::new(mem::replace(dst, String::new()).into_bytes());
+        let mut cursor = io::Cursor::new(mem::take(dst).into_bytes());
         cursor.set_position(pos as u64);
         self.write_expansion(&mut cursor, template, captures)
             .expect("expansion succeeded");
@@ -148,7 +220,7 @@ impl Expander {
         template: &str,
         captures: &Captures<'_>,
     ) -> io::Result<()> {
-        self.exec(template, |step| match step {
+        self.exec(template, |_, step| match step {
             Step::Char(c) => write!(dst, "{}", c),
             Step::GroupName(name) => {
                 if let Some(m) = captures.name(name) {
@@ -170,20 +242,25 @@ impl Expander {
         })
     }
 
+    /// Calls `f` with each step of expanding `template`, and the range of the template that the
+    /// step is for.
     fn exec<'t, E>(
         &self,
         template: &'t str,
-        mut f: impl FnMut(Step<'t>) -> Result<(), E>,
+        mut f: impl FnMut(Range<usize>, Step<'t>) -> Result<(), E>,
     ) -> Result<(), E> {
         debug_assert!(!self.open.is_empty());
         debug_assert!(!self.close.is_empty());
         let mut iter = template.chars();
         while let Some(c) = iter.next() {
+            let pos = template.len() - iter.as_str().len() - c.len_utf8();
+            let next = pos + c.len_utf8();
             if c == self.sub_char {
                 let tail = iter.as_str();
                 let skip = if tail.starts_with(self.sub_char) {
-                    f(Step::Char(self.sub_char))?;
-                    1
+                    let skip = self.sub_char.len_utf8();
+                    f(pos..next + skip, Step::Char(self.sub_char))?;
+                    skip
                 } else if let Some((id, skip)) =
                     parse_id(tail, self.open, self.close).or_else(|| {
                         if self.allow_undelimited_name {
@@ -193,19 +270,19 @@ impl Expander {
                         }
                     })
                 {
-                    f(Step::GroupName(id))?;
+                    f(pos..next + skip, Step::GroupName(id))?;
                     skip
                 } else if let Some((skip, num)) = parse_decimal(tail, 0) {
-                    f(Step::GroupNum(num))?;
+                    f(pos..next + skip, Step::GroupNum(num))?;
                     skip
                 } else {
-                    f(Step::Error)?;
-                    f(Step::Char(self.sub_char))?;
+                    f(pos..next, Step::Error)?;
+                    f(pos..next, Step::Char(self.sub_char))?;
                     0
                 };
                 iter = iter.as_str()[skip..].chars();
             } else {
-                f(Step::Char(c))?;
+                f(pos..next, Step::Char(c))?;
             }
         }
         Ok(())
@@ -0,0 +1,198 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Explaining why a regex doesn't match a text, see `Regex::explain_no_match`.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::vm::{self, Diagnostics, Failure, Prog};
+use crate::{anchored_prog, Regex, RegexImpl, RegexOptions, Result, RunConfig, ANCHORED_START_PC};
+
+/// Why a regex didn't match a text, returned by
+/// [`Regex::explain_no_match`](struct.Regex.html#method.explain_no_match).
+///
+/// The `Display` implementation describes it in a sentence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoMatch {
+    pattern: String,
+    start: usize,
+    furthest: usize,
+    span: Option<Range<usize>>,
+    alternatives: Vec<(usize, Option<Range<usize>>)>,
+}
+
+impl NoMatch {
+    /// The position in the text where the attempt that got furthest started.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// The position in the text where the attempt that got furthest failed.
+    pub fn furthest(&self) -> usize {
+        self.furthest
+    }
+
+    /// The span of the part of the pattern that failed at `furthest`, if it's known.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    /// The part of the pattern that failed at `furthest`, if it's known.
+    pub fn failed_part(&self) -> Option<&str> {
+        self.span.clone().map(|span| &self.pattern[span])
+    }
+
+    /// The alternatives that were still left to try when the attempt failed at `furthest` (the
+    /// top of the backtrack stack), as the position in the text and the span in the pattern where
+    /// each would have continued, the most recent one first.
+    pub fn alternatives(&self) -> &[(usize, Option<Range<usize>>)] {
+        &self.alternatives
+    }
+
+    fn part(&self, span: &Option<Range<usize>>) -> &str {
+        span.clone().map_or("?", |span| &self.pattern[span])
+    }
+}
+
+impl fmt::Display for NoMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no match; the attempt at position {} got furthest, to position {}, where `{}` failed",
+            self.start,
+            self.furthest,
+            self.part(&self.span)
+        )?;
+        for (i, (pos, span)) in self.alternatives.iter().enumerate() {
+            let separator = if i == 0 {
+                "; alternatives left to try:"
+            } else {
+                ","
+            };
+            write!(f, "{} `{}` at position {}", separator, self.part(span), pos)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn explain_no_match(regex: &Regex, text: &str) -> Result<Option<NoMatch>> {
+    match &*regex.inner {
+        RegexImpl::Wrap {
+            options, anchored, ..
+        } => {
+            let prog = anchored_prog(options, anchored)?;
+            explain_prog(&prog, 0, options, text)
+        }
+        RegexImpl::Fancy { prog, options, .. } => {
+            explain_prog(prog, ANCHORED_START_PC, options, text)
+        }
+    }
+}
+
+/// Run `prog` from `start_pc` at each position of `text`, and explain the failure that got
+/// furthest from where its attempt started.
+fn explain_prog(
+    prog: &Prog,
+    start_pc: usize,
+    options: &RegexOptions,
+    text: &str,
+) -> Result<Option<NoMatch>> {
+    let config = RunConfig::default();
+    let mut furthest: Option<(usize, Failure)> = None;
+    for start in (0..=text.len()).filter(|&ix| text.is_char_boundary(ix)) {
+        let mut diagnostics = Diagnostics::default();
+        let result = vm::run_diagnostic(
+            prog,
+            text,
+            start,
+            start_pc,
+            0,
+            options,
+            &config,
+            Some(&mut diagnostics),
+        )?;
+        if result.is_some() {
+            return Ok(None);
+        }
+        if let Some(failure) = diagnostics.furthest {
+            let further = furthest.as_ref().map_or(true, |(s, f)| {
+                // Look-behind can fail before the start
+                failure.ix.saturating_sub(start) > f.ix.saturating_sub(*s)
+            });
+            if further {
+                furthest = Some((start, failure));
+            }
+        }
+    }
+
+    let span = |pc: usize| prog.spans.get(pc).cloned().unwrap_or(None);
+    Ok(furthest.map(|(start, failure)| NoMatch {
+        pattern: options.pattern.clone(),
+        start,
+        furthest: failure.ix,
+        span: span(failure.pc),
+        alternatives: failure
+            .branches
+            .iter()
+            .map(|&(pc, ix)| (ix, span(pc)))
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Regex;
+
+    #[test]
+    fn explain_backref() {
+        let re = Regex::new(r"^(\w+)-\1$").unwrap();
+        assert_eq!(re.explain_no_match("ab-ab").unwrap(), None);
+
+        let explanation = re.explain_no_match("abc-abd").unwrap().unwrap();
+        assert_eq!(explanation.start(), 0);
+        assert_eq!(explanation.furthest(), 4);
+        assert_eq!(explanation.failed_part(), Some(r"\1"));
+        assert!(explanation.to_string().starts_with(
+            "no match; the attempt at position 0 got furthest, to position 4, where `\\1` failed"
+        ));
+    }
+
+    #[test]
+    fn explain_alternatives() {
+        let re = Regex::new(r"(?:ab|a)(?=c)x").unwrap();
+        let explanation = re.explain_no_match("zabcd").unwrap().unwrap();
+        assert_eq!(explanation.start(), 1);
+        assert_eq!(explanation.furthest(), 3);
+        assert_eq!(explanation.failed_part(), Some("x"));
+        // The shorter alternative of the group
+        let &(pos, _) = explanation.alternatives().first().unwrap();
+        assert!(pos <= 2, "{}", explanation);
+    }
+
+    #[test]
+    fn explain_wrapped() {
+        let re = Regex::new(r"\d+px").unwrap();
+        let explanation = re.explain_no_match("12em").unwrap().unwrap();
+        assert_eq!(explanation.start(), 0);
+        assert_eq!(explanation.furthest(), 0);
+        assert!(re.explain_no_match("12px").unwrap().is_none());
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Recognizing syntax of other regex flavors that isn't supported, so that the error can say
+//! where it comes from and what to use instead.
+
+use crate::ParseError;
+
+/// A construct of another flavor, recognized by the start of its syntax.
+struct Foreign {
+    prefix: &'static str,
+    name: &'static str,
+    flavor: &'static str,
+    suggestion: Option<&'static str>,
+}
+
+const FOREIGN: &[Foreign] = &[
+    Foreign {
+        prefix: "\\Z",
+        name: "end of text anchor that allows a final newline",
+        flavor: "Perl/PCRE",
+        suggestion: Some("`(?=\\n?\\z)` (or `\\z` for Python's `\\Z`)"),
+    },
+    Foreign {
+        prefix: "\\K",
+        name: "match start reset",
+        flavor: "Perl/PCRE",
+        suggestion: Some("a look-behind, e.g. `(?<=a)b` for `a\\Kb`"),
+    },
+    Foreign {
+        prefix: "\\G",
+        name: "anchor at the end of the previous match",
+        flavor: "Perl/PCRE/.NET",
+        suggestion: Some("`Regex::find_at` or `Regex::captures_at`"),
+    },
+    Foreign {
+        prefix: "\\Q",
+        name: "quoted text",
+        flavor: "Perl/PCRE",
+        suggestion: Some("`regex::escape` for the text"),
+    },
+    Foreign {
+        prefix: "\\g<",
+        name: "subroutine call",
+        flavor: "PCRE/Oniguruma",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "\\g'",
+        name: "subroutine call",
+        flavor: "PCRE/Oniguruma",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "\\R",
+        name: "line break",
+        flavor: "PCRE/Java",
+        suggestion: Some("`(?:\\r\\n|[\\n\\x0B\\f\\r\\x85\\u{2028}\\u{2029}])`"),
+    },
+    Foreign {
+        prefix: "\\N",
+        name: "character that is not a newline",
+        flavor: "Perl/PCRE",
+        suggestion: Some("`[^\\n]`"),
+    },
+    Foreign {
+        prefix: "\\o",
+        name: "octal escape",
+        flavor: "Perl/PCRE",
+        suggestion: Some("`\\x{...}`"),
+    },
+    Foreign {
+        prefix: "\\X",
+        name: "extended grapheme cluster",
+        flavor: "PCRE/Java/Oniguruma",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "\\C",
+        name: "single code unit",
+        flavor: "PCRE",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "(?|",
+        name: "branch reset group",
+        flavor: "Perl/PCRE",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "(?(",
+        name: "conditional",
+        flavor: "Perl/PCRE/.NET/Python",
+        suggestion: Some("an alternation with look-arounds, e.g. `(?=x)a|(?!x)b`"),
+    },
+    Foreign {
+        prefix: "(?R",
+        name: "recursion",
+        flavor: "Perl/PCRE",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "(?&",
+        name: "subroutine call",
+        flavor: "Perl/PCRE",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "(?P>",
+        name: "subroutine call",
+        flavor: "PCRE/Python regex",
+        suggestion: None,
+    },
+    Foreign {
+        prefix: "(*",
+        name: "backtracking control verb",
+        flavor: "Perl/PCRE",
+        suggestion: None,
+    },
+];
+
+/// Returns the error for an unsupported construct of another flavor at the start of `s`, if
+/// there is one. `s` starts with the `\` of an escape or the `(` of a group.
+pub(crate) fn unsupported(s: &str) -> Option<ParseError> {
+    let error = |construct: &str, name, flavor, suggestion| ParseError::UnsupportedSyntax {
+        construct: construct.to_string(),
+        name,
+        flavor,
+        suggestion,
+    };
+    // Recursion into a numbered group, e.g. `(?1)` or `(?+1)`
+    if s.starts_with("(?") {
+        if let Some(&b) = s.as_bytes().get(2) {
+            if b.is_ascii_digit() || b == b'+' {
+                return Some(error(&s[..3], "recursion", "Perl/PCRE", None));
+            }
+        }
+    }
+    FOREIGN
+        .iter()
+        .find(|foreign| s.starts_with(foreign.prefix))
+        .map(|foreign| {
+            error(
+                foreign.prefix,
+                foreign.name,
+                foreign.flavor,
+                foreign.suggestion,
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Error, Expr};
+
+    fn error(re: &str) -> String {
+        match Expr::parse_tree(re) {
+            Err(Error::ParseError(_, error)) => error.to_string(),
+            result => panic!("Expected a parse error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            error(r"a\Kb"),
+            "Unsupported match start reset `\\K` from Perl/PCRE, \
+             use a look-behind, e.g. `(?<=a)b` for `a\\Kb` instead"
+        );
+        assert_eq!(
+            error(r"\X"),
+            "Unsupported extended grapheme cluster `\\X` from PCRE/Java/Oniguruma, \
+             there is no equivalent"
+        );
+        assert_eq!(error(r"[\Q]"), error(r"\Q"));
+        assert_eq!(
+            error(r"(a)\g<1>"),
+            "Unsupported subroutine call `\\g<` from PCRE/Oniguruma, there is no equivalent"
+        );
+        // Other invalid escapes don't mention a flavor
+        assert_eq!(error(r"\q"), "Invalid escape: \\q");
+    }
+
+    #[test]
+    fn groups() {
+        assert_eq!(
+            error(r"(?(1)a|b)"),
+            "Unsupported conditional `(?(` from Perl/PCRE/.NET/Python, \
+             use an alternation with look-arounds, e.g. `(?=x)a|(?!x)b` instead"
+        );
+        assert_eq!(
+            error(r"(a|(?1))"),
+            "Unsupported recursion `(?1` from Perl/PCRE, there is no equivalent"
+        );
+        assert_eq!(
+            error(r"(*SKIP)"),
+            "Unsupported backtracking control verb `(*` from Perl/PCRE, there is no equivalent"
+        );
+        // Supported verbs still work
+        assert!(Expr::parse_tree(r"(*sr:a)").is_ok());
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Named pattern fragments that patterns can include, see `Fragments`.
+
+use std::collections::BTreeMap;
+
+use crate::parse::{parse_id, Parser};
+use crate::{Error, ParseError, Result};
+
+/// A library of named pattern fragments, which patterns built with
+/// [`RegexBuilder::fragments`](struct.RegexBuilder.html#method.fragments) can include with
+/// `(?&name)`, e.g. for maintaining a large grammar as small pieces.
+///
+/// A fragment is included as if it was a non-capturing group: groups in it don't capture, and
+/// flags like `(?i)` around the call don't apply to it. Fragments can include the fragments that
+/// were inserted before them, but not themselves.
+///
+/// Fragments can have parameters, see [`insert_with_params`](#method.insert_with_params). A call
+/// passes a pattern for each of them in parentheses after the name, e.g. `(?&list(\d+)(,))`, and
+/// the fragment includes them with `(?&param)`. Unlike the fragment, the arguments are parsed
+/// with the flags at the call; like it, their groups don't capture.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{Fragments, RegexBuilder};
+/// let mut defs = Fragments::new();
+/// defs.insert("ident", r"[A-Za-z_]\w*").unwrap();
+/// defs.insert("path", r"(?&ident)(?:::(?&ident))*").unwrap();
+/// let re = RegexBuilder::new(r"^use (?&path);$")
+///     .fragments(&defs)
+///     .build()
+///     .unwrap();
+/// assert!(re.is_match("use std::fmt;").unwrap());
+/// assert!(!re.is_match("use 1x;").unwrap());
+/// assert_eq!(re.captures_len(), 1);
+///
+/// defs.insert_with_params("list", &["item"], r"(?&item)(?:,\s*(?&item))*").unwrap();
+/// let re = RegexBuilder::new(r"^\[(?&list(\d+))\] = \[(?&list((?&path)))\]$")
+///     .fragments(&defs)
+///     .build()
+///     .unwrap();
+/// assert!(re.is_match("[1, 2] = [a::b, c]").unwrap());
+/// ```
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Fragments {
+    fragments: BTreeMap<String, Fragment>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct Fragment {
+    params: Vec<String>,
+    pattern: String,
+}
+
+impl Fragments {
+    /// Create an empty library of fragments.
+    pub fn new() -> Fragments {
+        Fragments::default()
+    }
+
+    /// Add the fragment `pattern` with `name`, replacing the fragment that had the name before.
+    ///
+    /// The name must be a valid group name, otherwise `ParseError::InvalidGroupName` is
+    /// returned. The pattern is checked for syntax errors, with positions in `pattern`; it can't
+    /// contain backrefs, and fragments that it includes must have been inserted already.
+    pub fn insert(&mut self, name: &str, pattern: &str) -> Result<()> {
+        self.insert_with_params(name, &[], pattern)
+    }
+
+    /// Add the fragment `pattern` with `name` and the parameters `params`, like `insert`.
+    ///
+    /// The pattern includes the argument for a parameter with `(?&param)`, which hides a
+    /// fragment with the same name. The parameters must be valid group names too, and different
+    /// from each other, otherwise `ParseError::InvalidGroupName` or
+    /// `ParseError::DuplicateGroupName` is returned.
+    pub fn insert_with_params(&mut self, name: &str, params: &[&str], pattern: &str) -> Result<()> {
+        if !is_valid_name(name) || !params.iter().all(|param| is_valid_name(param)) {
+            return Err(Error::ParseError(0, ParseError::InvalidGroupName));
+        }
+        for (i, param) in params.iter().enumerate() {
+            if params[..i].contains(param) {
+                let error = ParseError::DuplicateGroupName(param.to_string());
+                return Err(Error::ParseError(0, error));
+            }
+        }
+        Parser::parse_fragment(pattern, params, self)?;
+        let fragment = Fragment {
+            params: params.iter().map(|param| param.to_string()).collect(),
+            pattern: pattern.to_string(),
+        };
+        self.fragments.insert(name.to_string(), fragment);
+        Ok(())
+    }
+
+    /// The pattern of the fragment with `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fragments
+            .get(name)
+            .map(|fragment| fragment.pattern.as_str())
+    }
+
+    /// The parameters of the fragment with `name`, if there is one.
+    pub fn params(&self, name: &str) -> Option<&[String]> {
+        self.fragments
+            .get(name)
+            .map(|fragment| fragment.params.as_slice())
+    }
+
+    /// The parameters and the pattern of the fragment with `name`, if there is one.
+    pub(crate) fn get_with_params(&self, name: &str) -> Option<(&[String], &str)> {
+        self.fragments
+            .get(name)
+            .map(|fragment| (fragment.params.as_slice(), fragment.pattern.as_str()))
+    }
+
+    /// The number of fragments.
+    pub fn len(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// Returns `true` if there are no fragments.
+    pub fn is_empty(&self) -> bool {
+        self.fragments.is_empty()
+    }
+
+    /// Returns an iterator over the names and patterns of the fragments, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fragments
+            .iter()
+            .map(|(name, fragment)| (name.as_str(), fragment.pattern.as_str()))
+    }
+}
+
+/// Whether `name` can be the name of a group, and so of a fragment or parameter.
+fn is_valid_name(name: &str) -> bool {
+    match parse_id(name, "", "") {
+        Some((id, _)) => id.len() == name.len() && !id.starts_with(|c: char| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Expr, RegexOptions};
+
+    fn parse(re: &str, fragments: &Fragments) -> Result<Expr> {
+        let options = RegexOptions {
+            pattern: re.to_string(),
+            fragments: fragments.clone(),
+            ..RegexOptions::default()
+        };
+        Parser::parse_options(&options).map(|tree| tree.expr)
+    }
+
+    #[test]
+    fn insert_checks_fragment() {
+        let mut defs = Fragments::new();
+        assert_eq!(
+            defs.insert("1x", "a").unwrap_err().to_string(),
+            "Parsing error at position 0: Could not parse group name"
+        );
+        assert_eq!(
+            defs.insert("x", "a(").unwrap_err().to_string(),
+            "Parsing error at position 1: Opening parenthesis without closing parenthesis"
+        );
+        assert_eq!(
+            defs.insert("x", r"(a)\1").unwrap_err().to_string(),
+            "Parsing error at position 0: Invalid back reference"
+        );
+        assert_eq!(
+            defs.insert("x", "(?&y)").unwrap_err().to_string(),
+            "Parsing error at position 0: Unknown fragment: y"
+        );
+        assert!(defs.is_empty());
+        defs.insert("y", "b").unwrap();
+        defs.insert("x", "(?&y)").unwrap();
+        assert_eq!(
+            defs.iter().collect::<Vec<_>>(),
+            [("x", "(?&y)"), ("y", "b")]
+        );
+    }
+
+    #[test]
+    fn groups_dont_capture() {
+        let mut defs = Fragments::new();
+        defs.insert("pair", r"(?<key>\w)=(\w)").unwrap();
+        assert_eq!(
+            parse("((?&pair))", &defs).unwrap(),
+            parse(r"(\w=\w)", &Fragments::new()).unwrap()
+        );
+    }
+
+    #[test]
+    fn own_flags() {
+        let mut defs = Fragments::new();
+        defs.insert("a", "a").unwrap();
+        defs.insert("b", "(?i)b").unwrap();
+        assert_eq!(
+            parse("(?i)(?&a)(?&b)", &defs).unwrap(),
+            parse("a(?i)b", &Fragments::new()).unwrap()
+        );
+    }
+
+    #[test]
+    fn params() {
+        let mut defs = Fragments::new();
+        defs.insert("x", "x").unwrap();
+        defs.insert_with_params("pair", &["k", "x"], "(?&k)=(?&x)")
+            .unwrap();
+        assert_eq!(defs.params("pair").unwrap(), ["k", "x"]);
+        assert_eq!(defs.params("x").unwrap(), [] as [String; 0]);
+        // The arguments are parsed with the flags at the call and their groups don't capture,
+        // and the parameter `x` hides the fragment
+        assert_eq!(
+            parse("((?i)(?&pair((a))(b|c)))", &defs).unwrap(),
+            parse("((?i:a)=(?i:b|c))", &Fragments::new()).unwrap()
+        );
+        // Arguments can call fragments, and the parameters of the fragment they're in
+        defs.insert_with_params("list", &["item"], "(?&item)(,(?&item))*")
+            .unwrap();
+        defs.insert_with_params("pairs", &["v"], "(?&list((?&pair((?&x))((?&v)))))")
+            .unwrap();
+        assert_eq!(
+            parse("(?&pairs(1))", &defs).unwrap(),
+            parse("(?:x=1)(?:,(?:x=1))*", &Fragments::new()).unwrap()
+        );
+    }
+
+    #[test]
+    fn param_errors() {
+        let mut defs = Fragments::new();
+        assert_eq!(
+            defs.insert_with_params("a", &["x y"], "b")
+                .unwrap_err()
+                .to_string(),
+            "Parsing error at position 0: Could not parse group name"
+        );
+        assert_eq!(
+            defs.insert_with_params("a", &["x", "x"], "b")
+                .unwrap_err()
+                .to_string(),
+            "Parsing error at position 0: Duplicate group name: x"
+        );
+        assert_eq!(
+            defs.insert_with_params("a", &["x"], "(?&x(b))")
+                .unwrap_err()
+                .to_string(),
+            "Parsing error at position 0: Fragment x takes 0 arguments, found 1"
+        );
+        defs.insert_with_params("a", &["x"], "(?&x)").unwrap();
+        assert_eq!(
+            parse("b(?&a)", &defs).unwrap_err().to_string(),
+            "Parsing error at position 1: Fragment a takes 1 arguments, found 0"
+        );
+        assert_eq!(
+            parse("b(?&a(c)(d))", &defs).unwrap_err().to_string(),
+            "Parsing error at position 1: Fragment a takes 1 arguments, found 2"
+        );
+        assert_eq!(
+            parse(r"(a)(?&a(\1))", &defs).unwrap_err().to_string(),
+            "Parsing error at position 7: Invalid back reference"
+        );
+        assert_eq!(
+            parse("(?&a(c(d)", &defs).unwrap_err().to_string(),
+            "Parsing error at position 4: Opening parenthesis without closing parenthesis"
+        );
+        assert_eq!(
+            parse("(?&a(c)", &defs).unwrap_err().to_string(),
+            "Parsing error at position 0: Opening parenthesis without closing parenthesis"
+        );
+        assert!(parse("(?&a(c)d)", &defs).is_err());
+    }
+
+    #[test]
+    fn errors_at_call() {
+        let mut defs = Fragments::new();
+        defs.insert("a", "a").unwrap();
+        defs.insert("b", "(?&a)").unwrap();
+        // Replacing `a` makes `b` recursive
+        defs.insert("a", "(?&b)").unwrap();
+        assert_eq!(
+            parse("x(?&a)", &defs).unwrap_err().to_string(),
+            "Parsing error at position 1: Recursive fragment: a"
+        );
+        assert_eq!(
+            parse("x(?&c)", &defs).unwrap_err().to_string(),
+            "Parsing error at position 1: Unknown fragment: c"
+        );
+        // Without fragments, it's unsupported syntax
+        assert!(matches!(
+            parse("(?&a)", &Fragments::new()),
+            Err(Error::ParseError(0, ParseError::UnsupportedSyntax { .. }))
+        ));
+    }
+}
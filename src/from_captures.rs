@@ -0,0 +1,135 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Converting captures to structs, see `FromCaptures`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Captures, Error, Match, Result};
+
+/// Types that can be created from the captures of a match, see
+/// [`Regex::captures_parse`](struct.Regex.html#method.captures_parse).
+///
+/// With the `derive` feature, this can be derived for structs. The fields are converted from the
+/// capture groups with the same names (or the groups at the same positions for tuple structs)
+/// using `FromStr`. `#[capture(name = "...")]` on a field selects a group with a different name.
+/// A field with an `Option` type is `None` if its group didn't match.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "derive")] {
+/// use fancy_regex::{FromCaptures, Regex};
+///
+/// #[derive(FromCaptures)]
+/// struct Request {
+///     method: String,
+///     status: u16,
+///     #[capture(name = "ms")]
+///     duration: Option<u32>,
+/// }
+///
+/// let re = Regex::new(r"(?<method>[A-Z]+) \S+ (?<status>\d{3})(?: (?<ms>\d+)ms)?").unwrap();
+/// let request: Request = re.captures_parse("GET /index.html 200 15ms").unwrap().unwrap();
+/// assert_eq!(request.method, "GET");
+/// assert_eq!(request.status, 200);
+/// assert_eq!(request.duration, Some(15));
+/// # }
+/// ```
+pub trait FromCaptures: Sized {
+    /// Creates a value from the captures of a match. A failed conversion of a group returns
+    /// `Error::CaptureConversion`.
+    fn from_captures(caps: &Captures<'_>) -> Result<Self>;
+}
+
+/// Converts the match of a required group.
+pub fn convert<T>(group_match: Option<Match<'_>>, group: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match convert_optional(group_match, group)? {
+        Some(value) => Ok(value),
+        None => Err(Error::CaptureConversion {
+            group: group.to_string(),
+            reason: "group did not match".to_string(),
+        }),
+    }
+}
+
+/// Converts the match of an optional group, `None` if the group didn't match.
+pub fn convert_optional<T>(group_match: Option<Match<'_>>, group: &str) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match group_match {
+        Some(m) => m
+            .as_str()
+            .parse()
+            .map(Some)
+            .map_err(|e: T::Err| Error::CaptureConversion {
+                group: group.to_string(),
+                reason: e.to_string(),
+            }),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Regex;
+
+    struct Point(i32, i32);
+
+    impl FromCaptures for Point {
+        fn from_captures(caps: &Captures<'_>) -> Result<Self> {
+            Ok(Point(
+                convert(caps.get(1), "1")?,
+                convert(caps.get(2), "2")?,
+            ))
+        }
+    }
+
+    #[test]
+    fn manual_impl() {
+        let re = Regex::new(r"\((-?\d+),(-?\d+)\)").unwrap();
+        let point: Point = re.captures_parse("at (3,-4)").unwrap().unwrap();
+        assert_eq!((point.0, point.1), (3, -4));
+        assert!(re.captures_parse::<Point>("nowhere").unwrap().is_none());
+    }
+
+    #[test]
+    fn conversion_errors() {
+        let re = Regex::new(r"(\w+)(x)?").unwrap();
+        let caps = re.captures("abc").unwrap().unwrap();
+        assert_eq!(convert_optional::<String>(caps.get(2), "2").unwrap(), None);
+        assert_eq!(
+            convert::<u8>(caps.get(1), "1").unwrap_err().to_string(),
+            "Could not convert group 1: invalid digit found in string"
+        );
+        assert_eq!(
+            convert::<String>(caps.get(2), "2").unwrap_err().to_string(),
+            "Could not convert group 2: group did not match"
+        );
+    }
+}
@@ -0,0 +1,534 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Generating random strings that match a regex.
+//!
+//! Strings are generated by walking the AST. Backreferences repeat the text that was generated
+//! for their group. Positive look-ahead generates text that the rest of the string has to start
+//! with: single characters generated after it are picked to agree with it where possible, and
+//! if the string ends early, the rest of the look-ahead's text is appended. Positive look-behind
+//! at the start generates text that is put in front. Everything else that restricts where
+//! something can match (negative look-around, anchors, word boundaries) is not taken into
+//! account while generating; instead each candidate is checked against the regex and discarded
+//! if it doesn't match.
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::usize;
+
+use crate::parse::{NamedGroups, Parser};
+use crate::{Expr, LookAround, Regex, Result};
+
+/// Characters that delegated character classes and `.` are sampled from.
+const EXTRA_CHARS: &str = "\t\néßΩж中٣😀";
+
+/// Generates random strings that match a regex, e.g. for fuzzing or examples in documentation.
+///
+/// Generation is based on the regex's syntax tree. Each candidate string is checked against
+/// the regex, so all generated strings are guaranteed to match. For patterns with restrictive
+/// look-around or anchors, generation may fail after a number of attempts and return `None`.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{Generator, Regex};
+/// let re = Regex::new(r"(\d{2})-\1(?=!)").unwrap();
+/// let mut generator = Generator::new(&re);
+/// generator.seed(42);
+/// let s = generator.generate().unwrap().unwrap();
+/// assert!(re.is_match(&s).unwrap());
+/// assert_eq!(&s[0..2], &s[3..5]);
+/// ```
+#[derive(Debug)]
+pub struct Generator<'r> {
+    regex: &'r Regex,
+    expr: Expr,
+    named_groups: NamedGroups,
+    max_repeat: usize,
+    max_attempts: usize,
+    rng: Rng,
+    /// Characters that match a delegated character class, by class
+    classes: HashMap<String, Vec<char>>,
+}
+
+impl<'r> Generator<'r> {
+    /// Create a generator for strings matching `regex`, seeded randomly.
+    pub fn new(regex: &'r Regex) -> Generator<'r> {
+        // With the options, e.g. for including fragments
+        let tree = Parser::parse_options(regex.options()).expect("pattern of a regex is valid");
+        Generator {
+            regex,
+            expr: tree.expr,
+            named_groups: tree.named_groups,
+            max_repeat: 8,
+            max_attempts: 100,
+            rng: Rng::random(),
+            classes: HashMap::new(),
+        }
+    }
+
+    /// Maximum number of additional repetitions beyond the minimum of a repetition like `*`,
+    /// `+` or `{2,}`. Bounded repetitions like `{2,4}` don't exceed their maximum.
+    ///
+    /// Default is 8.
+    pub fn max_repeat(&mut self, max_repeat: usize) -> &mut Self {
+        self.max_repeat = max_repeat;
+        self
+    }
+
+    /// Number of candidates that are generated before giving up.
+    ///
+    /// Default is 100.
+    pub fn max_attempts(&mut self, max_attempts: usize) -> &mut Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Seed the random number generator, to get the same strings each time.
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+
+    /// Generate a string that matches the regex.
+    ///
+    /// Returns `None` if no matching string was found within the maximum number of attempts,
+    /// or an error if running the regex on a candidate failed.
+    pub fn generate(&mut self) -> Result<Option<String>> {
+        let expr = std::mem::replace(&mut self.expr, Expr::Empty);
+        let result = self.generate_with(&expr);
+        self.expr = expr;
+        result
+    }
+
+    fn generate_with(&mut self, expr: &Expr) -> Result<Option<String>> {
+        for _ in 0..self.max_attempts {
+            let mut state = State::default();
+            if !self.visit(expr, &mut state) {
+                continue;
+            }
+            if let Some(candidate) = state.finish() {
+                if self.regex.is_match(&candidate)? {
+                    return Ok(Some(candidate));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Returns false if generating the candidate failed.
+    fn visit(&mut self, expr: &Expr, state: &mut State) -> bool {
+        match *expr {
+            Expr::Empty | Expr::StartText | Expr::EndText | Expr::StartLine | Expr::EndLine => true,
+            Expr::Any { newline } => {
+                if let Some(c) = state.expected_char() {
+                    if newline || c != '\n' {
+                        state.output.push(c);
+                        return true;
+                    }
+                }
+                let c = loop {
+                    let c = self.random_char();
+                    if newline || c != '\n' {
+                        break c;
+                    }
+                };
+                state.output.push(c);
+                true
+            }
+            // Generated text is a string, where it matches any character
+            Expr::AnyByte => self.visit(&Expr::Any { newline: true }, state),
+            Expr::Literal { ref val, casei } => {
+                if casei {
+                    for c in val.chars() {
+                        if self.rng.below(2) == 0 {
+                            state.output.extend(c.to_uppercase());
+                        } else {
+                            state.output.extend(c.to_lowercase());
+                        }
+                    }
+                } else {
+                    state.output.push_str(val);
+                }
+                true
+            }
+            Expr::Concat(ref children) => children.iter().all(|child| self.visit(child, state)),
+            Expr::Alt(ref children) => {
+                let chosen = self.rng.below(children.len());
+                for (i, child) in children.iter().enumerate() {
+                    if i == chosen {
+                        if !self.visit(child, state) {
+                            return false;
+                        }
+                    } else {
+                        state.group += count_groups(child);
+                    }
+                }
+                true
+            }
+            Expr::Group(ref child) => {
+                state.group += 1;
+                let group = state.group;
+                let start = state.output.len();
+                if !self.visit(child, state) {
+                    return false;
+                }
+                let text = state.output[start..].to_string();
+                state.set_group(group, text);
+                true
+            }
+            Expr::AtomicGroup(ref child) | Expr::ScriptRun(ref child) => self.visit(child, state),
+            Expr::Repeat {
+                ref child, lo, hi, ..
+            } => {
+                let hi = hi.min(lo.saturating_add(self.max_repeat));
+                let count = lo + self.rng.below(hi - lo + 1);
+                let first_group = state.group;
+                let groups = count_groups(child);
+                for _ in 0..count {
+                    // Each repetition captures into the same groups
+                    state.group = first_group;
+                    if !self.visit(child, state) {
+                        return false;
+                    }
+                }
+                state.group = first_group + groups;
+                true
+            }
+            Expr::Delegate {
+                ref inner,
+                size,
+                casei,
+            } => {
+                if size == 0 {
+                    // Zero-width assertion like `\b`, checked later
+                    return true;
+                }
+                let expected = state.expected_char();
+                match self.class_char(inner, casei, expected) {
+                    Some(c) => {
+                        state.output.push(c);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            Expr::Backref { group, .. } => {
+                state.repeat_group(group);
+                true
+            }
+            Expr::NamedBackref(ref name) => {
+                if let Some(&group) = self.named_groups.get(name) {
+                    state.repeat_group(group);
+                }
+                true
+            }
+            Expr::LookAround(ref child, la) => {
+                // Generate the look-around's text separately, captures inside it are kept
+                let mut inner = State {
+                    group: state.group,
+                    groups: state.groups.clone(),
+                    ..State::default()
+                };
+                let ok = self.visit(child, &mut inner);
+                state.group = inner.group;
+                match la {
+                    LookAround::LookAhead => {
+                        if !ok || inner.output.is_empty() {
+                            return ok;
+                        }
+                        state.groups = inner.groups;
+                        let pos = state.output.len();
+                        state.lookaheads.push((pos, inner.output));
+                        true
+                    }
+                    LookAround::LookBehind => {
+                        if !ok {
+                            return false;
+                        }
+                        state.groups = inner.groups;
+                        if state.output.is_empty() && state.prefix.is_empty() {
+                            state.prefix = inner.output;
+                            true
+                        } else {
+                            state.output.ends_with(&inner.output)
+                        }
+                    }
+                    // Negative look-around is only checked at the end
+                    LookAround::LookAheadNeg | LookAround::LookBehindNeg => true,
+                }
+            }
+        }
+    }
+
+    fn random_char(&mut self) -> char {
+        let extra = EXTRA_CHARS.chars().count();
+        let printable = usize::from(b'~' - b' ') + 1;
+        let i = self.rng.below(printable + extra);
+        if i < printable {
+            char::from(b' ' + i as u8)
+        } else {
+            EXTRA_CHARS.chars().nth(i - printable).unwrap()
+        }
+    }
+
+    /// Pick a random character that matches the delegated class `inner`, or `expected` if it
+    /// matches.
+    fn class_char(&mut self, inner: &str, casei: bool, expected: Option<char>) -> Option<char> {
+        if !self.classes.contains_key(inner) {
+            let flags = if casei { "(?i)" } else { "" };
+            let chars = match regex::Regex::new(&format!("^{}(?:{})$", flags, inner)) {
+                Ok(re) => (b' '..=b'~')
+                    .map(char::from)
+                    .chain(EXTRA_CHARS.chars())
+                    .filter(|c| re.is_match(c.encode_utf8(&mut [0; 4])))
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            self.classes.insert(inner.to_string(), chars);
+        }
+        let chars = &self.classes[inner];
+        if let Some(c) = expected {
+            if chars.contains(&c) {
+                return Some(c);
+            }
+        }
+        if chars.is_empty() {
+            None
+        } else {
+            let i = self.rng.below(chars.len());
+            Some(chars[i])
+        }
+    }
+}
+
+/// State of generating one candidate.
+#[derive(Default)]
+struct State {
+    output: String,
+    /// Text generated for a look-behind at the start, which goes in front of the output
+    prefix: String,
+    /// The number of the last group that was entered
+    group: usize,
+    groups: Vec<Option<String>>,
+    /// Text generated for look-aheads, with the position in the output where it has to appear
+    lookaheads: Vec<(usize, String)>,
+}
+
+impl State {
+    fn set_group(&mut self, group: usize, text: String) {
+        if self.groups.len() <= group {
+            self.groups.resize(group + 1, None);
+        }
+        self.groups[group] = Some(text);
+    }
+
+    /// Append the text that was generated for `group` again, if it participated.
+    fn repeat_group(&mut self, group: usize) {
+        if let Some(Some(text)) = self.groups.get(group) {
+            let text = text.clone();
+            self.output.push_str(&text);
+        }
+    }
+
+    /// The character that a pending look-ahead expects at the end of the output, if any.
+    fn expected_char(&self) -> Option<char> {
+        let pos = self.output.len();
+        self.lookaheads
+            .iter()
+            .filter(|&&(start, ref text)| start <= pos && pos - start < text.len())
+            .filter_map(|&(start, ref text)| text.get(pos - start..))
+            .find_map(|rest| rest.chars().next())
+    }
+
+    /// Check the look-aheads, appending their text where the output ends, and return the
+    /// candidate.
+    fn finish(mut self) -> Option<String> {
+        for (pos, text) in &self.lookaheads {
+            let rest = &self.output[*pos..];
+            if rest.len() >= text.len() {
+                if !rest.starts_with(text.as_str()) {
+                    return None;
+                }
+            } else if text.starts_with(rest) {
+                let missing = text[rest.len()..].to_string();
+                self.output.push_str(&missing);
+            } else {
+                return None;
+            }
+        }
+        self.prefix.push_str(&self.output);
+        Some(self.prefix)
+    }
+}
+
+fn count_groups(expr: &Expr) -> usize {
+    match *expr {
+        Expr::Group(ref child) => 1 + count_groups(child),
+        Expr::Concat(ref children) | Expr::Alt(ref children) => {
+            children.iter().map(count_groups).sum()
+        }
+        Expr::AtomicGroup(ref child)
+        | Expr::ScriptRun(ref child)
+        | Expr::LookAround(ref child, _)
+        | Expr::Repeat { ref child, .. } => count_groups(child),
+        _ => 0,
+    }
+}
+
+/// A small xorshift random number generator, good enough for generating examples.
+#[derive(Debug)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        // The state must not be zero
+        Rng(seed ^ 0x9e37_79b9_7f4a_7c15 | 1)
+    }
+
+    /// Seeded differently each time.
+    pub(crate) fn random() -> Rng {
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Random number in `0..n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_all(re: &str) -> Vec<String> {
+        let regex = Regex::new(re).unwrap();
+        let mut generator = Generator::new(&regex);
+        generator.seed(1);
+        (0..20)
+            .map(|_| {
+                generator
+                    .generate()
+                    .unwrap()
+                    .unwrap_or_else(|| panic!("no string generated for {}", re))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generates_matching_strings() {
+        for re in &[
+            r"abc",
+            r"(?i)abc",
+            r"a|b|c+",
+            r"[a-f]{3}\d+",
+            r"^\w+@\w+\.com$",
+            r"(\w+) \1",
+            r"(?<x>[xy])-\k<x>",
+            r"(a|(b))+\2",
+            r"foo(?=bar)",
+            r"(?=\d)\w{3}",
+            r"(?<=\$)\d+",
+            r"\bword\b",
+            r"(?!a)[a-c]",
+            r"\p{Greek}",
+            r"(?s).{2}",
+            r"",
+        ] {
+            for s in generate_all(re) {
+                assert!(
+                    Regex::new(re).unwrap().is_match(&s).unwrap(),
+                    "{:?} doesn't match {}",
+                    s,
+                    re
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn backref_repeats_group() {
+        for s in generate_all(r"^(\d+)-\1$") {
+            let parts: Vec<_> = s.split('-').collect();
+            assert_eq!(parts[0], parts[1]);
+        }
+    }
+
+    #[test]
+    fn named_backref_repeats_group() {
+        let regex = Regex::new(r"^(?<x>\d+)-\k<x>$").unwrap();
+        let mut generator = Generator::new(&regex);
+        generator.seed(1);
+        // The parser resolves `\k<x>` to a numbered backref, so put in a named one by hand
+        if let Expr::Concat(ref mut children) = generator.expr {
+            assert!(matches!(children[3], Expr::Backref { group: 1, .. }));
+            children[3] = Expr::NamedBackref("x".to_string());
+        } else {
+            panic!("unexpected tree {:?}", generator.expr);
+        }
+        for _ in 0..20 {
+            let s = generator.generate().unwrap().unwrap();
+            let parts: Vec<_> = s.split('-').collect();
+            assert_eq!(parts[0], parts[1]);
+        }
+    }
+
+    #[test]
+    fn max_repeat() {
+        let regex = Regex::new(r"^a*$").unwrap();
+        let mut generator = Generator::new(&regex);
+        generator.max_repeat(3);
+        for _ in 0..20 {
+            assert!(generator.generate().unwrap().unwrap().len() <= 3);
+        }
+        let regex = Regex::new(r"^a{2,}$").unwrap();
+        let mut generator = Generator::new(&regex);
+        generator.max_repeat(0);
+        assert_eq!(generator.generate().unwrap().unwrap(), "aa");
+    }
+
+    #[test]
+    fn seed_is_deterministic() {
+        let regex = Regex::new(r"\w{10}").unwrap();
+        let mut a = Generator::new(&regex);
+        let mut b = Generator::new(&regex);
+        a.seed(7);
+        b.seed(7);
+        assert_eq!(a.generate().unwrap(), b.generate().unwrap());
+    }
+
+    #[test]
+    fn impossible() {
+        let regex = Regex::new(r"a(?=b)c").unwrap();
+        let mut generator = Generator::new(&regex);
+        generator.max_attempts(10);
+        assert_eq!(generator.generate().unwrap(), None);
+    }
+}
@@ -0,0 +1,262 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Marking up matches in text, e.g. with ANSI colors for a terminal.
+
+use std::cmp::Reverse;
+
+use crate::{Captures, Match, Result};
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// Produces a copy of a text with markers around matches and capture groups.
+///
+/// The markers are configured per group, with group 0 being the whole match. Groups without
+/// markers are not marked. Markers of nested groups are nested too, so e.g. with HTML tags as
+/// markers the result is well-formed.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{Highlighter, Regex};
+/// let re = Regex::new(r"(\w)\1").unwrap();
+/// let text = "aa bcc";
+///
+/// let mut highlighter = Highlighter::new("[", "]");
+/// assert_eq!(highlighter.highlight_matches(text, re.find_iter(text)).unwrap(), "[aa] b[cc]");
+///
+/// highlighter.group(1, "<", ">");
+/// assert_eq!(
+///     highlighter.highlight_captures(text, re.captures_iter(text)).unwrap(),
+///     "[<a>a] b[<c>c]"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Highlighter {
+    markers: Vec<Option<(String, String)>>,
+}
+
+impl Highlighter {
+    /// Create a highlighter that puts `begin` and `end` around each match.
+    pub fn new(begin: &str, end: &str) -> Highlighter {
+        Highlighter {
+            markers: vec![Some((begin.to_string(), end.to_string()))],
+        }
+    }
+
+    /// Create a highlighter that shows matches in bold red in a terminal, and capture groups 1 to
+    /// 6 in different colors.
+    ///
+    /// Note that the ANSI sequence for ending a group resets all attributes, so the rest of an
+    /// enclosing group isn't highlighted anymore.
+    pub fn ansi() -> Highlighter {
+        let mut highlighter = Highlighter::new("\x1b[1;31m", ANSI_RESET);
+        for (i, color) in (32..38).enumerate() {
+            highlighter.group(i + 1, &format!("\x1b[1;{}m", color), ANSI_RESET);
+        }
+        highlighter
+    }
+
+    /// Set the markers for the capture group with index `i` (0 is the whole match).
+    pub fn group(&mut self, i: usize, begin: &str, end: &str) -> &mut Highlighter {
+        if self.markers.len() <= i {
+            self.markers.resize(i + 1, None);
+        }
+        self.markers[i] = Some((begin.to_string(), end.to_string()));
+        self
+    }
+
+    /// Don't mark the capture group with index `i` (0 is the whole match).
+    pub fn clear_group(&mut self, i: usize) -> &mut Highlighter {
+        if let Some(markers) = self.markers.get_mut(i) {
+            *markers = None;
+        }
+        self
+    }
+
+    /// Returns `text` with the markers of group 0 around each of the `matches`, e.g. from
+    /// `Regex::find_iter`. Returns the first error of the iterator, if any.
+    pub fn highlight_matches<'t, I>(&self, text: &'t str, matches: I) -> Result<String>
+    where
+        I: IntoIterator<Item = Result<Match<'t>>>,
+    {
+        let mut markers = Vec::new();
+        for m in matches {
+            let m = m?;
+            self.add_markers(&mut markers, &[Some(m)]);
+        }
+        Ok(apply(text, markers))
+    }
+
+    /// Returns `text` with markers around the groups of each of the `captures`, e.g. from
+    /// `Regex::captures_iter`. Returns the first error of the iterator, if any.
+    pub fn highlight_captures<'t, I>(&self, text: &'t str, captures: I) -> Result<String>
+    where
+        I: IntoIterator<Item = Result<Captures<'t>>>,
+    {
+        let mut markers = Vec::new();
+        for caps in captures {
+            let caps = caps?;
+            let groups: Vec<_> = caps.iter().collect();
+            self.add_markers(&mut markers, &groups);
+        }
+        Ok(apply(text, markers))
+    }
+
+    /// Adds the markers for the groups of one match, in the order they have to be inserted.
+    fn add_markers<'h>(
+        &'h self,
+        markers: &mut Vec<(usize, &'h str)>,
+        groups: &[Option<Match<'_>>],
+    ) {
+        let match_end = match groups[0] {
+            Some(m) => m.end(),
+            None => return,
+        };
+        // Sort key: position, then a class for the kind of marker, then the order within the
+        // class. At the same position, end markers come first (they can only be for groups
+        // outside of the match when the match begins there), then the begin marker of the
+        // match, begin markers of groups in order of their opening parentheses (empty groups
+        // are treated like begin markers), the end marker of the match and begin markers of
+        // groups after the match.
+        let mut sorted = Vec::new();
+        for (i, m) in groups.iter().enumerate() {
+            let (m, (begin, end)) = match (m, self.markers.get(i)) {
+                (Some(m), Some(Some(markers))) => (m, markers),
+                _ => continue,
+            };
+            let (start, stop) = (m.start(), m.end());
+            if i == 0 {
+                sorted.push(((start, 1, Reverse(0), 0), begin.as_str(), ""));
+                sorted.push(((stop, 3, Reverse(0), 0), end.as_str(), ""));
+            } else if start == stop {
+                sorted.push(((start, 2, Reverse(0), i), begin.as_str(), end.as_str()));
+            } else {
+                let class = if start < match_end { 2 } else { 4 };
+                sorted.push(((start, class, Reverse(0), i), begin.as_str(), ""));
+                // Groups that start later end first
+                sorted.push((
+                    (stop, 0, Reverse(start), groups.len() - i),
+                    end.as_str(),
+                    "",
+                ));
+            }
+        }
+        sorted.sort_by_key(|marker| marker.0);
+        for ((pos, ..), first, second) in sorted {
+            markers.push((pos, first));
+            if !second.is_empty() {
+                markers.push((pos, second));
+            }
+        }
+    }
+}
+
+impl Default for Highlighter {
+    /// Same as `Highlighter::ansi()`.
+    fn default() -> Self {
+        Highlighter::ansi()
+    }
+}
+
+fn apply(text: &str, mut markers: Vec<(usize, &str)>) -> String {
+    // Stable sort, so markers at the same position stay in the order they were added in
+    markers.sort_by_key(|&(pos, _)| pos);
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (pos, marker) in markers {
+        if pos > last {
+            result.push_str(&text[last..pos]);
+            last = pos;
+        }
+        result.push_str(marker);
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Regex;
+
+    fn highlight(highlighter: &Highlighter, re: &str, text: &str) -> String {
+        let re = Regex::new(re).unwrap();
+        highlighter
+            .highlight_captures(text, re.captures_iter(text))
+            .unwrap()
+    }
+
+    #[test]
+    fn nested_groups() {
+        let mut highlighter = Highlighter::new("<0>", "</0>");
+        highlighter.group(1, "<1>", "</1>").group(2, "<2>", "</2>");
+        assert_eq!(
+            highlight(&highlighter, r"((a)b)", "xaby"),
+            "x<0><1><2>a</2>b</1></0>y"
+        );
+        assert_eq!(
+            highlight(&highlighter, r"(a)(b)", "ab ab"),
+            "<0><1>a</1><2>b</2></0> <0><1>a</1><2>b</2></0>"
+        );
+    }
+
+    #[test]
+    fn empty_and_unmatched_groups() {
+        let mut highlighter = Highlighter::new("[", "]");
+        highlighter.group(1, "<", ">").group(2, "{", "}");
+        assert_eq!(highlight(&highlighter, r"a()|(b)", "ab"), "[a<>][{b}]");
+        assert_eq!(highlight(&highlighter, r"\b", "a b"), "[]a[] []b[]");
+    }
+
+    #[test]
+    fn group_outside_of_match() {
+        let mut highlighter = Highlighter::new("[", "]");
+        highlighter.group(1, "<", ">");
+        assert_eq!(highlight(&highlighter, r"(?<=(a))b", "abc"), "<a>[b]c");
+    }
+
+    #[test]
+    fn clear_group() {
+        let mut highlighter = Highlighter::new("[", "]");
+        highlighter.group(1, "<", ">").clear_group(0);
+        assert_eq!(highlight(&highlighter, r"(a)b", "ab"), "<a>b");
+    }
+
+    #[test]
+    fn ansi() {
+        let highlighter = Highlighter::ansi();
+        assert_eq!(
+            highlight(&highlighter, r"a(b)", "xab"),
+            "x\x1b[1;31ma\x1b[1;32mb\x1b[0m\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn error() {
+        let re = crate::RegexBuilder::new(r"(a|b|ab)*(?=c)")
+            .backtrack_limit(1)
+            .build()
+            .unwrap();
+        let text = "abab";
+        let result = Highlighter::new("[", "]").highlight_matches(text, re.find_iter(text));
+        assert!(result.is_err());
+    }
+}
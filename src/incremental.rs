@@ -0,0 +1,211 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Updating the matches of a regex after an edit of the text, see `Regex::rematch`.
+//!
+//! A match attempt at a position only looks at a bounded part of the text around it: at most
+//! `max_lookbehind` characters before it and `max_size` characters after it. So attempts far
+//! enough away from the edit give the same result as before. The matches before the edited
+//! region are kept, and the search continues from the state after them. After the edited region,
+//! once a match is found that was also found before the edit, the search is in the same state as
+//! back then, so the rest of the old matches are kept as well.
+
+use std::ops::Range;
+
+use crate::{Matches, Regex, Result, RunConfig};
+
+/// An edit of a text: the bytes in `range` of the old text were replaced by `new_len` bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    /// The replaced range in the old text
+    pub range: Range<usize>,
+    /// The length in bytes of the text that replaced it
+    pub new_len: usize,
+}
+
+impl TextEdit {
+    /// Create an edit that replaces `range` of the old text with `new_text`.
+    pub fn new(range: Range<usize>, new_text: &str) -> TextEdit {
+        TextEdit {
+            range,
+            new_len: new_text.len(),
+        }
+    }
+
+    /// The end of the replacement in the new text.
+    fn new_end(&self) -> usize {
+        self.range.start + self.new_len
+    }
+
+    /// The position in the new text of `pos`, which is after the edit in the old text.
+    fn shift(&self, pos: usize) -> usize {
+        pos - self.range.end + self.new_end()
+    }
+}
+
+/// The positions in the new text `text` where match attempts can give a different result than
+/// before `edit`. `None` for the end means up to the end of the text, including there.
+pub(crate) fn dirty_range(regex: &Regex, text: &str, edit: &TextEdit) -> (usize, Option<usize>) {
+    let new_end = edit.new_end();
+    assert!(
+        new_end <= text.len()
+            && text.is_char_boundary(edit.range.start)
+            && text.is_char_boundary(new_end),
+        "edit {:?} doesn't fit text of length {}",
+        edit,
+        text.len()
+    );
+    let max_size = match regex.max_size {
+        Some(max_size) => max_size,
+        None => return (0, None),
+    };
+    // One more character on each side for word boundaries at the edge of a match
+    let start = text[..edit.range.start]
+        .char_indices()
+        .rev()
+        .nth(max_size)
+        .map_or(0, |(ix, _)| ix);
+    let end = text[new_end..]
+        .char_indices()
+        .nth(regex.max_lookbehind.saturating_add(1))
+        .map(|(ix, _)| new_end + ix);
+    (start, end)
+}
+
+pub(crate) fn rematch(
+    regex: &Regex,
+    text: &str,
+    old_matches: &[Range<usize>],
+    edit: &TextEdit,
+) -> Result<Vec<Range<usize>>> {
+    let (dirty_start, dirty_end) = dirty_range(regex, text, edit);
+    let mut result = Vec::new();
+    let mut matches = Matches {
+        re: regex,
+        text,
+        last_end: 0,
+        last_match: None,
+        config: RunConfig::default(),
+    };
+    for m in old_matches {
+        if m.start >= dirty_start {
+            break;
+        }
+        // The search finds these again, so just put it in the state after them
+        matches.advance(m.start, m.end);
+        result.push(m.clone());
+    }
+
+    let after: Vec<Range<usize>> = old_matches
+        .iter()
+        .filter(|m| m.start >= edit.range.end)
+        .map(|m| edit.shift(m.start)..edit.shift(m.end))
+        .collect();
+    let mut next_after = 0;
+    for m in matches {
+        let m = m?.range();
+        if dirty_end.map_or(false, |end| m.start >= end) {
+            while next_after < after.len() && after[next_after].start < m.start {
+                next_after += 1;
+            }
+            let same = after[next_after..]
+                .iter()
+                .take_while(|old| old.start == m.start)
+                .position(|old| *old == m);
+            if let Some(ix) = same {
+                result.extend(after[next_after + ix..].iter().cloned());
+                return Ok(result);
+            }
+        }
+        result.push(m);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EmptyMatchPolicy, RegexBuilder};
+
+    fn assert_rematch(regex: &Regex, old_text: &str, range: Range<usize>, new_text: &str) {
+        let old_matches: Vec<_> = regex
+            .find_iter(old_text)
+            .map(|m| m.unwrap().range())
+            .collect();
+        let text = [&old_text[..range.start], new_text, &old_text[range.end..]].concat();
+        let expected: Vec<_> = regex.find_iter(&text).map(|m| m.unwrap().range()).collect();
+        let edit = TextEdit::new(range, new_text);
+        assert_eq!(
+            rematch(regex, &text, &old_matches, &edit).unwrap(),
+            expected,
+            "regex {:?}, text {:?}, edit {:?}",
+            regex.as_str(),
+            text,
+            edit
+        );
+    }
+
+    fn assert_all_edits(regex: &Regex, old_text: &str) {
+        for start in 0..=old_text.len() {
+            for end in start..=old_text.len() {
+                for new_text in &["", "a", "b ", "ab"] {
+                    assert_rematch(regex, old_text, start..end, new_text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_as_find_iter() {
+        let text = "aab ab b aaab";
+        for re in &[
+            r"a+b",
+            r"(a)\1",
+            r"(?<=a)b",
+            r"\bab\b",
+            r"a*",
+            r"\w+(?= )",
+            r"b.*",
+        ] {
+            assert_all_edits(&Regex::new(re).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn retry_non_empty() {
+        let regex = RegexBuilder::new(r"a*?(?=b)|a")
+            .empty_match_policy(EmptyMatchPolicy::RetryNonEmpty)
+            .build()
+            .unwrap();
+        assert_all_edits(&regex, "aab ab b");
+    }
+
+    #[test]
+    fn dirty_range_is_bounded() {
+        let regex = Regex::new(r"(?<=x)a{2}").unwrap();
+        let text = "0123456789";
+        let edit = TextEdit::new(5..6, "x");
+        let (start, end) = dirty_range(&regex, text, &edit);
+        assert!(0 < start && start < 5, "{}", start);
+        assert!(matches!(end, Some(end) if 6 < end && end < 10), "{:?}", end);
+        let regex = Regex::new(r"a+").unwrap();
+        assert_eq!(dirty_range(&regex, text, &edit), (0, None));
+    }
+}
@@ -97,14 +97,30 @@ Escapes:
 `\H`
 : not hex digit (`[^0-9A-Fa-f]`) \
 `\e`
-: escape control character (`\x1B`)
+: escape control character (`\x1B`) \
+`\O`
+: any character including newline, regardless of the `s` flag; any single byte when searching
+  byte strings with the `bstr` feature \
+`\cM`
+: control character, e.g. `\cM` for carriage return (`\x0D`) \
+`\0`, `\033`
+: NUL and octal escapes of up to three digits; in character classes the leading `0` is optional \
+`\N{LATIN SMALL LETTER A}`
+: character by its Unicode name, only with the `unicode-names` feature
 
 Backreferences:
 
 `\1`
 : match the exact string that the first capture group matched \
 `\2`
-: backref to the second capture group, etc
+: backref to the second capture group, etc \
+`\g{10}`, `\g10`
+: backref to the tenth capture group; unlike `\10`, which can be an octal escape (see
+  `RegexBuilder::numbered_backrefs`), always a backref \
+`\g{-1}`
+: backref to the last capture group that was opened before it, `\g{-2}` to the one before, etc \
+`\g{name}`
+: same as `\k<name>`
 
 Named capture groups:
 
@@ -114,8 +130,23 @@ Named capture groups:
 : match the exact string that the capture group named *name* matched \
 `(?P<name>exp)`
 : same as `(?<name>exp)` for compatibility with Python, etc. \
+`(?'name'exp)`
+: same as `(?<name>exp)` for compatibility with .NET and PCRE \
 `(?P=name)`
-: same as `\k<name>` for compatibility with Python, etc.
+: same as `\k<name>` for compatibility with Python, etc. \
+`\k'name'`
+: same as `\k<name>` for compatibility with .NET and PCRE
+
+Names consist of letters, digits and `_`, and must not start with a digit. Each name can only be
+used for one group.
+
+Fragments that are defined with [`Fragments`](struct.Fragments.html) and passed to
+`RegexBuilder::fragments` can be included by name:
+
+`(?&name)`
+: match the fragment *name* as if it was written there in a non-capturing group \
+`(?&name(exp)(exp2))`
+: match the fragment *name* with the arguments *exp* and *exp2* for its parameters
 
 Look-around assertions for matching without changing the current position:
 
@@ -128,6 +159,10 @@ Look-around assertions for matching without changing the current position:
 `(?<!exp)`
 : negative look-behind, succeeds if *exp* doesn't match to the left
 
+A quantifier on a look-around is accepted like in PCRE: the look-around is matched once if the
+quantifier requires at least one repetition (e.g. `(?=exp)+`), is optional if it allows zero
+(e.g. `(?=exp)*`), and is skipped for `{0}`.
+
 Atomic groups using `(?>exp)` to prevent backtracking within `exp`, e.g.:
 
 ```
@@ -138,70 +173,348 @@ assert!(re.is_match("abcc").unwrap());
 assert!(!re.is_match("abc").unwrap());
 ```
 
+Groups inside an atomic group keep what they captured after it matched. If matching fails later
+and backtracks to before the atomic group, they are rolled back to the values from before it,
+like in PCRE:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"(?>(a+))b|a+c").unwrap();
+let caps = re.captures("aac").unwrap().unwrap();
+assert!(caps.get(1).is_none());
+```
+
+Script runs using `(*script_run:exp)` or `(*sr:exp)`, which only match if all characters matched by
+`exp` are from the same Unicode script, e.g. for detecting identifiers that mix lookalike Latin
+and Cyrillic letters. Characters that are used with several scripts, such as digits and
+punctuation, are allowed in any script run. `(*atomic_script_run:exp)` or `(*asr:exp)` is the
+same as `(?>(*sr:exp))`.
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"^(*sr:\w+)$").unwrap();
+assert!(re.is_match("paypal").unwrap());
+// The second `a` is the Cyrillic `а`
+assert!(!re.is_match("payp\u{430}l").unwrap());
+```
+
 [regex]: https://crates.io/crates/regex
 */
 
 #![doc(html_root_url = "https://docs.rs/fancy-regex/0.7.1")]
 #![deny(missing_docs)]
 #![deny(missing_debug_implementations)]
+// Without the compiler, parts of the parser are only used by it and by the tests.
+#![cfg_attr(not(feature = "compiler"), allow(dead_code, unused_imports))]
 
+use bit_set::BitSet;
+use std::cmp::{max, min};
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
 use std::ops::{Index, Range};
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::usize;
 
+#[cfg(feature = "compiler")]
+use once_cell::sync::OnceCell;
+
+// The modules are in three tiers: the parser is always built, the VM with the `vm` feature, and
+// everything else (including the regex crate) with the default `compiler` feature.
+#[cfg(feature = "compiler")]
 mod analyze;
+#[cfg(feature = "quickcheck")]
+mod arbitrary;
+#[cfg(all(feature = "compiler", feature = "bstr"))]
+mod byte_str;
+#[cfg(feature = "compiler")]
+mod cache;
+#[cfg(feature = "compiler")]
+mod canonical;
+#[cfg(feature = "vm")]
+mod case_fold;
+#[cfg(feature = "class-ranges")]
+mod class_ranges;
+#[cfg(feature = "compiler")]
 mod compile;
+#[cfg(feature = "vm")]
+mod config;
+#[cfg(feature = "conformance")]
+pub mod conformance;
+#[cfg(feature = "containment")]
+mod containment;
+#[cfg(feature = "differential")]
+pub mod differential;
 mod error;
+#[cfg(feature = "compiler")]
 mod expand;
+#[cfg(feature = "compiler")]
+mod explain;
+mod flavor;
+mod fragments;
+#[cfg(feature = "compiler")]
+mod from_captures;
+#[cfg(feature = "compiler")]
+mod generate;
+#[cfg(feature = "compiler")]
+mod highlight;
+#[cfg(feature = "compiler")]
+mod incremental;
+mod lint;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "compiler")]
+mod multi;
+#[cfg(all(feature = "compiler", feature = "unicode-normalization"))]
+mod normalize;
+#[cfg(feature = "compiler")]
+mod offsets;
+#[cfg(all(feature = "compiler", feature = "rayon"))]
+mod parallel;
 mod parse;
+#[cfg(feature = "compiler")]
+mod pattern_set;
+#[cfg(feature = "vm")]
+pub mod prog;
+#[cfg(feature = "compiler")]
 mod replacer;
+#[cfg(feature = "compiler")]
+mod resume;
+#[cfg(all(feature = "compiler", feature = "serde"))]
+mod serialize;
+#[cfg(feature = "compiler")]
+mod simplify;
+#[cfg(feature = "compiler")]
+mod static_regex;
+#[cfg(feature = "compiler")]
+mod stream;
+#[cfg(feature = "compiler")]
+mod token;
+#[cfg(feature = "vm")]
 mod vm;
 
-use crate::analyze::analyze;
-use crate::compile::compile;
+#[cfg(feature = "compiler")]
+use crate::analyze::analyze_with_options;
+#[cfg(feature = "compiler")]
+use crate::compile::{compile_with_options, LazyRegex};
 use crate::parse::{ExprTree, NamedGroups, Parser};
-use crate::vm::Prog;
-
-pub use crate::error::{Error, Result};
+#[cfg(feature = "compiler")]
+use crate::pattern_set::Interner;
+#[cfg(feature = "compiler")]
+use crate::vm::{GroupIteration, Insn, Prog};
+
+#[cfg(feature = "quickcheck")]
+pub use crate::arbitrary::SampleText;
+#[cfg(all(feature = "compiler", feature = "bstr"))]
+pub use crate::byte_str::{BStrCaptures, BStrMatch, BStrMatches};
+#[cfg(feature = "compiler")]
+pub use crate::canonical::CanonicalPattern;
+#[cfg(feature = "vm")]
+pub use crate::config::RunConfig;
+pub use crate::error::{CompileError, Error, ParseError, Result, RuntimeError};
+#[cfg(feature = "compiler")]
 pub use crate::expand::Expander;
+#[cfg(feature = "compiler")]
+pub use crate::explain::NoMatch;
+pub use crate::fragments::Fragments;
+#[cfg(feature = "compiler")]
+pub use crate::from_captures::FromCaptures;
+#[cfg(feature = "compiler")]
+pub use crate::generate::Generator;
+#[cfg(feature = "compiler")]
+pub use crate::highlight::Highlighter;
+#[cfg(feature = "compiler")]
+pub use crate::incremental::TextEdit;
+pub use crate::lint::{lint, Lint, LintKind, Suggestion};
+#[cfg(feature = "mmap")]
+pub use crate::mmap::{FileCaptureMatches, FileMatches, MappedFile};
+#[cfg(feature = "compiler")]
+pub use crate::multi::{MultiCaptureMatches, MultiRegex};
+#[cfg(feature = "compiler")]
+pub use crate::offsets::OffsetMap;
+#[cfg(all(feature = "compiler", feature = "rayon"))]
+pub use crate::parallel::ParMatches;
+#[cfg(feature = "compiler")]
+pub use crate::pattern_set::PatternSet;
+#[cfg(feature = "compiler")]
 pub use crate::replacer::{NoExpand, Replacer, ReplacerRef};
+#[cfg(feature = "compiler")]
+pub use crate::resume::{Progress, ResumableFind};
+#[cfg(feature = "compiler")]
+pub use crate::static_regex::StaticRegex;
+#[cfg(feature = "compiler")]
+pub use crate::token::{MatchPolicy, TokenMatcher};
+#[cfg(feature = "derive")]
+pub use fancy_regex_derive::FromCaptures;
 use std::borrow::Cow;
+use std::io;
 
 const MAX_RECURSION: usize = 64;
 
+/// Index of the first instruction after the `.*?` prefix of a fancy program, see `new_options`.
+#[cfg(feature = "compiler")]
+const ANCHORED_START_PC: usize = 3;
+
 // the public API
 
 /// A builder for a `Regex` to allow configuring options.
 #[derive(Debug)]
 pub struct RegexBuilder(RegexOptions);
 
+/// What groups inside a repeated expression capture, see
+/// [`RegexBuilder::repeated_groups`](struct.RegexBuilder.html#method.repeated_groups).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum RepeatedGroups {
+    /// Groups keep what they captured in earlier iterations if they don't match in a later one,
+    /// like in PCRE, Perl, Python and the regex crate. A backref inside the group it refers to
+    /// matches what the group captured in the previous iteration. A backref to a group that
+    /// hasn't matched doesn't match.
+    Pcre,
+    /// Groups are reset at the start of each iteration, like in JavaScript. A backref to a group
+    /// that hasn't matched in the current iteration, e.g. one inside the group it refers to,
+    /// matches the empty string.
+    JavaScript,
+}
+
+/// Where iterating over matches continues after an empty match, see
+/// [`RegexBuilder::empty_match_policy`](struct.RegexBuilder.html#method.empty_match_policy).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum EmptyMatchPolicy {
+    /// Continue searching at the next character. An empty match immediately after the previous
+    /// match is skipped, like in the regex crate.
+    SkipChar,
+    /// Continue searching at the next grapheme cluster boundary, e.g. after a letter and its
+    /// combining accents instead of between them. An empty match immediately after the previous
+    /// match is skipped. Requires the `unicode-segmentation` feature.
+    #[cfg(feature = "unicode-segmentation")]
+    SkipGrapheme,
+    /// Look for a non-empty match at the same position first, and only continue searching at the
+    /// next character if there is none, like PCRE2's `pcre2demo`, Perl, Python and JavaScript. An
+    /// empty match immediately after a non-empty match is found as well.
+    RetryNonEmpty,
+}
+
+/// What happens to repeats with a count above
+/// [`RegexBuilder::max_repeat`](struct.RegexBuilder.html#method.max_repeat), see
+/// [`RegexBuilder::large_repeat_policy`](struct.RegexBuilder.html#method.large_repeat_policy).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum LargeRepeatPolicy {
+    /// Reject the pattern with `ParseError::RepeatTooLarge`.
+    Reject,
+    /// Match the repeat by counting iterations in the backtracking VM, instead of letting the
+    /// regex crate match it, which expands it into as many copies of the repeated expression as
+    /// the count. This keeps the compiled regex small, but matching the repeat needs
+    /// backtracking.
+    Count,
+}
+
+/// How an escape of a number with more than one digit like `\10` is read, see
+/// [`RegexBuilder::numbered_backrefs`](struct.RegexBuilder.html#method.numbered_backrefs).
+/// Escapes with a single digit like `\1` are always backrefs, and `\g{10}` is always a backref
+/// to group 10.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum NumberedBackrefs {
+    /// Like PCRE and Perl: the whole number is a backref, except that two or three octal digits
+    /// are an octal escape if there are fewer groups before the escape, e.g. `\12` is a newline
+    /// in a pattern with fewer than twelve groups before it.
+    Pcre,
+    /// Like Python: three octal digits are an octal escape, otherwise the first two digits are a
+    /// backref, e.g. `\10` is always group 10 and `\108` is group 10 followed by `8`.
+    Python,
+    /// Like Java: the following digits are only part of the backref while the number is at most
+    /// the number of groups before the escape, e.g. `\10` is group 1 followed by `0` in a
+    /// pattern with fewer than ten groups before it.
+    Java,
+}
+
+/// Which ends of the range a match of
+/// [`Regex::find_in`](struct.Regex.html#method.find_in) is anchored at.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum RangeAnchor {
+    /// The match can start and end anywhere in the range.
+    Unanchored,
+    /// The match starts at the start of the range.
+    Start,
+    /// The match ends at the end of the range.
+    End,
+    /// The match starts at the start and ends at the end of the range, e.g. for checking whether
+    /// a selection matches exactly.
+    Both,
+}
+
 /// A compiled regular expression.
+///
+/// Cloning a `Regex` is cheap, clones share the compiled program. A `Regex` is `Send` and `Sync`,
+/// so it can also be used from multiple threads at the same time without wrapping it in an `Arc`.
+/// For a regex in a `static` item that is built on first use, see
+/// [`StaticRegex`](struct.StaticRegex.html).
 #[derive(Clone)]
+#[cfg(feature = "compiler")]
 pub struct Regex {
-    inner: RegexImpl,
+    inner: Arc<RegexImpl>,
     named_groups: Arc<NamedGroups>,
+    group_spans: Arc<Vec<Range<usize>>>,
+    /// Maximum number of characters a match attempt can inspect after its start, `None` if
+    /// unbounded. Used for working on text in chunks, e.g. when streaming.
+    max_size: Option<usize>,
+    /// Maximum number of characters a match attempt can inspect before its start.
+    max_lookbehind: usize,
+    hints: Arc<SearchHints>,
+    /// The number of each group in the compiled regex by its number in the pattern, if not all
+    /// groups are captured (see `RegexBuilder::captured_groups`)
+    group_map: Option<Arc<Vec<Option<usize>>>>,
+    /// Regex for matching whole lines, compiled when it's first needed, see `lines_matching`
+    whole_line: Arc<Mutex<Option<Regex>>>,
+}
+
+/// What is known about the matches of a regex, for skipping positions where no match can start.
+#[derive(Debug)]
+#[cfg(feature = "compiler")]
+struct SearchHints {
+    /// Minimum number of characters of a match
+    min_size: usize,
+    /// The bytes that a match can start with, `None` if that's not known
+    first_bytes: Option<BitSet>,
+    /// Literal text that every match ends with
+    suffix: Option<String>,
+    /// Finds the literals that a match starts with, see `Info::literal_prefixes`
+    #[cfg(feature = "perf-literal")]
+    prefilter: Option<Arc<aho_corasick::AhoCorasick>>,
 }
 
 // Separate enum because we don't want to expose any of this
-#[derive(Clone)]
+#[cfg(feature = "compiler")]
+#[allow(clippy::large_enum_variant)] // only ever stored behind the `Arc` in `Regex`
 enum RegexImpl {
     // Do we want to box this? It's pretty big...
     Wrap {
         inner: regex::Regex,
         options: RegexOptions,
+        /// Program for matching only at a position, compiled when it's first needed
+        anchored: Mutex<Option<Arc<Prog>>>,
+        /// Program for matching only at a position that can backtrack into every part of the
+        /// regex, for finding a non-empty match where the regex crate prefers an empty one.
+        /// Compiled when it's first needed.
+        exhaustive: Mutex<Option<Arc<Prog>>>,
     },
     Fancy {
         prog: Prog,
         n_groups: usize,
+        /// Save slot with the index of the alternative that matched, see `record_branch`
+        branch_slot: Option<usize>,
+        /// Program for `is_match`, which doesn't save the positions of groups
+        match_prog: Prog,
+        /// Program for finding matches, which only saves the position of group 0
+        find_prog: Prog,
         options: RegexOptions,
     },
 }
 
 /// A single match of a regex or group in an input text
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg(feature = "compiler")]
 pub struct Match<'t> {
     text: &'t str,
     start: usize,
@@ -216,13 +529,16 @@ pub struct Match<'t> {
 /// `'r` is the lifetime of the compiled regular expression and `'t` is the
 /// lifetime of the matched string.
 #[derive(Debug)]
+#[cfg(feature = "compiler")]
 pub struct Matches<'r, 't> {
     re: &'r Regex,
     text: &'t str,
     last_end: usize,
     last_match: Option<usize>,
+    config: RunConfig,
 }
 
+#[cfg(feature = "compiler")]
 impl<'r, 't> Matches<'r, 't> {
     /// Return the text being searched.
     pub fn text(&self) -> &'t str {
@@ -231,46 +547,127 @@ impl<'r, 't> Matches<'r, 't> {
 
     /// Return the underlying regex.
     pub fn regex(&self) -> &'r Regex {
-        &self.re
+        self.re
+    }
+
+    /// Return the byte position in the text where the search for the next match starts.
+    ///
+    /// This can be saved and passed to `Regex::find_iter_from_pos` later to continue the search,
+    /// e.g. after the text was edited behind that position. Once the iterator is exhausted, this
+    /// is the length of the text.
+    pub fn offset(&self) -> usize {
+        min(self.last_end, self.text.len())
+    }
+
+    fn remaining_hint(&self) -> (usize, Option<usize>) {
+        // At most one (possibly empty) match per position, including the end of the text
+        let positions = (self.text.len() + 1).saturating_sub(self.last_end);
+        if self.re.options().empty_match_policy == EmptyMatchPolicy::RetryNonEmpty {
+            // An empty match can be followed by a non-empty one at the same position
+            (0, Some(positions.saturating_mul(2)))
+        } else {
+            (0, Some(positions))
+        }
+    }
+
+    /// Whether the previous match was empty and a non-empty match at the same position needs to
+    /// be tried next, see `EmptyMatchPolicy::RetryNonEmpty`.
+    fn retry_non_empty(&self) -> bool {
+        self.last_match == Some(self.last_end)
+            && self.re.options().empty_match_policy == EmptyMatchPolicy::RetryNonEmpty
+    }
+
+    /// Updates where the search continues after a match from `start` to `end`. Returns `false`
+    /// if the match is to be ignored, because it's empty and immediately follows the previous
+    /// match.
+    fn advance(&mut self, start: usize, end: usize) -> bool {
+        let policy = self.re.options().empty_match_policy;
+        if start != end {
+            self.last_end = end;
+            // With `RetryNonEmpty`, an empty match may follow immediately
+            self.last_match = match policy {
+                EmptyMatchPolicy::RetryNonEmpty => None,
+                _ => Some(end),
+            };
+            return true;
+        }
+        // This is an empty match. To ensure we make progress, start the next search at the
+        // smallest possible starting position of the next match following this one, or look for
+        // a non-empty match at the same position first.
+        let ignore = Some(end) == self.last_match;
+        self.last_end = match policy {
+            EmptyMatchPolicy::SkipChar => next_utf8(self.text, end),
+            #[cfg(feature = "unicode-segmentation")]
+            EmptyMatchPolicy::SkipGrapheme => next_grapheme(self.text, end),
+            EmptyMatchPolicy::RetryNonEmpty => end,
+        };
+        self.last_match = Some(end);
+        // Don't accept empty matches immediately following a match.
+        !ignore
+    }
+
+    /// After an empty match, runs the regex for a non-empty match at the same position, and if
+    /// there is none, moves on to the next character.
+    fn find_non_empty(&mut self, groups: bool) -> Result<Option<Vec<usize>>> {
+        let saves = self
+            .re
+            .run_anchored(self.text, self.last_end, groups, true, &self.config)?;
+        self.last_end = match saves {
+            Some(ref saves) => saves[1],
+            None => next_utf8(self.text, self.last_end),
+        };
+        self.last_match = None;
+        Ok(saves)
     }
 }
 
+#[cfg(feature = "compiler")]
 impl<'r, 't> Iterator for Matches<'r, 't> {
     type Item = Result<Match<'t>>;
 
     /// Adapted from the `regex` crate. Calls `find_from_pos` repeatedly.
-    /// Ignores empty matches immediately after a match.
+    /// How empty matches are handled depends on the regex's `EmptyMatchPolicy`.
     fn next(&mut self) -> Option<Self::Item> {
         if self.last_end > self.text.len() {
             return None;
         }
 
-        let mat = match self.re.find_from_pos(self.text, self.last_end) {
+        if self.retry_non_empty() {
+            match self.find_non_empty(false) {
+                Err(error) => return Some(Err(error)),
+                Ok(Some(saves)) => return Some(Ok(Match::new(self.text, saves[0], saves[1]))),
+                Ok(None) => return self.next(),
+            }
+        }
+
+        let mat = match self
+            .re
+            .find_from_pos_impl(self.text, self.last_end, &self.config)
+        {
             Err(error) => return Some(Err(error)),
-            Ok(None) => return None,
+            Ok(None) => {
+                self.last_end = self.text.len() + 1;
+                return None;
+            }
             Ok(Some(mat)) => mat,
         };
 
-        if mat.start == mat.end {
-            // This is an empty match. To ensure we make progress, start
-            // the next search at the smallest possible starting position
-            // of the next match following this one.
-            self.last_end = next_utf8(self.text, mat.end);
-            // Don't accept empty matches immediately following a match.
+        if !self.advance(mat.start, mat.end) {
             // Just move on to the next match.
-            if Some(mat.end) == self.last_match {
-                return self.next();
-            }
-        } else {
-            self.last_end = mat.end;
+            return self.next();
         }
 
-        self.last_match = Some(mat.end);
-
         Some(Ok(mat))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining_hint()
+    }
 }
 
+#[cfg(feature = "compiler")]
+impl<'r, 't> FusedIterator for Matches<'r, 't> {}
+
 /// An iterator that yields all non-overlapping capture groups matching a
 /// particular regular expression.
 ///
@@ -279,8 +676,10 @@ impl<'r, 't> Iterator for Matches<'r, 't> {
 /// `'r` is the lifetime of the compiled regular expression and `'t` is the
 /// lifetime of the matched string.
 #[derive(Debug)]
+#[cfg(feature = "compiler")]
 pub struct CaptureMatches<'r, 't>(Matches<'r, 't>);
 
+#[cfg(feature = "compiler")]
 impl<'r, 't> CaptureMatches<'r, 't> {
     /// Return the text being searched.
     pub fn text(&self) -> &'t str {
@@ -289,52 +688,276 @@ impl<'r, 't> CaptureMatches<'r, 't> {
 
     /// Return the underlying regex.
     pub fn regex(&self) -> &'r Regex {
-        &self.0.re
+        self.0.re
+    }
+
+    /// Return the byte position in the text where the search for the next match starts, see
+    /// `Matches::offset`.
+    pub fn offset(&self) -> usize {
+        self.0.offset()
     }
 }
 
+#[cfg(feature = "compiler")]
 impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
     type Item = Result<Captures<'t>>;
 
     /// Adapted from the `regex` crate. Calls `captures_from_pos` repeatedly.
-    /// Ignores empty matches immediately after a match.
+    /// How empty matches are handled depends on the regex's `EmptyMatchPolicy`.
     fn next(&mut self) -> Option<Self::Item> {
         if self.0.last_end > self.0.text.len() {
             return None;
         }
 
+        if self.0.retry_non_empty() {
+            match self.0.find_non_empty(true) {
+                Err(error) => return Some(Err(error)),
+                Ok(Some(saves)) => {
+                    let captures = self.0.re.fancy_captures(self.0.text, saves, &self.0.config);
+                    return Some(Ok(captures));
+                }
+                Ok(None) => return self.next(),
+            }
+        }
+
         let captures = match self.0.re.captures_from_pos(self.0.text, self.0.last_end) {
             Err(error) => return Some(Err(error)),
-            Ok(None) => return None,
+            Ok(None) => {
+                self.0.last_end = self.0.text.len() + 1;
+                return None;
+            }
             Ok(Some(captures)) => captures,
         };
 
         let mat = captures
             .get(0)
             .expect("`Captures` is expected to have entire match at 0th position");
-        if mat.start == mat.end {
-            self.0.last_end = next_utf8(self.0.text, mat.end);
-            if Some(mat.end) == self.0.last_match {
-                return self.next();
+        if !self.0.advance(mat.start, mat.end) {
+            return self.next();
+        }
+
+        Some(Ok(captures))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.remaining_hint()
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl<'r, 't> FusedIterator for CaptureMatches<'r, 't> {}
+
+/// An iterator over the fields of a string that are separated by matches of a regex, yielding
+/// each field together with the capture groups of the separator that follows it.
+///
+/// The iterator yields a `Result<(&str, Option<Captures>)>`. The captures are `None` for the last
+/// field, which is followed by the end of the text instead of a separator.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the string being split.
+#[derive(Debug)]
+#[cfg(feature = "compiler")]
+pub struct SplitCaptures<'r, 't> {
+    finder: CaptureMatches<'r, 't>,
+    last: usize,
+    /// Number of fields that may still be yielded, or `None` if unlimited
+    remaining: Option<usize>,
+}
+
+#[cfg(feature = "compiler")]
+impl<'r, 't> Iterator for SplitCaptures<'r, 't> {
+    type Item = Result<(&'t str, Option<Captures<'t>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = self.finder.text();
+        match self.remaining {
+            Some(0) => return None,
+            Some(1) => {
+                self.remaining = Some(0);
+                return Some(Ok((&text[self.last..], None)));
             }
-        } else {
-            self.0.last_end = mat.end;
+            Some(ref mut n) => *n -= 1,
+            None => {}
+        }
+        match self.finder.next() {
+            Some(Err(error)) => {
+                self.remaining = Some(0);
+                Some(Err(error))
+            }
+            Some(Ok(captures)) => {
+                let m = captures
+                    .get(0)
+                    .expect("`Captures` is expected to have entire match at 0th position");
+                let field = &text[self.last..m.start()];
+                self.last = m.end();
+                Some(Ok((field, Some(captures))))
+            }
+            None => {
+                if self.last > text.len() {
+                    None
+                } else {
+                    let field = &text[self.last..];
+                    // make sure the last field is only returned once
+                    self.last = text.len() + 1;
+                    self.remaining = Some(0);
+                    Some(Ok((field, None)))
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the parts of a string that end with a match of a regex, see
+/// [Regex::split_inclusive()].
+///
+/// The iterator yields a `Result<&str>`. Each part includes the matched separator at its end,
+/// except for the last part if the text doesn't end with a separator.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the string being split.
+#[derive(Debug)]
+#[cfg(feature = "compiler")]
+pub struct SplitInclusive<'r, 't> {
+    finder: Matches<'r, 't>,
+    last: usize,
+}
+
+#[cfg(feature = "compiler")]
+impl<'r, 't> Iterator for SplitInclusive<'r, 't> {
+    type Item = Result<&'t str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = self.finder.text();
+        match self.finder.next() {
+            Some(Err(error)) => {
+                self.last = text.len();
+                Some(Err(error))
+            }
+            Some(Ok(m)) => {
+                let part = &text[self.last..m.end()];
+                self.last = m.end();
+                Some(Ok(part))
+            }
+            None => {
+                if self.last >= text.len() {
+                    None
+                } else {
+                    let part = &text[self.last..];
+                    self.last = text.len();
+                    Some(Ok(part))
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the lines of a string that contain a match of a regex, see
+/// [Regex::grep_lines()].
+///
+/// The iterator yields a `Result<(usize, Range<usize>, Match)>` with the line number (starting
+/// at 1), the byte range of the line (without the line terminator) and the first match that
+/// starts in the line.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the searched string.
+#[derive(Debug)]
+#[cfg(feature = "compiler")]
+pub struct GrepLines<'r, 't> {
+    finder: Matches<'r, 't>,
+    line_number: usize,
+    line_start: usize,
+}
+
+#[cfg(feature = "compiler")]
+impl<'r, 't> Iterator for GrepLines<'r, 't> {
+    type Item = Result<(usize, Range<usize>, Match<'t>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let text = self.finder.text;
+        let m = match self.finder.next()? {
+            Ok(m) => m,
+            Err(error) => {
+                self.finder.last_end = text.len() + 1;
+                return Some(Err(error));
+            }
+        };
+        // A newline at the end of the text terminates the last line, it doesn't start a new one
+        if m.start() == text.len() && text.ends_with('\n') {
+            return None;
+        }
+
+        let skipped = &text[self.line_start..m.start()];
+        if let Some(ix) = skipped.rfind('\n') {
+            self.line_number += skipped.bytes().filter(|&b| b == b'\n').count();
+            self.line_start += ix + 1;
         }
+        let line_end = text[m.start()..]
+            .find('\n')
+            .map_or(text.len(), |ix| m.start() + ix);
 
-        self.0.last_match = Some(mat.end);
+        // Only report each line once, continue with the next line (or after the match if it
+        // spans multiple lines)
+        self.finder.last_end = max(line_end + 1, m.end());
+        self.finder.last_match = None;
 
-        Some(Ok(captures))
+        Some(Ok((self.line_number, self.line_start..line_end, m)))
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl<'r, 't> FusedIterator for GrepLines<'r, 't> {}
+
+/// An iterator over the lines that a regex matches completely, see
+/// [`Regex::lines_matching`](struct.Regex.html#method.lines_matching).
+///
+/// The iterator yields a `Result<&str>` and stops after an error.
+#[derive(Debug)]
+#[cfg(feature = "compiler")]
+pub struct LinesMatching<'t> {
+    /// The regex anchored at both ends, `None` after an error
+    regex: Option<Regex>,
+    /// The error from compiling the anchored regex, which is returned first
+    error: Option<Error>,
+    lines: std::str::Lines<'t>,
+}
+
+#[cfg(feature = "compiler")]
+impl<'t> Iterator for LinesMatching<'t> {
+    type Item = Result<&'t str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let regex = self.regex.as_ref()?;
+        for line in &mut self.lines {
+            match regex.is_match(line) {
+                Ok(true) => return Some(Ok(line)),
+                Ok(false) => {}
+                Err(error) => {
+                    self.regex = None;
+                    return Some(Err(error));
+                }
+            }
+        }
+        None
     }
 }
 
+#[cfg(feature = "compiler")]
+impl<'t> FusedIterator for LinesMatching<'t> {}
+
 /// A set of capture groups found for a regex.
 #[derive(Debug)]
+#[cfg(feature = "compiler")]
 pub struct Captures<'t> {
     inner: CapturesImpl<'t>,
     named_groups: Arc<NamedGroups>,
+    group_map: Option<Arc<Vec<Option<usize>>>>,
 }
 
 #[derive(Debug)]
+#[cfg(feature = "compiler")]
 enum CapturesImpl<'t> {
     Wrap {
         text: &'t str,
@@ -342,23 +965,161 @@ enum CapturesImpl<'t> {
     },
     Fancy {
         text: &'t str,
-        saves: Vec<usize>,
+        n_groups: usize,
+        branch: Option<usize>,
+        saves: LazySaves,
     },
 }
 
+/// The saves of a match, where the groups other than group 0 may not have been found yet.
+#[cfg(feature = "compiler")]
+struct LazySaves {
+    /// The saves found with the match, which may only be those of group 0
+    found: Vec<usize>,
+    /// The regex to run again at the start of the match to find the other groups (and the
+    /// capture history), if that's needed
+    pending: Option<Arc<RegexImpl>>,
+    /// The saves of all groups and the iterations of the groups as `(slot, start, end)` (if the
+    /// regex records the capture history), once they have been found
+    groups: OnceCell<(Vec<usize>, Option<Vec<GroupIteration>>)>,
+    /// Where the text has replacements if it's a byte string, for running the regex again the
+    /// same way, see `RunConfig::byte_string`
+    byte_string: Option<Arc<[(usize, usize)]>>,
+}
+
+#[cfg(feature = "compiler")]
+impl LazySaves {
+    fn new(
+        found: Vec<usize>,
+        pending: Option<Arc<RegexImpl>>,
+        byte_string: Option<Arc<[(usize, usize)]>>,
+    ) -> LazySaves {
+        LazySaves {
+            found,
+            pending,
+            groups: OnceCell::new(),
+            byte_string,
+        }
+    }
+
+    /// The saves of group 0.
+    fn whole_match(&self) -> &[usize] {
+        &self.found[..2]
+    }
+
+    fn groups(&self, text: &str) -> &[usize] {
+        match self.pending {
+            Some(ref inner) => &self.find_groups(inner, text).0,
+            None => &self.found,
+        }
+    }
+
+    fn history(&self, text: &str) -> Option<&[GroupIteration]> {
+        let inner = self.pending.as_ref()?;
+        self.find_groups(inner, text).1.as_deref()
+    }
+
+    fn find_groups(
+        &self,
+        inner: &RegexImpl,
+        text: &str,
+    ) -> &(Vec<usize>, Option<Vec<GroupIteration>>) {
+        self.groups.get_or_init(|| {
+            let (prog, n_groups, options) = match inner {
+                RegexImpl::Fancy {
+                    prog,
+                    n_groups,
+                    options,
+                    ..
+                } => (prog, *n_groups, options),
+                RegexImpl::Wrap { .. } => return (self.found.clone(), None),
+            };
+            // This repeats what was done for finding the match, minus the attempts at earlier
+            // positions. The limits are lifted so that it can't fail halfway through a match
+            // that was already found.
+            let options = RegexOptions {
+                backtrack_limit: std::usize::MAX,
+                memory_limit: std::usize::MAX,
+                ..options.clone()
+            };
+            let mut config = RunConfig::new();
+            config.byte_string = self.byte_string.clone();
+            let result = if options.capture_history {
+                vm::run_with_history(
+                    prog,
+                    text,
+                    self.found[0],
+                    ANCHORED_START_PC,
+                    &options,
+                    &config,
+                )
+                .map(|result| result.map(|(saves, history)| (saves, Some(history))))
+            } else {
+                vm::run_from_pc(
+                    prog,
+                    text,
+                    self.found[0],
+                    ANCHORED_START_PC,
+                    0,
+                    &options,
+                    &config,
+                )
+                .map(|result| result.map(|saves| (saves, None)))
+            };
+            let (mut saves, history) = result
+                .ok()
+                .flatten()
+                .expect("running the regex again without limits finds the match again");
+            saves.truncate(n_groups * 2);
+            (saves, history)
+        })
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl Debug for LazySaves {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazySaves")
+            .field("found", &self.found)
+            .field("pending", &self.pending.is_some())
+            .field("groups", &self.groups.get())
+            .finish()
+    }
+}
+
 /// Iterator for captured groups in order in which they appear in the regex.
 #[derive(Debug)]
+#[cfg(feature = "compiler")]
 pub struct SubCaptureMatches<'c, 't> {
     caps: &'c Captures<'t>,
     i: usize,
 }
 
-#[derive(Clone, Debug)]
-struct RegexOptions {
+/// The options that a `Regex` was built with, see
+/// [`Regex::options`](struct.Regex.html#method.options) and
+/// [`RegexBuilder`](struct.RegexBuilder.html) for what they mean.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct RegexOptions {
     pattern: String,
     backtrack_limit: usize,
+    memory_limit: usize,
     delegate_size_limit: Option<usize>,
     delegate_dfa_size_limit: Option<usize>,
+    record_branch: bool,
+    captured_groups: Option<Vec<String>>,
+    repeated_groups: RepeatedGroups,
+    empty_match_policy: EmptyMatchPolicy,
+    swap_greed: bool,
+    strict: bool,
+    max_repeat: Option<usize>,
+    large_repeat_policy: LargeRepeatPolicy,
+    numbered_backrefs: NumberedBackrefs,
+    capture_history: bool,
+    fragments: Fragments,
+    #[cfg(feature = "unicode-normalization")]
+    normalization_insensitive: bool,
+    #[cfg(feature = "unicode-normalization")]
+    canonical_backrefs: bool,
 }
 
 impl Default for RegexOptions {
@@ -366,12 +1127,136 @@ impl Default for RegexOptions {
         RegexOptions {
             pattern: String::new(),
             backtrack_limit: 1_000_000,
+            memory_limit: 100 << 20,
             delegate_size_limit: None,
             delegate_dfa_size_limit: None,
+            record_branch: false,
+            captured_groups: None,
+            repeated_groups: RepeatedGroups::Pcre,
+            empty_match_policy: EmptyMatchPolicy::SkipChar,
+            swap_greed: false,
+            strict: false,
+            max_repeat: None,
+            large_repeat_policy: LargeRepeatPolicy::Reject,
+            numbered_backrefs: NumberedBackrefs::Pcre,
+            capture_history: false,
+            fragments: Fragments::new(),
+            #[cfg(feature = "unicode-normalization")]
+            normalization_insensitive: false,
+            #[cfg(feature = "unicode-normalization")]
+            canonical_backrefs: false,
         }
     }
 }
 
+impl RegexOptions {
+    /// The original pattern.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The maximum number of times that backtracking can happen, see
+    /// `RegexBuilder::backtrack_limit`.
+    pub fn backtrack_limit(&self) -> usize {
+        self.backtrack_limit
+    }
+
+    /// The maximum memory for backtracking, see `RegexBuilder::memory_limit`.
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    /// The size limit of the regex crate for delegated regexes, `None` for its default. See
+    /// `RegexBuilder::delegate_size_limit`.
+    pub fn delegate_size_limit(&self) -> Option<usize> {
+        self.delegate_size_limit
+    }
+
+    /// The size limit of the regex crate's DFA for delegated regexes, `None` for its default.
+    /// See `RegexBuilder::delegate_dfa_size_limit`.
+    pub fn delegate_dfa_size_limit(&self) -> Option<usize> {
+        self.delegate_dfa_size_limit
+    }
+
+    /// Whether the alternative that matched is recorded, see `RegexBuilder::record_branch`.
+    pub fn record_branch(&self) -> bool {
+        self.record_branch
+    }
+
+    /// The groups that are captured, or `None` if all of them are. See
+    /// `RegexBuilder::captured_groups`.
+    pub fn captured_groups(&self) -> Option<&[String]> {
+        self.captured_groups.as_deref()
+    }
+
+    /// What groups inside a repeat capture, see `RegexBuilder::repeated_groups`.
+    pub fn repeated_groups(&self) -> RepeatedGroups {
+        self.repeated_groups
+    }
+
+    /// Where iterating continues after empty matches, see `RegexBuilder::empty_match_policy`.
+    pub fn empty_match_policy(&self) -> EmptyMatchPolicy {
+        self.empty_match_policy
+    }
+
+    /// Whether greedy and lazy quantifiers are swapped, see `RegexBuilder::swap_greed`.
+    pub fn swap_greed(&self) -> bool {
+        self.swap_greed
+    }
+
+    /// Whether sloppy syntax is rejected, see `RegexBuilder::strict`.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// The bound for the counts of repeats, see `RegexBuilder::max_repeat`.
+    pub fn max_repeat(&self) -> Option<usize> {
+        self.max_repeat
+    }
+
+    /// What happens to repeats with a larger count than `max_repeat`, see
+    /// `RegexBuilder::large_repeat_policy`.
+    pub fn large_repeat_policy(&self) -> LargeRepeatPolicy {
+        self.large_repeat_policy
+    }
+
+    /// How escapes like `\10` are read, see `RegexBuilder::numbered_backrefs`.
+    pub fn numbered_backrefs(&self) -> NumberedBackrefs {
+        self.numbered_backrefs
+    }
+
+    /// Whether every iteration of groups inside repeats is recorded, see
+    /// `RegexBuilder::capture_history`.
+    pub fn capture_history(&self) -> bool {
+        self.capture_history
+    }
+
+    /// The fragments that the pattern can include, see `RegexBuilder::fragments`.
+    pub fn fragments(&self) -> &Fragments {
+        &self.fragments
+    }
+
+    /// Whether literals match regardless of Unicode normalization, see
+    /// `RegexBuilder::normalization_insensitive`. Requires the `unicode-normalization` feature.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalization_insensitive(&self) -> bool {
+        self.normalization_insensitive
+    }
+
+    /// Whether backrefs match canonically equivalent text, see
+    /// `RegexBuilder::canonical_backrefs`. Requires the `unicode-normalization` feature.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn canonical_backrefs(&self) -> bool {
+        self.canonical_backrefs
+    }
+
+    /// Create a builder with these options, e.g. for building the same regex again or a variant
+    /// of it.
+    pub fn to_builder(&self) -> RegexBuilder {
+        RegexBuilder(self.clone())
+    }
+}
+
 impl RegexBuilder {
     /// Create a new regex builder with a regex pattern.
     ///
@@ -385,13 +1270,23 @@ impl RegexBuilder {
     /// Build the `Regex`.
     ///
     /// Returns an [`Error`](enum.Error.html) if the pattern could not be parsed.
+    #[cfg(feature = "compiler")]
     pub fn build(&self) -> Result<Regex> {
         Regex::new_options(self.0.clone())
     }
 
+    /// Build the `Regex` using the process-wide cache, see
+    /// [`Regex::cached`](struct.Regex.html#method.cached).
+    ///
+    /// The cache is keyed by the pattern and all options of the builder.
+    #[cfg(feature = "compiler")]
+    pub fn build_cached(&self) -> Result<Regex> {
+        cache::get_or_compile(&self.0)
+    }
+
     /// Limit for how many times backtracking should be attempted for fancy regexes (where
     /// backtracking is used). If this limit is exceeded, execution returns an error with
-    /// [`Error::BacktrackLimitExceeded`](enum.Error.html#variant.BacktrackLimitExceeded).
+    /// [`RuntimeError::BacktrackLimitExceeded`](enum.RuntimeError.html#variant.BacktrackLimitExceeded).
     /// This is for preventing a regex with catastrophic backtracking to run for too long.
     ///
     /// Default is `1_000_000` (1 million).
@@ -400,11 +1295,28 @@ impl RegexBuilder {
         self
     }
 
+    /// Limit for how much memory (in bytes, approximately) the backtracking state may use while
+    /// executing fancy regexes. This includes the saved positions of groups and the stack of
+    /// backtrack branches. If this limit is exceeded, execution returns an error with
+    /// [`RuntimeError::MemoryLimitExceeded`](enum.RuntimeError.html#variant.MemoryLimitExceeded).
+    ///
+    /// Default is `100 << 20` (100 MiB).
+    pub fn memory_limit(&mut self, limit: usize) -> &mut Self {
+        self.0.memory_limit = limit;
+        self
+    }
+
     /// Set the approximate size limit of the compiled regular expression.
     ///
     /// This option is forwarded from the wrapped `regex` crate. Note that depending on the used
     /// regex features there may be multiple delegated sub-regexes fed to the `regex` crate. As
     /// such the actual limit is closer to `<number of delegated regexes> * delegate_size_limit`.
+    ///
+    /// If a delegated sub-regex exceeds the limit, compiling it fails with
+    /// [`CompileError::DelegateError`](enum.CompileError.html#variant.DelegateError), which has the span of
+    /// that part of the pattern. Sub-regexes are compiled when they're first used for matching, so
+    /// that's where the error is returned, unless [`Regex::compile_all`](struct.Regex.html#method.compile_all)
+    /// is used to compile them upfront. If the whole pattern is delegated, building fails.
     pub fn delegate_size_limit(&mut self, limit: usize) -> &mut Self {
         self.0.delegate_size_limit = Some(limit);
         self
@@ -420,31 +1332,444 @@ impl RegexBuilder {
         self.0.delegate_dfa_size_limit = Some(limit);
         self
     }
-}
 
-impl fmt::Debug for Regex {
-    /// Shows the original regular expression.
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
+    /// Record which alternative of a top-level alternation matched, so that it can be queried
+    /// with [`Captures::branch`](struct.Captures.html#method.branch).
+    ///
+    /// This is useful for matching one of several rules at once, without wrapping each of them
+    /// in a capture group to find out which one matched. Note that the regex is then always
+    /// matched with backtracking, which is slower for regexes that could otherwise be delegated
+    /// to the regex crate entirely.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"\d+|[a-z]+|\s+")
+    ///     .record_branch(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = re.captures("abc 123").unwrap().unwrap();
+    /// assert_eq!(caps.branch(), Some(1));
+    /// ```
+    pub fn record_branch(&mut self, yes: bool) -> &mut Self {
+        self.0.record_branch = yes;
+        self
     }
-}
 
-impl fmt::Display for Regex {
-    /// Shows the original regular expression
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
+    /// Only capture the given groups, by name or number. The other groups are compiled as
+    /// non-capturing groups, which makes matching faster, and `Captures::get` returns `None` for
+    /// them. Groups that are referenced by backrefs are always captured.
+    ///
+    /// Group numbers and names stay the same as in the pattern. Building the regex returns
+    /// [`CompileError::UnknownGroup`](enum.CompileError.html#variant.UnknownGroup) if one of the groups is not
+    /// in the pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(\w+)@(\w+)\.(?<tld>com|org)")
+    ///     .captured_groups(&["2", "tld"])
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = re.captures("mail bob@example.org").unwrap().unwrap();
+    /// assert_eq!(caps.get(1), None);
+    /// assert_eq!(&caps[2], "example");
+    /// assert_eq!(&caps["tld"], "org");
+    /// ```
+    pub fn captured_groups<S: AsRef<str>>(&mut self, groups: &[S]) -> &mut Self {
+        let groups = groups.iter().map(|group| group.as_ref().to_string());
+        self.0.captured_groups = Some(groups.collect());
+        self
     }
-}
 
-impl FromStr for Regex {
-    type Err = Error;
-
-    /// Attempts to parse a string into a regular expression
-    fn from_str(s: &str) -> Result<Regex> {
-        Regex::new(s)
+    /// Set what groups inside a repeated expression capture in iterations where they don't
+    /// match, which also decides what backrefs to them inside the repeat match, e.g. in
+    /// `(a|b\1)+`. See [`RepeatedGroups`](enum.RepeatedGroups.html) for the flavors.
+    ///
+    /// Repeats that contain groups are matched with backtracking for
+    /// `RepeatedGroups::JavaScript`, which is slower.
+    ///
+    /// Default is `RepeatedGroups::Pcre`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{RegexBuilder, RepeatedGroups};
+    /// let pcre = RegexBuilder::new(r"(?:(a)|b)+").build().unwrap();
+    /// let caps = pcre.captures("ab").unwrap().unwrap();
+    /// assert_eq!(&caps[1], "a");
+    ///
+    /// let js = RegexBuilder::new(r"(?:(a)|b)+")
+    ///     .repeated_groups(RepeatedGroups::JavaScript)
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = js.captures("ab").unwrap().unwrap();
+    /// assert_eq!(caps.get(1), None);
+    /// ```
+    pub fn repeated_groups(&mut self, policy: RepeatedGroups) -> &mut Self {
+        self.0.repeated_groups = policy;
+        self
     }
-}
 
+    /// Set where iterating over matches, e.g. with `find_iter`, `captures_iter`, `split` or
+    /// `replace_all`, continues after an empty match. See
+    /// [`EmptyMatchPolicy`](enum.EmptyMatchPolicy.html) for the policies.
+    ///
+    /// Default is `EmptyMatchPolicy::SkipChar`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{EmptyMatchPolicy, RegexBuilder};
+    /// let skip = RegexBuilder::new("a*?").build().unwrap();
+    /// let found: Vec<_> = skip.find_iter("aa").map(|m| m.unwrap().range()).collect();
+    /// assert_eq!(found, vec![0..0, 1..1, 2..2]);
+    ///
+    /// let retry = RegexBuilder::new("a*?")
+    ///     .empty_match_policy(EmptyMatchPolicy::RetryNonEmpty)
+    ///     .build()
+    ///     .unwrap();
+    /// let found: Vec<_> = retry.find_iter("aa").map(|m| m.unwrap().range()).collect();
+    /// assert_eq!(found, vec![0..0, 0..1, 1..1, 1..2, 2..2]);
+    /// ```
+    pub fn empty_match_policy(&mut self, policy: EmptyMatchPolicy) -> &mut Self {
+        self.0.empty_match_policy = policy;
+        self
+    }
+
+    /// Swap the meaning of greedy and lazy quantifiers for the whole pattern, so that e.g. `a*`
+    /// matches as few `a` as possible and `a*?` as many as possible. This is the same as starting
+    /// the pattern with the `U` flag, `(?U)`, and can be turned off for parts of the pattern with
+    /// `(?-U)`. It's useful for patterns written for tools where quantifiers are lazy by default.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(\w)<.+>\1")
+    ///     .swap_greed(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let m = re.find("x<a>x<b>x").unwrap().unwrap();
+    /// assert_eq!(m.as_str(), "x<a>x");
+    /// ```
+    pub fn swap_greed(&mut self, yes: bool) -> &mut Self {
+        self.0.swap_greed = yes;
+        self
+    }
+
+    /// Reject sloppy syntax that is accepted by default, because patterns from other tools often
+    /// rely on it:
+    ///
+    /// * A `{` that doesn't start a repetition, e.g. in `a{b}` or `{`, which is matched literally
+    ///   by default; strict mode returns `ParseError::InvalidRepeat`
+    /// * A `]` without an opening `[`, which is matched literally by default; strict mode returns
+    ///   `ParseError::UnmatchedBracket`
+    /// * An escape like `\12` with two or three octal digits when there are fewer groups before
+    ///   it, which is read as an octal escape like in PCRE by default (here a newline); strict
+    ///   mode returns `ParseError::InvalidBackref`
+    ///
+    /// [`lint`](fn.lint.html) reports these as warnings.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Error, ParseError, Regex, RegexBuilder};
+    /// assert!(Regex::new("a{b}").unwrap().is_match("a{b}").unwrap());
+    ///
+    /// let result = RegexBuilder::new("a{b}").strict(true).build();
+    /// assert!(matches!(result, Err(Error::ParseError(1, ParseError::InvalidRepeat))));
+    /// ```
+    pub fn strict(&mut self, yes: bool) -> &mut Self {
+        self.0.strict = yes;
+        self
+    }
+
+    /// Set a bound for the counts of repeats like `{n}`, `{n,}` and `{n,m}`, where the count is
+    /// `m`, or `n` if there's no upper bound. What happens to repeats with a larger count is set
+    /// with [`large_repeat_policy`](#method.large_repeat_policy): by default, the pattern is
+    /// rejected with `ParseError::RepeatTooLarge`.
+    ///
+    /// Repeats that don't need backtracking are matched by the regex crate, which expands
+    /// `(?:ab){1,10000}` into ten thousand copies of `ab`. With untrusted patterns, that can take
+    /// a lot of time and memory, or fail with `CompileError::InnerError` when the regex crate's
+    /// size limit is hit.
+    ///
+    /// Default is no bound.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Error, ParseError, RegexBuilder};
+    /// let result = RegexBuilder::new("(?:ab){1,10000}").max_repeat(1000).build();
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(Error::ParseError(6, ParseError::RepeatTooLarge { count: 10000, max: 1000 }))
+    /// ));
+    /// ```
+    pub fn max_repeat(&mut self, bound: usize) -> &mut Self {
+        self.0.max_repeat = Some(bound);
+        self
+    }
+
+    /// Set what happens to repeats with a count above [`max_repeat`](#method.max_repeat), see
+    /// [`LargeRepeatPolicy`](enum.LargeRepeatPolicy.html).
+    ///
+    /// Default is `LargeRepeatPolicy::Reject`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{LargeRepeatPolicy, RegexBuilder};
+    /// let re = RegexBuilder::new("^(?:ab){1,10000}$")
+    ///     .max_repeat(1000)
+    ///     .large_repeat_policy(LargeRepeatPolicy::Count)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(re.is_match(&"ab".repeat(5000)).unwrap());
+    /// assert!(!re.is_match(&"ab".repeat(10001)).unwrap());
+    /// ```
+    pub fn large_repeat_policy(&mut self, policy: LargeRepeatPolicy) -> &mut Self {
+        self.0.large_repeat_policy = policy;
+        self
+    }
+
+    /// Set how an escape of a number with more than one digit like `\10` is read, which differs
+    /// between regex flavors when there are fewer groups than the number. See
+    /// [`NumberedBackrefs`](enum.NumberedBackrefs.html) for the rules. Use `\g{10}` for a
+    /// backref that means the same in every flavor.
+    ///
+    /// Default is `NumberedBackrefs::Pcre`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{NumberedBackrefs, RegexBuilder};
+    /// let re = RegexBuilder::new(r"(a)\10")
+    ///     .numbered_backrefs(NumberedBackrefs::Java)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(re.is_match("aa0").unwrap());
+    /// ```
+    pub fn numbered_backrefs(&mut self, rule: NumberedBackrefs) -> &mut Self {
+        self.0.numbered_backrefs = rule;
+        self
+    }
+
+    /// Record every iteration of groups inside repeats, not only the last one, like the
+    /// `Captures` collection of .NET. E.g. for `(\w+,)+` matching `a,b,c,`, group 1 is `c,`,
+    /// but [`Captures::group_iterations`](struct.Captures.html#method.group_iterations) returns
+    /// `a,`, `b,` and `c,`.
+    ///
+    /// The groups are matched by the backtracking VM instead of being delegated to the regex
+    /// crate, so this is slower and off by default.
+    ///
+    /// Default is `false`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"^(?:(\w+),?)+$")
+    ///     .capture_history(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = re.captures("red,green,blue").unwrap().unwrap();
+    /// let words: Vec<&str> = caps.group_iterations(1).map(|m| m.as_str()).collect();
+    /// assert_eq!(words, ["red", "green", "blue"]);
+    /// ```
+    pub fn capture_history(&mut self, yes: bool) -> &mut Self {
+        self.0.capture_history = yes;
+        self
+    }
+
+    /// Let the pattern include the named fragments of `fragments` with `(?&name)`, see
+    /// [`Fragments`](struct.Fragments.html). The fragments are included when the pattern is
+    /// parsed, so the regex is the same as if the pattern contained them.
+    ///
+    /// Default is no fragments, where `(?&name)` is an error for unsupported syntax.
+    pub fn fragments(&mut self, fragments: &Fragments) -> &mut Self {
+        self.0.fragments = fragments.clone();
+        self
+    }
+
+    /// Match literal text regardless of Unicode normalization, so that e.g. `é` matches both the
+    /// precomposed `U+00E9` and `e` followed by the combining acute accent `U+0301`.
+    ///
+    /// Each character of a literal (with its combining marks) is matched in either its composed
+    /// (NFC) or its decomposed (NFD) form, partially composed forms are not matched. Characters
+    /// in character classes and the text matched by `.` are not affected.
+    ///
+    /// Default is `false`. Requires the `unicode-normalization` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new("caf\u{e9}")
+    ///     .normalization_insensitive(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(re.is_match("caf\u{e9}").unwrap());
+    /// assert!(re.is_match("cafe\u{301}").unwrap());
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalization_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.0.normalization_insensitive = yes;
+        self
+    }
+
+    /// Let backrefs match text that is canonically equivalent to what the group matched, e.g. so
+    /// that `(\w+) \1` finds the repeated word in `caf\u{e9} cafe\u{301}`, where the first `é` is
+    /// precomposed and the second one is `e` followed by a combining accent.
+    ///
+    /// The text matched by a backref is compared with the group's text in normalized form, and
+    /// it can't end between a character and its combining marks. This is slower than comparing
+    /// the text directly, so it is off by default. It's often combined with
+    /// `normalization_insensitive`, which does the same for literals.
+    ///
+    /// Default is `false`. Requires the `unicode-normalization` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"\b(\w+) \1\b")
+    ///     .canonical_backrefs(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(re.is_match("caf\u{e9} cafe\u{301}").unwrap());
+    /// assert!(!re.is_match("cafe cafe\u{301}").unwrap());
+    /// ```
+    #[cfg(feature = "unicode-normalization")]
+    pub fn canonical_backrefs(&mut self, yes: bool) -> &mut Self {
+        self.0.canonical_backrefs = yes;
+        self
+    }
+}
+
+/// Parse the pattern of `options`, applying the options that rewrite the expression.
+#[cfg(feature = "compiler")]
+fn parse_pattern(options: &RegexOptions) -> Result<ExprTree> {
+    let mut tree = Parser::parse_options(options)?;
+    // Simplifying would change the alternatives of the top-level alternation
+    if !options.record_branch {
+        tree.expr = simplify::simplify(tree.expr);
+    }
+    #[cfg(feature = "unicode-normalization")]
+    {
+        if options.normalization_insensitive {
+            tree.expr = normalize::expand_equivalents(tree.expr);
+        }
+    }
+    Ok(tree)
+}
+
+/// Compile the pattern of `options` to a program like `compile_anchored`, which explores all the
+/// ways of matching, see `compile::compile_exhaustive`.
+#[cfg(feature = "compiler")]
+fn compile_exhaustive(options: &RegexOptions) -> Result<Prog> {
+    let raw_tree = parse_pattern(options)?;
+    let tree = ExprTree {
+        expr: Expr::Group(Box::new(raw_tree.expr)),
+        ..raw_tree
+    };
+    let info = analyze_with_options(&tree, options)?;
+    compile::compile_exhaustive(&info, options)
+}
+
+/// Get the program for matching a wrapped regex only at a position, compiling it with
+/// `compile_anchored` when it's first needed.
+#[cfg(feature = "compiler")]
+fn anchored_prog(options: &RegexOptions, anchored: &Mutex<Option<Arc<Prog>>>) -> Result<Arc<Prog>> {
+    cached_prog(anchored, || compile_anchored(options))
+}
+
+/// Get the program in `cache`, compiling it with `compile` when it's first needed.
+#[cfg(feature = "compiler")]
+fn cached_prog<F>(cache: &Mutex<Option<Arc<Prog>>>, compile: F) -> Result<Arc<Prog>>
+where
+    F: FnOnce() -> Result<Prog>,
+{
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    match *cache {
+        Some(ref prog) => Ok(prog.clone()),
+        None => {
+            let prog = Arc::new(compile()?);
+            *cache = Some(prog.clone());
+            Ok(prog)
+        }
+    }
+}
+
+/// Compile the pattern of `options` to a program that only matches at the position it's run at,
+/// with group 0 for the bounds of the match.
+#[cfg(feature = "compiler")]
+fn compile_anchored(options: &RegexOptions) -> Result<Prog> {
+    let raw_tree = parse_pattern(options)?;
+    let tree = ExprTree {
+        expr: Expr::Group(Box::new(raw_tree.expr)),
+        ..raw_tree
+    };
+    let info = analyze_with_options(&tree, options)?;
+    compile_with_options(&info, options)
+}
+
+#[cfg(feature = "compiler")]
+impl fmt::Debug for Regex {
+    /// Shows the original regular expression.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl fmt::Display for Regex {
+    /// Shows the original regular expression
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl PartialEq for Regex {
+    /// Regexes are equal if they have the same pattern and were built with the same options.
+    /// Different patterns that match the same are not equal, e.g. `a+` and `aa*`.
+    fn eq(&self, other: &Regex) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner) || self.options() == other.options()
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl Eq for Regex {}
+
+#[cfg(feature = "compiler")]
+impl Hash for Regex {
+    /// Hashes the pattern and the options, consistent with `PartialEq`.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.options().hash(state);
+    }
+}
+
+#[cfg(feature = "compiler")]
+impl FromStr for Regex {
+    type Err = Error;
+
+    /// Attempts to parse a string into a regular expression
+    fn from_str(s: &str) -> Result<Regex> {
+        Regex::new(s)
+    }
+}
+
+#[cfg(feature = "compiler")]
 impl Regex {
     /// Parse and compile a regex with default options, see `RegexBuilder`.
     ///
@@ -457,8 +1782,68 @@ impl Regex {
         Self::new_options(options)
     }
 
+    /// Get a regex with default options from a process-wide cache, compiling and adding it to
+    /// the cache if it isn't there yet.
+    ///
+    /// This is useful when regexes are created from dynamic strings in a hot path, where the same
+    /// patterns come up again and again. The cache holds up to 256 regexes, when it's full the
+    /// least recently used one is evicted. Patterns that fail to compile are not cached.
+    ///
+    /// Use [`RegexBuilder::build_cached`](struct.RegexBuilder.html#method.build_cached) to get a
+    /// cached regex with other options.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// for word in &["foo", "bar", "foo"] {
+    ///     let re = Regex::cached(&format!(r"\b{}\b", word)).unwrap();
+    ///     assert!(re.is_match("foo bar baz").unwrap());
+    /// }
+    /// ```
+    pub fn cached(re: &str) -> Result<Regex> {
+        RegexBuilder::new(re).build_cached()
+    }
+
     fn new_options(options: RegexOptions) -> Result<Regex> {
-        let raw_tree = Expr::parse_tree(&options.pattern)?;
+        Regex::new_interned(options, &mut Interner::default())
+    }
+
+    /// Build a regex like `new_options`, sharing the tables in `interner` with the other regexes
+    /// that were built with it.
+    pub(crate) fn new_interned(options: RegexOptions, interner: &mut Interner) -> Result<Regex> {
+        let raw_tree = parse_pattern(&options)?;
+        Regex::new_tree(options, raw_tree, interner)
+    }
+
+    /// Build a regex from the parsed pattern of `options`, which may have been changed, e.g. to
+    /// add anchors.
+    fn new_tree(
+        options: RegexOptions,
+        mut raw_tree: ExprTree,
+        interner: &mut Interner,
+    ) -> Result<Regex> {
+        let group_map = match options.captured_groups {
+            Some(ref groups) => {
+                let mut keep = BitSet::new();
+                for group in groups {
+                    let i = match raw_tree.named_groups.get(group) {
+                        Some(&i) => i,
+                        None => match group.parse() {
+                            Ok(i) if i <= raw_tree.group_spans.len() => i,
+                            _ => {
+                                return Err(Error::CompileError(CompileError::UnknownGroup(
+                                    group.clone(),
+                                )))
+                            }
+                        },
+                    };
+                    keep.insert(i);
+                }
+                Some(Arc::new(raw_tree.prune_groups(&keep)))
+            }
+            None => None,
+        };
 
         // wrapper to search for re at arbitrary start position,
         // and to capture the match bounds
@@ -475,10 +1860,21 @@ impl Regex {
             ..raw_tree
         };
 
-        let info = analyze(&tree)?;
+        let info = analyze_with_options(&tree, &options)?;
 
         let inner_info = &info.children[1].children[0]; // references inner expr
-        if !inner_info.hard {
+        let max_size = inner_info.max_size;
+        let max_lookbehind = inner_info.max_lookbehind;
+        let hints = Arc::new(SearchHints {
+            min_size: inner_info.min_size,
+            first_bytes: inner_info.first_bytes(),
+            suffix: inner_info.literal_suffix(),
+            #[cfg(feature = "perf-literal")]
+            prefilter: inner_info
+                .literal_prefixes()
+                .map(|prefixes| interner.prefilter(prefixes)),
+        });
+        if !inner_info.hard && !options.record_branch {
             // easy case, wrap regex
 
             // we do our own to_str because escapes are different
@@ -492,129 +1888,1121 @@ impl Regex {
                 _ => unreachable!(),
             };
             raw_e.to_str(&mut re_cooked, 0);
-            let inner = compile::compile_inner(&re_cooked, &options)?;
+            let inner = compile::compile_inner(&re_cooked, &options.pattern, &options)?;
             return Ok(Regex {
-                inner: RegexImpl::Wrap { inner, options },
-                named_groups: Arc::new(tree.named_groups),
+                inner: Arc::new(RegexImpl::Wrap {
+                    inner,
+                    options,
+                    anchored: Mutex::new(None),
+                    exhaustive: Mutex::new(None),
+                }),
+                named_groups: interner.named_groups(tree.named_groups),
+                group_spans: Arc::new(tree.group_spans),
+                max_size,
+                max_lookbehind,
+                hints,
+                group_map,
+                whole_line: Arc::new(Mutex::new(None)),
             });
         }
 
-        let prog = compile(&info)?;
+        let (prog, branch_slot) = if options.record_branch {
+            let (prog, slot) =
+                compile::compile_recording_branch(&info, &options, &mut interner.delegates)?;
+            (prog, Some(slot))
+        } else {
+            let prog = compile::compile_sharing(&info, &options, &mut interner.delegates)?;
+            (prog, None)
+        };
+        let match_prog = compile::compile_saving_groups(&info, &tree.backrefs, &prog, &options)?;
+        let mut find_groups = tree.backrefs.clone();
+        find_groups.insert(0);
+        let find_prog = compile::compile_saving_groups(&info, &find_groups, &prog, &options)?;
+        debug_assert!(matches!(
+            prog.body[..ANCHORED_START_PC],
+            [Insn::Split(ANCHORED_START_PC, 1), Insn::Any, Insn::Jmp(0)]
+        ));
         Ok(Regex {
-            inner: RegexImpl::Fancy {
+            inner: Arc::new(RegexImpl::Fancy {
                 prog,
                 n_groups: info.end_group,
+                branch_slot,
+                match_prog,
+                find_prog,
                 options,
-            },
-            named_groups: Arc::new(tree.named_groups),
+            }),
+            named_groups: interner.named_groups(tree.named_groups),
+            group_spans: Arc::new(tree.group_spans),
+            max_size,
+            max_lookbehind,
+            hints,
+            group_map,
+            whole_line: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Get the regex that only matches the whole text, for `lines_matching`, compiling it when
+    /// it's first needed. Its options are the same as this regex's, so it must only be used for
+    /// matching: things that parse the pattern again (like finding the groups of a wrapped regex)
+    /// would miss the anchors.
+    fn whole_line_regex(&self) -> Result<Regex> {
+        let mut whole_line = self.whole_line.lock().unwrap_or_else(|e| e.into_inner());
+        match *whole_line {
+            Some(ref regex) => Ok(regex.clone()),
+            None => {
+                // Only for matching, so there's no need to record anything
+                let mut options = self.options().clone();
+                options.record_branch = false;
+                options.capture_history = false;
+                let raw_tree = parse_pattern(&options)?;
+                let tree = ExprTree {
+                    expr: Expr::Concat(vec![Expr::StartText, raw_tree.expr, Expr::EndText]),
+                    ..raw_tree
+                };
+                let regex = Regex::new_tree(options, tree, &mut Interner::default())?;
+                *whole_line = Some(regex.clone());
+                Ok(regex)
+            }
+        }
+    }
+
+    /// Returns the original string of this regex.
+    pub fn as_str(&self) -> &str {
+        &self.options().pattern
+    }
+
+    /// Returns the options that this regex was built with, e.g. for showing the configuration
+    /// of a regex or building it again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(\w+) \1")
+    ///     .backtrack_limit(1000)
+    ///     .build()
+    ///     .unwrap();
+    /// let options = re.options();
+    /// assert_eq!(options.pattern(), r"(\w+) \1");
+    /// assert_eq!(options.backtrack_limit(), 1000);
+    ///
+    /// let again = options.to_builder().build().unwrap();
+    /// assert_eq!(again, re);
+    /// ```
+    pub fn options(&self) -> &RegexOptions {
+        match &*self.inner {
+            RegexImpl::Wrap { options, .. } => options,
+            RegexImpl::Fancy { options, .. } => options,
+        }
+    }
+
+    /// Returns the warnings about the pattern, like [`lint`](fn.lint.html) but with the options
+    /// that the regex was built with (e.g. `RegexBuilder::fragments`), e.g. for making the CI of
+    /// a repository of patterns fail on warnings.
+    ///
+    /// `LintKind::NestedQuantifier` is only reported if the regex is matched with the
+    /// backtracking VM. Patterns that are delegated to the regex crate completely can't take
+    /// exponential time.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{LintKind, Regex};
+    /// let re = Regex::new(r"^(\w+\s?)+$").unwrap();
+    /// assert!(re.compile_warnings().is_empty());
+    ///
+    /// let re = Regex::new(r"^(\w+\s?)+(?<!\s)$").unwrap();
+    /// let warnings = re.compile_warnings();
+    /// assert_eq!(warnings[0].kind, LintKind::NestedQuantifier);
+    /// assert_eq!(warnings[0].span, 1..10);
+    /// ```
+    pub fn compile_warnings(&self) -> Vec<Lint> {
+        let (_, mut lints) =
+            Parser::parse_options_linted(self.options()).expect("the pattern was parsed before");
+        if let RegexImpl::Wrap { .. } = *self.inner {
+            lints.retain(|lint| lint.kind != LintKind::NestedQuantifier);
+        }
+        lints.sort_by_key(|lint| lint.span.start);
+        lints
+    }
+
+    /// Returns the pattern in a canonical form and a fingerprint of it, for finding patterns that
+    /// are written differently but mean the same, e.g. to deduplicate the rules of a rule set.
+    ///
+    /// In the canonical form, flags are applied to the parts of the pattern they affect,
+    /// whitespace and comments of `(?x)` are removed, named groups and backreferences are
+    /// numbered, non-capturing groups are only kept where they're needed, and the pattern is
+    /// simplified like for matching. Building a regex from the canonical pattern (with the same
+    /// options) matches the same.
+    ///
+    /// Only the pattern is canonicalized, so regexes that are built with different options, e.g.
+    /// `RegexBuilder::repeated_groups`, can behave differently even with the same canonical form.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let a = Regex::new(r"(?<word>\w+)-\k<word>").unwrap().canonical_pattern();
+    /// let b = Regex::new(r"(?x) (\w+) - \1  # repeated word").unwrap().canonical_pattern();
+    /// assert_eq!(a.as_str(), r"(\w+)-\1");
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// ```
+    pub fn canonical_pattern(&self) -> CanonicalPattern {
+        let tree = parse_pattern(self.options()).expect("the pattern was parsed before");
+        CanonicalPattern::new(&tree.expr)
+    }
+
+    /// Checks whether every text that this regex matches (anywhere, like `is_match`) is also
+    /// matched by `other`, e.g. to make sure that a rewritten pattern still accepts everything
+    /// that the old one did. Requires the `containment` feature.
+    ///
+    /// Both regexes are compiled to DFAs, so this only works for patterns that don't need
+    /// backtracking: for backrefs, look-around and the like, the error is
+    /// `CompileError::NotRegular`. Only valid UTF-8 texts are considered, and the check can take
+    /// long for large patterns because the DFAs are compiled fully.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let old = Regex::new(r"^(?:0|[1-9][0-9]*)$").unwrap();
+    /// let new = Regex::new(r"^(?:0|[1-9]\d*)$").unwrap();
+    /// assert!(old.is_subset_of(&new).unwrap());
+    /// assert!(!new.is_subset_of(&old).unwrap()); // `\d` also matches other digits
+    /// ```
+    #[cfg(feature = "containment")]
+    pub fn is_subset_of(&self, other: &Regex) -> Result<bool> {
+        Ok(self.subset_counterexample(other)?.is_none())
+    }
+
+    /// Checks whether this regex and `other` match the same texts, see
+    /// [`is_subset_of`](#method.is_subset_of). Requires the `containment` feature.
+    #[cfg(feature = "containment")]
+    pub fn is_equivalent_to(&self, other: &Regex) -> Result<bool> {
+        Ok(self.is_subset_of(other)? && other.is_subset_of(self)?)
+    }
+
+    /// Returns the shortest text that this regex matches but `other` doesn't, or `None` if
+    /// there is none, i.e. if this regex [`is_subset_of`](#method.is_subset_of) `other`. Useful
+    /// for explaining why a check failed. Requires the `containment` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let old = Regex::new(r"^[a-z]+(?:-[a-z]+)*$").unwrap();
+    /// let new = Regex::new(r"^[a-z-]+$").unwrap();
+    /// assert_eq!(old.subset_counterexample(&new).unwrap(), None);
+    /// assert_eq!(new.subset_counterexample(&old).unwrap(), Some("-".to_string()));
+    /// ```
+    #[cfg(feature = "containment")]
+    pub fn subset_counterexample(&self, other: &Regex) -> Result<Option<String>> {
+        containment::counterexample(self.options(), other.options())
+    }
+
+    /// Compile the parts of the regex that are delegated to the regex crate now.
+    ///
+    /// They're normally compiled when they're first needed for matching, so that building a regex
+    /// is fast, e.g. when an application builds a lot of regexes but only uses a few of them.
+    /// Call this to pay that cost upfront instead, and to find out early whether compiling them
+    /// fails, e.g. because of [`RegexBuilder::delegate_size_limit`](struct.RegexBuilder.html#method.delegate_size_limit).
+    /// Otherwise such an error is returned by the first match that needs that part.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+) \1").unwrap();
+    /// re.compile_all().unwrap();
+    /// assert!(re.is_match("mirror mirror").unwrap());
+    /// ```
+    pub fn compile_all(&self) -> Result<()> {
+        if let RegexImpl::Fancy {
+            prog,
+            match_prog,
+            find_prog,
+            ..
+        } = &*self.inner
+        {
+            prog.compile_delegates()?;
+            match_prog.compile_delegates()?;
+            find_prog.compile_delegates()?;
+        }
+        Ok(())
+    }
+
+    /// Check if the regex matches the input text.
+    ///
+    /// # Example
+    ///
+    /// Test if some text contains the same word twice:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    ///
+    /// let re = Regex::new(r"(\w+) \1").unwrap();
+    /// assert!(re.is_match("mirror mirror on the wall").unwrap());
+    /// ```
+    ///
+    /// Like `find`, `captures`, `find_iter` and `captures_iter`, this accepts any type of string
+    /// that implements `AsRef<str>`, e.g. in generic code:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// # use std::borrow::Cow;
+    /// fn count_matching<T: AsRef<str>>(re: &Regex, lines: &[T]) -> usize {
+    ///     lines.iter().filter(|line| re.is_match(*line).unwrap()).count()
+    /// }
+    ///
+    /// let re = Regex::new(r"(\w+) \1").unwrap();
+    /// let lines = [Cow::Borrowed("a a"), Cow::Owned("a b".to_string())];
+    /// assert_eq!(count_matching(&re, &lines), 1);
+    /// ```
+    pub fn is_match<T: AsRef<str> + ?Sized>(&self, text: &T) -> Result<bool> {
+        self.is_match_with_config(text.as_ref(), &RunConfig::default())
+    }
+
+    /// Check if the regex matches the input text, with the limits of `config` instead of the
+    /// default ones. See [`RunConfig`](struct.RunConfig.html) for an example.
+    pub fn is_match_with_config(&self, text: &str, config: &RunConfig) -> Result<bool> {
+        match &*self.inner {
+            RegexImpl::Wrap { ref inner, .. } => Ok(inner.is_match(text)),
+            RegexImpl::Fancy {
+                ref match_prog,
+                options,
+                ..
+            } => {
+                let result = self.run_fancy(match_prog, text, 0, options, config)?;
+                Ok(result.is_some())
+            }
+        }
+    }
+
+    /// Returns an iterator for each successive non-overlapping match in `text`.
+    ///
+    /// If you have capturing groups in your regex that you want to extract, use the [Regex::captures_iter()]
+    /// method.
+    ///
+    /// # Example
+    ///
+    /// Find all words followed by an exclamation point:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    ///
+    /// let re = Regex::new(r"\w+(?=!)").unwrap();
+    /// let mut matches = re.find_iter("so fancy! even with! iterators!");
+    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "fancy");
+    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "with");
+    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "iterators");
+    /// assert!(matches.next().is_none());
+    /// ```
+    pub fn find_iter<'r, 't, T: AsRef<str> + ?Sized>(&'r self, text: &'t T) -> Matches<'r, 't> {
+        self.find_iter_from_pos(text.as_ref(), 0)
+    }
+
+    /// Returns an iterator for each successive non-overlapping match in `text`, starting the
+    /// search at byte position `pos`.
+    ///
+    /// Together with `Matches::offset`, this allows to resume a search, e.g. in an editor after
+    /// the document was changed behind the position where the search stopped. Like with
+    /// `find_from_pos`, the text before `pos` is visible to look-behind.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\b\w").unwrap();
+    /// let mut matches = re.find_iter("ab cd ef");
+    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "a");
+    /// let offset = matches.offset();
+    ///
+    /// let edited = "ab xy ef";
+    /// let rest: Vec<_> = re
+    ///     .find_iter_from_pos(edited, offset)
+    ///     .map(|m| m.unwrap().as_str())
+    ///     .collect();
+    /// assert_eq!(rest, vec!["x", "e"]);
+    /// ```
+    pub fn find_iter_from_pos<'r, 't>(&'r self, text: &'t str, pos: usize) -> Matches<'r, 't> {
+        Matches {
+            re: self,
+            text,
+            last_end: pos,
+            last_match: None,
+            config: RunConfig::default(),
+        }
+    }
+
+    /// Returns the non-overlapping matches in `text` after it was edited, given `old_matches`, the
+    /// ranges of the matches that `find_iter` returned for the text before `edit`. The result is
+    /// the same as `find_iter` on the new text, but only the region around the edit is searched
+    /// again (see `rescan_range`), e.g. for keeping highlights up to date in an editor.
+    ///
+    /// After the edited region, the search stops as soon as it finds a match that was also found
+    /// before the edit, because from there on the matches are the same. If the length of matches
+    /// isn't bounded, e.g. for `a.*b`, the whole text is searched again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the replacement of `edit` is not at character boundaries of `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Regex, TextEdit};
+    /// let re = Regex::new(r"\b(\w)\w{0,5}\1\b").unwrap();
+    /// let old_text = "anna bob eve";
+    /// let old: Vec<_> = re.find_iter(old_text).map(|m| m.unwrap().range()).collect();
+    /// assert_eq!(old, vec![0..4, 5..8, 9..12]);
+    ///
+    /// // Replace "bob" with "bot"
+    /// let text = "anna bot eve";
+    /// let matches = re.rematch(text, &old, &TextEdit::new(7..8, "t")).unwrap();
+    /// assert_eq!(matches, vec![0..4, 9..12]);
+    /// ```
+    pub fn rematch(
+        &self,
+        text: &str,
+        old_matches: &[Range<usize>],
+        edit: &TextEdit,
+    ) -> Result<Vec<Range<usize>>> {
+        incremental::rematch(self, text, old_matches, edit)
+    }
+
+    /// Returns the region of `text` (after `edit`) where matches can start differently than they
+    /// did before the edit, which is what `rematch` searches again. It's derived from how far
+    /// a match attempt can look ahead and behind. If that's not bounded, this is the whole text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the replacement of `edit` is not at character boundaries of `text`.
+    pub fn rescan_range(&self, text: &str, edit: &TextEdit) -> Range<usize> {
+        let (start, end) = incremental::dirty_range(self, text, edit);
+        start..end.unwrap_or(text.len())
+    }
+
+    /// Returns all non-overlapping matches in `text`, the same as `find_iter`, but searches large
+    /// texts in parallel. Requires the `rayon` feature.
+    ///
+    /// Texts of more than a megabyte are split into chunks that are searched on the threads of
+    /// the rayon thread pool. The results are put together so that they are the same as with a
+    /// single search, also for matches that extend beyond the chunk they start in. This only
+    /// pays off if the length of matches is bounded, so for patterns like `a.*b` the text is
+    /// searched in one go.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\b(\w)\w{0,10}\1\b").unwrap();
+    /// let log = "anna bob ada eve otto\n".repeat(10_000);
+    /// let count = re.par_find_iter(&log).filter(|m| m.is_ok()).count();
+    /// assert_eq!(count, 50_000);
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_find_iter<'t>(&self, text: &'t str) -> ParMatches<'t> {
+        parallel::find_iter(self, text)
+    }
+
+    /// Returns an iterator over the non-overlapping matches in a byte string. Requires the `bstr`
+    /// feature.
+    ///
+    /// The byte string doesn't have to be valid UTF-8: each invalid sequence is treated as a
+    /// U+FFFD replacement character, the same as bstr does when iterating over characters. So it
+    /// can be matched by `.`, but not by `\w`, and `\b` treats it as a non-word character. The
+    /// positions of matches are byte offsets in the byte string.
+    ///
+    /// To match single bytes, e.g. of a binary format, use `\O`: it matches any byte, including
+    /// one in the middle of a character or of an invalid sequence. Only `\O` can continue matching
+    /// inside a character, and matches always start at a character boundary.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use bstr::ByteSlice;
+    ///
+    /// let re = Regex::new(r"\b(\w)\w*\1\b").unwrap();
+    /// let haystack = b"anna\xffbob".as_bstr();
+    /// let found: Vec<_> = re
+    ///     .find_iter_bstr(haystack)
+    ///     .map(|m| m.unwrap().as_bstr())
+    ///     .collect();
+    /// assert_eq!(found, vec!["anna", "bob"]);
+    ///
+    /// // A length of 2, then any two bytes
+    /// let re = Regex::new(r"\x02\O\O").unwrap();
+    /// let m = re.find_bstr(b"\x01\x02\xc3\xa9\x02\xff\x00".as_bstr()).unwrap().unwrap();
+    /// assert_eq!(m.range(), 1..4);
+    /// ```
+    #[cfg(feature = "bstr")]
+    pub fn find_iter_bstr<'r, 't>(&'r self, haystack: &'t bstr::BStr) -> BStrMatches<'r, 't> {
+        byte_str::find_iter(self, haystack)
+    }
+
+    /// Find the first match in a byte string. Requires the `bstr` feature, see
+    /// [`find_iter_bstr`](#method.find_iter_bstr) for how invalid UTF-8 is treated.
+    #[cfg(feature = "bstr")]
+    pub fn find_bstr<'t>(&self, haystack: &'t bstr::BStr) -> Result<Option<BStrMatch<'t>>> {
+        byte_str::find_iter(self, haystack).next().transpose()
+    }
+
+    /// Find the first match in the input text.
+    ///
+    /// If you have capturing groups in your regex that you want to extract, use the [Regex::captures()]
+    /// method.
+    ///
+    /// # Example
+    ///
+    /// Find a word that is followed by an exclamation point:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    ///
+    /// let re = Regex::new(r"\w+(?=!)").unwrap();
+    /// assert_eq!(re.find("so fancy!").unwrap().unwrap().as_str(), "fancy");
+    /// ```
+    pub fn find<'t, T: AsRef<str> + ?Sized>(&self, text: &'t T) -> Result<Option<Match<'t>>> {
+        self.find_from_pos(text.as_ref(), 0)
+    }
+
+    /// Find the first match in the input text, with the limits of `config` instead of the default
+    /// ones.
+    ///
+    /// # Example
+    ///
+    /// Give up on a search that takes too long:
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Regex, RunConfig};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let re = Regex::new(r"\w+(?=!)").unwrap();
+    /// let mut config = RunConfig::new();
+    /// config.deadline(Instant::now() + Duration::from_secs(1));
+    /// let m = re.find_with_config("so fancy!", &config).unwrap().unwrap();
+    /// assert_eq!(m.as_str(), "fancy");
+    /// ```
+    pub fn find_with_config<'t>(
+        &self,
+        text: &'t str,
+        config: &RunConfig,
+    ) -> Result<Option<Match<'t>>> {
+        self.find_from_pos_impl(text, 0, config)
+    }
+
+    /// Returns the first match in `text`, starting from the specified byte position `pos`.
+    ///
+    /// # Examples
+    ///
+    /// Finding match starting at a position:
+    ///
+    /// ```
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
+    /// let text = "1 test 123\n2 foo";
+    /// let mat = re.find_from_pos(text, 7).unwrap().unwrap();
+    ///
+    /// assert_eq!(mat.start(), 11);
+    /// assert_eq!(mat.end(), 12);
+    /// ```
+    ///
+    /// Note that in some cases this is not the same as using the `find`
+    /// method and passing a slice of the string, see [Regex::captures_from_pos()] for details.
+    pub fn find_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Match<'t>>> {
+        self.find_from_pos_impl(text, pos, &RunConfig::default())
+    }
+
+    fn find_from_pos_impl<'t>(
+        &self,
+        text: &'t str,
+        pos: usize,
+        config: &RunConfig,
+    ) -> Result<Option<Match<'t>>> {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => Ok(inner
+                .find_at(text, pos)
+                .map(|m| Match::new(text, m.start(), m.end()))),
+            RegexImpl::Fancy {
+                find_prog, options, ..
+            } => {
+                let result = self.run_fancy(find_prog, text, pos, options, config)?;
+                Ok(result.map(|saves| Match::new(text, saves[0], saves[1])))
+            }
+        }
+    }
+
+    /// Returns a search for the first match in `text` that can be run in steps, for embedding in
+    /// runtimes with their own scheduling (e.g. async executors or sandboxes) that can't let a
+    /// search with lots of backtracking block them.
+    ///
+    /// Each call to [`ResumableFind::resume`](struct.ResumableFind.html#method.resume) executes
+    /// at most the given number of instructions of the backtracking VM (its fuel), and returns
+    /// `Progress::Suspended` if the search isn't complete yet. The result is the same as with
+    /// `find`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Progress, Regex};
+    /// let re = Regex::new(r"(\w+)\s+\1").unwrap();
+    /// let text = "the quick brown fox jumps over the the lazy dog";
+    /// let mut search = re.find_resumable(text);
+    /// let mut calls = 0;
+    /// let m = loop {
+    ///     calls += 1;
+    ///     match search.resume(100).unwrap() {
+    ///         Progress::Done(m) => break m,
+    ///         // Let other tasks run here
+    ///         Progress::Suspended => {}
+    ///     }
+    /// };
+    /// assert_eq!(m.unwrap().as_str(), "the the");
+    /// assert!(calls > 1);
+    /// ```
+    pub fn find_resumable<'r, 't>(&'r self, text: &'t str) -> ResumableFind<'r, 't> {
+        ResumableFind::new(self, text)
+    }
+
+    /// Returns the number of non-overlapping matches in `text`, the same as
+    /// `find_iter(text).count()` would, but faster.
+    ///
+    /// Like `is_match`, this uses a program that doesn't keep track of capture groups, and the
+    /// state of the backtracking VM is reused between matches, so that counting doesn't allocate
+    /// for each match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// assert_eq!(re.count_matches("aa bb cd eee").unwrap(), 3);
+    /// ```
+    pub fn count_matches(&self, text: &str) -> Result<usize> {
+        let find_prog = match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. } => {
+                if options.empty_match_policy == EmptyMatchPolicy::SkipChar {
+                    return Ok(inner.find_iter(text).count());
+                }
+                None
+            }
+            RegexImpl::Fancy {
+                find_prog, options, ..
+            } => Some((find_prog, options)),
+        };
+        let config = RunConfig::default();
+        let mut scratch = vm::Scratch::new();
+        let mut matches = self.find_iter(text);
+        let mut count = 0;
+        while matches.last_end <= text.len() {
+            if matches.retry_non_empty() {
+                if matches.find_non_empty(false)?.is_some() {
+                    count += 1;
+                }
+                continue;
+            }
+            let pos = matches.last_end;
+            let found = match find_prog {
+                Some((prog, options)) => self.search(text, pos, |start, start_pc| {
+                    vm::run_with_scratch(
+                        prog,
+                        text,
+                        start,
+                        start_pc,
+                        options,
+                        &config,
+                        &mut scratch,
+                    )
+                })?,
+                None => self.find_from_pos(text, pos)?.map(|m| (m.start(), m.end())),
+            };
+            match found {
+                Some((start, end)) => {
+                    if matches.advance(start, end) {
+                        count += 1;
+                    }
+                }
+                None => break,
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns the match starting exactly at byte position `pos` in `text`, if any.
+    ///
+    /// Unlike `find_from_pos`, this doesn't search for a match after `pos`, the match is anchored
+    /// at `pos`. The text before `pos` is still visible to look-behind and to assertions like `\b`,
+    /// which makes this useful for writing tokenizers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<=\.)\w+").unwrap();
+    /// let text = "a.bc d";
+    /// assert_eq!(re.find_at(text, 2).unwrap().unwrap().as_str(), "bc");
+    /// // `find_from_pos` would find "bc" starting at 0 too, but `find_at` doesn't
+    /// assert!(re.find_at(text, 0).unwrap().is_none());
+    /// // Only `d` would match, but the look-behind sees the space before it
+    /// assert!(re.find_at(text, 5).unwrap().is_none());
+    /// ```
+    pub fn find_at<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Match<'t>>> {
+        let result = self.run_anchored(text, pos, false, false, &RunConfig::default())?;
+        Ok(result.map(|saves| Match::new(text, saves[0], saves[1])))
+    }
+
+    /// Returns the last match in `text`, the same as the last one of `find_iter`, if any.
+    ///
+    /// The text is searched backwards from the end, so this is faster than iterating over all
+    /// matches to get the last one. When the regex ends with literal text, positions after the
+    /// last occurrence of it are skipped entirely. The matches are only iterated from a position
+    /// that no match can span, which is found when the length of a match is bounded; otherwise
+    /// they're iterated from the start of the text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\b\d{1,3}-(?!0)").unwrap();
+    /// let m = re.find_last("1- 22- 3-0 44-").unwrap().unwrap();
+    /// assert_eq!(m.range(), 11..14);
+    ///
+    /// // Overlapping matches: like with `find_iter`, the last one is 0..2, not 1..3
+    /// let re = Regex::new("aa").unwrap();
+    /// assert_eq!(re.find_last("aaa").unwrap().unwrap().range(), 0..2);
+    /// ```
+    pub fn find_last<'t>(&self, text: &'t str) -> Result<Option<Match<'t>>> {
+        let mut pos = text.len();
+        if let Some(ref suffix) = self.hints.suffix {
+            // A match can't start after the last occurrence of the suffix, minus the rest of it
+            match text.rfind(suffix.as_str()) {
+                Some(ix) => pos = (ix + suffix.len()).saturating_sub(self.hints.min_size),
+                None => return Ok(None),
+            }
+        }
+        match self.max_size {
+            // After an empty match, skipping a grapheme cluster can jump over any number of
+            // characters, so even a position that no match spans can be skipped
+            Some(max_size) if !self.skips_graphemes() => {
+                match self.unspanned_start(text, pos, max_size)? {
+                    Some(start) => pos = start,
+                    None => return Ok(None),
+                }
+            }
+            _ => pos = 0,
+        }
+        let mut last = None;
+        for m in self.find_iter_from_pos(text, pos) {
+            last = Some(m?);
+        }
+        Ok(last)
+    }
+
+    /// Searches backwards from `pos`, after which no match starts, for a match start without
+    /// another one in the `max_size` characters before it. No match can span that position, so
+    /// `find_iter` continues from there in the same way as when it was started there. `None` if
+    /// no match starts at or before `pos`.
+    fn unspanned_start(
+        &self,
+        text: &str,
+        mut pos: usize,
+        max_size: usize,
+    ) -> Result<Option<usize>> {
+        while !text.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        let mut found = None;
+        let mut free = 0;
+        loop {
+            if self.can_start_at(text, pos) && self.find_at(text, pos)?.is_some() {
+                found = Some(pos);
+                free = 0;
+            } else if found.is_some() {
+                free += 1;
+            }
+            if pos == 0 || (found.is_some() && free == max_size) {
+                return Ok(found);
+            }
+            pos -= 1;
+            while !text.is_char_boundary(pos) {
+                pos -= 1;
+            }
+        }
+    }
+
+    /// Whether the search continues at the next grapheme cluster after an empty match.
+    fn skips_graphemes(&self) -> bool {
+        #[cfg(feature = "unicode-segmentation")]
+        {
+            if self.options().empty_match_policy == EmptyMatchPolicy::SkipGrapheme {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Runs the VM for a match starting at `pos` or later, using the search hints to skip
+    /// positions or the whole run where possible.
+    fn run_fancy(
+        &self,
+        prog: &Prog,
+        text: &str,
+        pos: usize,
+        options: &RegexOptions,
+        config: &RunConfig,
+    ) -> Result<Option<Vec<usize>>> {
+        self.search(text, pos, |start, start_pc| {
+            vm::run_from_pc(prog, text, start, start_pc, 0, options, config)
         })
     }
 
-    /// Returns the original string of this regex.
-    pub fn as_str(&self) -> &str {
-        match &self.inner {
-            RegexImpl::Wrap { options, .. } => &options.pattern,
-            RegexImpl::Fancy { options, .. } => &options.pattern,
+    /// Search for a match from `pos` with the hints, calling `run` with the positions to run the
+    /// VM at and the instruction to start from there: `ANCHORED_START_PC` for only matching at
+    /// the position, or 0 for searching from it.
+    fn search<T>(
+        &self,
+        text: &str,
+        pos: usize,
+        mut run: impl FnMut(usize, usize) -> Result<Option<T>>,
+    ) -> Result<Option<T>> {
+        if !self.may_match_from(text, pos) {
+            return Ok(None);
+        }
+        #[cfg(feature = "perf-literal")]
+        {
+            if let (Some(prefilter), true) = (&self.hints.prefilter, pos <= text.len()) {
+                // Only try the positions where one of the literals that a match starts with occurs
+                let mut start = pos;
+                while let Some(m) = prefilter.find(&text.as_bytes()[start..]) {
+                    let result = run(start + m.start(), ANCHORED_START_PC)?;
+                    if result.is_some() {
+                        return Ok(result);
+                    }
+                    start += m.start() + 1;
+                }
+                return Ok(None);
+            }
+        }
+        if self.hints.first_bytes.is_some() {
+            // Only try the positions where a match can start
+            for start in pos..text.len() {
+                if text.is_char_boundary(start) && self.can_start_at(text, start) {
+                    let result = run(start, ANCHORED_START_PC)?;
+                    if result.is_some() {
+                        return Ok(result);
+                    }
+                }
+            }
+            return Ok(None);
+        }
+        run(pos, 0)
+    }
+
+    /// Whether a match could start at `pos` or later, judging from the literal suffix: it has to
+    /// occur in the rest of the text, with room for the rest of the match before it.
+    ///
+    /// This avoids running the VM when it can't find a match, which is expensive for patterns
+    /// like `.*\.gz(?<!partial\.gz)` that would otherwise backtrack from every position.
+    fn may_match_from(&self, text: &str, pos: usize) -> bool {
+        let hints = &self.hints;
+        let (suffix, rest) = match (&hints.suffix, text.get(pos..)) {
+            (Some(suffix), Some(rest)) => (suffix, rest),
+            _ => return true,
+        };
+        let mut start = 0;
+        while let Some(ix) = rest[start..].find(suffix.as_str()) {
+            let end = start + ix + suffix.len();
+            if end >= hints.min_size {
+                return true;
+            }
+            start = end - suffix.len() + suffix.chars().next().map_or(1, char::len_utf8);
         }
+        false
     }
 
-    /// Check if the regex matches the input text.
+    /// Whether a match could start at `pos`, judging from the search hints.
+    fn can_start_at(&self, text: &str, pos: usize) -> bool {
+        let hints = &self.hints;
+        if text.len() - pos < hints.min_size {
+            return false;
+        }
+        match (&hints.first_bytes, text.as_bytes().get(pos)) {
+            (Some(first_bytes), Some(&b)) => first_bytes.contains(b as usize),
+            (Some(_), None) => false,
+            (None, _) => true,
+        }
+    }
+
+    /// Returns the capture groups for the match starting exactly at byte position `pos` in
+    /// `text`, if any. See `find_at` for how this differs from `captures_from_pos`.
     ///
     /// # Example
     ///
-    /// Test if some text contains the same word twice:
-    ///
     /// ```rust
     /// # use fancy_regex::Regex;
-    ///
-    /// let re = Regex::new(r"(\w+) \1").unwrap();
-    /// assert!(re.is_match("mirror mirror on the wall").unwrap());
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// let text = "abba";
+    /// assert!(re.captures_at(text, 0).unwrap().is_none());
+    /// let captures = re.captures_at(text, 1).unwrap().unwrap();
+    /// assert_eq!(captures.get(1).unwrap().as_str(), "b");
     /// ```
-    pub fn is_match(&self, text: &str) -> Result<bool> {
-        match &self.inner {
-            RegexImpl::Wrap { ref inner, .. } => Ok(inner.is_match(text)),
-            RegexImpl::Fancy {
-                ref prog, options, ..
-            } => {
-                let result = vm::run(prog, text, 0, 0, options)?;
-                Ok(result.is_some())
-            }
-        }
+    pub fn captures_at<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Captures<'t>>> {
+        let result = self.run_anchored(text, pos, true, false, &RunConfig::default())?;
+        Ok(result.map(|saves| self.fancy_captures(text, saves, &RunConfig::default())))
     }
 
-    /// Returns an iterator for each successive non-overlapping match in `text`.
+    /// Returns every distinct way that the regex can match starting exactly at byte position `pos`
+    /// in `text`, but at most `limit` of them. This is for debugging grammars and detecting
+    /// ambiguous patterns, it's much slower than matching.
     ///
-    /// If you have capturing groups in your regex that you want to extract, use the [Regex::captures_iter()]
-    /// method.
+    /// The ways are in the order the backtracking VM finds them, so the first one is what
+    /// `captures_at` returns (unless the regex is matched by the regex crate, which prefers the
+    /// same match but may capture groups differently). Two ways are distinct if the whole match or
+    /// one of the groups differs. The look-around assertions and atomic groups match only once, like
+    /// when matching normally.
     ///
     /// # Example
     ///
-    /// Find all words followed by an exclamation point:
-    ///
     /// ```rust
     /// # use fancy_regex::Regex;
-    ///
-    /// let re = Regex::new(r"\w+(?=!)").unwrap();
-    /// let mut matches = re.find_iter("so fancy! even with! iterators!");
-    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "fancy");
-    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "with");
-    /// assert_eq!(matches.next().unwrap().unwrap().as_str(), "iterators");
-    /// assert!(matches.next().is_none());
+    /// let re = Regex::new(r"(a|ab)(b?)c?").unwrap();
+    /// let ways: Vec<_> = re
+    ///     .all_matches_at("abc", 0, 10)
+    ///     .unwrap()
+    ///     .iter()
+    ///     .map(|caps| (caps.get(1).unwrap().as_str(), caps.get(0).unwrap().as_str()))
+    ///     .collect();
+    /// assert_eq!(
+    ///     ways,
+    ///     vec![("a", "abc"), ("a", "ab"), ("a", "a"), ("ab", "abc"), ("ab", "ab")]
+    /// );
     /// ```
-    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
-        Matches {
-            re: &self,
-            text,
-            last_end: 0,
-            last_match: None,
+    pub fn all_matches_at<'t>(
+        &self,
+        text: &'t str,
+        pos: usize,
+        limit: usize,
+    ) -> Result<Vec<Captures<'t>>> {
+        if limit == 0 {
+            return Ok(Vec::new());
         }
+        let options = self.options();
+        let prog = compile_exhaustive(options)?;
+        let n_groups = self.group_spans.len() + 1;
+        let mut diagnostics = vm::Diagnostics {
+            match_limit: limit,
+            match_slots: n_groups * 2,
+            ..Default::default()
+        };
+        vm::run_diagnostic(
+            &prog,
+            text,
+            pos,
+            0,
+            0,
+            options,
+            &RunConfig::default(),
+            Some(&mut diagnostics),
+        )?;
+        Ok(diagnostics
+            .matches
+            .into_iter()
+            .map(|saves| self.exhaustive_captures(text, saves))
+            .collect())
     }
 
-    /// Find the first match in the input text.
+    /// Returns the first match that is within `range` of `text` and anchored at the ends of the
+    /// range that `anchor` says.
     ///
-    /// If you have capturing groups in your regex that you want to extract, use the [Regex::captures()]
-    /// method.
+    /// Unlike searching in a slice of the text, look-around and assertions like `\b` still see
+    /// the text outside of the range, e.g. a look-ahead can check what follows the range. Anchors
+    /// like `^` and `$` refer to the start and end of the whole text, not the range. Returns
+    /// `None` if the range is out of bounds or not on character boundaries.
     ///
-    /// # Example
+    /// The match ending in the range is not just the first match that is cut off: all the ways
+    /// of matching are tried, e.g. `a+` finds `aa` in the range `0..2` of `aaaa`. This needs a
+    /// program that backtracks for everything, which is compiled for each call, so this is
+    /// slower than `find_from_pos` or `find_at`.
     ///
-    /// Find a word that is followed by an exclamation point:
+    /// # Example
     ///
     /// ```rust
-    /// # use fancy_regex::Regex;
-    ///
+    /// # use fancy_regex::{RangeAnchor, Regex};
     /// let re = Regex::new(r"\w+(?=!)").unwrap();
-    /// assert_eq!(re.find("so fancy!").unwrap().unwrap().as_str(), "fancy");
+    /// let text = "hello world!";
+    /// // The look-ahead sees the `!` after the range
+    /// let m = re.find_in(text, 6..11, RangeAnchor::Both).unwrap().unwrap();
+    /// assert_eq!(m.as_str(), "world");
+    /// // There's no match that ends at the end of the range
+    /// assert!(re.find_in(text, 0..5, RangeAnchor::End).unwrap().is_none());
     /// ```
-    pub fn find<'t>(&self, text: &'t str) -> Result<Option<Match<'t>>> {
-        self.find_from_pos(text, 0)
+    pub fn find_in<'t>(
+        &self,
+        text: &'t str,
+        range: Range<usize>,
+        anchor: RangeAnchor,
+    ) -> Result<Option<Match<'t>>> {
+        let result = self.run_in(text, range, anchor)?;
+        Ok(result.map(|saves| Match::new(text, saves[0], saves[1])))
     }
 
-    /// Returns the first match in `text`, starting from the specified byte position `pos`.
-    ///
-    /// # Examples
-    ///
-    /// Finding match starting at a position:
+    /// Returns the capture groups of the match that `find_in` finds.
     ///
-    /// ```
-    /// # use fancy_regex::Regex;
-    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
-    /// let text = "1 test 123\n2 foo";
-    /// let mat = re.find_from_pos(text, 7).unwrap().unwrap();
+    /// # Example
     ///
-    /// assert_eq!(mat.start(), 11);
-    /// assert_eq!(mat.end(), 12);
+    /// ```rust
+    /// # use fancy_regex::{RangeAnchor, Regex};
+    /// let re = Regex::new(r"(\d+)-(\d+)").unwrap();
+    /// let captures = re
+    ///     .captures_in("1-23-456", 2..8, RangeAnchor::Unanchored)
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(captures.get(1).unwrap().as_str(), "23");
+    /// assert_eq!(captures.get(2).unwrap().as_str(), "456");
     /// ```
-    ///
-    /// Note that in some cases this is not the same as using the `find`
-    /// method and passing a slice of the string, see [Regex::captures_from_pos()] for details.
-    pub fn find_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Match<'t>>> {
-        match &self.inner {
-            RegexImpl::Wrap { inner, .. } => Ok(inner
-                .find_at(text, pos)
-                .map(|m| Match::new(text, m.start(), m.end()))),
-            RegexImpl::Fancy { prog, options, .. } => {
-                let result = vm::run(prog, text, pos, 0, options)?;
-                Ok(result.map(|saves| Match::new(text, saves[0], saves[1])))
+    pub fn captures_in<'t>(
+        &self,
+        text: &'t str,
+        range: Range<usize>,
+        anchor: RangeAnchor,
+    ) -> Result<Option<Captures<'t>>> {
+        let result = self.run_in(text, range, anchor)?;
+        Ok(result.map(|saves| self.exhaustive_captures(text, saves)))
+    }
+
+    /// Runs the program that explores all ways of matching for a match in `range`, see
+    /// `find_in`.
+    fn run_in(
+        &self,
+        text: &str,
+        range: Range<usize>,
+        anchor: RangeAnchor,
+    ) -> Result<Option<Vec<usize>>> {
+        let Range { start, end } = range;
+        if start > end
+            || end > text.len()
+            || !text.is_char_boundary(start)
+            || !text.is_char_boundary(end)
+        {
+            return Ok(None);
+        }
+        let options = self.options();
+        let prog = compile_exhaustive(options)?;
+        let mut config = RunConfig::default();
+        let anchor_end = matches!(anchor, RangeAnchor::End | RangeAnchor::Both);
+        config.end_bound = Some((end, anchor_end));
+        let last_start = match anchor {
+            RangeAnchor::Start | RangeAnchor::Both => start,
+            RangeAnchor::Unanchored | RangeAnchor::End => end,
+        };
+        for pos in start..=last_start {
+            if text.is_char_boundary(pos) {
+                let result = vm::run(&prog, text, pos, 0, options, &config)?;
+                if result.is_some() {
+                    return Ok(result);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Turns the saves of a run of the program from `compile_exhaustive` into captures.
+    fn exhaustive_captures<'t>(&self, text: &'t str, mut saves: Vec<usize>) -> Captures<'t> {
+        // The groups are numbered as in the pattern, even if not all of them are captured
+        let n_groups = self.group_spans.len() + 1;
+        saves.truncate(n_groups * 2);
+        Captures {
+            inner: CapturesImpl::Fancy {
+                text,
+                n_groups,
+                branch: None,
+                saves: LazySaves::new(saves, None, None),
+            },
+            named_groups: self.named_groups.clone(),
+            group_map: None,
+        }
+    }
+
+    /// Turns the saves of a VM run into captures.
+    fn fancy_captures<'t>(
+        &self,
+        text: &'t str,
+        mut saves: Vec<usize>,
+        config: &RunConfig,
+    ) -> Captures<'t> {
+        let branch = match &*self.inner {
+            RegexImpl::Fancy {
+                branch_slot: Some(slot),
+                ..
+            } => Some(saves[*slot]),
+            _ => None,
+        };
+        let n_groups = self.n_groups();
+        saves.truncate(n_groups * 2);
+        // The capture history is found by running the regex again when it's asked for
+        let pending = if self.options().capture_history {
+            Some(self.inner.clone())
+        } else {
+            None
+        };
+        Captures {
+            inner: CapturesImpl::Fancy {
+                text,
+                n_groups,
+                branch,
+                saves: LazySaves::new(saves, pending, config.byte_string.clone()),
+            },
+            named_groups: self.named_groups.clone(),
+            group_map: self.group_map.clone(),
+        }
+    }
+
+    /// Runs the regex for a match starting exactly at `pos`. The positions of groups other than
+    /// group 0 are only saved if `groups` is true (and the regex isn't delegated). If `not_empty`
+    /// is true, only a non-empty match is accepted.
+    fn run_anchored(
+        &self,
+        text: &str,
+        pos: usize,
+        groups: bool,
+        not_empty: bool,
+        config: &RunConfig,
+    ) -> Result<Option<Vec<usize>>> {
+        let option_flags = if not_empty { vm::OPTION_NOT_EMPTY } else { 0 };
+        match &*self.inner {
+            RegexImpl::Wrap {
+                options,
+                anchored,
+                exhaustive,
+                ..
+            } => {
+                // The regex crate finds the match it prefers, which may be empty, so a non-empty
+                // one needs a program that backtracks into all of the regex
+                let prog = if not_empty {
+                    cached_prog(exhaustive, || compile_exhaustive(options))?
+                } else {
+                    anchored_prog(options, anchored)?
+                };
+                vm::run(&prog, text, pos, option_flags, options, config)
+            }
+            // Skip the `.*?` prefix that searches for the start of the match
+            RegexImpl::Fancy {
+                prog,
+                find_prog,
+                options,
+                ..
+            } => {
+                let prog = if groups { prog } else { find_prog };
+                vm::run_from_pc(
+                    prog,
+                    text,
+                    pos,
+                    ANCHORED_START_PC,
+                    option_flags,
+                    options,
+                    config,
+                )
             }
         }
     }
@@ -644,10 +3032,160 @@ impl Regex {
     ///
     /// assert!(all_captures.next().is_none());
     /// ```
-    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+    pub fn captures_iter<'r, 't, T: AsRef<str> + ?Sized>(
+        &'r self,
+        text: &'t T,
+    ) -> CaptureMatches<'r, 't> {
         CaptureMatches(self.find_iter(text))
     }
 
+    /// Returns an iterator over all the non-overlapping capture groups matched in `text`,
+    /// starting the search at byte position `pos`. See `find_iter_from_pos`.
+    pub fn captures_iter_from_pos<'r, 't>(
+        &'r self,
+        text: &'t str,
+        pos: usize,
+    ) -> CaptureMatches<'r, 't> {
+        CaptureMatches(self.find_iter_from_pos(text, pos))
+    }
+
+    /// Returns an iterator over the fields of `text` that are separated by matches of the regex.
+    /// Each field is yielded together with the capture groups of the separator following it,
+    /// like `re.split` with capturing groups in Python. The last field is yielded with `None` for
+    /// the captures.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\s*([,;])\s*").unwrap();
+    /// let mut fields = re.split_captures("a, b;c");
+    ///
+    /// let (field, captures) = fields.next().unwrap().unwrap();
+    /// assert_eq!(field, "a");
+    /// assert_eq!(&captures.unwrap()[1], ",");
+    ///
+    /// let (field, captures) = fields.next().unwrap().unwrap();
+    /// assert_eq!(field, "b");
+    /// assert_eq!(&captures.unwrap()[1], ";");
+    ///
+    /// let (field, captures) = fields.next().unwrap().unwrap();
+    /// assert_eq!(field, "c");
+    /// assert!(captures.is_none());
+    ///
+    /// assert!(fields.next().is_none());
+    /// ```
+    pub fn split_captures<'r, 't>(&'r self, text: &'t str) -> SplitCaptures<'r, 't> {
+        SplitCaptures {
+            finder: self.captures_iter(text),
+            last: 0,
+            remaining: None,
+        }
+    }
+
+    /// Like [Regex::split_captures()], but yields at most `limit` fields. The last field
+    /// contains the remainder of `text` (and is yielded with `None` for the captures). If `limit`
+    /// is `0`, no fields are yielded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\d)").unwrap();
+    /// let fields: Vec<&str> = re
+    ///     .splitn_captures("a1b2c3d", 2)
+    ///     .map(|field| field.unwrap().0)
+    ///     .collect();
+    /// assert_eq!(fields, vec!["a", "b2c3d"]);
+    /// ```
+    pub fn splitn_captures<'r, 't>(&'r self, text: &'t str, limit: usize) -> SplitCaptures<'r, 't> {
+        SplitCaptures {
+            finder: self.captures_iter(text),
+            last: 0,
+            remaining: Some(limit),
+        }
+    }
+
+    /// Returns an iterator over the parts of `text` that end with a match of the regex. Unlike
+    /// splitting, the matched separator is kept at the end of each part, similar to
+    /// `str::split_inclusive`. If `text` ends with a separator, there's no empty part at the end.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"[.!?](?=\s|$)").unwrap();
+    /// let sentences: Vec<&str> = re
+    ///     .split_inclusive("Hi! Version 1.2 is out. Yes")
+    ///     .map(|part| part.unwrap())
+    ///     .collect();
+    /// assert_eq!(sentences, vec!["Hi!", " Version 1.2 is out.", " Yes"]);
+    /// ```
+    pub fn split_inclusive<'r, 't>(&'r self, text: &'t str) -> SplitInclusive<'r, 't> {
+        SplitInclusive {
+            finder: self.find_iter(text),
+            last: 0,
+        }
+    }
+
+    /// Returns an iterator over the lines of `text` that contain a match, like `grep`.
+    ///
+    /// Each line is yielded once, with its line number (starting at 1), the byte range of the
+    /// line (without the `\n`) and the first match starting in it. Unlike matching each line
+    /// separately, the regex runs against the whole text, so look-around can see across lines.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// // Lines with a word that is repeated at the start of the next line
+    /// let re = Regex::new(r"(?m)(\w+)$(?=\n\1)").unwrap();
+    /// let text = "foo bar\nbar baz\nqux";
+    /// let lines: Vec<_> = re
+    ///     .grep_lines(text)
+    ///     .map(|line| {
+    ///         let (number, span, m) = line.unwrap();
+    ///         (number, &text[span], m.as_str())
+    ///     })
+    ///     .collect();
+    /// assert_eq!(lines, vec![(1, "foo bar", "bar")]);
+    /// ```
+    pub fn grep_lines<'r, 't>(&'r self, text: &'t str) -> GrepLines<'r, 't> {
+        GrepLines {
+            finder: self.find_iter(text),
+            line_number: 1,
+            line_start: 0,
+        }
+    }
+
+    /// Returns an iterator over the lines of `text` that the regex matches completely, as if it
+    /// was anchored with `^...$` for each line, e.g. for filtering a list of names.
+    ///
+    /// The lines are split like with `str::lines`, so they don't include the `\n` or `\r\n`.
+    /// Unlike with `grep_lines`, the regex only sees one line at a time. The anchored regex is
+    /// compiled once, when it's first needed, and shared by the clones of this regex.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+)-\1|\d+").unwrap();
+    /// let text = "abc-abc\nabc-abcd\r\n42\nx 42";
+    /// let lines: Vec<_> = re.lines_matching(text).map(|line| line.unwrap()).collect();
+    /// assert_eq!(lines, vec!["abc-abc", "42"]);
+    /// ```
+    pub fn lines_matching<'t>(&self, text: &'t str) -> LinesMatching<'t> {
+        let (regex, error) = match self.whole_line_regex() {
+            Ok(regex) => (Some(regex), None),
+            Err(error) => (None, Some(error)),
+        };
+        LinesMatching {
+            regex,
+            error,
+            lines: text.lines(),
+        }
+    }
+
     /// Returns the capture groups for the first match in `text`.
     ///
     /// If no match is found, then `Ok(None)` is returned.
@@ -668,8 +3206,47 @@ impl Regex {
     /// assert_eq!(captures.get(3).unwrap().as_str(), "07");
     /// assert_eq!(captures.get(0).unwrap().as_str(), "2018-04-07");
     /// ```
-    pub fn captures<'t>(&self, text: &'t str) -> Result<Option<Captures<'t>>> {
-        self.captures_from_pos(text, 0)
+    pub fn captures<'t, T: AsRef<str> + ?Sized>(
+        &self,
+        text: &'t T,
+    ) -> Result<Option<Captures<'t>>> {
+        self.captures_from_pos(text.as_ref(), 0)
+    }
+
+    /// Returns the first match in `text` converted to a `T`, e.g. a struct that derives
+    /// [`FromCaptures`](trait.FromCaptures.html).
+    ///
+    /// If no match is found, then `Ok(None)` is returned. If a group can't be converted to its
+    /// field, `Error::CaptureConversion` is returned.
+    pub fn captures_parse<T: FromCaptures>(&self, text: &str) -> Result<Option<T>> {
+        self.captures(text)?
+            .map(|caps| T::from_captures(&caps))
+            .transpose()
+    }
+
+    /// Returns the capture groups for the first match in `text`, with the limits of `config`
+    /// instead of the default ones.
+    ///
+    /// All groups are found during the call, so that the limits apply to that too.
+    pub fn captures_with_config<'t>(
+        &self,
+        text: &'t str,
+        config: &RunConfig,
+    ) -> Result<Option<Captures<'t>>> {
+        match &*self.inner {
+            RegexImpl::Fancy { prog, options, .. } => {
+                let result = self.run_fancy(prog, text, 0, options, config)?;
+                Ok(result.map(|saves| self.fancy_captures(text, saves, config)))
+            }
+            RegexImpl::Wrap { .. } => self.captures_from_pos(text, 0),
+        }
+    }
+
+    /// Returns the capture groups for the first match in a byte string. Requires the `bstr`
+    /// feature, see [`find_iter_bstr`](#method.find_iter_bstr) for how invalid UTF-8 is treated.
+    #[cfg(feature = "bstr")]
+    pub fn captures_bstr<'t>(&self, haystack: &'t bstr::BStr) -> Result<Option<BStrCaptures<'t>>> {
+        byte_str::captures(self, haystack)
     }
 
     /// Returns the capture groups for the first match in `text`, starting from
@@ -697,54 +3274,238 @@ impl Regex {
     ///
     /// ```
     /// # use fancy_regex::Regex;
-    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
-    /// let text = "1 test 123\n2 foo";
-    /// let captures = re.captures(&text[7..]).unwrap().unwrap();
-    /// assert_eq!(captures.get(1).unwrap().as_str(), "123");
+    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
+    /// let text = "1 test 123\n2 foo";
+    /// let captures = re.captures(&text[7..]).unwrap().unwrap();
+    /// assert_eq!(captures.get(1).unwrap().as_str(), "123");
+    /// ```
+    ///
+    /// This matched the number "123" because it's at the beginning of the text
+    /// of the string slice.
+    ///
+    pub fn captures_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Captures<'t>>> {
+        let named_groups = self.named_groups.clone();
+        match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => {
+                let mut locations = inner.capture_locations();
+                let result = inner.captures_read_at(&mut locations, text, pos);
+                Ok(result.map(|_| Captures {
+                    inner: CapturesImpl::Wrap { text, locations },
+                    named_groups,
+                    group_map: self.group_map.clone(),
+                }))
+            }
+            RegexImpl::Fancy {
+                find_prog,
+                branch_slot: None,
+                options,
+                ..
+            } => {
+                // Only find the match for now, the other groups are found when they're asked for
+                let result =
+                    self.run_fancy(find_prog, text, pos, options, &RunConfig::default())?;
+                Ok(result.map(|mut saves| {
+                    saves.truncate(2);
+                    Captures {
+                        inner: CapturesImpl::Fancy {
+                            text,
+                            n_groups: self.n_groups(),
+                            branch: None,
+                            saves: LazySaves::new(saves, Some(self.inner.clone()), None),
+                        },
+                        named_groups,
+                        group_map: self.group_map.clone(),
+                    }
+                }))
+            }
+            RegexImpl::Fancy { prog, options, .. } => {
+                let result = self.run_fancy(prog, text, pos, options, &RunConfig::default())?;
+                Ok(result.map(|saves| self.fancy_captures(text, saves, &RunConfig::default())))
+            }
+        }
+    }
+
+    /// Returns the number of captures, including the implicit capture of the entire expression.
+    pub fn captures_len(&self) -> usize {
+        match self.group_map {
+            Some(ref group_map) => group_map.len(),
+            None => self.n_groups(),
+        }
+    }
+
+    /// The number of groups that are captured by the compiled regex.
+    fn n_groups(&self) -> usize {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => inner.captures_len(),
+            RegexImpl::Fancy { n_groups, .. } => *n_groups,
+        }
+    }
+
+    /// Returns the index of the capture group with the given name, or `None` if there is no such
+    /// group.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\d+)-(?<word>\w+)").unwrap();
+    /// assert_eq!(re.group_index("word"), Some(2));
+    /// assert_eq!(re.group_index("other"), None);
+    /// ```
+    pub fn group_index(&self, name: &str) -> Option<usize> {
+        self.named_groups.get(name).cloned()
+    }
+
+    /// Returns the byte range of the capture group with index `i` in the pattern (see `as_str`),
+    /// including its parentheses, or `None` if there is no such group. Group 0 is the whole
+    /// pattern.
+    ///
+    /// This is useful for pointing at a group in the pattern, e.g. in an error message about a
+    /// replacement template.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\d+)-(?<word>\w+)").unwrap();
+    /// let span = re.group_span(2).unwrap();
+    /// assert_eq!(&re.as_str()[span], r"(?<word>\w+)");
+    /// assert_eq!(re.group_span(3), None);
+    /// ```
+    pub fn group_span(&self, i: usize) -> Option<Range<usize>> {
+        if i == 0 {
+            Some(0..self.as_str().len())
+        } else {
+            self.group_spans.get(i - 1).cloned()
+        }
+    }
+
+    /// Returns the span in the pattern that each instruction of the backtracking VM was compiled
+    /// from, for showing which part of the pattern is being matched at a step of a trace (see
+    /// [`RunConfig::trace_sink`](struct.RunConfig.html#method.trace_sink), where each line has
+    /// the index of the instruction).
+    ///
+    /// Returns `None` if the regex doesn't need backtracking and is matched by the regex crate.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+)\s\1").unwrap();
+    /// let spans = re.instruction_spans().unwrap();
+    /// let parts: Vec<&str> = spans
+    ///     .captures()
+    ///     .iter()
+    ///     .flatten()
+    ///     .map(|span| &re.as_str()[span.clone()])
+    ///     .collect();
+    /// assert!(parts.contains(&r"(\w+)"));
+    /// assert!(parts.contains(&r"\1"));
     /// ```
+    pub fn instruction_spans(&self) -> Option<InstructionSpans<'_>> {
+        match &*self.inner {
+            RegexImpl::Wrap { .. } => None,
+            RegexImpl::Fancy {
+                prog,
+                match_prog,
+                find_prog,
+                ..
+            } => Some(InstructionSpans {
+                captures: &prog.spans,
+                find: &find_prog.spans,
+                is_match: &match_prog.spans,
+            }),
+        }
+    }
+
+    /// Returns the regexes that the parts of the pattern that don't need backtracking are
+    /// delegated to, for finding out what is matched by the regex crate and what by the
+    /// backtracking VM, e.g. when diagnosing performance.
     ///
-    /// This matched the number "123" because it's at the beginning of the text
-    /// of the string slice.
+    /// If the regex doesn't need backtracking, that's one regex for the whole pattern. Otherwise
+    /// it's the regexes of all the programs of the regex (see
+    /// [`instruction_spans`](#method.instruction_spans)) without duplicates, in the order the
+    /// programs use them. Parts that "look left", like `\b`, have a second regex that also
+    /// matches the character before them.
     ///
-    pub fn captures_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Captures<'t>>> {
-        let named_groups = self.named_groups.clone();
-        match &self.inner {
-            RegexImpl::Wrap { inner, .. } => {
-                let mut locations = inner.capture_locations();
-                let result = inner.captures_read_at(&mut locations, text, pos);
-                Ok(result.map(|_| Captures {
-                    inner: CapturesImpl::Wrap { text, locations },
-                    named_groups,
-                }))
-            }
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+)\s+\1[.!?]").unwrap();
+    /// let delegated: Vec<&str> = re
+    ///     .delegated_regexes()
+    ///     .iter()
+    ///     .filter_map(|delegate| delegate.span())
+    ///     .map(|span| &re.as_str()[span])
+    ///     .collect();
+    /// assert!(delegated.contains(&r"[.!?]"));
+    /// assert!(!delegated.contains(&r"\1"));
+    /// ```
+    pub fn delegated_regexes(&self) -> Vec<DelegatedRegex<'_>> {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => vec![DelegatedRegex {
+                pattern: inner.as_str(),
+                span: Some(0..self.as_str().len()),
+                compiled: true,
+            }],
             RegexImpl::Fancy {
                 prog,
-                n_groups,
-                options,
+                match_prog,
+                find_prog,
                 ..
             } => {
-                let result = vm::run(prog, text, pos, 0, options)?;
-                Ok(result.map(|mut saves| {
-                    saves.truncate(n_groups * 2);
-                    Captures {
-                        inner: CapturesImpl::Fancy { text, saves },
-                        named_groups,
+                let mut seen: Vec<&Arc<LazyRegex>> = Vec::new();
+                let mut result = Vec::new();
+                for &prog in &[prog, find_prog, match_prog] {
+                    for (pc, re) in prog.delegates() {
+                        if seen.iter().any(|other| Arc::ptr_eq(other, re)) {
+                            continue;
+                        }
+                        seen.push(re);
+                        let span = prog.spans.get(pc).cloned().flatten();
+                        result.push(DelegatedRegex {
+                            pattern: re.as_str(),
+                            span: span.or_else(|| re.span()),
+                            compiled: re.is_compiled(),
+                        });
                     }
-                }))
+                }
+                result
             }
         }
     }
 
-    /// Returns the number of captures, including the implicit capture of the entire expression.
-    pub fn captures_len(&self) -> usize {
-        match &self.inner {
-            RegexImpl::Wrap { inner, .. } => inner.captures_len(),
-            RegexImpl::Fancy { n_groups, .. } => *n_groups,
-        }
+    /// Explains why the regex doesn't match `text`, or returns `None` if it does match. This is
+    /// meant for debugging patterns.
+    ///
+    /// The regex is tried at every position of the text in a diagnostic mode that remembers how
+    /// far each attempt got before it failed. The explanation is about the attempt that got
+    /// furthest: where it started, the position where it finally failed, the part of the pattern
+    /// that failed there, and the alternatives that were still left to try then. That's usually
+    /// where the text differs from what the pattern expects.
+    ///
+    /// For a regex that doesn't need backtracking, the whole pattern is matched by the regex
+    /// crate, so the failed part is the whole pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"^(\w+)-\1$").unwrap();
+    /// assert!(re.explain_no_match("abc-abc").unwrap().is_none());
+    ///
+    /// let explanation = re.explain_no_match("abc-abd").unwrap().unwrap();
+    /// assert_eq!(explanation.start(), 0);
+    /// assert_eq!(explanation.furthest(), 4);
+    /// println!("{}", explanation);
+    /// ```
+    pub fn explain_no_match(&self, text: &str) -> Result<Option<NoMatch>> {
+        explain::explain_no_match(self, text)
     }
 
     /// Returns an iterator over the capture names.
-    pub fn capture_names(&self) -> CaptureNames {
+    pub fn capture_names(&self) -> CaptureNames<'_> {
         let mut names = Vec::new();
         names.resize(self.captures_len(), None);
         for (name, &i) in self.named_groups.iter() {
@@ -756,7 +3517,7 @@ impl Regex {
     // for debugging only
     #[doc(hidden)]
     pub fn debug_print(&self) {
-        match &self.inner {
+        match &*self.inner {
             RegexImpl::Wrap { inner, .. } => println!("wrapped {:?}", inner),
             RegexImpl::Fancy { prog, .. } => prog.debug_print(),
         }
@@ -870,12 +3631,73 @@ impl Regex {
         self.replacen(text, 0, rep)
     }
 
+    /// Checks that the group references in the replacement `template` (as used by `replace` with
+    /// a string) are groups of this regex, e.g. for rejecting a misconfigured replace rule when
+    /// it's loaded instead of silently replacing with empty text.
+    ///
+    /// The error is `Error::InvalidTemplate` with the byte range of the invalid reference in
+    /// the template. Numbered references like `$2` are valid if there is such a group, even if
+    /// it's named. `$1st` is an error because it refers to a group named `1st`; write `${1}st`
+    /// for group 1 followed by `st`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Error, Regex};
+    /// let re = Regex::new(r"(?P<last>[^,\s]+),\s+(\S+)").unwrap();
+    /// assert!(re.validate_template("$2 $last").is_ok());
+    /// match re.validate_template("$2 ${first}") {
+    ///     Err(Error::InvalidTemplate { span, .. }) => assert_eq!(span, 3..11),
+    ///     result => panic!("unexpected result: {:?}", result),
+    /// }
+    /// ```
+    pub fn validate_template(&self, template: &str) -> Result<()> {
+        Expander::default().validate(template, self)
+    }
+
+    /// Replaces all non-overlapping matches in `text` with what `append` pushes onto the output
+    /// for each match.
+    ///
+    /// This is like `replace_all` with a closure, but the closure appends the replacement
+    /// directly instead of returning it, so no `String` needs to be allocated per match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<=user=)(\w)\w*").unwrap();
+    /// let result = re.replace_all_with_groups("user=alice user=bob", |caps, dst| {
+    ///     dst.push_str(&caps[1]);
+    ///     dst.push_str("***");
+    /// });
+    /// assert_eq!(result, "user=a*** user=b***");
+    /// ```
+    pub fn replace_all_with_groups<'t, F>(&self, text: &'t str, append: F) -> Cow<'t, str>
+    where
+        F: FnMut(&Captures<'_>, &mut String),
+    {
+        self.replacen(text, 0, replacer::Append(append))
+    }
+
     /// Replaces at most `limit` non-overlapping matches in `text` with the
     /// replacement provided. If `limit` is 0, then all non-overlapping matches
     /// are replaced.
     ///
     /// See the documentation for `replace` for details on how to access
     /// capturing group matches in the replacement string.
+    ///
+    /// If there's no match, `text` is returned as `Cow::Borrowed`, without allocating. That's the
+    /// same for `replace`, `replace_all` and `replace_all_with_groups`, which makes them cheap to
+    /// use on many texts of which only a few match, e.g. the lines of a file:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::borrow::Cow;
+    ///
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// assert!(matches!(re.replacen("abc", 2, "$1"), Cow::Borrowed("abc")));
+    /// assert_eq!(re.replacen("aabbcc", 2, "$1"), "abcc");
+    /// ```
     pub fn replacen<'t, R: Replacer>(
         &self,
         text: &'t str,
@@ -933,8 +3755,155 @@ impl Regex {
         new.push_str(&text[last_match..]);
         Cow::Owned(new)
     }
+
+    /// Returns the edits that [`replace_all`](#method.replace_all) would make to `text`, instead
+    /// of building the new text.
+    ///
+    /// Each edit is the byte range of a match in `text` together with its replacement. The edits
+    /// are in order and don't overlap, and their ranges refer to the original text, so they can
+    /// be applied in place to an editor buffer or rope (starting from the last one, so that the
+    /// earlier ranges stay valid) without materializing a second copy of the document.
+    ///
+    /// Unlike `replace_all`, errors from running the regex are returned instead of causing a
+    /// panic.
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+)@(\w+)").unwrap();
+    /// let mut text = String::from("mail bob@home or amy@work");
+    /// let edits = re.replace_all_edits(&text, "$2:$1").unwrap();
+    /// assert_eq!(edits, vec![(5..13, "home:bob".to_string()), (17..25, "work:amy".to_string())]);
+    ///
+    /// for (range, replacement) in edits.into_iter().rev() {
+    ///     text.replace_range(range, &replacement);
+    /// }
+    /// assert_eq!(text, "mail home:bob or work:amy");
+    /// ```
+    pub fn replace_all_edits<R: Replacer>(
+        &self,
+        text: &str,
+        rep: R,
+    ) -> Result<Vec<(Range<usize>, String)>> {
+        self.replacen_edits(text, 0, rep)
+    }
+
+    /// Returns the edits that [`replacen`](#method.replacen) would make to `text`, instead of
+    /// building the new text. At most `limit` edits are returned; if `limit` is 0, all matches
+    /// are replaced.
+    ///
+    /// See [`replace_all_edits`](#method.replace_all_edits) for the format of the edits.
+    pub fn replacen_edits<R: Replacer>(
+        &self,
+        text: &str,
+        limit: usize,
+        mut rep: R,
+    ) -> Result<Vec<(Range<usize>, String)>> {
+        let mut edits = Vec::new();
+        // Same fast path as in `replacen`: don't ask for captures if they aren't needed.
+        if let Some(rep) = rep.no_expansion() {
+            for m in self.find_iter(text) {
+                if limit > 0 && edits.len() >= limit {
+                    break;
+                }
+                let m = m?;
+                edits.push((m.range(), rep.to_string()));
+            }
+            return Ok(edits);
+        }
+
+        for cap in self.captures_iter(text) {
+            if limit > 0 && edits.len() >= limit {
+                break;
+            }
+            let cap = cap?;
+            // unwrap on 0 is OK because captures only reports matches
+            let m = cap.get(0).unwrap();
+            let mut replacement = String::new();
+            rep.replace_append(&cap, &mut replacement);
+            edits.push((m.range(), replacement));
+        }
+        Ok(edits)
+    }
+
+    /// Replaces all non-overlapping matches in the text read from `reader` with the replacement
+    /// provided, writing the result to `writer`.
+    ///
+    /// The input is processed in chunks so that it doesn't have to fit into memory. Matches that
+    /// span chunk boundaries are found correctly: enough text is retained between chunks to cover
+    /// the longest possible match (and look-around) of the regex. If the regex can match text of
+    /// unbounded length (e.g. because of `+` or `*`), all of the input is buffered before
+    /// replacing, see [`replace_all_stream_limited`](#method.replace_all_stream_limited) for
+    /// bounding the buffer instead.
+    ///
+    /// The input must be valid UTF-8, otherwise an error of kind `InvalidData` is returned. If
+    /// running the regex fails (e.g. the backtrack limit is exceeded), the [`Error`] is returned
+    /// wrapped in an `io::Error` of kind `Other`.
+    ///
+    /// See the documentation for `replace` for details on how to access capturing group matches
+    /// in the replacement string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<=\$)\d{1,3}\b").unwrap();
+    /// let mut output = Vec::new();
+    /// re.replace_all_stream("costs $10 or 20".as_bytes(), &mut output, "XX")
+    ///     .unwrap();
+    /// assert_eq!(output, b"costs $XX or 20");
+    /// ```
+    pub fn replace_all_stream<R: io::Read, W: io::Write, Rep: Replacer>(
+        &self,
+        reader: R,
+        writer: W,
+        rep: Rep,
+    ) -> io::Result<()> {
+        stream::replace_all(self, reader, writer, rep, None)
+    }
+
+    /// Replaces all non-overlapping matches in the text read from `reader` like
+    /// [`replace_all_stream`](#method.replace_all_stream), but retains at most `max_buffer` bytes
+    /// of text after the start of a match that isn't final yet (plus the text of a chunk and the
+    /// context for look-behind), e.g. for scanning network traffic with a fixed amount of memory.
+    ///
+    /// If the regex can match text that is longer than that (or look ahead further), e.g. because
+    /// of `+` or `*`, matches are assumed to need at most `max_buffer` bytes. A match that reaches
+    /// the end of the retained text, so that more text could change it, returns
+    /// [`RuntimeError::StreamBufferExceeded`](enum.RuntimeError.html#variant.StreamBufferExceeded)
+    /// wrapped in an `io::Error` of kind `Other`. A match that can only be completed with text
+    /// beyond the limit is not found. Regexes with shorter matches work like with
+    /// `replace_all_stream`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Error, Regex, RuntimeError};
+    /// let re = Regex::new(r"<[^>]*>").unwrap();
+    /// let mut output = Vec::new();
+    /// re.replace_all_stream_limited("a <b> c".as_bytes(), &mut output, "", 16)
+    ///     .unwrap();
+    /// assert_eq!(output, b"a  c");
+    ///
+    /// let input = format!("a <{}> c", "b".repeat(100));
+    /// let result = re.replace_all_stream_limited(input.as_bytes(), Vec::new(), "", 16);
+    /// let error = result.unwrap_err().into_inner().unwrap().downcast::<Error>().unwrap();
+    /// assert!(matches!(
+    ///     *error,
+    ///     Error::RuntimeError(RuntimeError::StreamBufferExceeded { limit: 16 })
+    /// ));
+    /// ```
+    pub fn replace_all_stream_limited<R: io::Read, W: io::Write, Rep: Replacer>(
+        &self,
+        reader: R,
+        writer: W,
+        rep: Rep,
+        max_buffer: usize,
+    ) -> io::Result<()> {
+        stream::replace_all(self, reader, writer, rep, Some(max_buffer))
+    }
 }
 
+#[cfg(feature = "compiler")]
 impl<'t> Match<'t> {
     /// Returns the starting byte offset of the match in the text.
     #[inline]
@@ -960,18 +3929,61 @@ impl<'t> Match<'t> {
         &self.text[self.start..self.end]
     }
 
+    /// Returns the range of the match in `char` indices instead of bytes, e.g. for reporting
+    /// positions like Python does.
+    ///
+    /// This counts the characters of the text up to the end of the match. For several groups
+    /// of the same match, use `Captures::char_ranges` which counts them only once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\w+$").unwrap();
+    /// let m = re.find("café olé").unwrap().unwrap();
+    /// assert_eq!(m.range(), 6..10);
+    /// assert_eq!(m.char_range(), 5..8);
+    /// ```
+    pub fn char_range(&self) -> Range<usize> {
+        let units = unit_offsets(self.text, &[self.start, self.end], |_| 1);
+        units[0]..units[1]
+    }
+
+    /// Returns the range of the match in UTF-16 code units instead of bytes, e.g. for reporting
+    /// positions like JavaScript, Java or the Language Server Protocol do. Characters outside the
+    /// Basic Multilingual Plane count as two units.
+    ///
+    /// This counts the characters of the text up to the end of the match. For several groups
+    /// of the same match, use `Captures::utf16_ranges` which counts them only once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\w+").unwrap();
+    /// let m = re.find("\u{1F600} ok").unwrap().unwrap();
+    /// assert_eq!(m.range(), 5..7);
+    /// assert_eq!(m.utf16_range(), 3..5);
+    /// ```
+    pub fn utf16_range(&self) -> Range<usize> {
+        let units = unit_offsets(self.text, &[self.start, self.end], char::len_utf16);
+        units[0]..units[1]
+    }
+
     /// Creates a new match from the given text and byte offsets.
     fn new(text: &'t str, start: usize, end: usize) -> Match<'t> {
         Match { text, start, end }
     }
 }
 
+#[cfg(feature = "compiler")]
 impl<'t> From<Match<'t>> for &'t str {
     fn from(m: Match<'t>) -> &'t str {
         m.as_str()
     }
 }
 
+#[cfg(feature = "compiler")]
 impl<'t> From<Match<'t>> for Range<usize> {
     fn from(m: Match<'t>) -> Range<usize> {
         m.range()
@@ -979,25 +3991,43 @@ impl<'t> From<Match<'t>> for Range<usize> {
 }
 
 #[allow(clippy::len_without_is_empty)] // follow regex's API
+#[cfg(feature = "compiler")]
 impl<'t> Captures<'t> {
     /// Get the capture group by its index in the regex.
     ///
     /// If there is no match for that group or the index does not correspond to a group, `None` is
     /// returned. The index 0 returns the whole match.
     pub fn get(&self, i: usize) -> Option<Match<'t>> {
+        let i = match self.group_map {
+            Some(ref group_map) => group_map.get(i).cloned().flatten()?,
+            None => i,
+        };
         match &self.inner {
             CapturesImpl::Wrap { text, locations } => {
                 locations
                     .get(i)
                     .map(|(start, end)| Match { text, start, end })
             }
-            CapturesImpl::Fancy { text, ref saves } => {
+            CapturesImpl::Fancy {
+                text,
+                n_groups,
+                saves,
+                ..
+            } => {
+                if i >= *n_groups {
+                    return None;
+                }
+                let saves = if i == 0 {
+                    saves.whole_match()
+                } else {
+                    saves.groups(text)
+                };
                 let slot = i * 2;
                 if slot >= saves.len() {
                     return None;
                 }
                 let lo = saves[slot];
-                if lo == std::usize::MAX {
+                if lo == usize::MAX {
                     return None;
                 }
                 let hi = saves[slot + 1];
@@ -1016,6 +4046,52 @@ impl<'t> Captures<'t> {
         self.named_groups.get(name).and_then(|i| self.get(*i))
     }
 
+    /// Returns every iteration of the capture group with index `i` in the order they matched,
+    /// e.g. all the repetitions of a group inside a repeat, where `get` only returns the last
+    /// one. Iterations that were undone by backtracking are not included.
+    ///
+    /// This needs the regex to be built with
+    /// [`RegexBuilder::capture_history`](struct.RegexBuilder.html#method.capture_history),
+    /// otherwise (and for the whole match) it only returns the match of `get`, if any.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(\w+,)+")
+    ///     .capture_history(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = re.captures("a,bc,d,").unwrap().unwrap();
+    /// let items: Vec<&str> = caps.group_iterations(1).map(|m| m.as_str()).collect();
+    /// assert_eq!(items, ["a,", "bc,", "d,"]);
+    /// assert_eq!(caps.get(1).unwrap().as_str(), "d,");
+    /// ```
+    pub fn group_iterations(&self, i: usize) -> impl Iterator<Item = Match<'t>> {
+        let recorded = match &self.inner {
+            CapturesImpl::Fancy { text, saves, .. } => {
+                let group = match self.group_map {
+                    Some(ref group_map) => group_map.get(i).cloned().flatten(),
+                    None => Some(i),
+                };
+                match (saves.history(text), group) {
+                    (Some(history), Some(group)) if group > 0 => Some(
+                        history
+                            .iter()
+                            .filter(|&&(slot, _, _)| slot == group * 2)
+                            .map(|&(_, start, end)| Match { text, start, end })
+                            .collect::<Vec<_>>(),
+                    ),
+                    _ => None,
+                }
+            }
+            CapturesImpl::Wrap { .. } => None,
+        };
+        recorded
+            .unwrap_or_else(|| self.get(i).into_iter().collect())
+            .into_iter()
+    }
+
     /// Expands all instances of `$group` in `replacement` to the corresponding
     /// capture group `name`, and writes them to the `dst` buffer given.
     ///
@@ -1049,9 +4125,71 @@ impl<'t> Captures<'t> {
     /// How many groups were captured. This is always at least 1 because group 0 returns the whole
     /// match.
     pub fn len(&self) -> usize {
+        if let Some(ref group_map) = self.group_map {
+            return group_map.len();
+        }
         match &self.inner {
             CapturesImpl::Wrap { locations, .. } => locations.len(),
-            CapturesImpl::Fancy { saves, .. } => saves.len() / 2,
+            CapturesImpl::Fancy { n_groups, .. } => *n_groups,
+        }
+    }
+
+    /// Returns the ranges of all groups in `char` indices instead of bytes, see
+    /// `Match::char_range`, or `None` for groups that didn't match.
+    ///
+    /// The text is scanned only once for all groups.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+)=(\w+)").unwrap();
+    /// let caps = re.captures("ü=ö").unwrap().unwrap();
+    /// assert_eq!(caps.get(2).unwrap().range(), 3..5);
+    /// assert_eq!(caps.char_ranges(), vec![Some(0..3), Some(0..1), Some(2..3)]);
+    /// ```
+    pub fn char_ranges(&self) -> Vec<Option<Range<usize>>> {
+        self.unit_ranges(|_| 1)
+    }
+
+    /// Returns the ranges of all groups in UTF-16 code units instead of bytes, see
+    /// `Match::utf16_range`, or `None` for groups that didn't match.
+    ///
+    /// The text is scanned only once for all groups.
+    pub fn utf16_ranges(&self) -> Vec<Option<Range<usize>>> {
+        self.unit_ranges(char::len_utf16)
+    }
+
+    /// The ranges of all groups in units where each character has the size `unit(c)`.
+    fn unit_ranges(&self, unit: fn(char) -> usize) -> Vec<Option<Range<usize>>> {
+        let groups: Vec<_> = self.iter().collect();
+        let text = match groups[0] {
+            Some(ref m) => m.text,
+            None => return groups.iter().map(|_| None).collect(),
+        };
+        let mut offsets: Vec<_> = groups
+            .iter()
+            .flatten()
+            .flat_map(|m| vec![m.start, m.end])
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        let units = unit_offsets(text, &offsets, unit);
+        let convert = |offset| units[offsets.binary_search(&offset).unwrap()];
+        groups
+            .iter()
+            .map(|group| group.map(|m| convert(m.start)..convert(m.end)))
+            .collect()
+    }
+
+    /// The index of the alternative of the top-level alternation that matched, if the regex was
+    /// built with [`RegexBuilder::record_branch`](struct.RegexBuilder.html#method.record_branch).
+    ///
+    /// For a regex that is not an alternation, this is `0`.
+    pub fn branch(&self) -> Option<usize> {
+        match self.inner {
+            CapturesImpl::Wrap { .. } => None,
+            CapturesImpl::Fancy { branch, .. } => branch,
         }
     }
 }
@@ -1069,6 +4207,7 @@ impl<'t> Captures<'t> {
 /// # Panics
 ///
 /// If there is no group at the given index.
+#[cfg(feature = "compiler")]
 impl<'t> Index<usize> for Captures<'t> {
     type Output = str;
 
@@ -1093,6 +4232,7 @@ impl<'t> Index<usize> for Captures<'t> {
 /// # Panics
 ///
 /// If there is no group named by the given value.
+#[cfg(feature = "compiler")]
 impl<'t, 'i> Index<&'i str> for Captures<'t> {
     type Output = str;
 
@@ -1103,6 +4243,7 @@ impl<'t, 'i> Index<&'i str> for Captures<'t> {
     }
 }
 
+#[cfg(feature = "compiler")]
 impl<'c, 't> Iterator for SubCaptureMatches<'c, 't> {
     type Item = Option<Match<'t>>;
 
@@ -1115,12 +4256,31 @@ impl<'c, 't> Iterator for SubCaptureMatches<'c, 't> {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.caps.len() - self.i;
+        (len, Some(len))
+    }
 }
 
-// TODO: might be nice to implement ExactSizeIterator etc for SubCaptures
+#[cfg(feature = "compiler")]
+impl<'c, 't> ExactSizeIterator for SubCaptureMatches<'c, 't> {}
+
+#[cfg(feature = "compiler")]
+impl<'c, 't> FusedIterator for SubCaptureMatches<'c, 't> {}
+
+#[cfg(feature = "compiler")]
+impl<'c, 't> IntoIterator for &'c Captures<'t> {
+    type Item = Option<Match<'t>>;
+    type IntoIter = SubCaptureMatches<'c, 't>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
 
 /// Regular expression AST. This is public for now but may change.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Expr {
     /// An empty expression, e.g. the last branch in `(a|b|)`
     Empty,
@@ -1129,6 +4289,9 @@ pub enum Expr {
         /// Whether it also matches newlines or not
         newline: bool,
     },
+    /// Any character including newline, regex `\O`. When searching byte strings, it matches a
+    /// single byte instead, which can be part of a character or of invalid UTF-8.
+    AnyByte,
     /// Start of input text
     StartText,
     /// End of input text
@@ -1180,12 +4343,22 @@ pub enum Expr {
     },
     /// Back reference to a capture group, e.g. `\1` in `(abc|def)\1` references the captured group
     /// and the whole regex matches either `abcabc` or `defdef`.
-    Backref(usize),
+    Backref {
+        /// The number of the referenced group
+        group: usize,
+        /// Whether the matching is case-insensitive or not, which is the case if the referenced
+        /// group is case-insensitive, e.g. `((?i)abc)\1`. The flags at the backref don't matter.
+        casei: bool,
+    },
     /// Back reference to a named capture group.
     NamedBackref(String),
     /// Atomic non-capturing group, e.g. `(?>ab|a)` in text that contains `ab` will match `ab` and
     /// never backtrack and try `a`, even if matching fails after the atomic group.
     AtomicGroup(Box<Expr>),
+    /// Script run, e.g. `(*sr:\w+)`, which only matches if all the characters matched by the
+    /// expression are from the same Unicode script. Characters that are used with several scripts
+    /// (e.g. digits, punctuation and combining marks) can be part of any script run.
+    ScriptRun(Box<Expr>),
 }
 
 /// Type of look-around assertion as used for a look-around expression.
@@ -1205,14 +4378,17 @@ pub enum LookAround {
 /// returns the name of each group, or [None] if the group has
 /// no name.  Because capture group 0 cannot have a name, the
 /// first item returned is always [None].
+#[cfg(feature = "compiler")]
 pub struct CaptureNames<'r>(std::vec::IntoIter<Option<&'r str>>);
 
+#[cfg(feature = "compiler")]
 impl Debug for CaptureNames<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str("<CaptureNames>")
     }
 }
 
+#[cfg(feature = "compiler")]
 impl<'r> Iterator for CaptureNames<'r> {
     type Item = Option<&'r str>;
 
@@ -1221,6 +4397,69 @@ impl<'r> Iterator for CaptureNames<'r> {
     }
 }
 
+/// The spans in the pattern of the instructions of the programs of a [Regex], see
+/// [`Regex::instruction_spans`](struct.Regex.html#method.instruction_spans).
+///
+/// A regex has separate programs for the methods that need the positions of groups, that only
+/// need the position of the match, and that only check whether there's a match. Each slice has
+/// the span of each instruction of one of them, by index of the instruction, or `None` if the
+/// instruction isn't for a part of the pattern (e.g. the loop for searching).
+#[derive(Clone, Copy, Debug)]
+#[cfg(feature = "compiler")]
+pub struct InstructionSpans<'r> {
+    captures: &'r [Option<Range<usize>>],
+    find: &'r [Option<Range<usize>>],
+    is_match: &'r [Option<Range<usize>>],
+}
+
+#[cfg(feature = "compiler")]
+impl<'r> InstructionSpans<'r> {
+    /// The spans for `captures` and the other methods that return captures.
+    pub fn captures(&self) -> &'r [Option<Range<usize>>] {
+        self.captures
+    }
+
+    /// The spans for `find`, `find_iter` and the other methods that only need the position of
+    /// the match, e.g. `split`.
+    pub fn find(&self) -> &'r [Option<Range<usize>>] {
+        self.find
+    }
+
+    /// The spans for `is_match`.
+    pub fn is_match(&self) -> &'r [Option<Range<usize>>] {
+        self.is_match
+    }
+}
+
+/// A regex that a part of a pattern is delegated to, see
+/// [`Regex::delegated_regexes`](struct.Regex.html#method.delegated_regexes).
+#[derive(Clone, Debug)]
+#[cfg(feature = "compiler")]
+pub struct DelegatedRegex<'r> {
+    pattern: &'r str,
+    span: Option<Range<usize>>,
+    compiled: bool,
+}
+
+#[cfg(feature = "compiler")]
+impl<'r> DelegatedRegex<'r> {
+    /// The pattern that is passed to the regex crate, in its syntax.
+    pub fn as_str(&self) -> &'r str {
+        self.pattern
+    }
+
+    /// The span of the part of the pattern that the regex is for, if it could be found.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+
+    /// Whether the regex has been compiled already. Delegated regexes are compiled when they're
+    /// first used for matching, see [`Regex::compile_all`](struct.Regex.html#method.compile_all).
+    pub fn is_compiled(&self) -> bool {
+        self.compiled
+    }
+}
+
 // silly to write my own, but this is super-fast for the common 1-digit
 // case.
 fn push_usize(s: &mut String, x: usize) {
@@ -1233,10 +4472,32 @@ fn push_usize(s: &mut String, x: usize) {
 }
 
 fn is_special(c: char) -> bool {
-    match c {
-        '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$'
-        | '#' => true,
-        _ => false,
+    matches!(
+        c,
+        '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '#'
+    )
+}
+
+/// Append the quantifier for a repeat, e.g. `*` or `{2,3}?`.
+fn push_quantifier(buf: &mut String, lo: usize, hi: usize, greedy: bool) {
+    match (lo, hi) {
+        (0, 1) => buf.push('?'),
+        (0, usize::MAX) => buf.push('*'),
+        (1, usize::MAX) => buf.push('+'),
+        (lo, hi) => {
+            buf.push('{');
+            push_usize(buf, lo);
+            if lo != hi {
+                buf.push(',');
+                if hi != usize::MAX {
+                    push_usize(buf, hi);
+                }
+            }
+            buf.push('}');
+        }
+    }
+    if !greedy {
+        buf.push('?');
     }
 }
 
@@ -1251,7 +4512,7 @@ fn push_quoted(buf: &mut String, s: &str) {
 
 /// Escapes special characters in `text` with '\\'.  Returns a string which, when interpreted
 /// as a regex, matches exactly `text`.
-pub fn escape(text: &str) -> Cow<str> {
+pub fn escape(text: &str) -> Cow<'_, str> {
     // Using bytes() is OK because all special characters are single bytes.
     match text.bytes().filter(|&b| is_special(b as char)).count() {
         0 => Cow::Borrowed(text),
@@ -1281,12 +4542,14 @@ impl Expr {
             Expr::Empty => (),
             Expr::Any { newline } => buf.push_str(if newline { "(?s:.)" } else { "." }),
             Expr::Literal { ref val, casei } => {
-                if casei {
-                    buf.push_str("(?i:");
+                // A literal of several characters needs a group when it's repeated
+                let group = casei || (precedence > 2 && val.chars().nth(1).is_some());
+                if group {
+                    buf.push_str(if casei { "(?i:" } else { "(?:" });
                 }
                 push_quoted(buf, val);
-                if casei {
-                    buf.push_str(")");
+                if group {
+                    buf.push(')');
                 }
             }
             Expr::StartText => buf.push('^'),
@@ -1333,25 +4596,7 @@ impl Expr {
                     buf.push_str("(?:");
                 }
                 child.to_str(buf, 3);
-                match (lo, hi) {
-                    (0, 1) => buf.push('?'),
-                    (0, usize::MAX) => buf.push('*'),
-                    (1, usize::MAX) => buf.push('+'),
-                    (lo, hi) => {
-                        buf.push('{');
-                        push_usize(buf, lo);
-                        if lo != hi {
-                            buf.push(',');
-                            if hi != usize::MAX {
-                                push_usize(buf, hi);
-                            }
-                        }
-                        buf.push('}');
-                    }
-                }
-                if !greedy {
-                    buf.push('?');
-                }
+                push_quantifier(buf, lo, hi, greedy);
                 if precedence > 2 {
                     buf.push(')');
                 }
@@ -1365,7 +4610,7 @@ impl Expr {
                 }
                 buf.push_str(inner);
                 if casei {
-                    buf.push_str(")");
+                    buf.push(')');
                 }
             }
             _ => panic!("attempting to format hard expr"),
@@ -1374,6 +4619,7 @@ impl Expr {
 }
 
 // precondition: ix > 0
+#[cfg(feature = "vm")]
 fn prev_codepoint_ix(s: &str, mut ix: usize) -> usize {
     let bytes = s.as_bytes();
     loop {
@@ -1395,9 +4641,41 @@ fn codepoint_len(b: u8) -> usize {
     }
 }
 
+/// Returns the index of the next grapheme cluster boundary after `i`, or `i + 1` at the end of the
+/// text.
+#[cfg(all(feature = "compiler", feature = "unicode-segmentation"))]
+fn next_grapheme(text: &str, i: usize) -> usize {
+    let mut cursor = unicode_segmentation::GraphemeCursor::new(i, text.len(), true);
+    match cursor.next_boundary(text, 0) {
+        Ok(Some(next)) => next,
+        _ => next_utf8(text, i),
+    }
+}
+
+/// Converts the sorted byte `offsets` in `text` to offsets in units where each character has the
+/// size `unit(c)`, e.g. 1 for `char` indices.
+#[cfg(feature = "compiler")]
+fn unit_offsets(text: &str, offsets: &[usize], unit: fn(char) -> usize) -> Vec<usize> {
+    let mut result = Vec::with_capacity(offsets.len());
+    let mut chars = text.char_indices().peekable();
+    let mut units = 0;
+    for &offset in offsets {
+        while let Some(&(ix, c)) = chars.peek() {
+            if ix >= offset {
+                break;
+            }
+            units += unit(c);
+            chars.next();
+        }
+        result.push(units);
+    }
+    result
+}
+
 /// Returns the smallest possible index of the next valid UTF-8 sequence
 /// starting after `i`.
 /// Adapted from a function with the same name in the `regex` crate.
+#[cfg(feature = "compiler")]
 fn next_utf8(text: &str, i: usize) -> usize {
     let b = match text.as_bytes().get(i) {
         None => return i + 1,
@@ -1440,18 +4718,32 @@ pub fn detect_possible_backref(re: &str) -> bool {
 /// The internal module only exists so that the toy example can access internals for debugging and
 /// experimenting.
 #[doc(hidden)]
+#[cfg(feature = "compiler")]
 pub mod internal {
     pub use crate::analyze::analyze;
-    pub use crate::compile::compile;
+    pub use crate::compile::{compile, LazyRegex};
     pub use crate::vm::{run_default, run_trace, Insn, Prog};
 }
 
-#[cfg(test)]
+/// Used by the code generated by the `FromCaptures` derive macro.
+#[doc(hidden)]
+#[cfg(feature = "compiler")]
+pub mod __private {
+    pub use crate::from_captures::{convert, convert_optional};
+}
+
+#[cfg(all(test, feature = "compiler"))]
 mod tests {
     use crate::parse::make_literal;
+    use crate::vm::Prog;
     use crate::Expr;
-    use crate::Regex;
+    use crate::{
+        CaptureMatches, CaptureNames, Captures, CapturesImpl, Error, GrepLines, LinesMatching,
+        Matches, MultiCaptureMatches, MultiRegex, PatternSet, Regex, RegexBuilder, SplitCaptures,
+        SplitInclusive, StaticRegex, SubCaptureMatches, TokenMatcher,
+    };
     use std::borrow::Cow;
+    use std::sync::Arc;
     use std::usize;
     //use detect_possible_backref;
 
@@ -1507,6 +4799,29 @@ mod tests {
         assert_eq!(s, format!("{}", regex));
     }
 
+    #[test]
+    #[allow(clippy::mutable_key_type)] // the hash only depends on the pattern and options
+    fn eq_and_hash() {
+        use std::collections::HashSet;
+
+        let regex = Regex::new(r"(a+)b\1").unwrap();
+        assert_eq!(regex, regex.clone());
+        assert_eq!(regex, Regex::new(r"(a+)b\1").unwrap());
+        assert_ne!(regex, Regex::new(r"(a+)b\1 ").unwrap());
+        let limited = crate::RegexBuilder::new(r"(a+)b\1")
+            .backtrack_limit(10)
+            .build()
+            .unwrap();
+        assert_ne!(regex, limited);
+
+        let mut set = HashSet::new();
+        set.insert(regex.clone());
+        assert!(set.contains(&Regex::new(r"(a+)b\1").unwrap()));
+        assert!(!set.contains(&limited));
+        set.insert(Regex::new(r"(a+)b\1").unwrap());
+        assert_eq!(set.len(), 1);
+    }
+
     #[test]
     fn from_str() {
         let s = r"(a+)b\1";
@@ -1514,6 +4829,66 @@ mod tests {
         assert_eq!(regex.as_str(), s);
     }
 
+    #[test]
+    fn clone_shares_program() {
+        let regex = Regex::new(r"(a+)b\1").unwrap();
+        let clone = regex.clone();
+        assert!(Arc::ptr_eq(&regex.inner, &clone.inner));
+    }
+
+    #[test]
+    fn send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Regex>();
+        assert_send_sync::<RegexBuilder>();
+        assert_send_sync::<StaticRegex>();
+        assert_send_sync::<Prog>();
+        assert_send_sync::<Error>();
+        assert_send_sync::<Matches<'_, '_>>();
+        assert_send_sync::<CaptureMatches<'_, '_>>();
+        assert_send_sync::<SplitCaptures<'_, '_>>();
+        assert_send_sync::<SplitInclusive<'_, '_>>();
+        assert_send_sync::<GrepLines<'_, '_>>();
+        assert_send_sync::<LinesMatching<'_>>();
+        assert_send_sync::<SubCaptureMatches<'_, '_>>();
+        assert_send_sync::<CaptureNames<'_>>();
+        assert_send_sync::<MultiRegex>();
+        assert_send_sync::<MultiCaptureMatches<'_, '_>>();
+        assert_send_sync::<PatternSet>();
+        assert_send_sync::<TokenMatcher>();
+
+        let regex = Regex::new(r"(\w)\1").unwrap();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let regex = regex.clone();
+                std::thread::spawn(move || regex.is_match("hello").unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn captures_finds_groups_when_asked_for() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Captures<'_>>();
+
+        let regex = Regex::new(r"(\w+)(?=!)|(?<=-)(\d+)").unwrap();
+        let caps = regex.captures("so -12 fancy!").unwrap().unwrap();
+        let pending = |caps: &Captures<'_>| match caps.inner {
+            CapturesImpl::Fancy { ref saves, .. } => saves.groups.get().is_none(),
+            CapturesImpl::Wrap { .. } => false,
+        };
+        assert_eq!(caps.len(), 3);
+        assert_eq!(caps.get(0).unwrap().range(), 4..6);
+        assert!(pending(&caps));
+        assert_eq!(caps.get(1), None);
+        assert!(!pending(&caps));
+        assert_eq!(caps.get(2).unwrap().range(), 4..6);
+        assert_eq!(caps.get(3), None);
+    }
+
     #[test]
     fn to_str_repeat() {
         fn repeat(lo: usize, hi: usize, greedy: bool) -> Expr {
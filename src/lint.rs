@@ -0,0 +1,329 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Warnings about patterns that are valid but probably not what was intended, see `lint`.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::parse::Parser;
+use crate::Result;
+
+/// Checks `pattern` for constructs that are valid but likely mistakes, unnecessary or slow, e.g.
+/// for showing warnings in an editor. To check a regex with the options it was built with, use
+/// [`Regex::compile_warnings`](struct.Regex.html#method.compile_warnings).
+///
+/// The warnings are in the order of their positions in the pattern. If the pattern can't be
+/// parsed, the parse error is returned instead.
+///
+/// # Example
+///
+/// ```rust
+/// use fancy_regex::{lint, LintKind};
+///
+/// let lints = lint(r"^(?<=a)b\/").unwrap();
+/// assert_eq!(lints.len(), 2);
+/// assert_eq!(lints[0].kind, LintKind::LookBehindAtStart);
+/// assert_eq!(lints[0].span, 1..7);
+///
+/// let fix = lints[1].suggestion.as_ref().unwrap();
+/// assert_eq!((fix.span.clone(), fix.replacement.as_str()), (8..10, "/"));
+/// ```
+pub fn lint(pattern: &str) -> Result<Vec<Lint>> {
+    let (_, mut lints) = Parser::parse_linted(pattern)?;
+    lints.sort_by_key(|lint| lint.span.start);
+    Ok(lints)
+}
+
+/// A warning about a part of a pattern, see [`lint`](fn.lint.html).
+///
+/// `Display` gives a message that describes the problem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// What kind of problem was found
+    pub kind: LintKind,
+    /// Byte range of the problem in the pattern
+    pub span: Range<usize>,
+    /// An edit of the pattern that fixes the problem without changing what is matched, if there
+    /// is one
+    pub suggestion: Option<Suggestion>,
+}
+
+/// The kinds of problems that [`lint`](fn.lint.html) finds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// An escaped character that doesn't need escaping, e.g. `\/` or `\"`
+    RedundantEscape(char),
+    /// A repetition of a group that can only match the empty string, e.g. `()*`
+    QuantifiedEmptyGroup,
+    /// A positive look-behind right after `^` or `\A`, which can never match because there is no
+    /// text before the start
+    LookBehindAtStart,
+    /// A backreference to a group that is not complete at that point, e.g. `\1(a)` or `(a\1)`,
+    /// which can never match
+    BackrefBeforeGroup(usize),
+    /// A `{` that doesn't start a repetition, e.g. in `a{b}`, which is matched literally
+    UnescapedBrace,
+    /// A `]` without an opening `[`, which is matched literally
+    UnescapedBracket,
+    /// An escape like `\12` that refers to a group that doesn't exist, which is read as an octal
+    /// escape for the character with that code point instead
+    OctalEscape(u32),
+    /// A part of a concatenation that can never match because of the parts before it, e.g. the
+    /// `.` in `\z.` (there is no text after the end), the `\A` in `(?<=a)\A` or the look-behind
+    /// in `^a(?<=ba)` (it needs more text than there can be before it)
+    NeverMatches,
+    /// An escape that means something else in other regex flavors, e.g. `\h`, which is a hex
+    /// digit like in Oniguruma, but horizontal whitespace in Perl, PCRE and Java
+    FlavorDependentEscape(char),
+    /// An unbounded repetition of something that contains an unbounded repetition itself, e.g.
+    /// `(a+)*`, which can take exponential time to fail when it's matched by backtracking. This
+    /// is only the case if the pattern needs the backtracking VM, e.g. for backrefs or
+    /// look-around, see [`Regex::compile_warnings`](struct.Regex.html#method.compile_warnings).
+    NestedQuantifier,
+
+    /// This enum may grow additional variants, so this makes sure clients don't count on exhaustive
+    /// matching. Otherwise, adding a new variant could break existing code.
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// A replacement of a part of the pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    /// Byte range in the pattern that is replaced
+    pub span: Range<usize>,
+    /// The text to replace it with
+    pub replacement: String,
+}
+
+impl Lint {
+    pub(crate) fn new(kind: LintKind, span: Range<usize>) -> Lint {
+        Lint {
+            kind,
+            span,
+            suggestion: None,
+        }
+    }
+
+    /// Adds a suggestion that replaces the whole span of the lint.
+    pub(crate) fn replace_with(mut self, replacement: &str) -> Lint {
+        self.suggestion = Some(Suggestion {
+            span: self.span.clone(),
+            replacement: replacement.to_string(),
+        });
+        self
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            LintKind::RedundantEscape(c) => write!(f, "Unnecessary escape of `{}`", c),
+            LintKind::QuantifiedEmptyGroup => {
+                write!(
+                    f,
+                    "Repetition of a group that only matches the empty string"
+                )
+            }
+            LintKind::LookBehindAtStart => {
+                write!(f, "Look-behind at the start of the text can never match")
+            }
+            LintKind::BackrefBeforeGroup(group) => write!(
+                f,
+                "Backreference to group {} before the group is complete can never match",
+                group
+            ),
+            LintKind::UnescapedBrace => write!(f, "`{{` that doesn't start a repetition"),
+            LintKind::UnescapedBracket => write!(f, "`]` without opening `[`"),
+            LintKind::OctalEscape(codepoint) => write!(
+                f,
+                "Backreference to a missing group is read as octal escape for U+{:04X}",
+                codepoint
+            ),
+            LintKind::NeverMatches => {
+                write!(f, "Can never match because of what comes before it")
+            }
+            LintKind::FlavorDependentEscape(c) => write!(
+                f,
+                "`\\{}` is about hex digits like in Oniguruma, not horizontal whitespace like \
+                 in Perl/PCRE/Java",
+                c
+            ),
+            LintKind::NestedQuantifier => write!(
+                f,
+                "Nested unbounded repetitions can take exponential time when backtracking"
+            ),
+            LintKind::__Nonexhaustive => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(pattern: &str) -> Vec<(LintKind, Range<usize>)> {
+        lint(pattern)
+            .unwrap()
+            .into_iter()
+            .map(|lint| (lint.kind, lint.span))
+            .collect()
+    }
+
+    fn fixed(pattern: &str) -> String {
+        let mut result = pattern.to_string();
+        for lint in lint(pattern).unwrap().iter().rev() {
+            if let Some(ref fix) = lint.suggestion {
+                result.replace_range(fix.span.clone(), &fix.replacement);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn clean() {
+        assert!(kinds(r"^(\w+)\.\d*(?<=\d)\1[\-\]]\ ").is_empty());
+        assert!(kinds(r"(?x)\ \#").is_empty());
+    }
+
+    #[test]
+    fn redundant_escapes() {
+        assert_eq!(
+            kinds(r#"a\/b[\"]"#),
+            vec![
+                (LintKind::RedundantEscape('/'), 1..3),
+                (LintKind::RedundantEscape('"'), 5..7)
+            ]
+        );
+        assert_eq!(fixed(r#"a\/b[\"]\:"#), r#"a/b["]:"#);
+    }
+
+    #[test]
+    fn quantified_empty_groups() {
+        assert_eq!(
+            kinds(r"a()*b(?>(?:))+"),
+            vec![
+                (LintKind::QuantifiedEmptyGroup, 1..4),
+                (LintKind::QuantifiedEmptyGroup, 5..14)
+            ]
+        );
+        // The capture group is kept so that the numbers of later groups don't change
+        assert_eq!(fixed(r"a()*b(?>(?:))+"), "a()b");
+        assert!(kinds(r"(a|)*").is_empty());
+    }
+
+    #[test]
+    fn look_behind_at_start() {
+        assert_eq!(kinds(r"^(?<=a)"), vec![(LintKind::LookBehindAtStart, 1..7)]);
+        assert_eq!(
+            kinds(r"x|\A(?<=ab)"),
+            vec![(LintKind::LookBehindAtStart, 4..11)]
+        );
+        assert!(kinds(r"^(?<!a)").is_empty());
+        assert!(kinds(r"(?m)^(?<=a)").is_empty());
+        assert!(kinds(r"^(?<=a?)").is_empty());
+    }
+
+    #[test]
+    fn never_matches() {
+        assert_eq!(kinds(r"a\z."), vec![(LintKind::NeverMatches, 3..4)]);
+        assert_eq!(kinds(r"a$b+c"), vec![(LintKind::NeverMatches, 2..4)]);
+        assert_eq!(kinds(r"(?<=a)\A"), vec![(LintKind::NeverMatches, 6..8)]);
+        assert_eq!(kinds(r"x|a^"), vec![(LintKind::NeverMatches, 3..4)]);
+        assert_eq!(
+            kinds(r"^a(?:b|cd)(?<=x\w{3})"),
+            vec![(LintKind::NeverMatches, 10..21)]
+        );
+        assert_eq!(
+            lint(r"\z.").unwrap()[0].to_string(),
+            "Can never match because of what comes before it"
+        );
+
+        assert!(kinds(r"a\z(?=)$").is_empty());
+        assert!(kinds(r"(?m)a$b^c").is_empty());
+        assert!(kinds(r"(?<!a)\A").is_empty());
+        assert!(kinds(r"^a(?:b|cd)(?<=\w{3})").is_empty());
+        assert!(kinds(r"^a+(?<=x\w{3})").is_empty());
+        assert!(kinds(r"^(a)\1(?<=aaa)").is_empty());
+    }
+
+    #[test]
+    fn backref_before_group() {
+        assert_eq!(
+            kinds(r"\1(a)(b\2)"),
+            vec![
+                (LintKind::BackrefBeforeGroup(1), 0..2),
+                (LintKind::BackrefBeforeGroup(2), 7..9)
+            ]
+        );
+        assert!(kinds(r"(a)\1").is_empty());
+        assert_eq!(
+            lint(r"\1(a)").unwrap()[0].to_string(),
+            "Backreference to group 1 before the group is complete can never match"
+        );
+    }
+
+    #[test]
+    fn sloppy_syntax() {
+        assert_eq!(
+            kinds(r"a{b}]{2}"),
+            vec![
+                (LintKind::UnescapedBrace, 1..2),
+                (LintKind::UnescapedBracket, 4..5)
+            ]
+        );
+        assert_eq!(fixed(r"a{b}]{2}"), r"a\{b}\]{2}");
+        assert!(kinds(r"a{2}[]{]\{").is_empty());
+
+        assert_eq!(kinds(r"(a)\12"), vec![(LintKind::OctalEscape(10), 3..6)]);
+        assert_eq!(fixed(r"(a)\12\101"), r"(a)\x{A}\x{41}");
+        assert!(kinds(r"(a)\1\012").is_empty());
+    }
+
+    #[test]
+    fn flavor_dependent_escapes() {
+        assert_eq!(
+            kinds(r"\h+\H"),
+            vec![
+                (LintKind::FlavorDependentEscape('h'), 0..2),
+                (LintKind::FlavorDependentEscape('H'), 3..5)
+            ]
+        );
+        assert_eq!(fixed(r"\h+"), "[0-9A-Fa-f]+");
+        assert_eq!(
+            lint(r"\H").unwrap()[0].to_string(),
+            "`\\H` is about hex digits like in Oniguruma, not horizontal whitespace like in \
+             Perl/PCRE/Java"
+        );
+    }
+
+    #[test]
+    fn nested_quantifiers() {
+        assert_eq!(
+            kinds(r"x(a+b?)*|(?:(\w{2,})|c)+"),
+            vec![
+                (LintKind::NestedQuantifier, 1..8),
+                (LintKind::NestedQuantifier, 9..24)
+            ]
+        );
+        assert!(kinds(r"(a+)?(a{1,3})+(?>a+)+(a+)++(?=a+)*").is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Searching files that are mapped into memory, enabled with the `mmap` feature.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::str;
+
+use memmap2::Mmap;
+
+use crate::{CaptureMatches, Captures, Match, Matches, Regex, Result};
+
+/// A file that is mapped into memory, for searching it without reading it into a `String`.
+///
+/// The file doesn't have to be valid UTF-8. The parts of it that are valid are searched
+/// separately: a match can't contain invalid bytes, and for assertions such as `^`, `$` and `\b`
+/// invalid bytes are like the end of one text and the start of another.
+///
+/// The iterators yield the matches together with the byte offset in the file of the part that
+/// they were found in. Add it to the positions of a match to get its position in the file.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use fancy_regex::{MappedFile, Regex};
+/// let re = Regex::new(r"(\w+)@example\.com(?!\.)").unwrap();
+/// // Safety: the file is not modified while it's mapped
+/// let file = unsafe { MappedFile::open("mail.log") }.unwrap();
+/// for result in file.find_iter(&re) {
+///     let (offset, m) = result.unwrap();
+///     println!("{}: {}", offset + m.start(), m.as_str());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MappedFile {
+    /// `None` for an empty file, which can't be mapped on all platforms
+    map: Option<Mmap>,
+}
+
+impl MappedFile {
+    /// Map the file at `path` into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified (by this or another process) while it's mapped, otherwise
+    /// the text that is being searched would change, which is undefined behavior.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile> {
+        let file = File::open(path)?;
+        let map = if file.metadata()?.len() == 0 {
+            None
+        } else {
+            Some(Mmap::map(&file)?)
+        };
+        Ok(MappedFile { map })
+    }
+
+    /// Returns the contents of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the non-overlapping matches of `regex` in the file, with the
+    /// offset of the text they were found in.
+    pub fn find_iter<'r, 'f>(&'f self, regex: &'r Regex) -> FileMatches<'r, 'f> {
+        FileMatches(Segmented::new(regex, self.as_bytes(), Regex::find_iter))
+    }
+
+    /// Returns an iterator over the captures of the non-overlapping matches of `regex` in the
+    /// file, with the offset of the text they were found in.
+    pub fn captures_iter<'r, 'f>(&'f self, regex: &'r Regex) -> FileCaptureMatches<'r, 'f> {
+        FileCaptureMatches(Segmented::new(regex, self.as_bytes(), Regex::captures_iter))
+    }
+}
+
+/// An iterator over the matches in a [`MappedFile`](struct.MappedFile.html).
+///
+/// The iterator yields a `Result<(usize, Match)>` with the offset of the text the match was found
+/// in, and stops after an error.
+#[derive(Debug)]
+pub struct FileMatches<'r, 'f>(Segmented<'r, 'f, Matches<'r, 'f>>);
+
+impl<'r, 'f> Iterator for FileMatches<'r, 'f> {
+    type Item = Result<(usize, Match<'f>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// An iterator over the captures of the matches in a [`MappedFile`](struct.MappedFile.html).
+///
+/// The iterator yields a `Result<(usize, Captures)>` with the offset of the text the match was
+/// found in, and stops after an error.
+#[derive(Debug)]
+pub struct FileCaptureMatches<'r, 'f>(Segmented<'r, 'f, CaptureMatches<'r, 'f>>);
+
+impl<'r, 'f> Iterator for FileCaptureMatches<'r, 'f> {
+    type Item = Result<(usize, Captures<'f>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+/// Runs an iterator over matches on each valid UTF-8 part of some bytes.
+#[derive(Debug)]
+struct Segmented<'r, 'f, I> {
+    regex: &'r Regex,
+    bytes: &'f [u8],
+    /// Where the next part starts, `None` once all parts were searched (or there was an error)
+    next_start: Option<usize>,
+    /// The iterator for the current part, and the part's offset
+    current: Option<(usize, I)>,
+    search: fn(&'r Regex, &'f str) -> I,
+}
+
+impl<'r, 'f, I, T> Segmented<'r, 'f, I>
+where
+    I: Iterator<Item = Result<T>>,
+{
+    fn new(regex: &'r Regex, bytes: &'f [u8], search: fn(&'r Regex, &'f str) -> I) -> Self {
+        Segmented {
+            regex,
+            bytes,
+            next_start: Some(0),
+            current: None,
+            search,
+        }
+    }
+
+    fn next(&mut self) -> Option<Result<(usize, T)>> {
+        loop {
+            if let Some((offset, ref mut iter)) = self.current {
+                match iter.next() {
+                    Some(Ok(item)) => return Some(Ok((offset, item))),
+                    Some(Err(error)) => {
+                        self.current = None;
+                        self.next_start = None;
+                        return Some(Err(error));
+                    }
+                    None => self.current = None,
+                }
+            }
+            let (offset, text) = self.next_part()?;
+            self.current = Some((offset, (self.search)(self.regex, text)));
+        }
+    }
+
+    /// Returns the next valid UTF-8 part of the bytes and its offset. An empty file has one
+    /// empty part, so that e.g. `^$` matches it.
+    fn next_part(&mut self) -> Option<(usize, &'f str)> {
+        let bytes = self.bytes;
+        loop {
+            let start = self.next_start?;
+            let rest = &bytes[start..];
+            let (valid, next_start) = match str::from_utf8(rest) {
+                Ok(_) => (rest.len(), None),
+                Err(e) => {
+                    // An incomplete sequence at the end is invalid too
+                    let invalid = e.error_len().unwrap_or(rest.len() - e.valid_up_to());
+                    (e.valid_up_to(), Some(start + e.valid_up_to() + invalid))
+                }
+            };
+            self.next_start = next_start.filter(|&ix| ix < bytes.len());
+            if valid > 0 || bytes.is_empty() {
+                let text = str::from_utf8(&rest[..valid]).expect("checked to be valid");
+                return Some((start, text));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn mapped(name: &str, contents: &[u8]) -> MappedFile {
+        let path =
+            std::env::temp_dir().join(format!("fancy-regex-{}-{}.txt", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        // The file is only written before it's mapped
+        let file = unsafe { MappedFile::open(&path) }.unwrap();
+        fs::remove_file(&path).ok();
+        file
+    }
+
+    #[test]
+    fn find_in_parts() {
+        let file = mapped("parts", b"aa b\xffcc\xe2\x82dd");
+        let re = Regex::new(r"\b(\w)\1").unwrap();
+        let found: Vec<_> = file
+            .find_iter(&re)
+            .map(|result| {
+                let (offset, m) = result.unwrap();
+                (offset + m.start(), m.as_str())
+            })
+            .collect();
+        assert_eq!(found, vec![(0, "aa"), (5, "cc"), (9, "dd")]);
+
+        let caps: Vec<_> = file
+            .captures_iter(&re)
+            .map(|result| result.unwrap().1.get(1).unwrap().as_str())
+            .collect();
+        assert_eq!(caps, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn empty_file() {
+        let file = mapped("empty", b"");
+        let re = Regex::new(r"^(?!x)").unwrap();
+        assert_eq!(file.find_iter(&re).count(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Searching for several patterns in one pass over the text, see `MultiRegex`.
+
+use std::collections::VecDeque;
+use std::iter::FusedIterator;
+
+use crate::{next_utf8, Captures, Regex, Result};
+
+/// A set of regexes that are searched for in a single pass over the text.
+///
+/// This gives the same matches as calling `captures_iter` for each of the regexes, but the text
+/// is only scanned once: the bytes that the matches of each regex can start with are combined
+/// into one table, and only the regexes that can match at a position are run there. The matches
+/// of all regexes are reported in the order of their start positions, which is useful for rule
+/// matching, e.g. in an intrusion detection system.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::MultiRegex;
+/// let multi = MultiRegex::new(&[r"(\w)\1", r"\d+(?=px)"]).unwrap();
+/// let found: Vec<_> = multi
+///     .captures_iter("width: 100px; color: #aabbcc")
+///     .map(|result| {
+///         let (id, caps) = result.unwrap();
+///         (id, caps.get(0).unwrap().as_str())
+///     })
+///     .collect();
+/// assert_eq!(found, vec![(1, "100"), (0, "00"), (0, "aa"), (0, "bb"), (0, "cc")]);
+/// ```
+#[derive(Debug)]
+pub struct MultiRegex {
+    regexes: Vec<Regex>,
+    /// For every byte, the regexes (in order) whose matches can start with it
+    by_first_byte: Vec<Vec<usize>>,
+    /// Regexes that could also match at the end of the text
+    at_end: Vec<usize>,
+}
+
+impl MultiRegex {
+    /// Compile the patterns, the index of a pattern in `patterns` is used to identify its
+    /// matches.
+    pub fn new<I, S>(patterns: I) -> Result<MultiRegex>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let regexes = patterns
+            .into_iter()
+            .map(|pattern| Regex::new(pattern.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(MultiRegex::from_regexes(regexes))
+    }
+
+    /// Combine regexes that were already compiled, e.g. with options set by
+    /// [`RegexBuilder`](struct.RegexBuilder.html).
+    pub fn from_regexes(regexes: Vec<Regex>) -> MultiRegex {
+        let mut by_first_byte = vec![Vec::new(); 256];
+        let mut at_end = Vec::new();
+        for (ix, regex) in regexes.iter().enumerate() {
+            match regex.hints.first_bytes {
+                Some(ref bytes) => {
+                    for b in bytes.iter() {
+                        by_first_byte[b].push(ix);
+                    }
+                }
+                None => {
+                    for candidates in &mut by_first_byte {
+                        candidates.push(ix);
+                    }
+                    at_end.push(ix);
+                }
+            }
+        }
+        MultiRegex {
+            regexes,
+            by_first_byte,
+            at_end,
+        }
+    }
+
+    /// Returns the number of regexes.
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Returns true if there are no regexes.
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    /// Returns the regex with index `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no regex with that index.
+    pub fn regex(&self, id: usize) -> &Regex {
+        &self.regexes[id]
+    }
+
+    /// Returns an iterator over the matches of all the regexes in `text`, as the index of the
+    /// regex and its captures.
+    ///
+    /// The matches of each regex don't overlap each other, the same as with
+    /// [`Regex::captures_iter`](struct.Regex.html#method.captures_iter), but they can overlap
+    /// the matches of other regexes. They are ordered by their start position, and by the index
+    /// of the regex for matches that start at the same position.
+    pub fn captures_iter<'m, 't>(&'m self, text: &'t str) -> MultiCaptureMatches<'m, 't> {
+        MultiCaptureMatches {
+            multi: self,
+            text,
+            pos: 0,
+            searches: vec![Search::default(); self.regexes.len()],
+            found: VecDeque::new(),
+        }
+    }
+}
+
+/// Where the search of one regex continues.
+#[derive(Clone, Debug, Default)]
+struct Search {
+    /// No match can start before this position
+    last_end: usize,
+    /// The end of the last match, to skip an empty match right after it
+    last_match: Option<usize>,
+}
+
+/// An iterator over the matches of the regexes of a `MultiRegex`.
+///
+/// The iterator yields a `Result<(usize, Captures)>` with the index of the regex that matched.
+/// `'m` is the lifetime of the `MultiRegex` and `'t` is the lifetime of the matched string.
+#[derive(Debug)]
+pub struct MultiCaptureMatches<'m, 't> {
+    multi: &'m MultiRegex,
+    text: &'t str,
+    /// The next position to try
+    pos: usize,
+    searches: Vec<Search>,
+    /// Matches that were found at the last position but not returned yet
+    found: VecDeque<(usize, Captures<'t>)>,
+}
+
+impl<'m, 't> MultiCaptureMatches<'m, 't> {
+    /// Runs the regexes that can match at `self.pos` and adds their matches to `found`.
+    fn search_at_pos(&mut self) -> Result<()> {
+        let (multi, text, pos) = (self.multi, self.text, self.pos);
+        let candidates = match text.as_bytes().get(pos) {
+            Some(&b) => &multi.by_first_byte[b as usize],
+            None => &multi.at_end,
+        };
+        for &id in candidates {
+            let search = &mut self.searches[id];
+            let regex = &multi.regexes[id];
+            if search.last_end > pos || !regex.can_start_at(text, pos) {
+                continue;
+            }
+            let caps = match regex.captures_at(text, pos)? {
+                Some(caps) => caps,
+                None => continue,
+            };
+            let end = caps.get(0).map_or(pos, |m| m.end());
+            if end == pos {
+                // Don't accept empty matches immediately following a match
+                search.last_end = next_utf8(text, end);
+                if search.last_match == Some(end) {
+                    continue;
+                }
+            } else {
+                search.last_end = end;
+            }
+            search.last_match = Some(end);
+            self.found.push_back((id, caps));
+        }
+        Ok(())
+    }
+}
+
+impl<'m, 't> Iterator for MultiCaptureMatches<'m, 't> {
+    type Item = Result<(usize, Captures<'t>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(found) = self.found.pop_front() {
+                return Some(Ok(found));
+            }
+            if self.pos > self.text.len() {
+                return None;
+            }
+            let result = self.search_at_pos();
+            self.pos = next_utf8(self.text, self.pos);
+            if let Err(error) = result {
+                self.pos = self.text.len() + 1;
+                self.found.clear();
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+impl<'m, 't> FusedIterator for MultiCaptureMatches<'m, 't> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(multi: &MultiRegex, text: &str) -> Vec<(usize, usize, usize)> {
+        multi
+            .captures_iter(text)
+            .map(|result| {
+                let (id, caps) = result.unwrap();
+                let m = caps.get(0).unwrap();
+                (id, m.start(), m.end())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_as_separate_searches() {
+        let patterns = [r"\w+(?=,)", "a", r"(?<=\s)\w", r"\d*", "$"];
+        let multi = MultiRegex::new(&patterns).unwrap();
+        let text = "ab, c1a, éa";
+        let mut expected = Vec::new();
+        for (id, pattern) in patterns.iter().enumerate() {
+            let regex = Regex::new(pattern).unwrap();
+            for m in regex.find_iter(text) {
+                let m = m.unwrap();
+                expected.push((m.start(), id, m.end()));
+            }
+        }
+        expected.sort();
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(start, id, end)| (id, start, end))
+            .collect();
+        assert_eq!(matches(&multi, text), expected);
+    }
+
+    #[test]
+    fn captures_of_each_pattern() {
+        let multi = MultiRegex::new(&[r"(\w)\1", r"(?P<key>\w+)=(?P<value>\w+)"]).unwrap();
+        let mut iter = multi.captures_iter("xx=yy");
+        let (id, caps) = iter.next().unwrap().unwrap();
+        assert_eq!((id, &caps[1]), (0, "x"));
+        let (id, caps) = iter.next().unwrap().unwrap();
+        assert_eq!((id, &caps["key"], &caps["value"]), (1, "xx", "yy"));
+        let (id, caps) = iter.next().unwrap().unwrap();
+        assert_eq!((id, &caps[1]), (0, "y"));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn error_ends_iteration() {
+        let regex = crate::RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
+            .backtrack_limit(100)
+            .build()
+            .unwrap();
+        let multi = MultiRegex::from_regexes(vec![regex]);
+        let mut iter = multi.captures_iter("abababababababababab");
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
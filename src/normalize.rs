@@ -0,0 +1,169 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Normalization-insensitive matching, enabled with the `unicode-normalization` feature.
+//!
+//! Instead of normalizing the text, literals in the expression are rewritten so that they match
+//! both the composed (NFC) and the decomposed (NFD) form of each character, e.g. `é` becomes
+//! `(?:é|e\u{301})`.
+
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::Expr;
+
+/// Rewrites the literals in `expr` to match canonically equivalent text. Adjacent literals are
+/// joined first, so that a base character and a following combining mark in the pattern are
+/// treated as one character.
+pub(crate) fn expand_equivalents(expr: Expr) -> Expr {
+    match expr {
+        Expr::Literal { val, casei } => expand_literal(&val, casei),
+        Expr::Concat(children) => {
+            let mut result = Vec::with_capacity(children.len());
+            let mut run: Option<(String, bool)> = None;
+            for child in children {
+                match child {
+                    Expr::Literal { val, casei } => match run {
+                        Some((ref mut s, run_casei)) if run_casei == casei => s.push_str(&val),
+                        _ => {
+                            if let Some((s, casei)) = run.take() {
+                                push_expanded(&mut result, expand_literal(&s, casei));
+                            }
+                            run = Some((val, casei));
+                        }
+                    },
+                    child => {
+                        if let Some((s, casei)) = run.take() {
+                            push_expanded(&mut result, expand_literal(&s, casei));
+                        }
+                        result.push(expand_equivalents(child));
+                    }
+                }
+            }
+            if let Some((s, casei)) = run {
+                push_expanded(&mut result, expand_literal(&s, casei));
+            }
+            Expr::Concat(result)
+        }
+        Expr::Alt(children) => Expr::Alt(children.into_iter().map(expand_equivalents).collect()),
+        Expr::Group(child) => Expr::Group(Box::new(expand_equivalents(*child))),
+        Expr::LookAround(child, la) => Expr::LookAround(Box::new(expand_equivalents(*child)), la),
+        Expr::Repeat {
+            child,
+            lo,
+            hi,
+            greedy,
+        } => Expr::Repeat {
+            child: Box::new(expand_equivalents(*child)),
+            lo,
+            hi,
+            greedy,
+        },
+        Expr::AtomicGroup(child) => Expr::AtomicGroup(Box::new(expand_equivalents(*child))),
+        Expr::ScriptRun(child) => Expr::ScriptRun(Box::new(expand_equivalents(*child))),
+        expr => expr,
+    }
+}
+
+/// Adds the expansion of a literal to the children of a concatenation, without nesting another
+/// concatenation in it.
+fn push_expanded(children: &mut Vec<Expr>, expanded: Expr) {
+    match expanded {
+        Expr::Concat(parts) => children.extend(parts),
+        expanded => children.push(expanded),
+    }
+}
+
+/// Splits the composed form of `val` into characters with their combining marks, and matches
+/// each of them as either its composed or its decomposed form.
+fn expand_literal(val: &str, casei: bool) -> Expr {
+    let literal = |val: String| Expr::Literal { val, casei };
+    let composed: String = val.nfc().collect();
+    let mut children = Vec::new();
+    let mut plain = String::new();
+    let mut chars = composed.char_indices().peekable();
+    while let Some((start, _)) = chars.next() {
+        let mut end = composed.len();
+        while let Some(&(ix, c)) = chars.peek() {
+            if canonical_combining_class(c) == 0 {
+                end = ix;
+                break;
+            }
+            chars.next();
+        }
+        let segment = &composed[start..end];
+        let decomposed: String = segment.nfd().collect();
+        if decomposed == segment {
+            plain.push_str(segment);
+        } else {
+            if !plain.is_empty() {
+                children.push(literal(std::mem::take(&mut plain)));
+            }
+            children.push(Expr::Alt(vec![
+                literal(segment.to_string()),
+                literal(decomposed),
+            ]));
+        }
+    }
+    if !plain.is_empty() {
+        children.push(literal(plain));
+    }
+    if children.len() == 1 {
+        children.pop().unwrap()
+    } else {
+        Expr::Concat(children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::make_literal;
+
+    fn expand(re: &str) -> String {
+        let expr = expand_equivalents(Expr::parse_tree(re).unwrap().expr);
+        let mut s = String::new();
+        expr.to_str(&mut s, 1);
+        s
+    }
+
+    #[test]
+    fn literal_without_equivalents() {
+        assert_eq!(expand_literal("abc", false), make_literal("abc"));
+    }
+
+    #[test]
+    fn composed_and_decomposed() {
+        assert_eq!(expand("\u{e9}"), "(?:\u{e9}|e\u{301})");
+        assert_eq!(expand("e\u{301}"), "(?:\u{e9}|e\u{301})");
+        assert_eq!(expand("caf\u{e9}s"), "caf(?:\u{e9}|e\u{301})s");
+    }
+
+    #[test]
+    fn nested_literals() {
+        assert_eq!(expand("(\u{e9})+"), "(\u{e9}|e\u{301})+");
+        assert_eq!(expand("a|\u{e9}+"), "(?:a|(?:\u{e9}|e\u{301})+)");
+    }
+
+    #[test]
+    fn hangul_syllable() {
+        assert_eq!(expand("\u{ac00}"), "(?:\u{ac00}|\u{1100}\u{1161})");
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+//! Mapping byte offsets of matches to character indices and line/column positions.
+
+use std::ops::Range;
+
+/// Converts byte offsets in a text, as used by [`Match`](struct.Match.html), to character
+/// indices and to line and column numbers, e.g. for showing the positions of matches in an
+/// editor or in the output of a command line tool.
+///
+/// Lines are separated by `\n`, like for [`Regex::grep_lines`](struct.Regex.html#method.grep_lines).
+/// A `\r` before it counts as a character at the end of the line. Line and column numbers start
+/// at 1, and columns count characters (Unicode scalar values), not bytes.
+///
+/// Looking up an offset takes time proportional to the length of its line, the start of each
+/// line is found once when creating the map.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{OffsetMap, Regex};
+/// let text = "héllo\nwörld";
+/// let re = Regex::new(r"(?<=ö)r").unwrap();
+/// let m = re.find(text).unwrap().unwrap();
+///
+/// let map = OffsetMap::new(text);
+/// assert_eq!(m.start(), 10);
+/// assert_eq!(map.char_index(m.start()), 8);
+/// assert_eq!(map.line_column(m.start()), (2, 3));
+/// ```
+#[derive(Clone, Debug)]
+pub struct OffsetMap<'t> {
+    text: &'t str,
+    /// Byte offset of the start of each line
+    line_starts: Vec<usize>,
+    /// Character index of the start of each line
+    line_chars: Vec<usize>,
+}
+
+impl<'t> OffsetMap<'t> {
+    /// Create a map for the offsets in `text`.
+    pub fn new(text: &'t str) -> OffsetMap<'t> {
+        let mut line_starts = vec![0];
+        let mut line_chars = vec![0];
+        let mut chars = 0;
+        let mut start = 0;
+        for (ix, _) in text.match_indices('\n') {
+            chars += text[start..ix].chars().count() + 1;
+            start = ix + 1;
+            line_starts.push(start);
+            line_chars.push(chars);
+        }
+        OffsetMap {
+            text,
+            line_starts,
+            line_chars,
+        }
+    }
+
+    /// Return the text of the map.
+    pub fn text(&self) -> &'t str {
+        self.text
+    }
+
+    /// Return the number of lines. A text that ends with `\n` has an empty line at the end.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Return the index of the character at byte `offset`, i.e. the number of characters before
+    /// it. The length of the text is a valid offset too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is greater than the length of the text or not at a character boundary.
+    pub fn char_index(&self, offset: usize) -> usize {
+        let line = self.line_index(offset);
+        self.line_chars[line] + self.column_chars(line, offset)
+    }
+
+    /// Return the character indices of the start and end of a byte range, e.g. of a match.
+    ///
+    /// # Panics
+    ///
+    /// Panics like `char_index` for the start or end.
+    pub fn char_range(&self, range: Range<usize>) -> Range<usize> {
+        self.char_index(range.start)..self.char_index(range.end)
+    }
+
+    /// Return the line and column number of byte `offset`, both starting at 1. The length of the
+    /// text is a valid offset too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is greater than the length of the text or not at a character boundary.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_index(offset);
+        (line + 1, self.column_chars(line, offset) + 1)
+    }
+
+    /// Return the byte range of line `line` (starting at 1), without the `\n` at its end, or
+    /// `None` if there's no such line.
+    pub fn line_range(&self, line: usize) -> Option<Range<usize>> {
+        let index = line.checked_sub(1)?;
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .map_or(self.text.len(), |next| next - 1);
+        Some(start..end)
+    }
+
+    /// The index of the line that contains `offset`, starting at 0
+    fn line_index(&self, offset: usize) -> usize {
+        assert!(
+            offset <= self.text.len(),
+            "offset {} is out of bounds of text of length {}",
+            offset,
+            self.text.len()
+        );
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        }
+    }
+
+    /// The number of characters from the start of `line` to `offset`
+    fn column_chars(&self, line: usize, offset: usize) -> usize {
+        self.text[self.line_starts[line]..offset].chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets() {
+        let text = "ab\r\nc\u{e9}d\n\n";
+        let map = OffsetMap::new(text);
+
+        assert_eq!(map.line_count(), 4);
+        assert_eq!(map.char_index(0), 0);
+        assert_eq!(map.line_column(0), (1, 1));
+        // The `\r` belongs to the first line
+        assert_eq!(map.line_column(2), (1, 3));
+        assert_eq!(map.line_column(3), (1, 4));
+        assert_eq!(map.char_index(4), 4);
+        assert_eq!(map.line_column(4), (2, 1));
+        // After the two bytes of `é`
+        assert_eq!(map.char_index(7), 6);
+        assert_eq!(map.line_column(7), (2, 3));
+        assert_eq!(map.char_range(5..7), 5..6);
+        assert_eq!(map.line_column(9), (3, 1));
+        assert_eq!(map.char_index(text.len()), 9);
+        assert_eq!(map.line_column(text.len()), (4, 1));
+    }
+
+    #[test]
+    fn line_range() {
+        let map = OffsetMap::new("ab\ncd\n");
+        assert_eq!(map.line_range(0), None);
+        assert_eq!(map.line_range(1), Some(0..2));
+        assert_eq!(map.line_range(2), Some(3..5));
+        assert_eq!(map.line_range(3), Some(6..6));
+        assert_eq!(map.line_range(4), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_inside_char() {
+        OffsetMap::new("\u{e9}").char_index(1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn offset_out_of_bounds() {
+        OffsetMap::new("a").line_column(2);
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Searching large texts in parallel, enabled with the `rayon` feature.
+//!
+//! The text is split into chunks of about the same size, and each chunk is searched on its own
+//! thread for matches that start in it. The regex still sees the whole text, so look-around and
+//! matches that extend into the next chunk work as usual. When the chunks are put together,
+//! a match that overlaps the next chunk means that the search in that chunk didn't start in the
+//! same state as a single search would have. So the start of that chunk is searched again until
+//! it finds one of the matches of the chunk, from where on the results are the same.
+
+use std::iter::FusedIterator;
+use std::vec;
+
+use rayon::prelude::*;
+
+use crate::{Match, Matches, Regex, Result, RunConfig};
+
+/// Texts up to this size (in bytes) are searched in one go, larger ones in chunks of this size.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// An iterator over the matches found by
+/// [`Regex::par_find_iter`](struct.Regex.html#method.par_find_iter).
+///
+/// The search is done when the iterator is created. It yields the same as
+/// [`Matches`](struct.Matches.html): `Result<Match>`, and stops after an error.
+#[derive(Debug)]
+pub struct ParMatches<'t> {
+    inner: vec::IntoIter<Result<Match<'t>>>,
+}
+
+impl<'t> Iterator for ParMatches<'t> {
+    type Item = Result<Match<'t>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'t> ExactSizeIterator for ParMatches<'t> {}
+
+impl<'t> FusedIterator for ParMatches<'t> {}
+
+pub(crate) fn find_iter<'t>(regex: &Regex, text: &'t str) -> ParMatches<'t> {
+    ParMatches {
+        inner: find_all(regex, text, CHUNK_SIZE).into_iter(),
+    }
+}
+
+/// Where the search of a `Matches` iterator continues.
+#[derive(Clone, Copy, PartialEq)]
+struct State {
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl State {
+    /// The state after finding `m`, the same as `Matches` would have.
+    fn after(self, regex: &Regex, text: &str, m: &Match<'_>) -> State {
+        let mut matches = self.matches(regex, text);
+        matches.advance(m.start(), m.end());
+        State {
+            last_end: matches.last_end,
+            last_match: matches.last_match,
+        }
+    }
+
+    fn matches<'r, 't>(self, regex: &'r Regex, text: &'t str) -> Matches<'r, 't> {
+        Matches {
+            re: regex,
+            text,
+            last_end: self.last_end,
+            last_match: self.last_match,
+            config: RunConfig::default(),
+        }
+    }
+}
+
+fn find_all<'t>(regex: &Regex, text: &'t str, chunk_size: usize) -> Vec<Result<Match<'t>>> {
+    if text.len() <= chunk_size || regex.max_size.is_none() {
+        // A match could extend over all of the following chunks, so their searches would be in
+        // vain. Like for each chunk, the search stops after an error.
+        let mut found = Vec::new();
+        for result in regex.find_iter(text) {
+            let is_error = result.is_err();
+            found.push(result);
+            if is_error {
+                break;
+            }
+        }
+        return found;
+    }
+
+    let mut bounds = Vec::new();
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = (start + chunk_size).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+        bounds.push((start, end));
+        start = end;
+    }
+    // The last chunk also includes empty matches at the end of the text
+    if let Some(last) = bounds.last_mut() {
+        last.1 = text.len() + 1;
+    }
+
+    let chunks: Vec<Vec<Result<Match<'t>>>> = bounds
+        .par_iter()
+        .map(|&(start, end)| {
+            let mut found = Vec::new();
+            for result in regex.find_iter_from_pos(text, start) {
+                match result {
+                    Ok(m) if m.start() >= end => break,
+                    Ok(m) => found.push(Ok(m)),
+                    Err(error) => {
+                        found.push(Err(error));
+                        break;
+                    }
+                }
+            }
+            found
+        })
+        .collect();
+
+    let mut result = Vec::new();
+    let mut state = State {
+        last_end: 0,
+        last_match: None,
+    };
+    for (&(start, end), found) in bounds.iter().zip(chunks) {
+        let mut skip = 0;
+        if state.last_end > start || state.last_match == Some(start) {
+            // Not in the state the search of the chunk started with, search until it is
+            skip = found.len();
+            let mut matches = state.matches(regex, text);
+            loop {
+                let m = match matches.next() {
+                    None => return result,
+                    Some(Err(error)) => {
+                        result.push(Err(error));
+                        return result;
+                    }
+                    Some(Ok(m)) if m.start() >= end => break,
+                    Some(Ok(m)) => m,
+                };
+                state = state.after(regex, text, &m);
+                result.push(Ok(m));
+                let same = |found: &Result<Match<'_>>| found.as_ref().ok() == Some(&m);
+                if let Some(ix) = found.iter().position(same) {
+                    skip = ix + 1;
+                    break;
+                }
+            }
+        }
+        for found in found.into_iter().skip(skip) {
+            match found {
+                Ok(m) => {
+                    state = state.after(regex, text, &m);
+                    result.push(Ok(m));
+                }
+                Err(error) => {
+                    result.push(Err(error));
+                    return result;
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same_as_find_iter(re: &str, text: &str) {
+        assert_regex_same_as_find_iter(&Regex::new(re).unwrap(), text);
+    }
+
+    fn assert_regex_same_as_find_iter(regex: &Regex, text: &str) {
+        let re = regex.as_str();
+        let expected: Vec<_> = regex.find_iter(text).map(|m| m.unwrap().range()).collect();
+        for chunk_size in 1..8 {
+            let found: Vec<_> = find_all(regex, text, chunk_size)
+                .into_iter()
+                .map(|m| m.unwrap().range())
+                .collect();
+            assert_eq!(found, expected, "{} with chunks of {}", re, chunk_size);
+        }
+    }
+
+    #[test]
+    fn same_as_find_iter() {
+        let text = "aa ab, 12 éa\nxaaa";
+        assert_same_as_find_iter("a", text);
+        assert_same_as_find_iter("a{2}", text);
+        assert_same_as_find_iter(r"\w{1,3}", text);
+        assert_same_as_find_iter(r"(?<=a)\w", text);
+        assert_same_as_find_iter(r"\w(?=\s)", text);
+        assert_same_as_find_iter(r"(\w)\1", text);
+        assert_same_as_find_iter(r"\d?", text);
+        assert_same_as_find_iter(r"(?m)^|a", text);
+        assert_same_as_find_iter("$", text);
+    }
+
+    #[test]
+    fn unbounded() {
+        assert_same_as_find_iter(r"a.*(?=a)", "ab ab ab ab");
+    }
+
+    #[test]
+    fn empty_match_policy() {
+        let text = "aa ab, 12 éa\nxaaa";
+        for re in &["a*", "a*?", r"\w*?(?!a)", r"(?m)^\w*"] {
+            let regex = crate::RegexBuilder::new(re)
+                .empty_match_policy(crate::EmptyMatchPolicy::RetryNonEmpty)
+                .build()
+                .unwrap();
+            assert_regex_same_as_find_iter(&regex, text);
+        }
+    }
+
+    #[test]
+    fn error() {
+        let regex = crate::RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
+            .backtrack_limit(100)
+            .build()
+            .unwrap();
+        let text = "ab".repeat(20);
+        let found = find_all(&regex, &text, 4);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].is_err());
+    }
+}
@@ -21,15 +21,22 @@
 //! A regex parser yielding an AST.
 
 use bit_set::BitSet;
-use regex::escape;
 use std::collections::HashMap;
+use std::ops::Range;
 use std::str::FromStr;
 use std::usize;
 
 use crate::codepoint_len;
+use crate::flavor;
+use crate::fragments::Fragments;
+use crate::lint::{Lint, LintKind};
 use crate::Error;
 use crate::Expr;
+use crate::LargeRepeatPolicy;
 use crate::LookAround::*;
+use crate::NumberedBackrefs;
+use crate::ParseError;
+use crate::RegexOptions;
 use crate::Result;
 use crate::MAX_RECURSION;
 
@@ -47,6 +54,106 @@ pub struct ExprTree {
     pub expr: Expr,
     pub backrefs: BitSet,
     pub named_groups: NamedGroups,
+    /// Byte range of each capture group (starting with group 1) in the pattern, including the
+    /// parentheses
+    pub group_spans: Vec<Range<usize>>,
+}
+
+impl ExprTree {
+    /// Turns the capture groups that are not in `keep` into non-capturing groups, except the ones
+    /// that are referenced by backrefs. The remaining groups are renumbered.
+    ///
+    /// Returns the new number of each group by its old number (starting with group 0 for the
+    /// whole match), `None` for groups that are not captured anymore. Named groups and group
+    /// spans keep referring to the old numbers.
+    pub(crate) fn prune_groups(&mut self, keep: &BitSet) -> Vec<Option<usize>> {
+        let mut map = vec![Some(0)];
+        number_groups(&self.expr, &mut |old| {
+            let new = if keep.contains(old) || self.backrefs.contains(old) {
+                Some(map.iter().flatten().count())
+            } else {
+                None
+            };
+            map.push(new);
+        });
+        let expr = std::mem::replace(&mut self.expr, Expr::Empty);
+        self.expr = renumber_groups(expr, &map, &mut 1);
+        self.backrefs = self.backrefs.iter().filter_map(|old| map[old]).collect();
+        map
+    }
+}
+
+/// Calls `f` with the number of each group, in the order they are numbered.
+fn number_groups<F: FnMut(usize)>(expr: &Expr, f: &mut F) {
+    fn visit<F: FnMut(usize)>(expr: &Expr, f: &mut F, next: &mut usize) {
+        match *expr {
+            Expr::Group(ref child) => {
+                f(*next);
+                *next += 1;
+                visit(child, f, next);
+            }
+            Expr::Concat(ref children) | Expr::Alt(ref children) => {
+                for child in children {
+                    visit(child, f, next);
+                }
+            }
+            Expr::LookAround(ref child, _)
+            | Expr::Repeat { ref child, .. }
+            | Expr::AtomicGroup(ref child)
+            | Expr::ScriptRun(ref child) => visit(child, f, next),
+            _ => {}
+        }
+    }
+    visit(expr, f, &mut 1)
+}
+
+fn renumber_groups(expr: Expr, map: &[Option<usize>], next: &mut usize) -> Expr {
+    let mut renumber = |child: Box<Expr>| Box::new(renumber_groups(*child, map, next));
+    match expr {
+        Expr::Group(child) => {
+            let old = *next;
+            *next += 1;
+            let child = renumber_groups(*child, map, next);
+            match map[old] {
+                Some(_) => Expr::Group(Box::new(child)),
+                // Formats as `(?:)` where a group is needed
+                None if child == Expr::Empty => Expr::Concat(Vec::new()),
+                None => child,
+            }
+        }
+        Expr::Concat(children) => Expr::Concat(
+            children
+                .into_iter()
+                .map(|child| renumber_groups(child, map, next))
+                .collect(),
+        ),
+        Expr::Alt(children) => Expr::Alt(
+            children
+                .into_iter()
+                .map(|child| renumber_groups(child, map, next))
+                .collect(),
+        ),
+        Expr::LookAround(child, la) => Expr::LookAround(renumber(child), la),
+        Expr::Repeat {
+            child,
+            lo,
+            hi,
+            greedy,
+        } => Expr::Repeat {
+            child: renumber(child),
+            lo,
+            hi,
+            greedy,
+        },
+        Expr::AtomicGroup(child) => Expr::AtomicGroup(renumber(child)),
+        Expr::ScriptRun(child) => Expr::ScriptRun(renumber(child)),
+        Expr::Backref { group, casei } => Expr::Backref {
+            // Groups referenced by backrefs are always kept
+            group: map.get(group).cloned().flatten().unwrap_or(group),
+            casei,
+        },
+        expr => expr,
+    }
 }
 
 #[derive(Debug)]
@@ -55,24 +162,86 @@ pub(crate) struct Parser<'a> {
     backrefs: BitSet,
     flags: u32,
     named_groups: NamedGroups,
-    numeric_backrefs: bool,
+    group_spans: Vec<Range<usize>>,
+    /// Groups that are case-insensitive, for backrefs to them
+    casei_groups: BitSet,
+    /// Position of the first numbered backref, for the error if there are named groups too
+    numeric_backref: Option<usize>,
     curr_group: usize, // need to keep track of which group number we're parsing
+    /// Warnings about the pattern, see `lint`
+    lints: Vec<Lint>,
+    /// Reject sloppy syntax instead of accepting it with a warning, see `RegexBuilder::strict`
+    strict: bool,
+    /// Reject repeats with a larger count, see `RegexBuilder::max_repeat`
+    max_repeat: Option<usize>,
+    /// How escapes like `\10` are read, see `RegexBuilder::numbered_backrefs`
+    numbered_backrefs: NumberedBackrefs,
+    /// The fragments that can be included with `(?&name)`, see `RegexBuilder::fragments`
+    fragments: Option<&'a Fragments>,
+    /// The names of the fragments that are being included, outermost first, for rejecting
+    /// recursion
+    including: Vec<&'a str>,
+    /// The arguments of the fragment that is being included, by the names of its parameters
+    params: Vec<(&'a str, Expr)>,
 }
 
 impl<'a> Parser<'a> {
     /// Parse the regex and return an expression (AST) and a bit set with the indexes of groups
     /// that are referenced by backrefs.
     pub(crate) fn parse(re: &str) -> Result<ExprTree> {
-        let mut p = Parser::new(re);
-        let (ix, expr) = p.parse_re(0, 0)?;
-        if ix < re.len() {
-            return Err(Error::ParseError);
+        Parser::parse_linted(re).map(|(tree, _)| tree)
+    }
+
+    /// Parse the regex like `parse`, and also return the warnings about it.
+    pub(crate) fn parse_linted(re: &str) -> Result<(ExprTree, Vec<Lint>)> {
+        Parser::new(re).parse_all()
+    }
+
+    /// Parse the pattern of `options` like `parse`, with the options that affect the syntax.
+    pub(crate) fn parse_options(options: &RegexOptions) -> Result<ExprTree> {
+        Parser::parse_options_linted(options).map(|(tree, _)| tree)
+    }
+
+    /// Parse the pattern of `options` like `parse_options`, and also return the warnings about
+    /// it.
+    pub(crate) fn parse_options_linted(options: &RegexOptions) -> Result<(ExprTree, Vec<Lint>)> {
+        let mut p = Parser::new(&options.pattern);
+        if options.swap_greed {
+            p.flags |= FLAG_SWAP_GREED;
+        }
+        p.strict = options.strict;
+        if options.large_repeat_policy == LargeRepeatPolicy::Reject {
+            p.max_repeat = options.max_repeat;
+        }
+        p.numbered_backrefs = options.numbered_backrefs;
+        if !options.fragments.is_empty() {
+            p.fragments = Some(&options.fragments);
         }
-        Ok(ExprTree {
+        p.parse_all()
+    }
+
+    /// Parse the pattern of a fragment on its own, for checking it when it's added to
+    /// `fragments`. The parameters match the empty string.
+    pub(crate) fn parse_fragment<'p>(
+        re: &'p str,
+        params: &[&'p str],
+        fragments: &'p Fragments,
+    ) -> Result<Expr> {
+        let mut p = Parser::new(re);
+        p.fragments = Some(fragments);
+        p.params = params.iter().map(|&param| (param, Expr::Empty)).collect();
+        p.parse_included(0)
+    }
+
+    fn parse_all(mut self) -> Result<(ExprTree, Vec<Lint>)> {
+        let expr = self.parse_to_end(0)?;
+        let tree = ExprTree {
             expr,
-            backrefs: Default::default(),
-            named_groups: p.named_groups,
-        })
+            backrefs: self.backrefs,
+            named_groups: self.named_groups,
+            group_spans: self.group_spans,
+        };
+        Ok((tree, self.lints))
     }
 
     fn new(re: &str) -> Parser<'_> {
@@ -80,12 +249,143 @@ impl<'a> Parser<'a> {
             re,
             backrefs: Default::default(),
             named_groups: Default::default(),
-            numeric_backrefs: false,
+            group_spans: Vec::new(),
+            casei_groups: Default::default(),
+            numeric_backref: None,
             flags: FLAG_UNICODE,
             curr_group: 0,
+            lints: Vec::new(),
+            strict: false,
+            max_repeat: None,
+            numbered_backrefs: NumberedBackrefs::Pcre,
+            fragments: None,
+            including: Vec::new(),
+            params: Vec::new(),
         }
     }
 
+    fn parse_to_end(&mut self, depth: usize) -> Result<Expr> {
+        let re = self.re;
+        let (ix, expr) = self.parse_re(0, depth)?;
+        if ix < re.len() {
+            let found = &re[ix..ix + codepoint_len(re.as_bytes()[ix])];
+            let message = format!("unexpected `{}`", found);
+            return Err(Error::ParseError(
+                ix,
+                ParseError::GeneralParseError(message),
+            ));
+        }
+        Ok(expr)
+    }
+
+    /// Parse the whole pattern as a fragment that is included in another pattern: it can't
+    /// contain backrefs, and its groups don't capture.
+    fn parse_included(mut self, depth: usize) -> Result<Expr> {
+        let expr = self.parse_to_end(depth)?;
+        if !self.backrefs.is_empty() {
+            return Err(Error::ParseError(0, ParseError::InvalidBackref));
+        }
+        let mut map = vec![None; self.curr_group + 1];
+        map[0] = Some(0);
+        Ok(renumber_groups(expr, &map, &mut 1))
+    }
+
+    /// Parse a call of a fragment, e.g. `(?&ident)` or `(?&list(\d+))`, with `ix` pointing to
+    /// the name. Errors in the fragment are reported at the position of the call.
+    fn parse_fragment_call(
+        &mut self,
+        open: usize,
+        ix: usize,
+        depth: usize,
+    ) -> Result<(usize, Expr)> {
+        let fragments = self.fragments.expect("only called with fragments");
+        let re = self.re;
+        let name = match parse_id(&re[ix..], "", "") {
+            Some((name, _)) => name,
+            None => return Err(Error::ParseError(open, ParseError::InvalidGroupName)),
+        };
+        let mut ix = ix + name.len();
+        let mut args = Vec::new();
+        while re[ix..].starts_with('(') {
+            let (end, arg) = self.parse_fragment_arg(ix, depth)?;
+            args.push(arg);
+            ix = end;
+        }
+        if ix == re.len() {
+            return Err(Error::ParseError(open, ParseError::UnclosedOpenParen));
+        } else if re.as_bytes()[ix] != b')' {
+            return Err(self.expected_close_paren(ix));
+        }
+        let wrong_args = |expected: usize| {
+            let error = ParseError::FragmentArguments {
+                name: name.to_string(),
+                expected,
+                found: args.len(),
+            };
+            Err(Error::ParseError(open, error))
+        };
+        // Parameters hide fragments with the same name
+        if let Some((_, arg)) = self.params.iter().find(|(param, _)| *param == name) {
+            if !args.is_empty() {
+                return wrong_args(0);
+            }
+            return Ok((ix + 1, arg.clone()));
+        }
+        let (params, pattern) = match fragments.get_with_params(name) {
+            Some(fragment) => fragment,
+            None => {
+                let error = ParseError::UnknownFragment(name.to_string());
+                return Err(Error::ParseError(open, error));
+            }
+        };
+        if self.including.contains(&name) {
+            let error = ParseError::RecursiveFragment(name.to_string());
+            return Err(Error::ParseError(open, error));
+        }
+        if args.len() != params.len() {
+            return wrong_args(params.len());
+        }
+        let mut p = Parser::new(pattern);
+        p.strict = self.strict;
+        p.max_repeat = self.max_repeat;
+        p.numbered_backrefs = self.numbered_backrefs;
+        p.fragments = self.fragments;
+        p.including = self.including.clone();
+        p.including.push(name);
+        p.params = params.iter().map(String::as_str).zip(args).collect();
+        let expr = p.parse_included(depth).map_err(|error| match error {
+            Error::ParseError(_, error) => Error::ParseError(open, error),
+            error => error,
+        })?;
+        Ok((ix + 1, expr))
+    }
+
+    /// Parse an argument of a fragment call, e.g. `(\d+)`, with `ix` pointing to its opening
+    /// parenthesis. The argument is parsed with the flags at the call, and like a fragment, it
+    /// can't contain backrefs and its groups don't capture.
+    fn parse_fragment_arg(&self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        let mut p = Parser::new(self.re);
+        p.flags = self.flags;
+        p.strict = self.strict;
+        p.max_repeat = self.max_repeat;
+        p.numbered_backrefs = self.numbered_backrefs;
+        p.fragments = self.fragments;
+        p.including = self.including.clone();
+        p.params = self.params.clone();
+        let (end, expr) = p.parse_re(ix + 1, depth + 1)?;
+        if end == self.re.len() {
+            return Err(Error::ParseError(ix, ParseError::UnclosedOpenParen));
+        } else if self.re.as_bytes()[end] != b')' {
+            return Err(self.expected_close_paren(end));
+        }
+        if !p.backrefs.is_empty() {
+            return Err(Error::ParseError(ix, ParseError::InvalidBackref));
+        }
+        let mut map = vec![None; p.curr_group + 1];
+        map[0] = Some(0);
+        Ok((end + 1, renumber_groups(expr, &map, &mut 1)))
+    }
+
     fn parse_re(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let (ix, child) = self.parse_branch(ix, depth)?;
         let mut ix = self.optional_whitespace(ix)?;
@@ -100,8 +400,10 @@ impl<'a> Parser<'a> {
             return Ok((ix, Expr::Alt(children)));
         }
         // can't have numeric backrefs and named backrefs
-        if self.numeric_backrefs && !self.named_groups.is_empty() {
-            return Err(Error::NamedBackrefOnly);
+        if let Some(backref) = self.numeric_backref {
+            if !self.named_groups.is_empty() {
+                return Err(Error::ParseError(backref, ParseError::NamedBackrefOnly));
+            }
         }
         Ok((ix, child))
     }
@@ -109,12 +411,17 @@ impl<'a> Parser<'a> {
     fn parse_branch(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let mut children = Vec::new();
         let mut ix = ix;
+        let mut position = Position::default();
         while ix < self.re.len() {
             let (next, child) = self.parse_piece(ix, depth)?;
             if next == ix {
                 break;
             }
             if child != Expr::Empty {
+                if let Some(kind) = position.advance(children.last(), &child) {
+                    let start = self.optional_whitespace(ix)?;
+                    self.lints.push(Lint::new(kind, start..next));
+                }
                 children.push(child);
             }
             ix = next;
@@ -127,8 +434,9 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_piece(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
-        let (ix, child) = self.parse_atom(ix, depth)?;
-        let mut ix = self.optional_whitespace(ix)?;
+        let start = self.optional_whitespace(ix)?;
+        let (atom_end, child) = self.parse_atom(start, depth)?;
+        let mut ix = self.optional_whitespace(atom_end)?;
         if ix < self.re.len() {
             // fail when child is empty?
             let (lo, hi) = match self.re.as_bytes()[ix] {
@@ -138,6 +446,7 @@ impl<'a> Parser<'a> {
                 b'{' => {
                     match self.parse_repeat(ix) {
                         Ok((next, lo, hi)) => {
+                            self.check_repeat_count(ix, lo, hi)?;
                             ix = next - 1;
                             (lo, hi)
                         }
@@ -150,7 +459,7 @@ impl<'a> Parser<'a> {
                 _ => return Ok((ix, child)),
             };
             if !self.is_repeatable(&child) {
-                return Err(Error::TargetNotRepeatable);
+                return Err(Error::ParseError(ix, ParseError::TargetNotRepeatable));
             }
             ix += 1;
             ix = self.optional_whitespace(ix)?;
@@ -160,6 +469,14 @@ impl<'a> Parser<'a> {
                 ix += 1;
             }
             greedy ^= self.flag(FLAG_SWAP_GREED);
+            // Capture groups are kept, so that the numbers of later groups don't change
+            let empty_group_replacement = if !is_empty_group(&child) {
+                None
+            } else if has_capture_group(&child) {
+                Some(&self.re[start..atom_end])
+            } else {
+                Some("")
+            };
             let mut node = Expr::Repeat {
                 child: Box::new(child),
                 lo,
@@ -170,29 +487,34 @@ impl<'a> Parser<'a> {
                 ix += 1;
                 node = Expr::AtomicGroup(Box::new(node));
             }
+            if let Some(replacement) = empty_group_replacement {
+                let lint = Lint::new(LintKind::QuantifiedEmptyGroup, start..ix);
+                self.lints.push(lint.replace_with(replacement));
+            } else if let Expr::Repeat { hi, ref child, .. } = node {
+                if hi == usize::MAX && has_unbounded_repeat(child) {
+                    self.lints
+                        .push(Lint::new(LintKind::NestedQuantifier, start..ix));
+                }
+            }
             return Ok((ix, node));
         }
         Ok((ix, child))
     }
 
     fn is_repeatable(&self, child: &Expr) -> bool {
-        match child {
-            Expr::LookAround(_, _) => false,
-            Expr::Empty => false,
-            Expr::StartText => false,
-            Expr::EndText => false,
-            Expr::StartLine => false,
-            Expr::EndLine => false,
-            _ => true,
-        }
+        !matches!(
+            child,
+            Expr::Empty | Expr::StartText | Expr::EndText | Expr::StartLine | Expr::EndLine
+        )
     }
 
     // ix, lo, hi
-    fn parse_repeat(&self, ix: usize) -> Result<(usize, usize, usize)> {
-        let ix = self.optional_whitespace(ix + 1)?; // skip opening '{'
+    fn parse_repeat(&self, start: usize) -> Result<(usize, usize, usize)> {
+        let invalid = Err(Error::ParseError(start, ParseError::InvalidRepeat));
+        let ix = self.optional_whitespace(start + 1)?; // skip opening '{'
         let bytes = self.re.as_bytes();
         if ix == self.re.len() {
-            return Err(Error::InvalidRepeat);
+            return invalid;
         }
         let mut end = ix;
         let lo = if bytes[ix] == b',' {
@@ -201,11 +523,11 @@ impl<'a> Parser<'a> {
             end = next;
             lo
         } else {
-            return Err(Error::InvalidRepeat);
+            return invalid;
         };
         let ix = self.optional_whitespace(end)?; // past lo number
         if ix == self.re.len() {
-            return Err(Error::InvalidRepeat);
+            return invalid;
         }
         end = ix;
         let hi = match bytes[ix] {
@@ -219,15 +541,27 @@ impl<'a> Parser<'a> {
                     usize::MAX
                 }
             }
-            _ => return Err(Error::InvalidRepeat),
+            _ => return invalid,
         };
         let ix = self.optional_whitespace(end)?; // past hi number
         if ix == self.re.len() || bytes[ix] != b'}' {
-            return Err(Error::InvalidRepeat);
+            return invalid;
         }
         Ok((ix + 1, lo, hi))
     }
 
+    /// Reject the repeat `{lo,hi}` at `ix` if its count is above `max_repeat`.
+    fn check_repeat_count(&self, ix: usize, lo: usize, hi: usize) -> Result<()> {
+        let count = repeat_count(lo, hi);
+        match self.max_repeat {
+            Some(max) if count > max => Err(Error::ParseError(
+                ix,
+                ParseError::RepeatTooLarge { count, max },
+            )),
+            _ => Ok(()),
+        }
+    }
+
     fn parse_atom(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let ix = self.optional_whitespace(ix)?;
         if ix == self.re.len() {
@@ -257,16 +591,13 @@ impl<'a> Parser<'a> {
                 },
             )),
             b'(' => self.parse_group(ix, depth),
-            b'\\' => {
-                let (next, expr) = self.parse_escape(ix)?;
-                if let Expr::Backref(group) = expr {
-                    self.backrefs.insert(group);
-                }
-                Ok((next, expr))
-            }
+            b'\\' => self.parse_escape(ix),
             b'+' | b'*' | b'?' | b'|' | b')' => Ok((ix, Expr::Empty)),
             b'[' => self.parse_class(ix),
             b => {
+                if b == b'{' || b == b']' {
+                    self.check_sloppy_literal(ix)?;
+                }
                 // TODO: maybe want to match multiple codepoints?
                 let next = ix + codepoint_len(b);
                 Ok((
@@ -280,47 +611,152 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_backref(&self, ix: usize, open: &str, close: &str) -> Result<(usize, Expr)> {
+    fn backref(&mut self, group: usize, span: Range<usize>) -> Expr {
+        if group > 0 {
+            // A group that is still open has an empty span
+            let complete = group <= self.curr_group && {
+                let group_span = &self.group_spans[group - 1];
+                group_span.start < group_span.end
+            };
+            if !complete {
+                let lint = Lint::new(LintKind::BackrefBeforeGroup(group), span);
+                self.lints.push(lint);
+            }
+        }
+        self.backrefs.insert(group);
+        Expr::Backref {
+            group,
+            casei: self.casei_groups.contains(group),
+        }
+    }
+
+    // ix points to the `\` of a numbered backref and end after its digits
+    fn numbered_backref_expr(
+        &mut self,
+        ix: usize,
+        end: usize,
+        group: usize,
+    ) -> Result<(usize, Expr)> {
+        // protect BitSet against unreasonably large value
+        if group < self.re.len() / 2 {
+            self.numeric_backref = self.numeric_backref.or(Some(ix));
+            Ok((end, self.backref(group, ix..end)))
+        } else {
+            Err(Error::ParseError(ix, ParseError::InvalidBackref))
+        }
+    }
+
+    // ix points to `\` in `\g`
+    fn parse_g_backref(&mut self, ix: usize) -> Result<(usize, Expr)> {
+        let invalid = Err(Error::ParseError(ix, ParseError::InvalidBackref));
+        let start = ix + 2;
+        let braces = self.re[start..].starts_with('{');
+        if braces && !self.re[start + 1..].starts_with(|c: char| c == '-' || c.is_ascii_digit()) {
+            return self.parse_backref(ix, start, "{", "}");
+        }
+        let num_start = if braces { start + 1 } else { start };
+        let relative = self.re[num_start..].starts_with('-');
+        let digits = if relative { num_start + 1 } else { num_start };
+        let (mut end, n) = match parse_decimal(self.re, digits) {
+            Some(parsed) => parsed,
+            None => return invalid,
+        };
+        if braces {
+            if !self.re[end..].starts_with('}') {
+                return invalid;
+            }
+            end += 1;
+        }
+        let group = if relative {
+            // \g{-1} is the last group that was opened before it
+            if n == 0 || n > self.curr_group {
+                return invalid;
+            }
+            self.curr_group + 1 - n
+        } else {
+            n
+        };
+        self.numbered_backref_expr(ix, end, group)
+    }
+
+    fn parse_backref(
+        &mut self,
+        start: usize,
+        ix: usize,
+        open: &str,
+        close: &str,
+    ) -> Result<(usize, Expr)> {
         if let Some((id, skip)) = parse_id(&self.re[ix..], open, close) {
             let group = if let Some(group) = self.named_groups.get(id) {
                 Some(*group)
-            } else if let Ok(group) = id.parse() {
-                Some(group)
             } else {
-                None
+                id.parse().ok()
             };
             if let Some(group) = group {
-                return Ok((ix + skip, Expr::Backref(group)));
+                return Ok((ix + skip, self.backref(group, start..ix + skip)));
             }
             // here the name is parsed but it is invalid
-            Err(Error::InvalidGroupNameBackref(id.to_string()))
+            Err(Error::ParseError(
+                start,
+                ParseError::InvalidGroupNameBackref(id.to_string()),
+            ))
         } else {
             // in this case the name can't be parsed
-            Err(Error::InvalidGroupName)
+            Err(Error::ParseError(start, ParseError::InvalidGroupName))
+        }
+    }
+
+    // ix points to a `{` that doesn't start a repetition or a `]` without `[`
+    fn check_sloppy_literal(&mut self, ix: usize) -> Result<()> {
+        let (kind, error) = if self.re.as_bytes()[ix] == b'{' {
+            (LintKind::UnescapedBrace, ParseError::InvalidRepeat)
+        } else {
+            (LintKind::UnescapedBracket, ParseError::UnmatchedBracket)
+        };
+        if self.strict {
+            return Err(Error::ParseError(ix, error));
         }
+        let lint = Lint::new(kind, ix..ix + 1);
+        self.lints
+            .push(lint.replace_with(&format!("\\{}", &self.re[ix..ix + 1])));
+        Ok(())
     }
 
     // ix points to \ character
     fn parse_escape(&mut self, ix: usize) -> Result<(usize, Expr)> {
         if ix + 1 == self.re.len() {
-            return Err(Error::TrailingBackslash);
+            return Err(Error::ParseError(ix, ParseError::TrailingBackslash));
         }
         let bytes = self.re.as_bytes();
         let b = bytes[ix + 1];
         let mut end = ix + 1 + codepoint_len(b);
         let mut size = 1;
-        if is_digit(b) {
-            if let Some((end, group)) = parse_decimal(self.re, ix + 1) {
-                // protect BitSet against unreasonably large value
-                if group < self.re.len() / 2 {
-                    self.numeric_backrefs = true;
-                    return Ok((end, Expr::Backref(group)));
+        if b == b'0' {
+            return self.parse_octal(ix + 1);
+        } else if is_digit(b) {
+            if let Some(end) = self.octal_like_backref(ix + 1) {
+                if self.strict {
+                    return Err(Error::ParseError(ix, ParseError::InvalidBackref));
                 }
+                let codepoint = u32::from_str_radix(&self.re[ix + 1..end], 8).unwrap();
+                let lint = Lint::new(LintKind::OctalEscape(codepoint), ix..end);
+                self.lints
+                    .push(lint.replace_with(&format!("\\x{{{:X}}}", codepoint)));
+                return self.char_literal(ix, end, codepoint);
+            }
+            if let Some((end, group)) = self.numbered_backref(ix + 1) {
+                return self.numbered_backref_expr(ix, end, group);
             }
-            return Err(Error::InvalidBackref);
+            return Err(Error::ParseError(ix, ParseError::InvalidBackref));
+        } else if b == b'g' && !matches!(bytes.get(end), Some(b'<') | Some(b'\'')) {
+            // Unambiguous backref: \g1, \g{10}, \g{-1} or \g{name}
+            return self.parse_g_backref(ix);
+        } else if b == b'k' && bytes.get(end) == Some(&b'\'') {
+            // Named backref using .NET/PCRE syntax: \k'name'
+            return self.parse_backref(ix, ix + 2, "'", "'");
         } else if b == b'k' {
             // Named backref: \k<name>
-            return self.parse_backref(ix + 2, "<", ">");
+            return self.parse_backref(ix, ix + 2, "<", ">");
         } else if b == b'A' || b == b'z' || b == b'b' || b == b'B' {
             size = 0;
         } else if (b | 32) == b'd'
@@ -334,6 +770,9 @@ impl<'a> Parser<'a> {
             || b == b'v'
         {
             // size = 1
+        } else if b == b'O' {
+            // Oniguruma's "true anychar", which ignores the `s` flag
+            return Ok((end, Expr::AnyByte));
         } else if b == b'e' {
             let inner = String::from(r"\x1B");
             return Ok((
@@ -350,6 +789,8 @@ impl<'a> Parser<'a> {
             } else {
                 "[^0-9A-Fa-f]"
             };
+            let lint = Lint::new(LintKind::FlavorDependentEscape(b as char), ix..end);
+            self.lints.push(lint.replace_with(s));
             let inner = String::from(s);
             return Ok((
                 end,
@@ -365,17 +806,22 @@ impl<'a> Parser<'a> {
             return self.parse_hex(end, 4);
         } else if b == b'U' {
             return self.parse_hex(end, 8);
+        } else if b == b'c' {
+            return self.parse_control(end);
+        } else if b == b'N' && bytes.get(end) == Some(&b'{') {
+            return self.parse_char_name(end + 1);
         } else if (b | 32) == b'p' {
             // allow whitespace?
             if end == self.re.len() {
-                return Err(Error::TrailingBackslash); // better name?
+                return Err(Error::ParseError(ix, ParseError::TrailingBackslash));
+                // better name?
             }
             let b = bytes[end];
             end += codepoint_len(b);
             if b == b'{' {
                 loop {
                     if end == self.re.len() {
-                        return Err(Error::UnclosedUnicodeName);
+                        return Err(Error::ParseError(ix, ParseError::UnclosedUnicodeName));
                     }
                     let b = bytes[end];
                     if b == b'}' {
@@ -386,9 +832,17 @@ impl<'a> Parser<'a> {
                 }
             }
         } else if b'a' <= (b | 32) && (b | 32) <= b'z' {
-            return Err(Error::InvalidEscape(format!("\\{}", &self.re[ix + 1..end])));
-        } else if 0x20 <= b && b <= 0x7f {
+            let error = flavor::unsupported(&self.re[ix..]).unwrap_or_else(|| {
+                ParseError::InvalidEscape(format!("\\{}", &self.re[ix + 1..end]))
+            });
+            return Err(Error::ParseError(ix, error));
+        } else if (0x20..=0x7f).contains(&b) {
             // printable ASCII (including space, see issue #29)
+            let c = b as char;
+            if !is_special(c) && c != ' ' {
+                let lint = Lint::new(LintKind::RedundantEscape(c), ix..end);
+                self.lints.push(lint.replace_with(&self.re[ix + 1..end]));
+            }
             return Ok((end, make_literal(&self.re[ix + 1..end])));
         }
         // what to do with characters outside printable ASCII?
@@ -405,9 +859,11 @@ impl<'a> Parser<'a> {
 
     // ix points after '\x', eg to 'A0' or '{12345}', or after `\u` or `\U`
     fn parse_hex(&self, ix: usize, digits: usize) -> Result<(usize, Expr)> {
+        let start = ix - 2;
+        let invalid = Err(Error::ParseError(start, ParseError::InvalidHex));
         if ix >= self.re.len() {
             // Incomplete escape sequence
-            return Err(Error::InvalidHex);
+            return invalid;
         }
         let bytes = self.re.as_bytes();
         let b = bytes[ix];
@@ -421,7 +877,7 @@ impl<'a> Parser<'a> {
             let mut endhex = starthex;
             loop {
                 if endhex == self.re.len() {
-                    return Err(Error::InvalidHex);
+                    return invalid;
                 }
                 let b = bytes[endhex];
                 if endhex > starthex && b == b'}' {
@@ -430,31 +886,128 @@ impl<'a> Parser<'a> {
                 if is_hex_digit(b) && endhex < starthex + 8 {
                     endhex += 1;
                 } else {
-                    return Err(Error::InvalidHex);
+                    return invalid;
                 }
             }
             (endhex + 1, &self.re[starthex..endhex])
         } else {
-            return Err(Error::InvalidHex);
+            return invalid;
         };
         let codepoint = u32::from_str_radix(s, 16).unwrap();
-        if let Some(c) = ::std::char::from_u32(codepoint) {
-            let mut inner = String::with_capacity(4);
-            inner.push(c);
-            Ok((
+        self.char_literal(start, end, codepoint)
+    }
+
+    // ix points to the first octal digit, eg to '0' in '\0' or to '4' in '[\44]'; up to three
+    // digits are used
+    /// Returns the end of an escape like `\12` that is read as an octal escape with the rule of
+    /// `RegexBuilder::numbered_backrefs`: with `Pcre`, one that has two or three octal digits and
+    /// refers to a group that doesn't exist at that point, with `Python`, one that starts with
+    /// three octal digits. `ix` points after the backslash.
+    fn octal_like_backref(&self, ix: usize) -> Option<usize> {
+        let (end, group) = parse_decimal(self.re, ix)?;
+        let digits = &self.re.as_bytes()[ix..end];
+        let is_octal = |digits: &[u8]| digits.iter().all(|b| *b < b'8');
+        match self.numbered_backrefs {
+            NumberedBackrefs::Pcre => {
+                let octal = (2..=3).contains(&digits.len()) && is_octal(digits);
+                if octal && group > self.curr_group {
+                    Some(end)
+                } else {
+                    None
+                }
+            }
+            NumberedBackrefs::Python if digits.len() >= 3 && is_octal(&digits[..3]) => Some(ix + 3),
+            NumberedBackrefs::Python | NumberedBackrefs::Java => None,
+        }
+    }
+
+    /// Returns the end and group of the numbered backref at `ix`, which points after the
+    /// backslash, using the rule of `RegexBuilder::numbered_backrefs`. Octal escapes with the
+    /// `Pcre` rule are handled by `octal_like_backref`.
+    fn numbered_backref(&self, ix: usize) -> Option<(usize, usize)> {
+        let (end, group) = parse_decimal(self.re, ix)?;
+        match self.numbered_backrefs {
+            NumberedBackrefs::Pcre => Some((end, group)),
+            NumberedBackrefs::Python => {
+                let end = end.min(ix + 2);
+                Some((end, usize::from_str(&self.re[ix..end]).ok()?))
+            }
+            NumberedBackrefs::Java => {
+                let bytes = self.re.as_bytes();
+                let mut end = ix + 1;
+                let mut group = usize::from(bytes[ix] - b'0');
+                while end < self.re.len() && is_digit(bytes[end]) {
+                    let next = group
+                        .checked_mul(10)
+                        .and_then(|g| g.checked_add(usize::from(bytes[end] - b'0')));
+                    match next {
+                        Some(next) if next <= self.curr_group => group = next,
+                        _ => break,
+                    }
+                    end += 1;
+                }
+                Some((end, group))
+            }
+        }
+    }
+
+    fn parse_octal(&self, ix: usize) -> Result<(usize, Expr)> {
+        let bytes = self.re.as_bytes();
+        let mut end = ix + 1;
+        while end < self.re.len() && end < ix + 3 && (b'0'..=b'7').contains(&bytes[end]) {
+            end += 1;
+        }
+        let codepoint = u32::from_str_radix(&self.re[ix..end], 8).unwrap();
+        self.char_literal(ix - 1, end, codepoint)
+    }
+
+    // ix points after '\c', eg to 'M' in '\cM'
+    fn parse_control(&self, ix: usize) -> Result<(usize, Expr)> {
+        match self.re.as_bytes().get(ix) {
+            Some(&b) if b.is_ascii_alphabetic() => {
+                self.char_literal(ix - 2, ix + 1, u32::from(b & 0x1f))
+            }
+            _ => {
+                let end = ix + self.re[ix..].chars().next().map_or(0, char::len_utf8);
+                let escape = format!("\\c{}", &self.re[ix..end]);
+                Err(Error::ParseError(ix - 2, ParseError::InvalidEscape(escape)))
+            }
+        }
+    }
+
+    // ix points after '\N{', eg to 'L' in '\N{LATIN SMALL LETTER A}'
+    fn parse_char_name(&self, ix: usize) -> Result<(usize, Expr)> {
+        let start = ix - 3;
+        let close = match self.re[ix..].find('}') {
+            Some(i) => ix + i,
+            None => return Err(Error::ParseError(start, ParseError::UnclosedUnicodeName)),
+        };
+        let name = &self.re[ix..close];
+        match lookup_char_name(name) {
+            Some(c) => self.char_literal(start, close + 1, u32::from(c)),
+            None => {
+                let escape = format!("\\N{{{}}}", name);
+                Err(Error::ParseError(start, ParseError::InvalidEscape(escape)))
+            }
+        }
+    }
+
+    fn char_literal(&self, start: usize, end: usize, codepoint: u32) -> Result<(usize, Expr)> {
+        match ::std::char::from_u32(codepoint) {
+            Some(c) => Ok((
                 end,
                 Expr::Literal {
-                    val: inner,
+                    val: c.to_string(),
                     casei: self.flag(FLAG_CASEI),
                 },
-            ))
-        } else {
-            Err(Error::InvalidCodepointValue)
+            )),
+            None => Err(Error::ParseError(start, ParseError::InvalidCodepointValue)),
         }
     }
 
     fn parse_class(&mut self, ix: usize) -> Result<(usize, Expr)> {
         let bytes = self.re.as_bytes();
+        let start = ix;
         let mut ix = ix + 1; // skip opening '['
         let mut class = String::new();
         let mut nest = 1;
@@ -474,25 +1027,30 @@ impl<'a> Parser<'a> {
 
         loop {
             if ix == self.re.len() {
-                return Err(Error::InvalidClass);
+                return Err(Error::ParseError(start, ParseError::InvalidClass));
             }
             let end = match bytes[ix] {
                 b'\\' => {
                     if ix + 1 == self.re.len() {
-                        return Err(Error::InvalidClass);
+                        return Err(Error::ParseError(start, ParseError::InvalidClass));
                     }
 
+                    // There are no backrefs in classes, so all digits are octal escapes.
                     // We support more escapes than regex, so parse it ourselves before delegating.
-                    let (end, expr) = self.parse_escape(ix)?;
+                    let (end, expr) = if (b'0'..=b'7').contains(&bytes[ix + 1]) {
+                        self.parse_octal(ix + 1)?
+                    } else {
+                        self.parse_escape(ix)?
+                    };
                     match expr {
                         Expr::Literal { val, .. } => {
-                            class.push_str(&escape(&val));
+                            class.push_str(&escape_class_literal(&val));
                         }
                         Expr::Delegate { inner, .. } => {
                             class.push_str(&inner);
                         }
                         _ => {
-                            return Err(Error::InvalidClass);
+                            return Err(Error::ParseError(ix, ParseError::InvalidClass));
                         }
                     }
                     end
@@ -533,9 +1091,13 @@ impl<'a> Parser<'a> {
     fn parse_group(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let depth = depth + 1;
         if depth >= MAX_RECURSION {
-            return Err(Error::RecursionExceeded);
+            return Err(Error::ParseError(ix, ParseError::RecursionExceeded));
         }
+        let open = ix;
+        let prev_group = self.curr_group;
+        let oldflags = self.flags;
         let ix = self.optional_whitespace(ix + 1)?;
+        let mut script_run = None;
         let (la, skip) = if self.re[ix..].starts_with("?=") {
             (Some(LookAhead), 2)
         } else if self.re[ix..].starts_with("?!") {
@@ -546,49 +1108,88 @@ impl<'a> Parser<'a> {
             (Some(LookBehindNeg), 3)
         } else if self.re[ix..].starts_with("?<") {
             // Named capture group using Oniguruma syntax: (?<name>...)
-            self.curr_group += 1;
-            if let Some((id, skip)) = parse_id(&self.re[ix + 1..], "<", ">") {
-                self.named_groups.insert(id.to_string(), self.curr_group);
-                (None, skip + 1)
-            } else {
-                return Err(Error::InvalidGroupName);
-            }
+            (None, self.parse_group_name(ix, ix + 1, "<", ">")?)
+        } else if self.re[ix..].starts_with("?'") {
+            // Named capture group using .NET/PCRE syntax: (?'name'...)
+            (None, self.parse_group_name(ix, ix + 1, "'", "'")?)
         } else if self.re[ix..].starts_with("?P<") {
             // Named capture group using Python syntax: (?P<name>...)
-            self.curr_group += 1; // this is a capture group
-            if let Some((id, skip)) = parse_id(&self.re[ix + 2..], "<", ">") {
-                self.named_groups.insert(id.to_string(), self.curr_group);
-                (None, skip + 2)
-            } else {
-                return Err(Error::InvalidGroupName);
-            }
+            (None, self.parse_group_name(ix, ix + 2, "<", ">")?)
         } else if self.re[ix..].starts_with("?P=") {
             // Backref using Python syntax: (?P=name)
-            return self.parse_backref(ix + 3, "", ")");
+            return self.parse_backref(open, ix + 3, "", ")");
         } else if self.re[ix..].starts_with("?>") {
             (None, 2)
+        } else if let Some((atomic, skip)) = parse_script_run_verb(&self.re[ix..]) {
+            script_run = Some(atomic);
+            (None, skip)
+        } else if self.fragments.is_some() && self.re[ix..].starts_with("?&") {
+            return self.parse_fragment_call(open, ix + 2, depth);
+        } else if let Some(error) = flavor::unsupported(&self.re[open..]) {
+            return Err(Error::ParseError(open, error));
         } else if self.re[ix..].starts_with('?') {
             return self.parse_flags(ix, depth);
         } else {
             self.curr_group += 1; // this is a capture group
             (None, 0)
         };
+        let group = if self.curr_group > prev_group {
+            self.group_spans.push(open..open);
+            self.update_casei_group(self.curr_group);
+            Some(self.curr_group)
+        } else {
+            None
+        };
         let ix = ix + skip;
         let (ix, child) = self.parse_re(ix, depth)?;
         let ix = self.optional_whitespace(ix)?;
         if ix == self.re.len() {
-            return Err(Error::UnclosedOpenParen);
+            return Err(Error::ParseError(open, ParseError::UnclosedOpenParen));
         } else if self.re.as_bytes()[ix] != b')' {
-            return Err(Error::ParseError);
+            return Err(self.expected_close_paren(ix));
         };
-        let result = match (la, skip) {
-            (Some(la), _) => Expr::LookAround(Box::new(child), la),
-            (None, 2) => Expr::AtomicGroup(Box::new(child)),
+        if let Some(group) = group {
+            self.group_spans[group - 1].end = ix + 1;
+            // Flags set inside the group, e.g. `((?i)a)`, apply to backrefs to it too
+            self.update_casei_group(group);
+        }
+        // Flags set inside the group don't apply after it
+        self.flags = oldflags;
+        let result = match (la, script_run, skip) {
+            (Some(la), _, _) => Expr::LookAround(Box::new(child), la),
+            // `(*atomic_script_run:...)` is the same as `(?>(*script_run:...))`
+            (None, Some(true), _) => Expr::AtomicGroup(Box::new(Expr::ScriptRun(Box::new(child)))),
+            (None, Some(false), _) => Expr::ScriptRun(Box::new(child)),
+            (None, None, 2) => Expr::AtomicGroup(Box::new(child)),
             _ => Expr::Group(Box::new(child)),
         };
         Ok((ix + 1, result))
     }
 
+    /// Parses the name of a named group and starts the group, with the same rules for all
+    /// syntaxes: the name must not start with a digit (so that it can't be confused with a group
+    /// number) and must not be used by another group. `start` points to `?` and `ix` to the
+    /// opening delimiter. Returns the length of the group's start after `start`.
+    fn parse_group_name(
+        &mut self,
+        start: usize,
+        ix: usize,
+        open: &str,
+        close: &str,
+    ) -> Result<usize> {
+        let (id, skip) = match parse_id(&self.re[ix..], open, close) {
+            Some((id, skip)) if !id.starts_with(|c: char| c.is_ascii_digit()) => (id, skip),
+            _ => return Err(Error::ParseError(start, ParseError::InvalidGroupName)),
+        };
+        if self.named_groups.contains_key(id) {
+            let error = ParseError::DuplicateGroupName(id.to_string());
+            return Err(Error::ParseError(start, error));
+        }
+        self.curr_group += 1; // this is a capture group
+        self.named_groups.insert(id.to_string(), self.curr_group);
+        Ok(ix - start + skip)
+    }
+
     // ix points to `?` in `(?`
     fn parse_flags(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let start = ix + 1;
@@ -596,16 +1197,18 @@ impl<'a> Parser<'a> {
         fn unknown_flag(re: &str, start: usize, end: usize) -> Error {
             let after_end = end + codepoint_len(re.as_bytes()[end]);
             let s = format!("(?{}", &re[start..after_end]);
-            Error::UnknownFlag(s)
+            Error::ParseError(end, ParseError::UnknownFlag(s))
         }
 
+        let open = start - 2;
+
         let mut ix = start;
         let mut neg = false;
         let oldflags = self.flags;
         loop {
             ix = self.optional_whitespace(ix)?;
             if ix == self.re.len() {
-                return Err(Error::UnclosedOpenParen);
+                return Err(Error::ParseError(open, ParseError::UnclosedOpenParen));
             }
             let b = self.re.as_bytes()[ix];
             match b {
@@ -616,7 +1219,7 @@ impl<'a> Parser<'a> {
                 b'x' => self.update_flag(FLAG_IGNORE_SPACE, neg),
                 b'u' => {
                     if neg {
-                        return Err(Error::NonUnicodeUnsupported);
+                        return Err(Error::ParseError(ix, ParseError::NonUnicodeUnsupported));
                     }
                 }
                 b'-' => {
@@ -638,9 +1241,9 @@ impl<'a> Parser<'a> {
                     ix += 1;
                     let (ix, child) = self.parse_re(ix, depth)?;
                     if ix == self.re.len() {
-                        return Err(Error::UnclosedOpenParen);
+                        return Err(Error::ParseError(open, ParseError::UnclosedOpenParen));
                     } else if self.re.as_bytes()[ix] != b')' {
-                        return Err(Error::ParseError);
+                        return Err(self.expected_close_paren(ix));
                     };
                     self.flags = oldflags;
                     return Ok((ix + 1, child));
@@ -651,6 +1254,20 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn expected_close_paren(&self, ix: usize) -> Error {
+        let found = &self.re[ix..ix + codepoint_len(self.re.as_bytes()[ix])];
+        let message = format!("expected `)`, found `{}`", found);
+        Error::ParseError(ix, ParseError::GeneralParseError(message))
+    }
+
+    fn update_casei_group(&mut self, group: usize) {
+        if self.flag(FLAG_CASEI) {
+            self.casei_groups.insert(group);
+        } else {
+            self.casei_groups.remove(group);
+        }
+    }
+
     fn flag(&self, flag: u32) -> bool {
         (self.flags & flag) != 0
     }
@@ -678,10 +1295,11 @@ impl<'a> Parser<'a> {
                 }
                 b' ' | b'\r' | b'\n' | b'\t' if self.flag(FLAG_IGNORE_SPACE) => ix += 1,
                 b'(' if bytes[ix..].starts_with(b"(?#") => {
+                    let open = ix;
                     ix += 3;
                     loop {
                         if ix >= self.re.len() {
-                            return Err(Error::UnclosedOpenParen);
+                            return Err(Error::ParseError(open, ParseError::UnclosedOpenParen));
                         }
                         match bytes[ix] {
                             b')' => {
@@ -699,6 +1317,29 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// The count of a repeat `{lo,hi}` for `RegexBuilder::max_repeat`: the upper bound, or the lower
+/// one if there's no upper bound.
+pub(crate) fn repeat_count(lo: usize, hi: usize) -> usize {
+    if hi == usize::MAX {
+        lo
+    } else {
+        hi
+    }
+}
+
+/// Escapes the characters of `text` that are special in a character class of the regex crate, like
+/// `regex::escape` (which isn't available when only the parser is built).
+fn escape_class_literal(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if r"\.+*?()|[]{}^$#&-~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
 // return (ix, value)
 pub(crate) fn parse_decimal(s: &str, ix: usize) -> Option<(usize, usize)> {
     let mut end = ix;
@@ -734,18 +1375,218 @@ pub(crate) fn parse_id<'a>(s: &'a str, open: &'_ str, close: &'_ str) -> Option<
     }
 }
 
+/// Parses the start of a script run group after the `(`, e.g. `*sr:`. Returns whether the group
+/// is atomic and the length of the start.
+fn parse_script_run_verb(s: &str) -> Option<(bool, usize)> {
+    const VERBS: [(&str, bool); 4] = [
+        ("*script_run:", false),
+        ("*sr:", false),
+        ("*atomic_script_run:", true),
+        ("*asr:", true),
+    ];
+    VERBS
+        .iter()
+        .find(|(verb, _)| s.starts_with(verb))
+        .map(|&(verb, atomic)| (atomic, verb.len()))
+}
+
+/// Whether an escape of the character is needed for matching it literally in some context, e.g.
+/// `-` in a class. Escapes of other characters are reported by `lint`.
+fn is_special(c: char) -> bool {
+    "\\.+*?()|[]{}^$#&-~".contains(c)
+}
+
+/// What is known about the position in the text while going through the children of a
+/// concatenation, for finding children that can never match because of the ones before them.
+#[derive(Default)]
+struct Position {
+    /// Minimum number of characters before the position
+    min: usize,
+    /// Maximum number of characters before the position, if the concatenation is anchored at the
+    /// start of the text
+    max: Option<usize>,
+    /// Whether the position is at the end of the text
+    at_end: bool,
+    /// Whether a child that can never match was already found, so that only the first one is
+    /// reported
+    never: bool,
+}
+
+impl Position {
+    /// Moves the position after `child`, which follows `prev`. Returns the kind of lint for
+    /// `child` if it can never match.
+    fn advance(&mut self, prev: Option<&Expr>, child: &Expr) -> Option<LintKind> {
+        let (min, max) = width_bounds(child);
+        let mut kind = None;
+        if is_start_text(child) {
+            if self.min > 0 {
+                kind = Some(LintKind::NeverMatches);
+            }
+            self.max = Some(0);
+        } else if let Expr::LookAround(inner, LookBehind) = child {
+            let (inner_min, _) = width_bounds(inner);
+            if self.max.map_or(false, |max| inner_min > max) {
+                kind = Some(if prev.map_or(false, is_start_text) {
+                    LintKind::LookBehindAtStart
+                } else {
+                    LintKind::NeverMatches
+                });
+            }
+            self.min = self.min.max(inner_min);
+        } else if self.at_end && min > 0 {
+            kind = Some(LintKind::NeverMatches);
+        }
+        if is_end_text(child) {
+            self.at_end = true;
+        }
+        self.min = self.min.saturating_add(min);
+        self.max = match (self.max, max) {
+            (Some(before), Some(max)) => before.checked_add(max),
+            _ => None,
+        };
+        // The children after one that can never match don't matter
+        if kind == Some(LintKind::NeverMatches) && std::mem::replace(&mut self.never, true) {
+            kind = None;
+        }
+        kind
+    }
+}
+
+/// The minimum and maximum number of characters that `expr` can match, `None` if there is no
+/// maximum. Only returns bounds that are certain.
+fn width_bounds(expr: &Expr) -> (usize, Option<usize>) {
+    match expr {
+        Expr::Literal { val, .. } => {
+            let len = val.chars().count();
+            (len, Some(len))
+        }
+        Expr::Any { .. } => (1, Some(1)),
+        // Several bytes of a byte string can be in the same character
+        Expr::AnyByte => (0, Some(1)),
+        Expr::Delegate { size, .. } => (*size, Some(*size)),
+        Expr::Empty
+        | Expr::StartText
+        | Expr::EndText
+        | Expr::StartLine
+        | Expr::EndLine
+        | Expr::LookAround(..) => (0, Some(0)),
+        Expr::Concat(children) => children.iter().map(width_bounds).fold(
+            (0, Some(0)),
+            |(min, max), (child_min, child_max)| {
+                let max = match (max, child_max) {
+                    (Some(max), Some(child_max)) => max.checked_add(child_max),
+                    _ => None,
+                };
+                (min.saturating_add(child_min), max)
+            },
+        ),
+        Expr::Alt(children) => {
+            let mut bounds = children.iter().map(width_bounds);
+            let first = bounds.next().unwrap_or((0, Some(0)));
+            bounds.fold(first, |(min, max), (child_min, child_max)| {
+                let max = match (max, child_max) {
+                    (Some(max), Some(child_max)) => Some(max.max(child_max)),
+                    _ => None,
+                };
+                (min.min(child_min), max)
+            })
+        }
+        Expr::Group(child) | Expr::AtomicGroup(child) | Expr::ScriptRun(child) => {
+            width_bounds(child)
+        }
+        Expr::Repeat { child, lo, hi, .. } => {
+            let (min, max) = width_bounds(child);
+            let max = match max {
+                Some(0) => Some(0),
+                Some(max) if *hi != usize::MAX => max.checked_mul(*hi),
+                _ => None,
+            };
+            (min.saturating_mul(*lo), max)
+        }
+        Expr::Backref { .. } | Expr::NamedBackref(_) => (0, None),
+    }
+}
+
+fn is_end_text(expr: &Expr) -> bool {
+    match expr {
+        Expr::EndText => true,
+        Expr::Delegate { inner, .. } => inner == "\\z",
+        _ => false,
+    }
+}
+
+fn is_start_text(expr: &Expr) -> bool {
+    match expr {
+        Expr::StartText => true,
+        Expr::Delegate { inner, .. } => inner == "\\A",
+        _ => false,
+    }
+}
+
+/// Whether `expr` is a group (or nested groups) that can only match the empty string.
+fn is_empty_group(expr: &Expr) -> bool {
+    fn is_empty(expr: &Expr) -> bool {
+        match expr {
+            Expr::Empty => true,
+            Expr::Concat(children) => children.iter().all(is_empty),
+            Expr::Group(child) | Expr::AtomicGroup(child) | Expr::ScriptRun(child) => {
+                is_empty(child)
+            }
+            _ => false,
+        }
+    }
+    match expr {
+        Expr::Group(child) | Expr::AtomicGroup(child) | Expr::ScriptRun(child) => is_empty(child),
+        _ => false,
+    }
+}
+
+fn has_capture_group(expr: &Expr) -> bool {
+    match expr {
+        Expr::Group(_) => true,
+        Expr::Concat(children) | Expr::Alt(children) => children.iter().any(has_capture_group),
+        Expr::LookAround(child, _)
+        | Expr::AtomicGroup(child)
+        | Expr::ScriptRun(child)
+        | Expr::Repeat { child, .. } => has_capture_group(child),
+        _ => false,
+    }
+}
+
+/// Whether `expr` contains a repeat without an upper bound that backtracking can go into from
+/// outside, i.e. not in an atomic group or look-around.
+fn has_unbounded_repeat(expr: &Expr) -> bool {
+    match expr {
+        Expr::Repeat { hi, child, .. } => *hi == usize::MAX || has_unbounded_repeat(child),
+        Expr::Concat(children) | Expr::Alt(children) => children.iter().any(has_unbounded_repeat),
+        Expr::Group(child) | Expr::ScriptRun(child) => has_unbounded_repeat(child),
+        _ => false,
+    }
+}
+
 fn is_id_char(c: char) -> bool {
     c.is_alphanumeric() || c == '_'
 }
 
 fn is_digit(b: u8) -> bool {
-    b'0' <= b && b <= b'9'
+    b.is_ascii_digit()
 }
 
 fn is_hex_digit(b: u8) -> bool {
     is_digit(b) || (b'a' <= (b | 32) && (b | 32) <= b'f')
 }
 
+#[cfg(feature = "unicode-names")]
+fn lookup_char_name(name: &str) -> Option<char> {
+    unicode_names2::character(name)
+}
+
+// Without the name table, no name is known.
+#[cfg(not(feature = "unicode-names"))]
+fn lookup_char_name(_name: &str) -> Option<char> {
+    None
+}
+
 pub(crate) fn make_literal(s: &str) -> Expr {
     Expr::Literal {
         val: String::from(s),
@@ -755,15 +1596,25 @@ pub(crate) fn make_literal(s: &str) -> Expr {
 
 #[cfg(test)]
 mod tests {
-    use crate::parse::{make_literal, parse_id};
+    use crate::parse::{escape_class_literal, make_literal, parse_id, Parser};
+    use crate::Error;
     use crate::Expr;
+    use crate::LargeRepeatPolicy;
     use crate::LookAround::*;
+    use crate::NumberedBackrefs;
+    use crate::ParseError;
+    use crate::RegexBuilder;
+    use crate::Result;
     use std::usize;
 
     fn p(s: &str) -> Expr {
         Expr::parse_tree(s).unwrap().expr
     }
 
+    fn p_options(builder: &mut RegexBuilder) -> Result<Expr> {
+        Parser::parse_options(&builder.0).map(|tree| tree.expr)
+    }
+
     #[cfg_attr(feature = "track_caller", track_caller)]
     fn fail(s: &str) {
         assert!(Expr::parse_tree(s).is_err());
@@ -771,9 +1622,17 @@ mod tests {
 
     #[cfg_attr(feature = "track_caller", track_caller)]
     fn assert_error(re: &str, expected_error: &str) {
-        let result = Expr::parse_tree(re);
-        assert!(result.is_err());
-        assert_eq!(&format!("{}", result.err().unwrap()), expected_error);
+        match Expr::parse_tree(re) {
+            Err(Error::ParseError(_, error)) => assert_eq!(&format!("{}", error), expected_error),
+            result => panic!("Expected a parse error, got {:?}", result),
+        }
+    }
+
+    fn error_position(re: &str) -> usize {
+        match Expr::parse_tree(re) {
+            Err(Error::ParseError(position, _)) => position,
+            result => panic!("Expected a parse error, got {:?}", result),
+        }
     }
 
     #[test]
@@ -838,6 +1697,14 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "compiler")]
+    #[test]
+    fn escape_class_literal_like_regex() {
+        for text in &["a-z", r"\.+*?()|[]{}^$#&-~", "é&&ü~~"] {
+            assert_eq!(escape_class_literal(text), regex::escape(text));
+        }
+    }
+
     #[test]
     fn literal_escape() {
         assert_eq!(p("\\'"), make_literal("'"));
@@ -873,6 +1740,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn any_escape() {
+        assert_eq!(p("\\O"), Expr::AnyByte);
+        assert_eq!(p("(?-s:\\O)"), Expr::AnyByte);
+    }
+
     #[test]
     fn invalid_escape() {
         assert_error("\\", "Backslash without following character");
@@ -889,6 +1762,72 @@ mod tests {
         assert_error("\\u{}", "Invalid hex escape");
         assert_error("\\U1234567", "Invalid hex escape");
         assert_error("\\U{}", "Invalid hex escape");
+        assert_error("\\c", "Invalid escape: \\c");
+        assert_error("\\c1", "Invalid escape: \\c1");
+        assert_error("\\cé", "Invalid escape: \\cé");
+    }
+
+    #[test]
+    fn octal_escape() {
+        assert_eq!(p("\\0"), make_literal("\0"));
+        assert_eq!(p("\\07"), make_literal("\x07"));
+        assert_eq!(p("\\012"), make_literal("\n"));
+        assert_eq!(
+            p("\\0123"),
+            Expr::Concat(vec![make_literal("\n"), make_literal("3")])
+        );
+        assert_eq!(
+            p("\\08"),
+            Expr::Concat(vec![make_literal("\0"), make_literal("8")])
+        );
+        assert_eq!(
+            p("[\\0-\\037]"),
+            Expr::Delegate {
+                inner: String::from("[\0-\x1f]"),
+                size: 1,
+                casei: false
+            }
+        );
+    }
+
+    #[test]
+    fn control_escape() {
+        assert_eq!(p("\\cM"), make_literal("\r"));
+        assert_eq!(p("\\cj"), make_literal("\n"));
+        assert_eq!(
+            p("[\\cA-\\cZ]"),
+            Expr::Delegate {
+                inner: String::from("[\x01-\x1a]"),
+                size: 1,
+                casei: false
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-names")]
+    fn char_name_escape() {
+        assert_eq!(p("\\N{LATIN SMALL LETTER A}"), make_literal("a"));
+        assert_eq!(p("\\N{SNOWMAN}"), make_literal("\u{2603}"));
+        assert_eq!(
+            p("[\\N{DIGIT ZERO}-\\N{DIGIT NINE}]"),
+            Expr::Delegate {
+                inner: String::from("[0-9]"),
+                size: 1,
+                casei: false
+            }
+        );
+        assert_error(
+            "\\N{NO SUCH CHARACTER}",
+            "Invalid escape: \\N{NO SUCH CHARACTER}",
+        );
+        assert_error("\\N{SNOWMAN", "Unicode escape not closed");
+    }
+
+    #[test]
+    #[cfg(not(feature = "unicode-names"))]
+    fn char_name_escape_unsupported() {
+        assert_error("\\N{SNOWMAN}", "Invalid escape: \\N{SNOWMAN}");
     }
 
     #[test]
@@ -1092,28 +2031,249 @@ mod tests {
         );
     }
 
+    #[test]
+    fn prune_groups() {
+        let mut tree = Expr::parse_tree(r"(a)(b)*(c)\3").unwrap();
+        let map = tree.prune_groups(&[2].iter().cloned().collect());
+        assert_eq!(map, vec![Some(0), None, Some(1), Some(2)]);
+        assert_eq!(tree.backrefs, [2].iter().cloned().collect());
+        assert_eq!(
+            tree.expr,
+            Expr::Concat(vec![
+                make_literal("a"),
+                Expr::Repeat {
+                    child: Box::new(Expr::Group(Box::new(make_literal("b")))),
+                    lo: 0,
+                    hi: usize::MAX,
+                    greedy: true,
+                },
+                Expr::Group(Box::new(make_literal("c"))),
+                Expr::Backref {
+                    group: 2,
+                    casei: false,
+                },
+            ])
+        );
+    }
+
     #[test]
     fn backref() {
         assert_eq!(
             p("(.)\\1"),
             Expr::Concat(vec![
                 Expr::Group(Box::new(Expr::Any { newline: false })),
-                Expr::Backref(1),
+                Expr::Backref {
+                    group: 1,
+                    casei: false,
+                },
             ])
         );
     }
 
+    #[test]
+    fn octal_like_backref() {
+        assert_eq!(p("\\12"), make_literal("\n"));
+        assert_eq!(p("(a)\\101"), p("(a)A"));
+        let tree = Expr::parse_tree("(a)(a)(a)(a)(a)(a)(a)(a)(a)(a)\\10").unwrap();
+        assert!(tree.backrefs.contains(10));
+    }
+
+    #[test]
+    fn g_backref() {
+        let backref = |group| Expr::Backref {
+            group,
+            casei: false,
+        };
+        let group = || Expr::Group(Box::new(make_literal("a")));
+        assert_eq!(p("(a)\\g1"), Expr::Concat(vec![group(), backref(1)]));
+        assert_eq!(p("(a)\\g{1}"), p("(a)\\g1"));
+        assert_eq!(p("(a)(a)\\g{-1}"), p("(a)(a)\\2"));
+        assert_eq!(p("(a)(a)\\g-2"), p("(a)(a)\\1"));
+        assert_eq!(p("(?<x>a)\\g{x}"), p("(?<x>a)\\k<x>"));
+        let tree = Expr::parse_tree(&format!("{}\\g{{10}}", "(a)".repeat(10))).unwrap();
+        assert!(tree.backrefs.contains(10));
+        assert!(matches!(
+            Expr::parse_tree("(a)\\g{-2}"),
+            Err(Error::ParseError(3, ParseError::InvalidBackref))
+        ));
+        assert!(matches!(
+            Expr::parse_tree("(a)\\g{1"),
+            Err(Error::ParseError(3, ParseError::InvalidBackref))
+        ));
+        assert!(matches!(
+            Expr::parse_tree("\\g"),
+            Err(Error::ParseError(0, ParseError::InvalidBackref))
+        ));
+    }
+
+    #[test]
+    fn numbered_backrefs() {
+        let parse = |re: &str, rule| {
+            let mut builder = RegexBuilder::new(re);
+            builder.numbered_backrefs(rule);
+            Parser::parse_options(&builder.0)
+        };
+        let ten_groups = "(a)(a)(a)(a)(a)(a)(a)(a)(a)(a)";
+        let group = || Expr::Group(Box::new(make_literal("a")));
+        let backref = |group| Expr::Backref {
+            group,
+            casei: false,
+        };
+
+        let tree = parse("(a)\\10", NumberedBackrefs::Pcre).unwrap();
+        assert_eq!(tree.expr, p("(a)\\x{8}"));
+
+        let tree = parse(&format!("{}\\108", ten_groups), NumberedBackrefs::Python).unwrap();
+        assert!(tree.backrefs.contains(10));
+        let tree = parse(&format!("{}\\101", ten_groups), NumberedBackrefs::Python).unwrap();
+        assert_eq!(tree.expr, p(&format!("{}A", ten_groups)));
+        assert!(matches!(
+            parse("(a)\\10", NumberedBackrefs::Python),
+            Err(Error::ParseError(3, ParseError::InvalidBackref))
+        ));
+
+        let tree = parse("(a)\\10", NumberedBackrefs::Java).unwrap();
+        assert_eq!(
+            tree.expr,
+            Expr::Concat(vec![group(), backref(1), make_literal("0")])
+        );
+        let tree = parse(&format!("{}\\10", ten_groups), NumberedBackrefs::Java).unwrap();
+        assert!(tree.backrefs.contains(10));
+        let tree = parse("\\2(a)(b)", NumberedBackrefs::Java).unwrap();
+        assert!(tree.backrefs.contains(2));
+    }
+
+    #[test]
+    fn strict() {
+        let strict = |re| p_options(RegexBuilder::new(re).strict(true));
+        assert!(matches!(
+            strict("a{b}"),
+            Err(Error::ParseError(1, ParseError::InvalidRepeat))
+        ));
+        assert!(matches!(
+            strict("{2}"),
+            Err(Error::ParseError(0, ParseError::InvalidRepeat))
+        ));
+        assert!(matches!(
+            strict("a]"),
+            Err(Error::ParseError(1, ParseError::UnmatchedBracket))
+        ));
+        assert!(matches!(
+            strict("(a)\\12"),
+            Err(Error::ParseError(3, ParseError::InvalidBackref))
+        ));
+        assert_eq!(strict("a{2}[]{]\\{\\]}").unwrap(), p("a{2}[]{]\\{\\]}"));
+        assert_eq!(strict("\\012").unwrap(), make_literal("\n"));
+    }
+
+    #[test]
+    fn max_repeat() {
+        let limited = |re| p_options(RegexBuilder::new(re).max_repeat(100));
+        assert!(matches!(
+            limited("a{101}"),
+            Err(Error::ParseError(
+                1,
+                ParseError::RepeatTooLarge {
+                    count: 101,
+                    max: 100
+                }
+            ))
+        ));
+        assert!(matches!(
+            limited("(?:ab){2,1000}"),
+            Err(Error::ParseError(
+                6,
+                ParseError::RepeatTooLarge {
+                    count: 1000,
+                    max: 100
+                }
+            ))
+        ));
+        assert!(matches!(
+            limited("a{200,}"),
+            Err(Error::ParseError(
+                1,
+                ParseError::RepeatTooLarge {
+                    count: 200,
+                    max: 100
+                }
+            ))
+        ));
+        assert_eq!(limited("a{100}b*c{5,}").unwrap(), p("a{100}b*c{5,}"));
+        // Not a repeat
+        assert_eq!(limited("a{1000").unwrap(), p("a{1000"));
+        // Counted instead
+        assert_eq!(
+            p_options(
+                RegexBuilder::new("a{101}")
+                    .max_repeat(100)
+                    .large_repeat_policy(LargeRepeatPolicy::Count)
+            )
+            .unwrap(),
+            p("a{101}")
+        );
+    }
+
     #[test]
     fn named_backref() {
         assert_eq!(
             p("(?<i>.)\\k<i>"),
             Expr::Concat(vec![
                 Expr::Group(Box::new(Expr::Any { newline: false })),
-                Expr::Backref(1),
+                Expr::Backref {
+                    group: 1,
+                    casei: false,
+                },
             ])
         );
     }
 
+    #[test]
+    fn named_group_styles() {
+        let expected = p("(?<i>.)\\k<i>");
+        for re in &[
+            "(?'i'.)\\k<i>",
+            "(?P<i>.)\\k<i>",
+            "(?<i>.)\\k'i'",
+            "(?<i>.)(?P=i)",
+            "(?'i'.)\\g{i}",
+        ] {
+            assert_eq!(p(re), expected, "{}", re);
+        }
+        let tree = Expr::parse_tree("(?<a>.)(?'b'.)(?P<c>.)").unwrap();
+        assert_eq!(tree.named_groups.len(), 3);
+        assert_eq!(tree.named_groups["c"], 3);
+    }
+
+    #[test]
+    fn named_group_validation() {
+        for re in &[
+            "(?<1a>.)",
+            "(?'1a'.)",
+            "(?P<1a>.)",
+            "(?<a-b>.)",
+            "(?'a>.)",
+            "(?P<>.)",
+        ] {
+            assert!(
+                matches!(
+                    Expr::parse_tree(re),
+                    Err(Error::ParseError(1, ParseError::InvalidGroupName))
+                ),
+                "{}",
+                re
+            );
+        }
+        for re in &["(?<a>.)(?<a>.)", "(?<a>.)(?'a'.)", "(?<a>.)(?P<a>.)"] {
+            match Expr::parse_tree(re) {
+                Err(Error::ParseError(8, ParseError::DuplicateGroupName(name))) => {
+                    assert_eq!(name, "a")
+                }
+                result => panic!("Expected DuplicateGroupName for {}, got {:?}", re, result),
+            }
+        }
+    }
+
     #[test]
     fn lookaround() {
         assert_eq!(
@@ -1179,6 +2339,16 @@ mod tests {
         assert_eq!(p("a*?"), p("(?U:a*)"));
     }
 
+    #[test]
+    fn parse_swap_greed() {
+        let swapped = |re| p_options(RegexBuilder::new(re).swap_greed(true)).unwrap();
+        assert_eq!(swapped("a*"), p("a*?"));
+        assert_eq!(swapped("a+?"), p("a+"));
+        assert_eq!(swapped("(a{2,}b??)"), p("(a{2,}?b?)"));
+        assert_eq!(swapped("(?-U)a*"), p("(?-U)a*"));
+        assert_eq!(swapped("(?-U:a*)b*"), p("(?-U:a*)b*?"));
+    }
+
     #[test]
     fn invalid_flags() {
         assert!(Expr::parse_tree("(?").is_err());
@@ -1262,6 +2432,26 @@ mod tests {
         assert_eq!(p("(?>a)"), Expr::AtomicGroup(Box::new(make_literal("a"))));
     }
 
+    #[test]
+    fn script_run() {
+        let run = Expr::ScriptRun(Box::new(make_literal("a")));
+        assert_eq!(p("(*script_run:a)"), run);
+        assert_eq!(p("(*sr:a)"), run);
+        let atomic = Expr::AtomicGroup(Box::new(run));
+        assert_eq!(p("(*atomic_script_run:a)"), atomic);
+        assert_eq!(p("(*asr:a)"), atomic);
+        assert_eq!(
+            p("(*sr:(a))\\1"),
+            Expr::Concat(vec![
+                Expr::ScriptRun(Box::new(Expr::Group(Box::new(make_literal("a"))))),
+                Expr::Backref {
+                    group: 1,
+                    casei: false
+                },
+            ])
+        );
+    }
+
     #[test]
     fn possessive() {
         assert_eq!(
@@ -1300,6 +2490,22 @@ mod tests {
         fail(".\\c"); // not decimal
     }
 
+    #[test]
+    fn error_positions() {
+        assert_eq!(error_position("ab\\q"), 2);
+        assert_eq!(error_position("a(b(c)"), 1);
+        assert_eq!(error_position("a(?i:b"), 1);
+        assert_eq!(error_position("ab)"), 2);
+        assert_eq!(error_position("a[b"), 1);
+        assert_eq!(error_position("a\\x{D800}"), 1);
+        assert_eq!(error_position("(?i)(?q)"), 6);
+        assert_eq!(error_position("a(?<x>.)b\\1"), 9);
+        assert_eq!(
+            Expr::parse_tree("ab)").unwrap_err().to_string(),
+            "Parsing error at position 2: General parsing error: unexpected `)`"
+        );
+    }
+
     #[test]
     fn invalid_group_name_backref() {
         assert_error(
@@ -1332,11 +2538,29 @@ mod tests {
     }
 
     #[test]
-    fn no_quantifiers_on_lookarounds() {
-        assert_error("(?=hello)+", "Target of repeat operator is invalid");
-        assert_error("(?<!hello)*", "Target of repeat operator is invalid");
-        assert_error("(?<=hello){2,3}", "Target of repeat operator is invalid");
-        assert_error("(?!hello)?", "Target of repeat operator is invalid");
+    fn quantifiers_on_lookarounds() {
+        assert_eq!(
+            p("(?=a)+"),
+            Expr::Repeat {
+                child: Box::new(Expr::LookAround(Box::new(make_literal("a")), LookAhead)),
+                lo: 1,
+                hi: usize::MAX,
+                greedy: true,
+            }
+        );
+        assert_eq!(
+            p("(?<!a){2,3}?"),
+            Expr::Repeat {
+                child: Box::new(Expr::LookAround(Box::new(make_literal("a")), LookBehindNeg)),
+                lo: 2,
+                hi: 3,
+                greedy: false,
+            }
+        );
+    }
+
+    #[test]
+    fn no_quantifiers_on_assertions() {
         assert_error("^?", "Target of repeat operator is invalid");
         assert_error("${2}", "Target of repeat operator is invalid");
         assert_error("(?m)^?", "Target of repeat operator is invalid");
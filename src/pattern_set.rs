@@ -0,0 +1,235 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Compiling many patterns together, see `PatternSet`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::compile::Delegates;
+use crate::parse::NamedGroups;
+use crate::{Regex, RegexBuilder, Result};
+
+/// A batch of regexes that are compiled together, e.g. the patterns of a syntax highlighting
+/// grammar.
+///
+/// The regexes share what they have in common instead of each having their own copy: the
+/// sub-regexes that are delegated to the regex crate (which are compiled only once, when one of
+/// the regexes first needs them), the tables for finding the literals that matches start with,
+/// and the tables of group names. That makes loading hundreds of similar patterns faster and
+/// uses less memory.
+///
+/// With the `serde` feature, a set can be serialized as one bundle, which is the sequence of its
+/// patterns. Deserializing compiles them with the default options.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::PatternSet;
+/// let set = PatternSet::new(&[r"\b(?<keyword>if|else)\b", r#""(?:[^"\\]|\\.)*""#]).unwrap();
+/// assert_eq!(set.len(), 2);
+/// assert!(set.regex(0).is_match("if x").unwrap());
+/// assert!(!set.regex(0).is_match("elsewhere").unwrap());
+/// assert_eq!(set.patterns().collect::<Vec<_>>()[1], r#""(?:[^"\\]|\\.)*""#);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PatternSet {
+    regexes: Vec<Regex>,
+}
+
+impl PatternSet {
+    /// Compile the patterns with the default options. The index of a pattern in `patterns` is
+    /// its id in the set.
+    pub fn new<I, S>(patterns: I) -> Result<PatternSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        PatternSet::with_builder(&RegexBuilder::new(""), patterns)
+    }
+
+    /// Compile the patterns with the options of `builder` (its pattern is ignored).
+    pub fn with_builder<I, S>(builder: &RegexBuilder, patterns: I) -> Result<PatternSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut interner = Interner::default();
+        let regexes = patterns
+            .into_iter()
+            .map(|pattern| {
+                let mut options = builder.0.clone();
+                options.pattern = pattern.as_ref().to_string();
+                Regex::new_interned(options, &mut interner)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(PatternSet { regexes })
+    }
+
+    /// Returns the number of regexes.
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Returns true if there are no regexes.
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    /// Returns the regex with id `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there's no regex with that id.
+    pub fn regex(&self, id: usize) -> &Regex {
+        &self.regexes[id]
+    }
+
+    /// Returns the regexes in the order of their ids.
+    pub fn regexes(&self) -> &[Regex] {
+        &self.regexes
+    }
+
+    /// Returns an iterator over the patterns in the order of their ids.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.regexes.iter().map(Regex::as_str)
+    }
+
+    /// Compile the delegated sub-regexes of all the regexes now, see
+    /// [`Regex::compile_all`](struct.Regex.html#method.compile_all).
+    pub fn compile_all(&self) -> Result<()> {
+        for regex in &self.regexes {
+            regex.compile_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// The tables that regexes built together share, so that identical ones are only made once.
+#[derive(Default)]
+pub(crate) struct Interner {
+    /// Note that an error from compiling a shared delegate has the span of the pattern that it
+    /// was first made for.
+    pub(crate) delegates: Delegates,
+    /// Group names by their sorted entries
+    named_groups: HashMap<Vec<(String, usize)>, Arc<NamedGroups>>,
+    #[cfg(feature = "perf-literal")]
+    prefilters: HashMap<Vec<String>, Arc<aho_corasick::AhoCorasick>>,
+}
+
+impl Interner {
+    pub(crate) fn named_groups(&mut self, named_groups: NamedGroups) -> Arc<NamedGroups> {
+        let mut key: Vec<_> = named_groups
+            .iter()
+            .map(|(name, &i)| (name.clone(), i))
+            .collect();
+        key.sort();
+        self.named_groups
+            .entry(key)
+            .or_insert_with(|| Arc::new(named_groups))
+            .clone()
+    }
+
+    /// The automaton for finding the literals that a match starts with, see
+    /// `Info::literal_prefixes`.
+    #[cfg(feature = "perf-literal")]
+    pub(crate) fn prefilter(&mut self, prefixes: Vec<String>) -> Arc<aho_corasick::AhoCorasick> {
+        if let Some(prefilter) = self.prefilters.get(&prefixes) {
+            return prefilter.clone();
+        }
+        let prefilter = aho_corasick::AhoCorasickBuilder::new()
+            .match_kind(aho_corasick::MatchKind::LeftmostFirst)
+            .build(&prefixes);
+        let prefilter = Arc::new(prefilter);
+        self.prefilters.insert(prefixes, prefilter.clone());
+        prefilter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile::LazyRegex;
+    use crate::vm::Insn;
+    use crate::RegexImpl;
+
+    fn delegates(regex: &Regex) -> Vec<Arc<LazyRegex>> {
+        let prog = match &*regex.inner {
+            RegexImpl::Fancy { prog, .. } => prog,
+            RegexImpl::Wrap { .. } => panic!("Expected a fancy regex: {:?}", regex),
+        };
+        prog.body
+            .iter()
+            .filter_map(|insn| match insn {
+                Insn::Delegate { inner, .. } | Insn::DelegateSized { inner, .. } => {
+                    Some(inner.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shares_delegates() {
+        let set = PatternSet::new(&[r"(a)\1[a-z]+\d", r"(b)\1[a-z]+\d"]).unwrap();
+        let (first, second) = (delegates(set.regex(0)), delegates(set.regex(1)));
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].as_str(), "^[a-z]");
+        assert_eq!(first[1].as_str(), r"^\d");
+        assert_eq!(second.len(), 2);
+        assert!(Arc::ptr_eq(&first[0], &second[0]));
+        assert!(Arc::ptr_eq(&first[1], &second[1]));
+
+        assert!(set.regex(0).is_match("aax1").unwrap());
+        assert!(set.regex(1).is_match("bbx1").unwrap());
+        assert!(!set.regex(1).is_match("aax1").unwrap());
+    }
+
+    #[test]
+    fn shares_named_groups() {
+        let set = PatternSet::new(&[r"(?<x>a)\k<x>", r"(?<x>b)\k<x>", r"(?<y>a)\k<y>"]).unwrap();
+        assert!(Arc::ptr_eq(
+            &set.regex(0).named_groups,
+            &set.regex(1).named_groups
+        ));
+        assert!(!Arc::ptr_eq(
+            &set.regex(0).named_groups,
+            &set.regex(2).named_groups
+        ));
+    }
+
+    #[test]
+    fn with_builder() {
+        let mut builder = RegexBuilder::new("ignored");
+        builder.backtrack_limit(10);
+        let set = PatternSet::with_builder(&builder, &["a", r"(a)\1"]).unwrap();
+        assert_eq!(set.patterns().collect::<Vec<_>>(), vec!["a", r"(a)\1"]);
+        for regex in set.regexes() {
+            assert_eq!(regex.options().backtrack_limit(), 10);
+        }
+        set.compile_all().unwrap();
+    }
+
+    #[test]
+    fn error_in_pattern() {
+        assert!(PatternSet::new(&["a", "(b"]).is_err());
+        assert!(PatternSet::new(Vec::<String>::new()).unwrap().is_empty());
+    }
+}
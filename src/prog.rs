@@ -0,0 +1,405 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Low-level access to the backtracking VM: building programs from instructions and running them.
+//!
+//! This is for experimenting with the VM and for front-ends that compile other kinds of patterns
+//! (e.g. globs or SQL `LIKE`) to instructions instead of going through a regex. A program is run
+//! at one position of the text and is anchored there; to search, the program has to contain the
+//! loop that tries the following positions itself, like the ones that `Regex` compiles.
+//!
+//! # Example
+//!
+//! A program for `a+b` (matching at the position it runs at) that saves the end of the match in
+//! slot 0:
+//!
+//! ```rust
+//! use fancy_regex::prog::{Insn, Prog};
+//!
+//! let prog = Prog::from_insns(
+//!     vec![
+//!         Insn::Lit("a".to_string()),
+//!         Insn::Split(0, 2),
+//!         Insn::Lit("b".to_string()),
+//!         Insn::Save(0),
+//!         Insn::End,
+//!     ],
+//!     1,
+//! )
+//! .unwrap();
+//! assert_eq!(prog.run("xaab", 1).unwrap(), Some(vec![4]));
+//! assert_eq!(prog.run("xaab", 0).unwrap(), None);
+//! ```
+//!
+//! With the `vm` feature but without the default `compiler` feature, this module is all there is
+//! for matching, e.g. for running programs that were compiled ahead of time in a minimal runtime.
+//! The instructions that delegate to the regex crate are not available then.
+
+use bit_set::BitSet;
+
+use crate::vm::{self, run};
+use crate::CompileError;
+use crate::Error;
+use crate::RegexOptions;
+use crate::Result;
+use crate::RunConfig;
+
+#[cfg(feature = "compiler")]
+pub use crate::compile::LazyRegex;
+pub use crate::vm::{Insn, Prog};
+
+impl Prog {
+    /// Make a program from instructions, using `n_saves` slots for saving positions and counters.
+    ///
+    /// The program is checked with [`validate`](#method.validate), and the regexes of delegate
+    /// instructions are compiled, so errors in them are returned here.
+    pub fn from_insns(body: Vec<Insn>, n_saves: usize) -> Result<Prog> {
+        let prog = Prog::new(body, n_saves);
+        prog.validate()?;
+        #[cfg(feature = "compiler")]
+        prog.compile_delegates()?;
+        Ok(prog)
+    }
+
+    /// Check that running the program can't go wrong in ways that would be hard to debug:
+    ///
+    /// * Instructions only jump to instructions of the program, and the last instruction doesn't
+    ///   continue after the end of the program.
+    /// * Instructions only use slots below `n_saves`.
+    /// * Atomic groups are balanced: each `EndAtomic` has a `BeginAtomic` before it on all the
+    ///   paths that reach it, and all paths to an instruction are in the same number of groups.
+    /// * The slot for the count of a repeat is initialized with `Save0` on all the paths to the
+    ///   repeat instruction.
+    ///
+    /// Returns a `CompileError::InvalidProgram` for the first problem that is found. Programs
+    /// compiled from regexes are checked like this in debug builds.
+    ///
+    /// Mistakes that only show when running, e.g. restoring a slot that was never saved, result in
+    /// a `RuntimeError::InternalBug` for the instruction.
+    pub fn validate(&self) -> Result<()> {
+        let body = &self.body;
+        let invalid = |pc: usize, reason: String| -> Result<()> {
+            Err(Error::CompileError(CompileError::InvalidProgram {
+                pc,
+                reason,
+            }))
+        };
+        match body.last() {
+            None => return invalid(0, "program is empty".to_string()),
+            Some(insn) if insn.falls_through() => {
+                return invalid(
+                    body.len() - 1,
+                    "last instruction continues after the end".to_string(),
+                )
+            }
+            Some(_) => {}
+        }
+        let mut repeat_slots = Vec::new();
+        for (pc, insn) in body.iter().enumerate() {
+            if let Some(target) = insn.targets().find(|&target| target >= body.len()) {
+                return invalid(pc, format!("jump to instruction {} out of range", target));
+            }
+            let slots = insn.slots_needed();
+            if slots > self.n_saves {
+                return invalid(
+                    pc,
+                    format!("needs {} slots but there are {}", slots, self.n_saves),
+                );
+            }
+            if let Some(slot) = repeat_slot(insn) {
+                repeat_slots.push(slot);
+            }
+        }
+        repeat_slots.sort_unstable();
+        repeat_slots.dedup();
+
+        // Follow the paths through the program, keeping track of the number of atomic groups
+        // they're in and the repeat slots that are initialized on all of them (by index in
+        // `repeat_slots`)
+        let mut states: Vec<Option<(usize, BitSet)>> = vec![None; body.len()];
+        states[0] = Some((0, BitSet::new()));
+        let mut pending = vec![0];
+        while let Some(pc) = pending.pop() {
+            let (mut depth, mut initialized) = states[pc].clone().unwrap();
+            let insn = &body[pc];
+            match *insn {
+                Insn::BeginAtomic => depth += 1,
+                Insn::EndAtomic if depth == 0 => {
+                    return invalid(pc, "end of atomic group without its start".to_string());
+                }
+                Insn::EndAtomic => depth -= 1,
+                Insn::Save0(slot) => {
+                    if let Ok(i) = repeat_slots.binary_search(&slot) {
+                        initialized.insert(i);
+                    }
+                }
+                _ => {}
+            }
+            if let Some(slot) = repeat_slot(insn) {
+                let i = repeat_slots.binary_search(&slot).unwrap();
+                if !initialized.contains(i) {
+                    return invalid(
+                        pc,
+                        format!("repeat count in slot {} is not initialized", slot),
+                    );
+                }
+            }
+            let next = if insn.falls_through() {
+                Some(pc + 1)
+            } else {
+                None
+            };
+            for target in next.into_iter().chain(insn.targets()) {
+                let state = &mut states[target];
+                match *state {
+                    None => {
+                        *state = Some((depth, initialized.clone()));
+                        pending.push(target);
+                    }
+                    Some((target_depth, _)) if target_depth != depth => {
+                        return invalid(
+                            target,
+                            "reached inside different numbers of atomic groups".to_string(),
+                        );
+                    }
+                    Some((_, ref mut target_initialized)) => {
+                        let count = target_initialized.len();
+                        target_initialized.intersect_with(&initialized);
+                        if target_initialized.len() != count {
+                            pending.push(target);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of slots of the program.
+    pub fn n_saves(&self) -> usize {
+        self.n_saves
+    }
+
+    /// Run the program at position `pos` of `text`. Returns the slots if it reaches `End`, with
+    /// `usize::MAX` for ones that weren't set.
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is not a character boundary of `text`.
+    pub fn run(&self, text: &str, pos: usize) -> Result<Option<Vec<usize>>> {
+        self.run_with_config(text, pos, &RunConfig::default())
+    }
+
+    /// Run the program like `run`, with the limits of `config`.
+    pub fn run_with_config(
+        &self,
+        text: &str,
+        pos: usize,
+        config: &RunConfig,
+    ) -> Result<Option<Vec<usize>>> {
+        let result = run(self, text, pos, 0, &RegexOptions::default(), config)?;
+        Ok(self.slots(result))
+    }
+
+    /// Run the program like `run`, printing each step for debugging.
+    pub fn run_trace(&self, text: &str, pos: usize) -> Result<Option<Vec<usize>>> {
+        let result = vm::run_trace(self, text, pos)?;
+        Ok(self.slots(result))
+    }
+
+    /// The slots of the result of a run, without the explicit stack after them.
+    fn slots(&self, result: Option<Vec<usize>>) -> Option<Vec<usize>> {
+        result.map(|mut saves| {
+            saves.truncate(self.n_saves);
+            saves
+        })
+    }
+}
+
+/// The slot with the count of a repeat instruction.
+fn repeat_slot(insn: &Insn) -> Option<usize> {
+    match *insn {
+        Insn::RepeatGr { repeat, .. }
+        | Insn::RepeatNg { repeat, .. }
+        | Insn::RepeatEpsilonGr { repeat, .. }
+        | Insn::RepeatEpsilonNg { repeat, .. } => Some(repeat),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuntimeError;
+    #[cfg(feature = "compiler")]
+    use std::sync::Arc;
+
+    fn invalid_pc(body: Vec<Insn>, n_saves: usize) -> Option<usize> {
+        match Prog::from_insns(body, n_saves) {
+            Err(Error::CompileError(CompileError::InvalidProgram { pc, .. })) => Some(pc),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(invalid_pc(vec![], 0), Some(0));
+        assert_eq!(invalid_pc(vec![Insn::Any], 0), Some(0));
+        assert_eq!(invalid_pc(vec![Insn::Split(1, 2), Insn::End], 0), Some(0));
+        assert_eq!(invalid_pc(vec![Insn::Any, Insn::Jmp(2)], 0), Some(1));
+        assert_eq!(
+            invalid_pc(vec![Insn::Save(0), Insn::Save(2), Insn::End], 2),
+            Some(1)
+        );
+        assert_eq!(invalid_pc(vec![Insn::Backref(0), Insn::End], 1), Some(0));
+        assert_eq!(
+            invalid_pc(vec![Insn::Save(std::usize::MAX), Insn::End], 2),
+            Some(0)
+        );
+        assert!(Prog::from_insns(vec![Insn::Backref(0), Insn::End], 2).is_ok());
+        assert!(Prog::from_insns(vec![Insn::Any, Insn::Jmp(0)], 0).is_ok());
+    }
+
+    #[test]
+    fn validate_atomic_groups() {
+        assert_eq!(invalid_pc(vec![Insn::EndAtomic, Insn::End], 0), Some(0));
+        let body = vec![Insn::Split(1, 2), Insn::BeginAtomic, Insn::Any, Insn::End];
+        assert_eq!(invalid_pc(body, 0), Some(2));
+        let body = vec![
+            Insn::Split(1, 4),
+            Insn::BeginAtomic,
+            Insn::Any,
+            Insn::EndAtomic,
+            Insn::End,
+        ];
+        assert!(Prog::from_insns(body, 0).is_ok());
+    }
+
+    #[test]
+    fn validate_repeat_initialization() {
+        let repeat = |next: usize| Insn::RepeatGr {
+            lo: 1,
+            hi: 2,
+            next,
+            repeat: 0,
+        };
+        let body = vec![repeat(3), Insn::Any, Insn::Jmp(0), Insn::End];
+        assert_eq!(invalid_pc(body, 1), Some(0));
+        // Only initialized on one of the paths
+        let body = vec![
+            Insn::Split(1, 2),
+            Insn::Save0(0),
+            repeat(5),
+            Insn::Any,
+            Insn::Jmp(2),
+            Insn::End,
+        ];
+        assert_eq!(invalid_pc(body, 1), Some(2));
+
+        let body = vec![
+            Insn::Save0(0),
+            repeat(4),
+            Insn::Any,
+            Insn::Jmp(1),
+            Insn::End,
+        ];
+        let prog = Prog::from_insns(body, 1).unwrap();
+        assert!(prog.validate().is_ok());
+        assert!(prog.run("abc", 0).unwrap().is_some());
+        assert!(prog.run("abc", 3).unwrap().is_none());
+    }
+
+    #[cfg(feature = "compiler")]
+    #[test]
+    fn compiled_programs_are_valid() {
+        for re in &[
+            r"(a|b)*c",
+            r"((?>a+|b))\1",
+            r"(?<!x)a{2,5}?(?=b)",
+            r"(a?)*?b(?!(?>c)+)",
+            r"^(?:(\w)\1|x{3})+$",
+        ] {
+            let options = crate::RegexOptions {
+                pattern: re.to_string(),
+                ..crate::RegexOptions::default()
+            };
+            let prog = crate::compile_anchored(&options).unwrap();
+            assert!(prog.validate().is_ok(), "{}: {:?}", re, prog.validate());
+        }
+    }
+
+    #[cfg(feature = "compiler")]
+    #[test]
+    fn delegate() {
+        let delegate = |pattern: &str| Insn::Delegate {
+            inner: Arc::new(LazyRegex::from_pattern(pattern)),
+            inner1: None,
+            groups: vec![],
+        };
+        let prog =
+            Prog::from_insns(vec![delegate("^[0-9]+"), Insn::Save(0), Insn::End], 1).unwrap();
+        assert_eq!(prog.n_saves(), 1);
+        assert_eq!(prog.run("ab123c", 2).unwrap(), Some(vec![5]));
+        assert_eq!(prog.run("ab123c", 1).unwrap(), None);
+
+        assert!(matches!(
+            Prog::from_insns(vec![delegate("^("), Insn::End], 0),
+            Err(Error::CompileError(CompileError::DelegateError { .. }))
+        ));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        // Not validated, so that the checks of the VM are reached
+        let internal_bug = |body: Vec<Insn>, n_saves: usize| {
+            let prog = Prog::new(body, n_saves);
+            match prog.run("abc", 0) {
+                Err(Error::RuntimeError(RuntimeError::InternalBug { pc, .. })) => Some(pc),
+                _ => None,
+            }
+        };
+        assert_eq!(internal_bug(vec![Insn::Restore(0), Insn::End], 1), Some(0));
+        assert_eq!(
+            internal_bug(vec![Insn::Any, Insn::EndAtomic, Insn::End], 0),
+            Some(1)
+        );
+        assert_eq!(
+            internal_bug(vec![Insn::ScriptRun(0), Insn::End], 1),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn atomic_slots_are_not_returned() {
+        let prog = Prog::from_insns(
+            vec![
+                Insn::Save(0),
+                Insn::BeginAtomic,
+                Insn::Any,
+                Insn::EndAtomic,
+                Insn::Save(1),
+                Insn::End,
+            ],
+            2,
+        )
+        .unwrap();
+        assert_eq!(prog.run("abc", 1).unwrap(), Some(vec![1, 2]));
+    }
+}
@@ -7,6 +7,9 @@ use std::borrow::Cow;
 /// since implementations are already provided for `&str` along with other
 /// variants of string types and `FnMut(&Captures) -> String` (or any
 /// `FnMut(&Captures) -> T` where `T: AsRef<str>`), which covers most use cases.
+///
+/// A replacer can run other regexes, or the regex that it is replacing matches of, as the
+/// matching engine keeps no global or thread-local state while calling it.
 pub trait Replacer {
     /// Appends text to `dst` to replace the current match.
     ///
@@ -24,7 +27,7 @@ pub trait Replacer {
     /// be beneficial to avoid finding sub-captures.
     ///
     /// In general, this is called once for every call to `replacen`.
-    fn no_expansion(&mut self) -> Option<Cow<str>> {
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
         None
     }
 
@@ -49,7 +52,7 @@ pub trait Replacer {
     ///     dst.into_owned()
     /// }
     /// ```
-    fn by_ref(&mut self) -> ReplacerRef<Self> {
+    fn by_ref(&mut self) -> ReplacerRef<'_, Self> {
         ReplacerRef(self)
     }
 }
@@ -69,9 +72,9 @@ impl<'a, R: Replacer + ?Sized + 'a> Replacer for ReplacerRef<'a, R> {
     }
 }
 
-impl<'a> Replacer for &'a str {
+impl Replacer for &str {
     fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
-        caps.expand(*self, dst);
+        caps.expand(self, dst);
     }
 
     fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
@@ -79,7 +82,7 @@ impl<'a> Replacer for &'a str {
     }
 }
 
-impl<'a> Replacer for &'a String {
+impl Replacer for &String {
     fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
         self.as_str().replace_append(caps, dst)
     }
@@ -138,6 +141,19 @@ where
     }
 }
 
+/// Replacer for a closure that appends the replacement itself, see
+/// `Regex::replace_all_with_groups`.
+pub(crate) struct Append<F>(pub(crate) F);
+
+impl<F> Replacer for Append<F>
+where
+    F: FnMut(&Captures<'_>, &mut String),
+{
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        (self.0)(caps, dst)
+    }
+}
+
 /// `NoExpand` indicates literal string replacement.
 ///
 /// It can be used with `replace` and `replace_all` to do a literal string
@@ -0,0 +1,147 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Searching in steps with a bounded number of VM instructions each, for embedding the matching
+//! in runtimes with their own scheduling.
+
+use std::fmt;
+#[cfg(feature = "async")]
+use std::future::Future;
+
+use crate::vm::SuspendedRun;
+use crate::{Match, Regex, RegexImpl, Result, RunConfig};
+
+/// How far a resumable search got, see [`ResumableFind`](struct.ResumableFind.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress<T> {
+    /// The search is complete, with its result
+    Done(T),
+    /// The search ran out of fuel before it was complete, resume it to continue
+    Suspended,
+}
+
+/// A search for the first match of a regex that executes a bounded number of instructions of the
+/// backtracking VM at a time, see [`Regex::find_resumable`](struct.Regex.html#method.find_resumable).
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the lifetime of the
+/// searched string.
+pub struct ResumableFind<'r, 't> {
+    re: &'r Regex,
+    text: &'t str,
+    config: RunConfig,
+    run: Option<SuspendedRun>,
+    result: Option<Option<Match<'t>>>,
+}
+
+impl<'r, 't> ResumableFind<'r, 't> {
+    pub(crate) fn new(re: &'r Regex, text: &'t str) -> ResumableFind<'r, 't> {
+        ResumableFind {
+            re,
+            text,
+            config: RunConfig::default(),
+            run: None,
+            result: None,
+        }
+    }
+
+    /// Continue the search, executing at most `fuel` instructions of the VM. Returns
+    /// `Progress::Suspended` if that wasn't enough to complete it, otherwise the match like
+    /// [`Regex::find`](struct.Regex.html#method.find) would.
+    ///
+    /// Once the search is complete, further calls return the same result again. Regexes that
+    /// don't need backtracking are matched by the regex crate, which runs in linear time and
+    /// isn't counted, so their search is always complete after the first call.
+    pub fn resume(&mut self, fuel: usize) -> Result<Progress<Option<Match<'t>>>> {
+        if let Some(result) = self.result {
+            return Ok(Progress::Done(result));
+        }
+        let result = match &*self.re.inner {
+            RegexImpl::Wrap { .. } => self.re.find(self.text)?,
+            RegexImpl::Fancy {
+                find_prog, options, ..
+            } => {
+                let config = &self.config;
+                let run = self
+                    .run
+                    .get_or_insert_with(|| SuspendedRun::new(find_prog, 0, 0, options, config));
+                match run.resume(find_prog, self.text, fuel, options, config)? {
+                    Some(saves) => saves.map(|saves| Match::new(self.text, saves[0], saves[1])),
+                    None => return Ok(Progress::Suspended),
+                }
+            }
+        };
+        self.run = None;
+        self.result = Some(result);
+        Ok(Progress::Done(result))
+    }
+}
+
+impl<'r, 't> fmt::Debug for ResumableFind<'r, 't> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResumableFind")
+            .field("re", &self.re)
+            .field("text", &self.text)
+            .field("suspended", &self.run.is_some())
+            .field("result", &self.result)
+            .finish()
+    }
+}
+
+#[cfg(feature = "async")]
+impl Regex {
+    /// Returns the first match in `text` like `find`, awaiting `yield_now()` after every
+    /// `yield_every` instructions of the backtracking VM, so that a search with lots of
+    /// backtracking doesn't block the thread of an async executor. Requires the `async` feature.
+    ///
+    /// `yield_now` is a hook for the executor, e.g. `tokio::task::yield_now`. The search is
+    /// done with [`find_resumable`](struct.Regex.html#method.find_resumable).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # async fn example() -> fancy_regex::Result<()> {
+    /// use fancy_regex::Regex;
+    ///
+    /// let re = Regex::new(r"(\w+)\s+\1")?;
+    /// // With tokio: `re.find_async(text, 10_000, tokio::task::yield_now).await?`
+    /// let m = re.find_async("hello hello", 10_000, || async {}).await?;
+    /// assert_eq!(m.unwrap().as_str(), "hello hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn find_async<'t, F, Fut>(
+        &self,
+        text: &'t str,
+        yield_every: usize,
+        mut yield_now: F,
+    ) -> Result<Option<Match<'t>>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let mut search = self.find_resumable(text);
+        loop {
+            match search.resume(yield_every)? {
+                Progress::Done(result) => return Ok(result),
+                Progress::Suspended => yield_now().await,
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Serialization of match results with serde, enabled with the `serde` feature.
+//!
+//! A `Match` is serialized as a struct with `start`, `end` and `text`. `Captures` are serialized
+//! as a sequence with an entry for each group, which is either null if the group didn't
+//! participate in the match, or a struct with `name` (null for unnamed groups), `start`, `end`
+//! and `text`.
+//!
+//! A `PatternSet` is serialized as the sequence of its patterns, and deserialized by compiling
+//! them with the default options.
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeSeq, SerializeStruct, Serializer};
+
+use crate::{Captures, Match, PatternSet};
+
+impl<'t> Serialize for Match<'t> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Match", 3)?;
+        s.serialize_field("start", &self.start())?;
+        s.serialize_field("end", &self.end())?;
+        s.serialize_field("text", self.as_str())?;
+        s.end()
+    }
+}
+
+struct Group<'a, 't> {
+    name: Option<&'a str>,
+    m: Match<'t>,
+}
+
+impl<'a, 't> Serialize for Group<'a, 't> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Group", 4)?;
+        s.serialize_field("name", &self.name)?;
+        s.serialize_field("start", &self.m.start())?;
+        s.serialize_field("end", &self.m.end())?;
+        s.serialize_field("text", self.m.as_str())?;
+        s.end()
+    }
+}
+
+impl<'t> Serialize for Captures<'t> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut names = vec![None; self.len()];
+        for (name, &i) in self.named_groups.iter() {
+            if let Some(slot) = names.get_mut(i) {
+                *slot = Some(name.as_str());
+            }
+        }
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for (m, name) in self.iter().zip(names) {
+            seq.serialize_element(&m.map(|m| Group { name, m }))?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for PatternSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.patterns())
+    }
+}
+
+impl<'de> Deserialize<'de> for PatternSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let patterns = Vec::<String>::deserialize(deserializer)?;
+        PatternSet::new(&patterns).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PatternSet, Regex};
+
+    #[test]
+    fn serialize_match() {
+        let re = Regex::new(r"\w+(?=!)").unwrap();
+        let m = re.find("so fancy!").unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_string(&m).unwrap(),
+            r#"{"start":3,"end":8,"text":"fancy"}"#
+        );
+    }
+
+    #[test]
+    fn serialize_captures() {
+        let re = Regex::new(r"(?<word>\w+)(x)?(!)").unwrap();
+        let caps = re.captures("so fancy!").unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_string(&caps).unwrap(),
+            concat!(
+                r#"[{"name":null,"start":3,"end":9,"text":"fancy!"},"#,
+                r#"{"name":"word","start":3,"end":8,"text":"fancy"},"#,
+                r#"null,"#,
+                r#"{"name":null,"start":8,"end":9,"text":"!"}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn pattern_set_bundle() {
+        let set = PatternSet::new(&[r"(\w)\1", r"\d+(?=px)"]).unwrap();
+        let bundle = serde_json::to_string(&set).unwrap();
+        assert_eq!(bundle, r#"["(\\w)\\1","\\d+(?=px)"]"#);
+
+        let loaded: PatternSet = serde_json::from_str(&bundle).unwrap();
+        assert_eq!(
+            loaded.patterns().collect::<Vec<_>>(),
+            vec![r"(\w)\1", r"\d+(?=px)"]
+        );
+        assert!(loaded.regex(1).is_match("12px").unwrap());
+
+        assert!(serde_json::from_str::<PatternSet>(r#"["(a"]"#).is_err());
+    }
+}
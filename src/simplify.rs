@@ -0,0 +1,303 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Simplification of parsed expressions, which makes both the compiled program and the delegated
+//! regexes smaller.
+//!
+//! The rewrites don't change what is matched, including the positions of capture groups: groups
+//! are never removed or duplicated.
+
+use std::usize;
+
+use crate::Expr;
+
+/// Simplifies `expr` bottom-up:
+///
+/// * Nested concatenations and alternations are flattened, and adjacent literals are merged
+/// * Alternatives that are the same as an earlier one are removed, e.g. `a|b|a` becomes `a|b`
+/// * Literal prefixes that adjacent alternatives have in common are hoisted out, e.g. `abc|abd|e`
+///   becomes `ab(?:c|d)|e`
+/// * `x{1}` becomes `x`, and nested repeats like `(?:x+)*` become `x*`
+pub(crate) fn simplify(expr: Expr) -> Expr {
+    match expr {
+        Expr::Concat(children) => concat(children.into_iter().map(simplify).collect()),
+        Expr::Alt(children) => alt(children.into_iter().map(simplify).collect()),
+        Expr::Group(child) => Expr::Group(Box::new(simplify(*child))),
+        Expr::LookAround(child, la) => Expr::LookAround(Box::new(simplify(*child)), la),
+        Expr::AtomicGroup(child) => Expr::AtomicGroup(Box::new(simplify(*child))),
+        Expr::ScriptRun(child) => Expr::ScriptRun(Box::new(simplify(*child))),
+        Expr::Repeat {
+            child,
+            lo,
+            hi,
+            greedy,
+        } => repeat(simplify(*child), lo, hi, greedy),
+        expr => expr,
+    }
+}
+
+fn concat(children: Vec<Expr>) -> Expr {
+    let mut result: Vec<Expr> = Vec::with_capacity(children.len());
+    for child in children {
+        match child {
+            Expr::Concat(grandchildren) => {
+                for grandchild in grandchildren {
+                    push_concat_child(&mut result, grandchild);
+                }
+            }
+            child => push_concat_child(&mut result, child),
+        }
+    }
+    if result.len() == 1 {
+        result.pop().unwrap()
+    } else {
+        // An empty concatenation is kept, it's needed where a group is, e.g. `(?:)*`
+        Expr::Concat(result)
+    }
+}
+
+fn push_concat_child(result: &mut Vec<Expr>, child: Expr) {
+    if let Expr::Literal {
+        val: ref next,
+        casei: next_casei,
+    } = child
+    {
+        if let Some(Expr::Literal { val, casei }) = result.last_mut() {
+            if *casei == next_casei {
+                val.push_str(next);
+                return;
+            }
+        }
+    }
+    if child != Expr::Empty {
+        result.push(child);
+    }
+}
+
+fn alt(children: Vec<Expr>) -> Expr {
+    let mut flattened: Vec<Expr> = Vec::with_capacity(children.len());
+    for child in children {
+        let alternatives = match child {
+            Expr::Alt(grandchildren) => grandchildren,
+            child => vec![child],
+        };
+        for alternative in alternatives {
+            // An alternative is only tried if the same earlier one didn't lead to a match
+            if has_group(&alternative) || !flattened.contains(&alternative) {
+                flattened.push(alternative);
+            }
+        }
+    }
+
+    let mut result = Vec::with_capacity(flattened.len());
+    let mut alternatives = flattened.into_iter().peekable();
+    while let Some(first) = alternatives.next() {
+        let (mut prefix, casei) = match literal_prefix(&first) {
+            Some((prefix, casei)) => (prefix.to_string(), casei),
+            None => {
+                result.push(first);
+                continue;
+            }
+        };
+        let mut run = vec![first];
+        while let Some(next) = alternatives.peek() {
+            let common = match literal_prefix(next) {
+                Some((next_prefix, next_casei)) if next_casei == casei => {
+                    common_prefix_len(&prefix, next_prefix)
+                }
+                _ => 0,
+            };
+            if common == 0 {
+                break;
+            }
+            prefix.truncate(common);
+            run.push(alternatives.next().unwrap());
+        }
+        if run.len() == 1 {
+            result.extend(run);
+        } else {
+            let rest = run
+                .into_iter()
+                .map(|alternative| strip_literal_prefix(alternative, prefix.len()))
+                .collect();
+            result.push(concat(vec![
+                Expr::Literal { val: prefix, casei },
+                alt(rest),
+            ]));
+        }
+    }
+    if result.len() == 1 {
+        result.pop().unwrap()
+    } else {
+        Expr::Alt(result)
+    }
+}
+
+/// Returns the literal that `expr` starts with, if any.
+fn literal_prefix(expr: &Expr) -> Option<(&str, bool)> {
+    match expr {
+        Expr::Literal { val, casei } => Some((val, *casei)),
+        Expr::Concat(children) => match children.first() {
+            Some(Expr::Literal { val, casei }) => Some((val, *casei)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Removes the first `len` bytes of the literal prefix of `expr`.
+fn strip_literal_prefix(expr: Expr, len: usize) -> Expr {
+    let strip = |val: String, casei: bool| {
+        if val.len() == len {
+            Expr::Empty
+        } else {
+            Expr::Literal {
+                val: val[len..].to_string(),
+                casei,
+            }
+        }
+    };
+    match expr {
+        Expr::Literal { val, casei } => strip(val, casei),
+        Expr::Concat(mut children) => {
+            if let Expr::Literal { val, casei } = children.remove(0) {
+                children.insert(0, strip(val, casei));
+            }
+            concat(children)
+        }
+        expr => expr,
+    }
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.char_indices()
+        .zip(b.chars())
+        .find(|&((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((i, _), _)| i)
+}
+
+fn repeat(child: Expr, lo: usize, hi: usize, greedy: bool) -> Expr {
+    match child {
+        // Repeating nothing still matches nothing, and there's nothing to put the repeat on
+        Expr::Empty => Expr::Empty,
+        child if lo == 1 && hi == 1 => child,
+        // `x*`, `x+` or `x?` of `y*` or `y+` is `y*` or `y+`, with the same greediness. Only
+        // without groups inside, whose positions could be different.
+        Expr::Repeat {
+            child: inner,
+            lo: inner_lo,
+            hi: usize::MAX,
+            greedy: inner_greedy,
+        } if inner_lo <= 1
+            && lo <= 1
+            && (hi == 1 || hi == usize::MAX)
+            && inner_greedy == greedy
+            && !has_group(&inner) =>
+        {
+            Expr::Repeat {
+                child: inner,
+                lo: lo * inner_lo,
+                hi: usize::MAX,
+                greedy,
+            }
+        }
+        child => Expr::Repeat {
+            child: Box::new(child),
+            lo,
+            hi,
+            greedy,
+        },
+    }
+}
+
+fn has_group(expr: &Expr) -> bool {
+    match expr {
+        Expr::Group(_) => true,
+        Expr::Concat(children) | Expr::Alt(children) => children.iter().any(has_group),
+        Expr::LookAround(child, _)
+        | Expr::AtomicGroup(child)
+        | Expr::ScriptRun(child)
+        | Expr::Repeat { child, .. } => has_group(child),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simplified(re: &str) -> String {
+        let expr = simplify(Expr::parse_tree(re).unwrap().expr);
+        let mut s = String::new();
+        expr.to_str(&mut s, 0);
+        s
+    }
+
+    #[test]
+    fn merges_literals() {
+        assert_eq!(
+            simplify(Expr::parse_tree("abc").unwrap().expr),
+            Expr::Literal {
+                val: "abc".to_string(),
+                casei: false
+            }
+        );
+        assert_eq!(simplified("a(?:bc)d"), "abcd");
+        assert_eq!(simplified("a(?i)bc"), "a(?i:bc)");
+        assert_eq!(simplified("(?:ab)*c"), "(?:ab)*c");
+    }
+
+    #[test]
+    fn removes_duplicate_alternatives() {
+        assert_eq!(simplified("a|b|a"), "a|b");
+        assert_eq!(simplified("x(?:a|a)"), "xa");
+        assert_eq!(simplified("a|(?:b|a)"), "a|b");
+        // Both groups are still needed
+        assert_eq!(simplified("(a)|(a)"), "(a)|(a)");
+    }
+
+    #[test]
+    fn hoists_common_prefixes() {
+        assert_eq!(simplified("abc|abd"), "ab(?:c|d)");
+        assert_eq!(simplified("abc|abd|e|ef"), "ab(?:c|d)|e(?:|f)");
+        assert_eq!(simplified("abc|abd|ae"), "a(?:b(?:c|d)|e)");
+        assert_eq!(simplified("ab\\d|ac"), "a(?:b\\d|c)");
+        assert_eq!(simplified("ab|(?i)ac"), "ab|(?i:ac)");
+        assert_eq!(simplified("\u{e9}a|\u{e8}b"), "\u{e9}a|\u{e8}b");
+    }
+
+    #[test]
+    fn flattens_repeats() {
+        assert_eq!(simplified("a{1}"), "a");
+        assert_eq!(simplified("(?:a+)*"), "a*");
+        assert_eq!(simplified("(?:a*)+"), "a*");
+        assert_eq!(simplified("(?:a+)+"), "a+");
+        assert_eq!(simplified("(?:a*)?"), "a*");
+        assert_eq!(simplified("(?:a*?)*"), "(?:a*?)*");
+        assert_eq!(simplified("(?:a{2,})*"), "(?:a{2,})*");
+        assert_eq!(simplified("(a+)*"), "(a+)*");
+    }
+
+    #[test]
+    fn repeats_of_empty() {
+        assert_eq!(simplified("(?:|)*b"), "b");
+        assert_eq!(simplified("(?:|){1,2}"), "");
+    }
+}
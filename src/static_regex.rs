@@ -0,0 +1,128 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Regexes in `static` items that are built when they're first used, see `StaticRegex`.
+
+use std::fmt;
+use std::ops::Deref;
+
+use once_cell::sync::OnceCell;
+
+use crate::{Regex, Result};
+
+/// A regex for a `static` item, which is built when it's first used and then shared by all
+/// threads, so that each global regex doesn't need its own `lazy_static!` or `once_cell`
+/// wrapper.
+///
+/// It dereferences to the [`Regex`](struct.Regex.html), building it on the first access. If
+/// the pattern is invalid, that panics, like `Regex::new(pattern).unwrap()` would; use
+/// [`try_get`](#method.try_get) to handle the error instead.
+///
+/// # Example
+///
+/// ```rust
+/// use fancy_regex::StaticRegex;
+///
+/// static REPEATED_WORD: StaticRegex = StaticRegex::new(r"\b(\w+)\s+\1\b");
+///
+/// fn has_repeated_word(text: &str) -> bool {
+///     REPEATED_WORD.is_match(text).unwrap_or(false)
+/// }
+///
+/// assert!(has_repeated_word("it is is fancy"));
+/// assert!(!has_repeated_word("it is fancy"));
+/// ```
+pub struct StaticRegex {
+    pattern: &'static str,
+    regex: OnceCell<Regex>,
+}
+
+impl StaticRegex {
+    /// Create a static regex for `pattern`, without building it yet.
+    pub const fn new(pattern: &'static str) -> StaticRegex {
+        StaticRegex {
+            pattern,
+            regex: OnceCell::new(),
+        }
+    }
+
+    /// The pattern of the regex.
+    pub fn as_str(&self) -> &'static str {
+        self.pattern
+    }
+
+    /// Get the regex, building it first if it's used for the first time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is invalid.
+    pub fn get(&self) -> &Regex {
+        match self.try_get() {
+            Ok(regex) => regex,
+            Err(error) => panic!("Invalid static regex {:?}: {}", self.pattern, error),
+        }
+    }
+
+    /// Get the regex, building it first if it's used for the first time, or the error if the
+    /// pattern is invalid. After an error, the next call tries again.
+    pub fn try_get(&self) -> Result<&Regex> {
+        self.regex.get_or_try_init(|| Regex::new(self.pattern))
+    }
+}
+
+impl Deref for StaticRegex {
+    type Target = Regex;
+
+    fn deref(&self) -> &Regex {
+        self.get()
+    }
+}
+
+impl fmt::Debug for StaticRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StaticRegex").field(&self.pattern).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticRegex;
+
+    static DOUBLED: StaticRegex = StaticRegex::new(r"(\w)\1");
+    static INVALID: StaticRegex = StaticRegex::new(r"(a");
+
+    #[test]
+    fn shared_between_threads() {
+        let handles: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(|| DOUBLED.is_match("hello").unwrap()))
+            .collect();
+        for handle in handles {
+            assert!(handle.join().unwrap());
+        }
+        assert!(std::ptr::eq(DOUBLED.get(), DOUBLED.get()));
+        assert_eq!(DOUBLED.as_str(), r"(\w)\1");
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(INVALID.try_get().is_err());
+        assert!(std::panic::catch_unwind(|| INVALID.get().as_str().len()).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Replacing matches in text that is read in chunks.
+//!
+//! The text read so far is kept in a buffer. A match is only accepted once it can't be affected
+//! by text that hasn't been read yet, i.e. when the regex's maximum size (plus one character for
+//! assertions like `$` or `\b` that peek at the next character) fits into the buffer after the
+//! start of the match. Text before that point is written out, keeping enough of it around for
+//! look-behind.
+//!
+//! With a limit for the buffer, the margin is capped at the limit. Matches are then assumed to be
+//! no longer than that, and a match that reaches the end of the buffer is an error, because more
+//! text could change it.
+
+use std::io::{self, Read, Write};
+use std::str;
+use std::usize;
+
+use crate::{
+    prev_codepoint_ix, CaptureMatches, Error, Matches, Regex, Replacer, RunConfig, RuntimeError,
+};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Maximum number of bytes per character in UTF-8.
+const MAX_CHAR_BYTES: usize = 4;
+
+pub(crate) fn replace_all<R: Read, W: Write, Rep: Replacer>(
+    regex: &Regex,
+    mut reader: R,
+    mut writer: W,
+    mut rep: Rep,
+    max_buffer: Option<usize>,
+) -> io::Result<()> {
+    // Bytes that are needed after the start of a match for it to be final
+    let needed = regex
+        .max_size
+        .and_then(|size| size.checked_add(1))
+        .and_then(|size| size.checked_mul(MAX_CHAR_BYTES));
+    // Whether matches starting before the limit are certainly final, otherwise they are checked
+    let (margin, exact) = match (needed, max_buffer) {
+        (Some(needed), Some(max_buffer)) if needed > max_buffer => (Some(max_buffer), false),
+        (None, Some(max_buffer)) => (Some(max_buffer), false),
+        (needed, _) => (needed, true),
+    };
+    // Characters that are needed before the search position, at least one so that `^` can't
+    // match at the start of the buffer once text has been discarded
+    let context = regex.max_lookbehind.saturating_add(1);
+
+    let mut chunk = vec![0; CHUNK_SIZE];
+    // Bytes that were read but are not a complete UTF-8 sequence yet
+    let mut pending = Vec::new();
+    let mut buf = String::new();
+    let mut out = String::new();
+    // Everything before `written` has been written out (or replaced)
+    let mut written = 0;
+    // State of the `Matches` iterator, continued between chunks
+    let mut last_end = 0;
+    let mut last_match = None;
+
+    loop {
+        let n = match reader.read(&mut chunk) {
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        let eof = n == 0;
+        if eof {
+            if !pending.is_empty() {
+                return Err(invalid_utf8());
+            }
+        } else {
+            pending.extend_from_slice(&chunk[..n]);
+            let valid = match str::from_utf8(&pending) {
+                Ok(s) => s.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+            buf.push_str(str::from_utf8(&pending[..valid]).unwrap());
+            pending.drain(..valid);
+        }
+
+        // Matches starting before `limit` are final
+        let limit = match margin {
+            _ if eof => usize::MAX,
+            Some(margin) => buf.len().saturating_sub(margin),
+            // Matches could be arbitrarily long, so we need all of the input
+            None => continue,
+        };
+
+        loop {
+            let mut it = CaptureMatches(Matches {
+                re: regex,
+                text: &buf,
+                last_end,
+                last_match,
+                config: RunConfig::default(),
+            });
+            let caps = match it.next() {
+                None => break,
+                Some(Err(e)) => return Err(runtime_error(e)),
+                Some(Ok(caps)) => caps,
+            };
+            let m = caps.get(0).unwrap();
+            if m.start() >= limit {
+                break;
+            }
+            if !exact && !eof && m.end().saturating_add(MAX_CHAR_BYTES) > buf.len() {
+                let limit = max_buffer.unwrap_or(usize::MAX);
+                let error = Error::RuntimeError(RuntimeError::StreamBufferExceeded { limit });
+                return Err(runtime_error(error));
+            }
+            out.push_str(&buf[written..m.start()]);
+            rep.replace_append(&caps, &mut out);
+            written = m.end();
+            last_end = it.0.last_end;
+            last_match = it.0.last_match;
+        }
+
+        if eof {
+            out.push_str(&buf[written..]);
+            writer.write_all(out.as_bytes())?;
+            return writer.flush();
+        }
+
+        // No match can start before the limit anymore, so continue searching from there
+        if limit > last_end {
+            let mut ix = limit;
+            while !buf.is_char_boundary(ix) {
+                ix -= 1;
+            }
+            last_end = ix;
+        }
+        let flush_to = last_end.min(buf.len()).max(written);
+        out.push_str(&buf[written..flush_to]);
+        written = flush_to;
+        writer.write_all(out.as_bytes())?;
+        out.clear();
+
+        // Discard text that is no longer needed, apart from the context for look-behind
+        let mut keep_from = last_end.min(buf.len());
+        for _ in 0..context {
+            if keep_from == 0 {
+                break;
+            }
+            keep_from = prev_codepoint_ix(&buf, keep_from);
+        }
+        if keep_from > 0 {
+            buf.drain(..keep_from);
+            written -= keep_from;
+            last_end -= keep_from;
+            last_match = last_match.and_then(|ix: usize| ix.checked_sub(keep_from));
+        }
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+fn runtime_error(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
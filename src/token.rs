@@ -0,0 +1,215 @@
+// Copyright 2016 The Fancy Regex Authors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in
+// all copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
+// THE SOFTWARE.
+
+//! Matching one of several patterns at a given position, e.g. for writing a lexer.
+
+use crate::analyze::analyze;
+use crate::compile::compile;
+use crate::parse::ExprTree;
+use crate::vm::{self, Prog};
+use crate::Expr;
+use crate::{Match, RegexOptions, Result, RunConfig};
+
+/// Which pattern wins if several patterns of a `TokenMatcher` match at a position.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MatchPolicy {
+    /// The pattern with the longest match wins. If several patterns have a match of the same
+    /// length, the one that comes first in the list wins.
+    LongestMatch,
+    /// The first pattern in the list that matches wins.
+    FirstMatch,
+}
+
+/// A set of patterns that are matched at a given position of the text, for example to split
+/// the input of a parser into tokens.
+///
+/// Unlike `Regex`, a `TokenMatcher` doesn't search for a match, matches have to start exactly at
+/// the given position. The text before the position is still used for look-behind and
+/// assertions like `\b`.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{MatchPolicy, TokenMatcher};
+/// let tokens = TokenMatcher::new(&["if", r"\w+", r"\d+", r"\s+"], MatchPolicy::LongestMatch).unwrap();
+///
+/// let (index, m) = tokens.match_at("if iffy", 0).unwrap().unwrap();
+/// assert_eq!((index, m.as_str()), (0, "if"));
+///
+/// let (index, m) = tokens.match_at("if iffy", 3).unwrap().unwrap();
+/// assert_eq!((index, m.as_str()), (1, "iffy"));
+///
+/// assert!(tokens.match_at("if iffy?", 7).unwrap().is_none());
+/// ```
+#[derive(Debug)]
+pub struct TokenMatcher {
+    progs: Vec<Prog>,
+    policy: MatchPolicy,
+    options: RegexOptions,
+    /// For every byte, the patterns (in order) that can match text starting with it
+    by_first_byte: Vec<Vec<usize>>,
+    /// Patterns that could also match at the end of the text
+    at_end: Vec<usize>,
+}
+
+impl TokenMatcher {
+    /// Compile the patterns, the index of a pattern in `patterns` is used to identify it in the
+    /// result of `match_at`.
+    pub fn new<I, S>(patterns: I, policy: MatchPolicy) -> Result<TokenMatcher>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut progs = Vec::new();
+        let mut by_first_byte = vec![Vec::new(); 256];
+        let mut at_end = Vec::new();
+        for (ix, pattern) in patterns.into_iter().enumerate() {
+            let raw_tree = Expr::parse_tree(pattern.as_ref())?;
+            // Group 0 for the match bounds, but no `.*?` prefix, so the match is anchored
+            let tree = ExprTree {
+                expr: Expr::Group(Box::new(raw_tree.expr)),
+                ..raw_tree
+            };
+            let info = analyze(&tree)?;
+            match info.first_bytes() {
+                Some(bytes) => {
+                    for b in bytes.iter() {
+                        by_first_byte[b].push(ix);
+                    }
+                }
+                None => {
+                    for candidates in &mut by_first_byte {
+                        candidates.push(ix);
+                    }
+                    at_end.push(ix);
+                }
+            }
+            progs.push(compile(&info)?);
+        }
+        Ok(TokenMatcher {
+            progs,
+            policy,
+            options: RegexOptions::default(),
+            by_first_byte,
+            at_end,
+        })
+    }
+
+    /// Returns the number of patterns.
+    pub fn len(&self) -> usize {
+        self.progs.len()
+    }
+
+    /// Returns true if there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.progs.is_empty()
+    }
+
+    /// Match the patterns at position `pos` of `text`.
+    ///
+    /// Returns the index of the winning pattern (see `MatchPolicy`) and its match, or `None` if
+    /// no pattern matches at the position.
+    pub fn match_at<'t>(&self, text: &'t str, pos: usize) -> Result<Option<(usize, Match<'t>)>> {
+        let candidates = match text.as_bytes().get(pos) {
+            Some(&b) => &self.by_first_byte[b as usize],
+            None => &self.at_end,
+        };
+        let mut best: Option<(usize, Match<'t>)> = None;
+        for &ix in candidates {
+            if let Some(saves) = vm::run(
+                &self.progs[ix],
+                text,
+                pos,
+                0,
+                &self.options,
+                &RunConfig::default(),
+            )? {
+                let m = Match::new(text, saves[0], saves[1]);
+                match self.policy {
+                    MatchPolicy::FirstMatch => return Ok(Some((ix, m))),
+                    MatchPolicy::LongestMatch => {
+                        if best.map_or(true, |(_, b)| m.end() > b.end()) {
+                            best = Some((ix, m));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_match() {
+        let tokens =
+            TokenMatcher::new(&["a", "ab", "a.", "abc?"], MatchPolicy::LongestMatch).unwrap();
+        let (ix, m) = tokens.match_at("xabc", 1).unwrap().unwrap();
+        assert_eq!((ix, m.start(), m.end()), (3, 1, 4));
+        let (ix, m) = tokens.match_at("xabd", 1).unwrap().unwrap();
+        assert_eq!((ix, m.start(), m.end()), (1, 1, 3));
+        assert_eq!(tokens.match_at("xabc", 0).unwrap(), None);
+    }
+
+    #[test]
+    fn first_match() {
+        let tokens = TokenMatcher::new(&["a", "ab", "a."], MatchPolicy::FirstMatch).unwrap();
+        let (ix, m) = tokens.match_at("ab", 0).unwrap().unwrap();
+        assert_eq!((ix, m.as_str()), (0, "a"));
+    }
+
+    #[test]
+    fn anchored() {
+        let tokens = TokenMatcher::new(&["b", r"\d+"], MatchPolicy::LongestMatch).unwrap();
+        assert_eq!(tokens.match_at("ab12", 0).unwrap(), None);
+        assert_eq!(tokens.match_at("ab12", 2).unwrap().unwrap().0, 1);
+    }
+
+    #[test]
+    fn fancy_patterns() {
+        let tokens = TokenMatcher::new(
+            &[r"(\w)\1", r"(?<=\.)\w+", r"\w+(?=\()"],
+            MatchPolicy::FirstMatch,
+        )
+        .unwrap();
+        let text = "aab.cd ef(";
+        assert_eq!(tokens.match_at(text, 0).unwrap().unwrap().0, 0);
+        assert_eq!(tokens.match_at(text, 1).unwrap(), None);
+        let (ix, m) = tokens.match_at(text, 4).unwrap().unwrap();
+        assert_eq!((ix, m.as_str()), (1, "cd"));
+        let (ix, m) = tokens.match_at(text, 7).unwrap().unwrap();
+        assert_eq!((ix, m.as_str()), (2, "ef"));
+    }
+
+    #[test]
+    fn empty_match_at_end() {
+        let tokens = TokenMatcher::new(&["x", "$"], MatchPolicy::LongestMatch).unwrap();
+        let (ix, m) = tokens.match_at("ab", 2).unwrap().unwrap();
+        assert_eq!((ix, m.start(), m.end()), (1, 2, 2));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(TokenMatcher::new(&["a", "("], MatchPolicy::FirstMatch).is_err());
+    }
+}
@@ -69,22 +69,58 @@
 //! 5. We continue with the previously saved thread at PC 4 and IX 0 (backtracking)
 //! 6. Both `Lit("a")` and `Lit("c")` match and we reach `End` -> successful match (index 0 to 2)
 
-use regex::Regex;
+use smallvec::{smallvec, SmallVec};
+use std::cmp::max;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::mem::size_of;
+use std::ops::{Index, Range};
+use std::str;
+#[cfg(feature = "compiler")]
+use std::sync::Arc;
+use std::time::Instant;
+use std::u32;
 use std::usize;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::char::{canonical_combining_class, decompose_canonical};
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+use unicode_script::ScriptExtension;
 
+use crate::case_fold::simple_fold;
+#[cfg(feature = "compiler")]
+use crate::compile::LazyRegex;
+use crate::config::{RunConfig, TraceSink};
 use crate::prev_codepoint_ix;
 use crate::Error;
 use crate::Result;
-use crate::{codepoint_len, RegexOptions};
+use crate::RuntimeError;
+use crate::{codepoint_len, RegexOptions, RepeatedGroups};
 
 const OPTION_TRACE: u32 = 1;
+/// Set for running a program that is one-pass from the start instruction, see
+/// `Prog::one_pass_start`
+const OPTION_ONE_PASS: u32 = 1 << 1;
+/// Only accept a non-empty match, i.e. fail at `End` if the position is still the start position
+/// of the run. Only meaningful for runs that match at the start position.
+pub(crate) const OPTION_NOT_EMPTY: u32 = 1 << 2;
 
-// TODO: make configurable
-const MAX_STACK: usize = 1_000_000;
+/// Number of backtrack branches in each chunk of `BranchStack`
+const BRANCH_CHUNK_SIZE: usize = 4096;
+
+/// Programs with at most this many slots copy all of them when pushing a backtrack branch instead
+/// of keeping track of the old values of the ones that change, see `State::snapshot`
+const SNAPSHOT_MAX_SAVES: usize = 8;
+
+/// Number of steps between checks of the deadline, which is more expensive than counting
+const DEADLINE_CHECK_INTERVAL: usize = 256;
+
+/// The length of U+FFFD in UTF-8, which replaces invalid UTF-8 in byte strings
+const REPLACEMENT_LEN: usize = 3;
 
 /// Instruction of the VM.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Insn {
     /// Successful end of program
     End,
@@ -92,8 +128,14 @@ pub enum Insn {
     Any,
     /// Match any character (not including newline)
     AnyNoNL,
+    /// Match any character (including newline), or a single byte when searching a byte string,
+    /// which can be part of a character or of invalid UTF-8
+    AnyByte,
     /// Match the literal string at the current index
     Lit(String), // should be cow?
+    /// Match the literal string at the current index ignoring case. The string is stored with its
+    /// characters replaced by their simple case folding, see `case_fold`.
+    LitCasei(String),
     /// Split execution into two threads. The two fields are positions of instructions. Execution
     /// first tries the first thread. If that fails, the second position is tried.
     Split(usize, usize),
@@ -103,8 +145,40 @@ pub enum Insn {
     Save(usize),
     /// Save `0` into the specified slot
     Save0(usize),
+    /// Save a value into the specified slot, e.g. to record which alternative was taken
+    SaveValue {
+        /// The slot to save into
+        slot: usize,
+        /// The value to save
+        value: usize,
+    },
+    /// Save the value of another slot into the specified slot, e.g. the start of a group that is
+    /// only set when the group ends
+    SaveFrom {
+        /// The slot to save into
+        slot: usize,
+        /// The slot with the value
+        from: usize,
+    },
+    /// Reset the saves of the groups from `start` to `end` (exclusive) to unmatched, e.g. at the
+    /// start of each iteration of a repeat for `RepeatedGroups::JavaScript`
+    ResetGroups {
+        /// The first group to reset, by its index among the groups that have slots
+        start: usize,
+        /// The group after the last one to reset
+        end: usize,
+    },
     /// Set the string index to the value that was saved in the specified slot
     Restore(usize),
+    /// Record the positions of a group that just matched in the capture history, as another
+    /// iteration of the group, see `RegexBuilder::capture_history`
+    RecordGroup {
+        /// The first of the two slots of the group
+        slot: usize,
+        /// The slot with the length of the history, which undoes the iterations that were
+        /// recorded after a branch when backtracking to it
+        history: usize,
+    },
     /// Repeat greedily (match as much as possible)
     RepeatGr {
         /// Minimum number of matches
@@ -153,18 +227,50 @@ pub enum Insn {
     FailNegativeLookAround,
     /// Set IX back by the specified number of characters
     GoBack(usize),
+    /// Set IX back by one of the specified numbers of characters, trying them in the order given
+    /// (e.g. shortest or longest first). If the rest of the program fails for one of them, it
+    /// backtracks to the next one. Numbers that would go back past the start of the text are
+    /// skipped.
+    GoBackAny(Vec<usize>),
+    /// Set IX back by what `AnyByte` matches: one character, or one byte when searching a byte
+    /// string
+    GoBackByte,
     /// Back reference to a group number to check
     Backref(usize),
-    /// Begin of atomic group
+    /// Back reference to a group number to check, ignoring case
+    BackrefCasei(usize),
+    /// Back reference to a group number to check, matching canonically equivalent text
+    #[cfg(feature = "unicode-normalization")]
+    BackrefCanonical {
+        /// The slot of the start of the group
+        slot: usize,
+        /// Whether to ignore case as well
+        casei: bool,
+    },
+    /// Begin of atomic group, checkpoints the backtrack stack
     BeginAtomic,
-    /// End of atomic group
+    /// End of atomic group, discards the backtrack branches since the checkpoint while keeping
+    /// the values of the slots from before the group for restoring them on backtracking
     EndAtomic,
+    /// Check that the text between the index saved in the specified slot and IX is a script run,
+    /// i.e. that all its characters are from the same Unicode script
+    ScriptRun(usize),
     /// Delegate matching to the regex crate for a fixed size
-    DelegateSized(Box<Regex>, usize),
+    #[cfg(feature = "compiler")]
+    DelegateSized {
+        /// The regex, shared with other instructions that use the same regex
+        inner: Arc<LazyRegex>,
+        /// The same regex but matching an additional character on the left, for regexes that
+        /// "look left", see `Delegate`
+        inner1: Option<Arc<LazyRegex>>,
+        /// The number of characters of a match
+        size: usize,
+    },
     /// Delegate matching to the regex crate
+    #[cfg(feature = "compiler")]
     Delegate {
-        /// The regex
-        inner: Box<Regex>,
+        /// The regex, shared with other instructions that use the same regex
+        inner: Arc<LazyRegex>,
         /// The same regex but matching an additional character on the left.
         ///
         /// E.g. if `inner` is `^\b`, `inner1` is `^(?s:.)\b`. Why do we need this? Because `\b`
@@ -174,26 +280,187 @@ pub enum Insn {
         /// against `xy`, which does not match.
         ///
         /// We only need this for regexes that "look left", i.e. need to know what the previous
-        /// character was.
-        inner1: Option<Box<Regex>>,
-        /// The first group number that this regex captures (if it contains groups)
-        start_group: usize,
-        /// The last group number
-        end_group: usize,
+        /// character was. This includes `^` and `\A`, which must not match at the start of the
+        /// slice unless it's the start of the text. The slice always extends to the end of the
+        /// text, so `$` and `\z` don't need anything like this.
+        inner1: Option<Arc<LazyRegex>>,
+        /// The groups of the regex whose positions are saved, as pairs of the index of the group
+        /// in the regex and the first of its two slots. If empty, it's only matched.
+        groups: Vec<(usize, usize)>,
     },
 }
 
+impl Insn {
+    /// Whether the instruction can push a backtrack branch, or continue at another instruction
+    /// than the next one.
+    fn may_branch(&self) -> bool {
+        matches!(
+            self,
+            Insn::Split(..)
+                | Insn::Jmp(_)
+                | Insn::RepeatGr { .. }
+                | Insn::RepeatNg { .. }
+                | Insn::RepeatEpsilonGr { .. }
+                | Insn::RepeatEpsilonNg { .. }
+                | Insn::GoBackAny(_)
+                | Insn::FailNegativeLookAround
+        )
+    }
+
+    /// Whether execution can continue at the next instruction after this one.
+    pub(crate) fn falls_through(&self) -> bool {
+        !matches!(
+            self,
+            Insn::End | Insn::Jmp(_) | Insn::Split(..) | Insn::FailNegativeLookAround
+        )
+    }
+
+    /// The instructions that execution can continue at after this one, other than the next one.
+    pub(crate) fn targets(&self) -> impl Iterator<Item = usize> {
+        let (first, second) = match *self {
+            Insn::Split(x, y) => (Some(x), Some(y)),
+            Insn::Jmp(target) => (Some(target), None),
+            Insn::RepeatGr { next, .. }
+            | Insn::RepeatNg { next, .. }
+            | Insn::RepeatEpsilonGr { next, .. }
+            | Insn::RepeatEpsilonNg { next, .. } => (Some(next), None),
+            _ => (None, None),
+        };
+        first.into_iter().chain(second)
+    }
+
+    /// Whether the instruction reads or writes `slot`, other than as part of the explicit stack.
+    fn uses_slot(&self, slot: usize) -> bool {
+        match *self {
+            Insn::Save(s)
+            | Insn::Save0(s)
+            | Insn::SaveValue { slot: s, .. }
+            | Insn::Restore(s)
+            | Insn::ScriptRun(s) => s == slot,
+            Insn::SaveFrom { slot: s, from } => s == slot || from == slot,
+            Insn::Backref(s) | Insn::BackrefCasei(s) => s == slot || s + 1 == slot,
+            #[cfg(feature = "unicode-normalization")]
+            Insn::BackrefCanonical { slot: s, .. } => s == slot || s + 1 == slot,
+            Insn::ResetGroups { start, end } => start * 2 <= slot && slot < end * 2,
+            Insn::RecordGroup { slot: s, history } => s == slot || s + 1 == slot || history == slot,
+            Insn::RepeatGr { repeat, .. } | Insn::RepeatNg { repeat, .. } => repeat == slot,
+            Insn::RepeatEpsilonGr { repeat, check, .. }
+            | Insn::RepeatEpsilonNg { repeat, check, .. } => repeat == slot || check == slot,
+            #[cfg(feature = "compiler")]
+            Insn::Delegate { ref groups, .. } => {
+                groups.iter().any(|&(_, s)| s == slot || s + 1 == slot)
+            }
+            _ => false,
+        }
+    }
+
+    /// The number of slots that the instruction needs, i.e. one more than the highest slot that
+    /// it reads or writes.
+    pub(crate) fn slots_needed(&self) -> usize {
+        match *self {
+            Insn::Save(s)
+            | Insn::Save0(s)
+            | Insn::SaveValue { slot: s, .. }
+            | Insn::Restore(s)
+            | Insn::ScriptRun(s)
+            | Insn::RepeatGr { repeat: s, .. }
+            | Insn::RepeatNg { repeat: s, .. } => s.saturating_add(1),
+            Insn::SaveFrom { slot, from } => max(slot, from).saturating_add(1),
+            Insn::RepeatEpsilonGr { repeat, check, .. }
+            | Insn::RepeatEpsilonNg { repeat, check, .. } => max(repeat, check).saturating_add(1),
+            Insn::Backref(s) | Insn::BackrefCasei(s) => s.saturating_add(2),
+            #[cfg(feature = "unicode-normalization")]
+            Insn::BackrefCanonical { slot, .. } => slot.saturating_add(2),
+            Insn::ResetGroups { end, .. } => end.saturating_mul(2),
+            Insn::RecordGroup { slot, history } => {
+                max(slot.saturating_add(2), history.saturating_add(1))
+            }
+            #[cfg(feature = "compiler")]
+            Insn::Delegate { ref groups, .. } => groups
+                .iter()
+                .map(|&(_, s)| s.saturating_add(2))
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
 /// Sequence of instructions for the VM to execute.
 #[derive(Debug, Clone)]
 pub struct Prog {
     /// Instructions of the program
     pub body: Vec<Insn>,
-    n_saves: usize,
+    pub(crate) n_saves: usize,
+    /// The first instruction from which on the program is one-pass: there are no instructions
+    /// after it that branch or jump, so execution from there never backtracks. Failing means
+    /// there's no match, and old values of saves don't need to be kept for restoring them.
+    pub(crate) one_pass_start: usize,
+    /// The instruction that saves the start of the whole match into slot 0, if the program
+    /// doesn't need the old values of slots 0 and 1 on backtracking. That's the case if they're
+    /// not used other than by that instruction and a save of the end into slot 1 right before
+    /// the only `End`, and no instruction continues from before it to after it or the other way
+    /// around: a run that starts at or before it saves the start again after backtracking to a
+    /// branch from before it, and the end is always saved right before the match is complete.
+    pub(crate) whole_match_save_pc: Option<usize>,
+    /// The slot with the length of the capture history, if the program records one, see
+    /// `Insn::RecordGroup`
+    pub(crate) history_slot: Option<usize>,
+    /// The span in the pattern of the expression that each instruction was compiled from, by
+    /// index of the instruction. `None` for instructions that aren't for a part of the pattern,
+    /// e.g. the loop for searching, and where it couldn't be found.
+    pub spans: Vec<Option<Range<usize>>>,
 }
 
 impl Prog {
     pub(crate) fn new(body: Vec<Insn>, n_saves: usize) -> Prog {
-        Prog { body, n_saves }
+        let one_pass_start = body
+            .iter()
+            .rposition(Insn::may_branch)
+            .map_or(0, |pc| pc + 1);
+        let whole_match_save_pc = whole_match_save_pc(&body, n_saves);
+        let history_slot = body.iter().find_map(|insn| match *insn {
+            Insn::RecordGroup { history, .. } => Some(history),
+            _ => None,
+        });
+        Prog {
+            body,
+            n_saves,
+            one_pass_start,
+            whole_match_save_pc,
+            history_slot,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Compile the regexes of the delegate instructions that haven't been compiled yet.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn compile_delegates(&self) -> Result<()> {
+        for (_, re) in self.delegates() {
+            re.get()?;
+        }
+        Ok(())
+    }
+
+    /// The regexes that instructions delegate to, with the index of the instruction.
+    #[cfg(feature = "compiler")]
+    pub(crate) fn delegates(&self) -> impl Iterator<Item = (usize, &Arc<LazyRegex>)> {
+        self.body.iter().enumerate().flat_map(|(pc, insn)| {
+            let (inner, inner1) = match *insn {
+                Insn::Delegate {
+                    ref inner,
+                    ref inner1,
+                    ..
+                }
+                | Insn::DelegateSized {
+                    ref inner,
+                    ref inner1,
+                    ..
+                } => (Some(inner), inner1.as_ref()),
+                _ => (None, None),
+            };
+            inner.into_iter().chain(inner1).map(move |re| (pc, re))
+        })
     }
 
     #[doc(hidden)]
@@ -204,34 +471,254 @@ impl Prog {
     }
 }
 
+/// The type of the values in slots and of the positions in backtrack branches. Texts shorter
+/// than 4 GiB use `u32`, which halves the memory that copying saves around takes.
+trait SlotValue: Copy + fmt::Debug {
+    /// The value of a slot that is not set, corresponding to `usize::MAX`
+    const UNSET: Self;
+
+    /// Convert a value, which must be less than the length of the text or `usize::MAX`.
+    fn from_usize(value: usize) -> Self;
+
+    fn to_usize(self) -> usize;
+}
+
+impl SlotValue for usize {
+    const UNSET: usize = usize::MAX;
+
+    #[inline]
+    fn from_usize(value: usize) -> usize {
+        value
+    }
+
+    #[inline]
+    fn to_usize(self) -> usize {
+        self
+    }
+}
+
+impl SlotValue for u32 {
+    const UNSET: u32 = u32::MAX;
+
+    #[inline]
+    fn from_usize(value: usize) -> u32 {
+        if value >= u32::MAX as usize {
+            u32::MAX
+        } else {
+            value as u32
+        }
+    }
+
+    #[inline]
+    fn to_usize(self) -> usize {
+        if self == u32::MAX {
+            usize::MAX
+        } else {
+            self as usize
+        }
+    }
+}
+
+/// Whether the positions in a text of length `len` fit into `u32` slots.
+fn fits_u32(len: usize) -> bool {
+    len < u32::MAX as usize
+}
+
 #[derive(Debug)]
-struct Branch {
-    pc: usize,
-    ix: usize,
-    nsave: usize,
+struct Branch<V> {
+    pc: u32,
+    ix: V,
+    nsave: u32,
+}
+
+/// Find the instruction for `Prog::whole_match_save_pc`.
+fn whole_match_save_pc(body: &[Insn], n_saves: usize) -> Option<usize> {
+    // Otherwise the explicit stack could use the slots
+    if n_saves < 2 {
+        return None;
+    }
+    let mut start_pc = None;
+    let mut end_pc = None;
+    for (pc, insn) in body.iter().enumerate() {
+        match *insn {
+            Insn::Save(0) if start_pc.is_none() => start_pc = Some(pc),
+            Insn::Save(1) if end_pc.is_none() => end_pc = Some(pc),
+            _ if insn.uses_slot(0) || insn.uses_slot(1) => return None,
+            _ => {}
+        }
+    }
+    let start_pc = start_pc?;
+    let end_pc = end_pc?;
+    let single_end = body
+        .iter()
+        .enumerate()
+        .all(|(pc, insn)| !matches!(insn, Insn::End) || pc == end_pc + 1);
+    if !matches!(body.get(end_pc + 1), Some(Insn::End)) || !single_end {
+        return None;
+    }
+    // All paths from before the instruction go through it, and none come back
+    let skips = body.iter().enumerate().any(|(pc, insn)| {
+        insn.targets()
+            .any(|target| (pc < start_pc) != (target <= start_pc))
+    });
+    if skips {
+        None
+    } else {
+        Some(start_pc)
+    }
+}
+
+/// Stack of backtrack branches, stored in chunks of `BRANCH_CHUNK_SIZE` branches.
+///
+/// A deep stack grows by allocating another chunk instead of reallocating and copying all the
+/// branches, so its size is only bounded by the memory limit. The bottom chunk grows like a
+/// vector and has room for a few branches inline, so shallow stacks don't need to allocate.
+struct BranchStack<V> {
+    /// The bottom of the stack, full if there are other chunks
+    first: SmallVec<[Branch<V>; 8]>,
+    /// The chunks above `first`. All are full except the last one, which is never empty.
+    rest: Vec<Vec<Branch<V>>>,
+    /// An emptied chunk, kept so that pushing and popping at a chunk boundary doesn't allocate
+    spare: Option<Vec<Branch<V>>>,
+}
+
+impl<V> BranchStack<V> {
+    fn new() -> BranchStack<V> {
+        BranchStack {
+            first: SmallVec::new(),
+            rest: Vec::new(),
+            spare: None,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self.rest.last() {
+            Some(chunk) => BRANCH_CHUNK_SIZE * self.rest.len() + chunk.len(),
+            None => self.first.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.first.is_empty()
+    }
+
+    fn push(&mut self, branch: Branch<V>) {
+        if self.first.len() < BRANCH_CHUNK_SIZE {
+            self.first.push(branch);
+            return;
+        }
+        match self.rest.last_mut() {
+            Some(chunk) if chunk.len() < BRANCH_CHUNK_SIZE => chunk.push(branch),
+            _ => {
+                let mut chunk = self
+                    .spare
+                    .take()
+                    .unwrap_or_else(|| Vec::with_capacity(BRANCH_CHUNK_SIZE));
+                chunk.push(branch);
+                self.rest.push(chunk);
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<Branch<V>> {
+        match self.rest.last_mut() {
+            Some(chunk) => {
+                let branch = chunk.pop();
+                if chunk.is_empty() {
+                    self.spare = self.rest.pop();
+                }
+                branch
+            }
+            None => self.first.pop(),
+        }
+    }
+
+    /// Only keep the bottom `len` branches.
+    fn truncate(&mut self, len: usize) {
+        while !self.rest.is_empty() {
+            let below = BRANCH_CHUNK_SIZE * self.rest.len();
+            let chunk = self.rest.last_mut().unwrap();
+            if len > below {
+                chunk.truncate(len - below);
+                return;
+            }
+            chunk.clear();
+            self.spare = self.rest.pop();
+        }
+        self.first.truncate(len);
+    }
+
+    fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// The branches from the bottom to the top of the stack.
+    fn iter(&self) -> impl DoubleEndedIterator<Item = &Branch<V>> {
+        self.first
+            .iter()
+            .chain(self.rest.iter().flat_map(|chunk| chunk.iter()))
+    }
+}
+
+impl<V> Index<usize> for BranchStack<V> {
+    type Output = Branch<V>;
+
+    fn index(&self, index: usize) -> &Branch<V> {
+        if index < BRANCH_CHUNK_SIZE {
+            &self.first[index]
+        } else {
+            &self.rest[index / BRANCH_CHUNK_SIZE - 1][index % BRANCH_CHUNK_SIZE]
+        }
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for BranchStack<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
 }
 
 #[derive(Debug)]
-struct Save {
-    slot: usize,
-    value: usize,
+struct Save<V> {
+    slot: u32,
+    value: V,
 }
 
-struct State {
+// The vectors of the state have room for a few elements inline, so that matching simple patterns
+// on short texts doesn't need to allocate.
+struct State<V> {
     /// Saved values indexed by slot. Mostly indices to s, but can be repeat values etc.
     /// Always contains the saves of the current state.
-    saves: Vec<usize>,
+    saves: SmallVec<[V; 8]>,
     /// Stack of backtrack branches.
-    stack: Vec<Branch>,
+    stack: BranchStack<V>,
     /// Old saves (slot, value)
-    oldsave: Vec<Save>,
+    oldsave: SmallVec<[Save<V>; 8]>,
     /// Number of saves at the end of `oldsave` that need to be restored to `saves` on pop
     nsave: usize,
     explicit_sp: usize,
+    /// The slots below this are not restored on backtracking, see `Prog::whole_match_save_pc`
+    untracked_slots: usize,
+    /// Whether pushing a branch copies all the saves to `snapshots` (and its `nsave` is their
+    /// number), instead of `save` keeping the old values of the slots that change in `oldsave`.
+    /// With few slots, copying them is cheaper than checking which were already saved.
+    snapshot: bool,
+    /// The saves for each branch on the stack if `snapshot` is set, from the bottom to the top
+    snapshots: SmallVec<[V; 16]>,
+    /// The iterations of groups recorded by `Insn::RecordGroup` as `(slot, start, end)`. Only
+    /// as many as the value of the history slot are from the current state, the ones after them
+    /// are left over from branches that failed.
+    history: Vec<GroupIteration>,
     /// Maximum size of the stack. If the size would be exceeded during execution, a `StackOverflow`
     /// error is raised.
     max_stack: usize,
+    /// Maximum number of bytes used by `saves`, `stack` and `oldsave`. If it would be exceeded
+    /// when pushing a backtrack branch or a value on the explicit stack, a `MemoryLimitExceeded`
+    /// error is raised.
+    max_memory: usize,
     options: u32,
+    /// Where trace output goes if `OPTION_TRACE` is set, standard output if `None`
+    trace_sink: Option<TraceSink>,
 }
 
 // Each element in the stack conceptually represents the entire state
@@ -242,83 +729,191 @@ struct State {
 // top `nsave` elements in `oldsave` represent the delta from the
 // current machine state to the top of stack.
 
-impl State {
-    fn new(n_saves: usize, max_stack: usize, options: u32) -> State {
+impl<V: SlotValue> State<V> {
+    fn new(n_saves: usize, max_stack: usize, max_memory: usize, options: u32) -> State<V> {
         State {
-            saves: vec![usize::MAX; n_saves],
-            stack: Vec::new(),
-            oldsave: Vec::new(),
+            saves: smallvec![V::UNSET; n_saves],
+            stack: BranchStack::new(),
+            oldsave: SmallVec::new(),
             nsave: 0,
             explicit_sp: n_saves,
+            untracked_slots: 0,
+            snapshot: false,
+            snapshots: SmallVec::new(),
+            history: Vec::new(),
             max_stack,
+            max_memory,
             options,
+            trace_sink: None,
+        }
+    }
+
+    /// Prepare the state for a run of `prog` from `start_pc`, reusing the memory it already has.
+    fn prepare(
+        &mut self,
+        prog: &Prog,
+        start_pc: usize,
+        option_flags: u32,
+        options: &RegexOptions,
+        config: &RunConfig,
+    ) {
+        let mut option_flags = if start_pc >= prog.one_pass_start {
+            option_flags | OPTION_ONE_PASS
+        } else {
+            option_flags
+        };
+        if config.trace_sink.is_some() {
+            option_flags |= OPTION_TRACE;
+        }
+        self.saves.clear();
+        self.saves.resize(prog.n_saves, V::UNSET);
+        self.stack.clear();
+        self.oldsave.clear();
+        self.snapshots.clear();
+        self.history.clear();
+        self.nsave = 0;
+        self.explicit_sp = prog.n_saves;
+        self.snapshot = prog.n_saves <= SNAPSHOT_MAX_SAVES;
+        self.untracked_slots = match prog.whole_match_save_pc {
+            Some(pc) if start_pc <= pc => 2,
+            _ => 0,
+        };
+        self.max_stack = config.max_stack;
+        self.max_memory = options.memory_limit;
+        self.options = option_flags;
+        self.trace_sink = config.trace_sink.clone();
+    }
+
+    fn trace(&self, args: fmt::Arguments<'_>) {
+        match self.trace_sink {
+            Some(ref sink) => sink(&args.to_string()),
+            None => println!("{}", args),
+        }
+    }
+
+    /// Approximate number of bytes used by the state.
+    ///
+    /// Saves are only added to `oldsave` once per slot and branch, so checking this whenever the
+    /// stack or the number of slots grows is enough to bound the total.
+    fn memory(&self) -> usize {
+        self.saves.len() * size_of::<V>()
+            + self.stack.len() * size_of::<Branch<V>>()
+            + self.oldsave.len() * size_of::<Save<V>>()
+            + self.snapshots.len() * size_of::<V>()
+            + self.history.len() * size_of::<GroupIteration>()
+    }
+
+    fn check_memory(&self) -> Result<()> {
+        if self.memory() > self.max_memory {
+            Err(Error::RuntimeError(RuntimeError::MemoryLimitExceeded))
+        } else {
+            Ok(())
         }
     }
 
     // push a backtrack branch
     fn push(&mut self, pc: usize, ix: usize) -> Result<()> {
+        debug_assert!(
+            self.options & OPTION_ONE_PASS == 0,
+            "push in one-pass program"
+        );
+        self.check_memory()?;
         if self.stack.len() < self.max_stack {
-            let nsave = self.nsave;
-            self.stack.push(Branch { pc, ix, nsave });
+            if self.snapshot {
+                self.snapshots.extend_from_slice(&self.saves);
+                self.nsave = self.saves.len();
+            }
+            self.stack.push(Branch {
+                pc: pc as u32,
+                ix: V::from_usize(ix),
+                nsave: self.nsave as u32,
+            });
             self.nsave = 0;
             self.trace_stack("push");
             Ok(())
         } else {
-            Err(Error::StackOverflow)
+            Err(Error::RuntimeError(RuntimeError::StackOverflow))
         }
     }
 
-    // pop a backtrack branch
-    fn pop(&mut self) -> (usize, usize) {
+    // pop a backtrack branch, or return `None` if there is none (which is a bug in the program)
+    fn pop(&mut self) -> Option<(usize, usize)> {
+        if self.stack.is_empty() || self.oldsave.len() < self.nsave {
+            return None;
+        }
         for _ in 0..self.nsave {
-            let Save { slot, value } = self.oldsave.pop().unwrap();
-            self.saves[slot] = value;
+            let Save { slot, value } = self.oldsave.pop()?;
+            self.saves[slot as usize] = value;
+        }
+        let Branch { pc, ix, nsave } = self.stack.pop()?;
+        if self.snapshot {
+            let start = self.snapshots.len().checked_sub(nsave as usize)?;
+            self.saves.clear();
+            self.saves.extend_from_slice(&self.snapshots[start..]);
+            self.snapshots.truncate(start);
+            self.trace_stack("pop");
+            return Some((pc as usize, ix.to_usize()));
         }
-        let Branch { pc, ix, nsave } = self.stack.pop().unwrap();
-        self.nsave = nsave;
+        self.nsave = nsave as usize;
         self.trace_stack("pop");
-        (pc, ix)
+        Some((pc as usize, ix.to_usize()))
     }
 
     fn save(&mut self, slot: usize, val: usize) {
+        let val = V::from_usize(val);
+        if self.options & OPTION_ONE_PASS != 0 || slot < self.untracked_slots {
+            // Never backtracks, or the old value is never read again, so it's not needed
+            self.saves[slot] = val;
+            return;
+        }
+        if self.snapshot {
+            // The old value is in the snapshot of the branch
+            self.saves[slot] = val;
+            if self.options & OPTION_TRACE != 0 {
+                self.trace(format_args!("saves: {:?}", self.saves));
+            }
+            return;
+        }
         for i in 0..self.nsave {
             // could avoid this iteration with some overhead; worth it?
-            if self.oldsave[self.oldsave.len() - i - 1].slot == slot {
+            if self.oldsave[self.oldsave.len() - i - 1].slot as usize == slot {
                 // already saved, just update
                 self.saves[slot] = val;
                 return;
             }
         }
         self.oldsave.push(Save {
-            slot,
+            slot: slot as u32,
             value: self.saves[slot],
         });
         self.nsave += 1;
         self.saves[slot] = val;
 
         if self.options & OPTION_TRACE != 0 {
-            println!("saves: {:?}", self.saves);
+            self.trace(format_args!("saves: {:?}", self.saves));
         }
     }
 
     fn get(&self, slot: usize) -> usize {
-        self.saves[slot]
+        self.saves[slot].to_usize()
     }
 
     // push a value onto the explicit stack; note: the entire contents of
     // the explicit stack is saved and restored on backtrack.
-    fn stack_push(&mut self, val: usize) {
+    fn stack_push(&mut self, val: usize) -> Result<()> {
+        self.check_memory()?;
         if self.saves.len() == self.explicit_sp {
-            self.saves.push(self.explicit_sp + 1);
+            self.saves.push(V::from_usize(self.explicit_sp + 1));
         }
         let explicit_sp = self.explicit_sp;
         let sp = self.get(explicit_sp);
         if self.saves.len() == sp {
-            self.saves.push(val);
+            self.saves.push(V::from_usize(val));
         } else {
             self.save(sp, val);
         }
         self.save(explicit_sp, sp + 1);
+        Ok(())
     }
 
     // pop a value from the explicit stack
@@ -330,6 +925,53 @@ impl State {
         result
     }
 
+    /// Enter an atomic group: checkpoint the number of backtrack branches on the explicit stack,
+    /// so that the ones pushed inside the group can be discarded at its end.
+    fn begin_atomic(&mut self) -> Result<()> {
+        let count = self.backtrack_count();
+        self.stack_push(count)
+    }
+
+    /// Leave an atomic group: discard the backtrack branches that were pushed inside it.
+    ///
+    /// The slots keep the values from inside the group, e.g. its captures. For each slot that was
+    /// changed inside the group, the value from before the group is kept as the one to restore,
+    /// so that backtracking to a branch from before the group rolls back all its changes, like
+    /// in PCRE. Changes from alternatives inside the group that failed were already rolled back.
+    fn end_atomic(&mut self) {
+        let count = self.stack_pop();
+        self.backtrack_cut(count);
+    }
+
+    /// Whether there's a checkpoint of an atomic group on the explicit stack.
+    fn in_atomic(&self) -> bool {
+        self.saves.len() > self.explicit_sp && self.get(self.explicit_sp) > self.explicit_sp + 1
+    }
+
+    /// Record the positions in `slot` and `slot + 1` in the capture history, dropping the
+    /// iterations after the length in the `history` slot, which were recorded by branches that
+    /// failed.
+    fn record_group(&mut self, slot: usize, history: usize) -> Result<()> {
+        let len = match self.get(history) {
+            usize::MAX => 0,
+            len => len,
+        };
+        self.history.truncate(len);
+        self.history
+            .push((slot, self.get(slot), self.get(slot + 1)));
+        self.save(history, self.history.len());
+        self.check_memory()
+    }
+
+    /// The iterations of groups recorded in the capture history of the current state.
+    fn recorded_history(&self, history: usize) -> &[GroupIteration] {
+        let len = match self.get(history) {
+            usize::MAX => 0,
+            len => len,
+        };
+        &self.history[..len]
+    }
+
     /// Get the current number of backtrack branches
     fn backtrack_count(&self) -> usize {
         self.stack.len()
@@ -347,13 +989,24 @@ impl State {
             // no backtrack branches to discard, all good
             return;
         }
+        if self.snapshot {
+            // The snapshots of the remaining branches are from before the discarded ones
+            let discarded = (count..self.stack.len())
+                .map(|i| self.stack[i].nsave as usize)
+                .sum::<usize>();
+            let len = self.snapshots.len() - discarded;
+            self.snapshots.truncate(len);
+            self.stack.truncate(count);
+            return;
+        }
         // start and end indexes of old saves for the branch we're cutting to
         let (oldsave_start, oldsave_end) = {
-            let mut end = self.oldsave.len() - self.nsave;
-            for &Branch { nsave, .. } in &self.stack[count + 1..] {
-                end -= nsave;
-            }
-            let start = end - self.stack[count].nsave;
+            let end = self.oldsave.len()
+                - self.nsave
+                - (count + 1..self.stack.len())
+                    .map(|i| self.stack[i].nsave as usize)
+                    .sum::<usize>();
+            let start = end - self.stack[count].nsave as usize;
             (start, end)
         };
         let mut saved = BTreeSet::new();
@@ -381,87 +1034,645 @@ impl State {
     #[inline]
     fn trace_stack(&self, operation: &str) {
         if self.options & OPTION_TRACE != 0 {
-            println!("stack after {}: {:?}", operation, self.stack);
+            self.trace(format_args!("stack after {}: {:?}", operation, self.stack));
         }
     }
 }
 
+fn internal_bug(pc: usize, reason: &str) -> Error {
+    Error::RuntimeError(RuntimeError::InternalBug {
+        pc,
+        reason: reason.to_string(),
+    })
+}
+
+/// Returns the index that is `count` characters before `ix`, or `None` if there aren't enough.
+fn go_back(s: &str, mut ix: usize, count: usize) -> Option<usize> {
+    for _ in 0..count {
+        if ix == 0 {
+            return None;
+        }
+        ix = prev_codepoint_ix(s, ix);
+    }
+    Some(ix)
+}
+
 fn codepoint_len_at(s: &str, ix: usize) -> usize {
     codepoint_len(s.as_bytes()[ix])
 }
 
+/// Returns the index after the byte at `ix` of a byte string, whose invalid UTF-8 was replaced
+/// by U+FFFD at the positions in `replacements` (see `RunConfig::byte_string`). The positions
+/// inside a replacement stand for the bytes it replaces after the first one.
+fn next_byte(replacements: &[(usize, usize)], ix: usize) -> usize {
+    let (pos, len) = match replacements.binary_search_by_key(&ix, |&(pos, _)| pos) {
+        Ok(i) => replacements[i],
+        Err(0) => return ix + 1,
+        Err(i) => replacements[i - 1],
+    };
+    if ix >= pos + REPLACEMENT_LEN || ix + 1 < pos + len {
+        ix + 1
+    } else {
+        pos + REPLACEMENT_LEN
+    }
+}
+
+/// Returns the index before the byte that ends at `ix` of a byte string, the reverse of
+/// `next_byte`. `ix` must not be 0.
+fn prev_byte(replacements: &[(usize, usize)], ix: usize) -> usize {
+    let (pos, len) = match replacements.binary_search_by_key(&(ix - 1), |&(pos, _)| pos) {
+        Ok(i) => replacements[i],
+        Err(0) => return ix - 1,
+        Err(i) => replacements[i - 1],
+    };
+    if ix == pos + REPLACEMENT_LEN {
+        pos + len - 1
+    } else {
+        ix - 1
+    }
+}
+
 #[inline]
-fn matches_literal(s: &str, ix: usize, end: usize, literal: &str) -> bool {
+fn matches_literal(s: &str, ix: usize, end: usize, literal: &[u8]) -> bool {
     // Compare as bytes because the literal might be a single byte char whereas ix
     // points to a multibyte char. Comparing with str would result in an error like
     // "byte index N is not a char boundary".
-    end <= s.len() && &s.as_bytes()[ix..end] == literal.as_bytes()
+    end <= s.len() && &s.as_bytes()[ix..end] == literal
+}
+
+/// Returns the end of the text at `ix` that is equal to `literal` ignoring case with simple case
+/// folding, if any.
+fn matches_literal_casei(s: &str, ix: usize, literal: &str) -> Option<usize> {
+    let mut chars = s[ix..].char_indices();
+    for expected in literal.chars() {
+        let (_, c) = chars.next()?;
+        if c != expected && simple_fold(c) != simple_fold(expected) {
+            return None;
+        }
+    }
+    Some(ix + chars.next().map_or(s.len() - ix, |(i, _)| i))
+}
+
+/// Returns the end of the text at `ix` whose characters fold to the ones of `folded` with simple
+/// case folding, if any.
+fn matches_literal_folded(s: &str, ix: usize, folded: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut end = ix;
+    for expected in folded.chars() {
+        let b = *bytes.get(end)?;
+        if b < 0x80 {
+            // ASCII characters only fold to ASCII characters, no need to decode or look up
+            if b.to_ascii_lowercase() as char != expected {
+                return None;
+            }
+            end += 1;
+        } else {
+            let c = s[end..].chars().next()?;
+            if simple_fold(c) != expected {
+                return None;
+            }
+            end += c.len_utf8();
+        }
+    }
+    Some(end)
+}
+
+/// Returns the end of the text at `ix` that is canonically equivalent to `literal` (ignoring case
+/// if `casei`), if any. The text is compared in decomposed form (NFD), and it can't end before a
+/// combining mark, as that would split a character.
+#[cfg(feature = "unicode-normalization")]
+fn matches_literal_canonical(s: &str, ix: usize, literal: &str, casei: bool) -> Option<usize> {
+    let fold = |c: char| if casei { simple_fold(c) } else { c };
+    let expected: Vec<char> = literal.nfd().map(fold).collect();
+    if expected.is_empty() {
+        return Some(ix);
+    }
+    // Decomposing never makes text shorter, so only one end can have the right length
+    let mut len = 0;
+    let mut chars = s[ix..].char_indices().peekable();
+    while len < expected.len() {
+        let (_, c) = chars.next()?;
+        decompose_canonical(c, |_| len += 1);
+    }
+    let end = match chars.peek() {
+        Some(&(_, c)) if canonical_combining_class(c) != 0 => return None,
+        Some(&(i, _)) => ix + i,
+        None => s.len(),
+    };
+    if len == expected.len() && s[ix..end].nfd().map(fold).eq(expected) {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+/// Whether all characters of `s` are from the same script, taking into account that some characters
+/// are used with several scripts (script extensions).
+fn is_script_run(s: &str) -> bool {
+    !ScriptExtension::for_str(s).is_empty()
 }
 
 /// Run the program with trace printing for debugging.
 pub fn run_trace(prog: &Prog, s: &str, pos: usize) -> Result<Option<Vec<usize>>> {
-    run(prog, s, pos, OPTION_TRACE, &RegexOptions::default())
+    run(
+        prog,
+        s,
+        pos,
+        OPTION_TRACE,
+        &RegexOptions::default(),
+        &RunConfig::default(),
+    )
 }
 
 /// Run the program with default options.
 pub fn run_default(prog: &Prog, s: &str, pos: usize) -> Result<Option<Vec<usize>>> {
-    run(prog, s, pos, 0, &RegexOptions::default())
+    run(
+        prog,
+        s,
+        pos,
+        0,
+        &RegexOptions::default(),
+        &RunConfig::default(),
+    )
 }
 
 /// Run the program with options.
-#[allow(clippy::cognitive_complexity)]
 pub(crate) fn run(
     prog: &Prog,
     s: &str,
     pos: usize,
     option_flags: u32,
     options: &RegexOptions,
+    config: &RunConfig,
+) -> Result<Option<Vec<usize>>> {
+    run_from_pc(prog, s, pos, 0, option_flags, options, config)
+}
+
+/// Run the program with options, starting at instruction `start_pc` instead of the first one.
+pub(crate) fn run_from_pc(
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+    config: &RunConfig,
 ) -> Result<Option<Vec<usize>>> {
-    let mut state = State::new(prog.n_saves, MAX_STACK, option_flags);
-    if option_flags & OPTION_TRACE != 0 {
-        println!("pos\tinstruction");
+    run_diagnostic(prog, s, pos, start_pc, option_flags, options, config, None)
+}
+
+/// What a diagnostic run of the VM records, see `run_diagnostic`.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    /// The failure that got furthest into the text
+    pub(crate) furthest: Option<Failure>,
+    /// If not 0, the run doesn't stop at the first match, but backtracks to find the other ways
+    /// of matching too, until there are this many in `matches`
+    pub(crate) match_limit: usize,
+    /// The number of slots of a match that are compared for finding distinct ones
+    pub(crate) match_slots: usize,
+    /// The slots of the distinct matches that were found
+    pub(crate) matches: Vec<Vec<usize>>,
+}
+
+/// The failure during a run of the VM that got furthest into the text, for explaining why there's
+/// no match.
+#[derive(Debug)]
+pub(crate) struct Failure {
+    /// The position in the text where the instruction failed
+    pub(crate) ix: usize,
+    /// The instruction that failed
+    pub(crate) pc: usize,
+    /// The backtrack branches that were still pending as `(pc, ix)`, the most recent one first
+    pub(crate) branches: Vec<(usize, usize)>,
+}
+
+/// The number of pending backtrack branches that a `Failure` keeps
+const FAILURE_BRANCHES: usize = 5;
+
+/// Run the program like `run_from_pc`, and if `diagnostics` are given, record the failure that got
+/// furthest into the text and (if requested) all matches in them.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn run_diagnostic(
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+    config: &RunConfig,
+    diagnostics: Option<&mut Diagnostics>,
+) -> Result<Option<Vec<usize>>> {
+    if fits_u32(s.len()) {
+        run_new_state::<u32>(
+            prog,
+            s,
+            pos,
+            start_pc,
+            option_flags,
+            options,
+            config,
+            diagnostics,
+        )
+    } else {
+        run_new_state::<usize>(
+            prog,
+            s,
+            pos,
+            start_pc,
+            option_flags,
+            options,
+            config,
+            diagnostics,
+        )
     }
-    let mut backtrack_count = 0;
-    let mut pc = 0;
-    let mut ix = pos;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_new_state<V: SlotValue>(
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+    config: &RunConfig,
+    diagnostics: Option<&mut Diagnostics>,
+) -> Result<Option<Vec<usize>>> {
+    let mut state = State::<V>::new(
+        prog.n_saves,
+        config.max_stack,
+        options.memory_limit,
+        option_flags,
+    );
+    state.prepare(prog, start_pc, option_flags, options, config);
+    if run_state(
+        &mut state,
+        prog,
+        s,
+        pos,
+        start_pc,
+        options,
+        config,
+        diagnostics,
+    )? {
+        Ok(Some(
+            state.saves.iter().map(|value| value.to_usize()).collect(),
+        ))
+    } else {
+        Ok(None)
+    }
+}
+
+/// An iteration of a group recorded by `Insn::RecordGroup`, as `(slot, start, end)`
+pub(crate) type GroupIteration = (usize, usize, usize);
+
+/// Run the program like `run_from_pc`, also returning the capture history of the match: the
+/// iterations of groups recorded by `Insn::RecordGroup` as `(slot, start, end)`, in the order
+/// they matched.
+pub(crate) fn run_with_history(
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    options: &RegexOptions,
+    config: &RunConfig,
+) -> Result<Option<(Vec<usize>, Vec<GroupIteration>)>> {
+    // The length of the history may not fit into a `u32` slot
+    let mut state = State::<usize>::new(prog.n_saves, config.max_stack, options.memory_limit, 0);
+    state.prepare(prog, start_pc, 0, options, config);
+    if !run_state(&mut state, prog, s, pos, start_pc, options, config, None)? {
+        return Ok(None);
+    }
+    let history = match prog.history_slot {
+        Some(slot) => state.recorded_history(slot).to_vec(),
+        None => Vec::new(),
+    };
+    Ok(Some((state.saves.to_vec(), history)))
+}
+
+/// State of the VM that is kept between runs, so that running the VM many times (e.g. for
+/// counting matches) doesn't allocate each time.
+///
+/// It belongs to a single call and must not be cached in a global or thread-local, so that
+/// callbacks such as replacers and trace sinks can run regexes themselves.
+pub(crate) struct Scratch {
+    /// The state for texts whose positions fit into `u32`
+    narrow: State<u32>,
+    wide: State<usize>,
+}
+
+impl Scratch {
+    pub(crate) fn new() -> Scratch {
+        Scratch {
+            narrow: State::new(0, 0, 0, 0),
+            wide: State::new(0, 0, 0, 0),
+        }
+    }
+}
+
+/// Run the program like `run_from_pc` with the state in `scratch`, returning the bounds of the
+/// match (the values of slots 0 and 1) instead of all the slots.
+pub(crate) fn run_with_scratch(
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    options: &RegexOptions,
+    config: &RunConfig,
+    scratch: &mut Scratch,
+) -> Result<Option<(usize, usize)>> {
+    if fits_u32(s.len()) {
+        run_prepared(prog, s, pos, start_pc, options, config, &mut scratch.narrow)
+    } else {
+        run_prepared(prog, s, pos, start_pc, options, config, &mut scratch.wide)
+    }
+}
+
+fn run_prepared<V: SlotValue>(
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    options: &RegexOptions,
+    config: &RunConfig,
+    state: &mut State<V>,
+) -> Result<Option<(usize, usize)>> {
+    state.prepare(prog, start_pc, 0, options, config);
+    if run_state(state, prog, s, pos, start_pc, options, config, None)? {
+        Ok(Some((state.get(0), state.get(1))))
+    } else {
+        Ok(None)
+    }
+}
+
+/// A run of the VM that stops after executing a number of instructions (its fuel), and can be
+/// resumed from there, see `Regex::find_resumable`.
+pub(crate) struct SuspendedRun {
+    state: State<usize>,
+    registers: Registers,
+    pos: usize,
+}
+
+impl SuspendedRun {
+    /// Prepare a run of the program at `pos`, starting at instruction `start_pc`.
+    pub(crate) fn new(
+        prog: &Prog,
+        pos: usize,
+        start_pc: usize,
+        options: &RegexOptions,
+        config: &RunConfig,
+    ) -> SuspendedRun {
+        let mut state = State::new(prog.n_saves, config.max_stack, options.memory_limit, 0);
+        state.prepare(prog, start_pc, 0, options, config);
+        SuspendedRun {
+            state,
+            registers: Registers::new(start_pc, pos),
+            pos,
+        }
+    }
+
+    /// Continue the run for at most `fuel` instructions. Returns `None` if it was suspended
+    /// again, otherwise the result like `run`. The program, text, options and config must be the
+    /// same for all calls.
+    pub(crate) fn resume(
+        &mut self,
+        prog: &Prog,
+        s: &str,
+        fuel: usize,
+        options: &RegexOptions,
+        config: &RunConfig,
+    ) -> Result<Option<Option<Vec<usize>>>> {
+        let fuel_end = self.registers.steps.saturating_add(fuel);
+        let step = run_state_fuel(
+            &mut self.state,
+            prog,
+            s,
+            self.pos,
+            &mut self.registers,
+            fuel_end,
+            options,
+            config,
+            None,
+        )?;
+        Ok(match step {
+            Step::Done(true) => Some(Some(
+                self.state.saves[..prog.n_saves]
+                    .iter()
+                    .map(|value| value.to_usize())
+                    .collect(),
+            )),
+            Step::Done(false) => Some(None),
+            Step::Suspended => None,
+        })
+    }
+}
+
+/// Run the program on a prepared `state`. Returns whether it matched, the slots are in `state`.
+#[allow(clippy::too_many_arguments)]
+fn run_state<V: SlotValue>(
+    state: &mut State<V>,
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    start_pc: usize,
+    options: &RegexOptions,
+    config: &RunConfig,
+    diagnostics: Option<&mut Diagnostics>,
+) -> Result<bool> {
+    let mut registers = Registers::new(start_pc, pos);
+    let step = run_state_fuel(
+        state,
+        prog,
+        s,
+        pos,
+        &mut registers,
+        usize::MAX,
+        options,
+        config,
+        diagnostics,
+    )?;
+    match step {
+        Step::Done(matched) => Ok(matched),
+        // The step limits are reached before that much fuel is used up
+        Step::Suspended => Err(internal_bug(registers.pc, "ran out of unlimited fuel")),
+    }
+}
+
+/// The registers of a run of the VM, which are kept when it's suspended, see `SuspendedRun`.
+#[derive(Debug, Clone, Copy)]
+struct Registers {
+    /// The instruction to execute next
+    pc: usize,
+    /// The position in the text
+    ix: usize,
+    /// The number of instructions that were executed
+    steps: usize,
+    /// The number of times that the run backtracked
+    backtrack_count: usize,
+}
+
+impl Registers {
+    fn new(start_pc: usize, pos: usize) -> Registers {
+        Registers {
+            pc: start_pc,
+            ix: pos,
+            steps: 0,
+            backtrack_count: 0,
+        }
+    }
+}
+
+/// How far a run of the VM got, see `run_state_fuel`.
+enum Step {
+    /// The run is complete, with whether it matched
+    Done(bool),
+    /// The run was suspended because it ran out of fuel
+    Suspended,
+}
+
+/// Run the program on a prepared `state` from `registers` like `run_state`, until the number of
+/// executed instructions would exceed `fuel_end`. Then the run is suspended, with `registers` set
+/// for resuming it.
+#[allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
+fn run_state_fuel<V: SlotValue>(
+    state: &mut State<V>,
+    prog: &Prog,
+    s: &str,
+    pos: usize,
+    registers: &mut Registers,
+    fuel_end: usize,
+    options: &RegexOptions,
+    config: &RunConfig,
+    mut diagnostics: Option<&mut Diagnostics>,
+) -> Result<Step> {
+    let option_flags = state.options;
+    if option_flags & OPTION_TRACE != 0 && registers.steps == 0 {
+        state.trace(format_args!("pos\tinstruction"));
+    }
+    let unmatched_backrefs_match = options.repeated_groups == RepeatedGroups::JavaScript;
+    let step_limit = config.step_limit.unwrap_or(usize::MAX);
+    let auto_step_limit = config.auto_step_limit_for(s.len().saturating_sub(pos), prog.body.len());
+    let limit_steps = step_limit.min(auto_step_limit.unwrap_or(usize::MAX));
+    let max_steps = limit_steps.min(fuel_end);
+    let Registers {
+        mut pc,
+        mut ix,
+        mut steps,
+        mut backtrack_count,
+    } = *registers;
     loop {
         // break from this loop to fail, causes stack to pop
         'fail: loop {
+            let insn = match prog.body.get(pc) {
+                Some(insn) => insn,
+                None => return Err(internal_bug(pc, "instruction out of range")),
+            };
+            steps += 1;
+            if steps > max_steps {
+                if steps <= limit_steps {
+                    // Out of fuel, this instruction is executed first when resuming
+                    *registers = Registers {
+                        pc,
+                        ix,
+                        steps: steps - 1,
+                        backtrack_count,
+                    };
+                    return Ok(Step::Suspended);
+                }
+                if steps > step_limit {
+                    return Err(Error::RuntimeError(RuntimeError::StepLimitExceeded));
+                }
+                return Err(Error::RuntimeError(RuntimeError::AutoStepLimitExceeded {
+                    limit: limit_steps,
+                    text_len: s.len().saturating_sub(pos),
+                    instructions: prog.body.len(),
+                    backtracks: backtrack_count,
+                }));
+            }
+            if let Some(deadline) = config.deadline {
+                if steps % DEADLINE_CHECK_INTERVAL == 1 && Instant::now() >= deadline {
+                    return Err(Error::RuntimeError(RuntimeError::DeadlineExceeded));
+                }
+            }
             if option_flags & OPTION_TRACE != 0 {
-                println!("{}\t{} {:?}", ix, pc, prog.body[pc]);
+                state.trace(format_args!("{}\t{} {:?}", ix, pc, insn));
             }
-            match prog.body[pc] {
+            match *insn {
                 Insn::End => {
+                    if option_flags & OPTION_NOT_EMPTY != 0 && ix == pos {
+                        break 'fail;
+                    }
+                    if let Some((end, exact)) = config.end_bound {
+                        if ix > end || (exact && ix < end) {
+                            break 'fail;
+                        }
+                    }
+                    if let Some(ref mut diagnostics) = diagnostics {
+                        if diagnostics.match_limit > 0 {
+                            let slots: Vec<usize> = state.saves[..diagnostics.match_slots]
+                                .iter()
+                                .map(|value| value.to_usize())
+                                .collect();
+                            if !diagnostics.matches.contains(&slots) {
+                                diagnostics.matches.push(slots);
+                            }
+                            if diagnostics.matches.len() >= diagnostics.match_limit {
+                                return Ok(Step::Done(false));
+                            }
+                            // Look for other ways of matching
+                            break 'fail;
+                        }
+                    }
                     // save of end position into slot 1 is now done
                     // with an explicit group; we might want to
                     // optimize that.
                     //state.saves[1] = ix;
                     if option_flags & OPTION_TRACE != 0 {
-                        println!("saves: {:?}", state.saves);
+                        state.trace(format_args!("saves: {:?}", state.saves));
                     }
-                    return Ok(Some(state.saves));
+                    return Ok(Step::Done(true));
                 }
                 Insn::Any => {
-                    if ix < s.len() {
+                    // After `AnyByte`, a byte string can be matched from inside a character
+                    if ix < s.len() && s.is_char_boundary(ix) {
                         ix += codepoint_len_at(s, ix);
                     } else {
                         break 'fail;
                     }
                 }
                 Insn::AnyNoNL => {
-                    if ix < s.len() && s.as_bytes()[ix] != b'\n' {
+                    if ix < s.len() && s.as_bytes()[ix] != b'\n' && s.is_char_boundary(ix) {
                         ix += codepoint_len_at(s, ix);
                     } else {
                         break 'fail;
                     }
                 }
+                Insn::AnyByte => {
+                    if ix >= s.len() {
+                        break 'fail;
+                    }
+                    ix = match config.byte_string {
+                        Some(ref replacements) => next_byte(replacements, ix),
+                        None => ix + codepoint_len_at(s, ix),
+                    };
+                }
                 Insn::Lit(ref val) => {
                     let ix_end = ix + val.len();
-                    if !matches_literal(s, ix, ix_end, val) {
+                    if !matches_literal(s, ix, ix_end, val.as_bytes()) {
                         break 'fail;
                     }
                     ix = ix_end;
                 }
+                Insn::LitCasei(ref folded) => {
+                    if !s.is_char_boundary(ix) {
+                        break 'fail;
+                    }
+                    match matches_literal_folded(s, ix, folded) {
+                        Some(ix_end) => ix = ix_end,
+                        None => break 'fail,
+                    }
+                }
                 Insn::Split(x, y) => {
                     state.push(y, ix)?;
                     pc = x;
@@ -473,7 +1684,25 @@ pub(crate) fn run(
                 }
                 Insn::Save(slot) => state.save(slot, ix),
                 Insn::Save0(slot) => state.save(slot, 0),
-                Insn::Restore(slot) => ix = state.get(slot),
+                Insn::SaveValue { slot, value } => state.save(slot, value),
+                Insn::SaveFrom { slot, from } => {
+                    let value = state.get(from);
+                    state.save(slot, value);
+                }
+                Insn::ResetGroups { start, end } => {
+                    for slot in start * 2..end * 2 {
+                        state.save(slot, usize::MAX);
+                    }
+                }
+                Insn::Restore(slot) => {
+                    ix = state.get(slot);
+                    if ix > s.len() {
+                        return Err(internal_bug(pc, "restore of a slot that isn't set"));
+                    }
+                }
+                Insn::RecordGroup { slot, history } => {
+                    state.record_group(slot, history)?;
+                }
                 Insn::RepeatGr {
                     lo,
                     hi,
@@ -516,8 +1745,9 @@ pub(crate) fn run(
                 } => {
                     let repcount = state.get(repeat);
                     if repcount > lo && state.get(check) == ix {
-                        // prevent zero-length match on repeat
-                        break 'fail;
+                        // An empty iteration ends the repeat, like in the regex crate and PCRE
+                        pc = next;
+                        continue;
                     }
                     state.save(repeat, repcount + 1);
                     if repcount >= lo {
@@ -533,8 +1763,9 @@ pub(crate) fn run(
                 } => {
                     let repcount = state.get(repeat);
                     if repcount > lo && state.get(check) == ix {
-                        // prevent zero-length match on repeat
-                        break 'fail;
+                        // An empty iteration ends the repeat, like in the regex crate and PCRE
+                        pc = next;
+                        continue;
                     }
                     state.save(repeat, repcount + 1);
                     if repcount >= lo {
@@ -552,6 +1783,28 @@ pub(crate) fn run(
                         ix = prev_codepoint_ix(s, ix);
                     }
                 }
+                Insn::GoBackByte => {
+                    if ix == 0 {
+                        break 'fail;
+                    }
+                    ix = match config.byte_string {
+                        Some(ref replacements) => prev_byte(replacements, ix),
+                        None => prev_codepoint_ix(s, ix),
+                    };
+                }
+                Insn::GoBackAny(ref counts) => {
+                    let mut positions = counts.iter().filter_map(|&count| go_back(s, ix, count));
+                    let first = match positions.next() {
+                        Some(first) => first,
+                        None => break 'fail,
+                    };
+                    // Push the others in reverse, so that they're tried in order when backtracking
+                    let others: Vec<usize> = positions.collect();
+                    for &other in others.iter().rev() {
+                        state.push(pc + 1, other)?;
+                    }
+                    ix = first;
+                }
                 Insn::FailNegativeLookAround => {
                     // Reaching this instruction means that the body of the
                     // look-around matched. Because it's a *negative* look-around,
@@ -560,7 +1813,9 @@ pub(crate) fn run(
                     // been pushed with the look-around, because we don't want to
                     // explore them.
                     loop {
-                        let (popped_pc, _) = state.pop();
+                        let (popped_pc, _) = state.pop().ok_or_else(|| {
+                            internal_bug(pc, "no branch for negative look-around")
+                        })?;
                         if popped_pc == pc + 1 {
                             // We've reached the state that would jump us to
                             // after the look-around (in case the look-around
@@ -571,29 +1826,93 @@ pub(crate) fn run(
                     break 'fail;
                 }
                 Insn::Backref(slot) => {
-                    let lo = state.get(slot);
-                    if lo == usize::MAX {
+                    let ref_text = match group_text(state, s, slot) {
+                        Some(text) => text,
+                        None if unmatched_backrefs_match => &[],
                         // Referenced group hasn't matched, so the backref doesn't match either
-                        break 'fail;
-                    }
-                    let hi = state.get(slot + 1);
-                    let ref_text = &s[lo..hi];
+                        None => break 'fail,
+                    };
                     let ix_end = ix + ref_text.len();
                     if !matches_literal(s, ix, ix_end, ref_text) {
                         break 'fail;
                     }
                     ix = ix_end;
                 }
-                Insn::BeginAtomic => {
-                    let count = state.backtrack_count();
-                    state.stack_push(count);
+                Insn::BackrefCasei(slot) => {
+                    let ref_text = match group_text(state, s, slot) {
+                        Some(text) => text,
+                        None if unmatched_backrefs_match => &[],
+                        // Referenced group hasn't matched, so the backref doesn't match either
+                        None => break 'fail,
+                    };
+                    // When matching bytes, the group or the text here can be inside a character
+                    let ref_text = match str::from_utf8(ref_text) {
+                        Ok(text) if s.is_char_boundary(ix) => text,
+                        _ => break 'fail,
+                    };
+                    match matches_literal_casei(s, ix, ref_text) {
+                        Some(ix_end) => ix = ix_end,
+                        None => break 'fail,
+                    }
+                }
+                #[cfg(feature = "unicode-normalization")]
+                Insn::BackrefCanonical { slot, casei } => {
+                    let ref_text = match group_text(state, s, slot) {
+                        Some(text) => text,
+                        None if unmatched_backrefs_match => &[],
+                        // Referenced group hasn't matched, so the backref doesn't match either
+                        None => break 'fail,
+                    };
+                    // When matching bytes, the group or the text here can be inside a character
+                    let ref_text = match str::from_utf8(ref_text) {
+                        Ok(text) if s.is_char_boundary(ix) => text,
+                        _ => break 'fail,
+                    };
+                    match matches_literal_canonical(s, ix, ref_text, casei) {
+                        Some(ix_end) => ix = ix_end,
+                        None => break 'fail,
+                    }
                 }
+                Insn::BeginAtomic => state.begin_atomic()?,
                 Insn::EndAtomic => {
-                    let count = state.stack_pop();
-                    state.backtrack_cut(count);
+                    if !state.in_atomic() {
+                        return Err(internal_bug(pc, "end of atomic group without its start"));
+                    }
+                    state.end_atomic();
+                }
+                Insn::ScriptRun(slot) => {
+                    let start = state.get(slot);
+                    if start > s.len() {
+                        return Err(internal_bug(pc, "script run from a slot that isn't set"));
+                    }
+                    // In a look-behind, the run is matched right-to-left and ends at the slot
+                    let run = if start <= ix {
+                        s.get(start..ix)
+                    } else {
+                        s.get(ix..start)
+                    };
+                    // When matching bytes, the run can start or end inside a character
+                    if !run.map_or(false, is_script_run) {
+                        break 'fail;
+                    }
                 }
-                Insn::DelegateSized(ref inner, size) => {
-                    if inner.is_match(&s[ix..]) {
+                #[cfg(feature = "compiler")]
+                Insn::DelegateSized {
+                    ref inner,
+                    ref inner1,
+                    size,
+                } => {
+                    if !s.is_char_boundary(ix) {
+                        break 'fail;
+                    }
+                    // See `Delegate` for why `inner1` is needed
+                    let is_match = match *inner1 {
+                        Some(ref inner1) if ix > 0 => {
+                            inner1.get()?.is_match(&s[prev_codepoint_ix(s, ix)..])
+                        }
+                        _ => inner.get()?.is_match(&s[ix..]),
+                    };
+                    if is_match {
                         // We could analyze for ascii-only, and ix += size in
                         // that case. Unlikely to be speed-limiting though.
                         for _ in 0..size {
@@ -603,17 +1922,20 @@ pub(crate) fn run(
                         break 'fail;
                     }
                 }
+                #[cfg(feature = "compiler")]
                 Insn::Delegate {
                     ref inner,
                     ref inner1,
-                    start_group,
-                    end_group,
+                    ref groups,
                 } => {
                     // Note: Why can't we use `find_at` or `captures_read_at` here instead of the
                     // `inner1` regex? We only want to match at the current location, so our regexes
                     // need to have an anchor: `^foo` (without `^`, it would match `foo` anywhere).
                     // But regex like `^foo` won't match in `bar foo` with `find_at(s, 4)` because
                     // `^` only matches at the beginning of the text.
+                    if !s.is_char_boundary(ix) {
+                        break 'fail;
+                    }
                     let re = match *inner1 {
                         Some(ref inner1) if ix > 0 => {
                             ix = prev_codepoint_ix(s, ix);
@@ -621,7 +1943,8 @@ pub(crate) fn run(
                         }
                         _ => inner,
                     };
-                    if start_group == end_group {
+                    let re = re.get()?;
+                    if groups.is_empty() {
                         // No groups, so we can use `find` which is faster than `captures_read`
                         match re.find(&s[ix..]) {
                             Some(m) => ix += m.end(),
@@ -630,14 +1953,12 @@ pub(crate) fn run(
                     } else {
                         let mut locations = re.capture_locations();
                         if let Some(m) = re.captures_read(&mut locations, &s[ix..]) {
-                            for i in 0..(end_group - start_group) {
-                                let slot = (start_group + i) * 2;
-                                if let Some((start, end)) = locations.get(i + 1) {
+                            // Groups that didn't participate keep what they captured before,
+                            // e.g. in an earlier iteration of a repeat
+                            for &(group, slot) in groups {
+                                if let Some((start, end)) = locations.get(group) {
                                     state.save(slot, ix + start);
                                     state.save(slot + 1, ix + end);
-                                } else {
-                                    state.save(slot, usize::MAX);
-                                    state.save(slot + 1, usize::MAX);
                                 }
                             }
                             ix += m.end();
@@ -650,57 +1971,133 @@ pub(crate) fn run(
             pc += 1;
         }
         if option_flags & OPTION_TRACE != 0 {
-            println!("fail");
+            state.trace(format_args!("fail"));
         }
         // "break 'fail" goes here
+        if let Some(ref mut diagnostics) = diagnostics {
+            if diagnostics
+                .furthest
+                .as_ref()
+                .map_or(true, |failure| ix > failure.ix)
+            {
+                diagnostics.furthest = Some(Failure {
+                    ix,
+                    pc,
+                    branches: state
+                        .stack
+                        .iter()
+                        .rev()
+                        .take(FAILURE_BRANCHES)
+                        .map(|branch| (branch.pc as usize, branch.ix.to_usize()))
+                        .collect(),
+                });
+            }
+        }
         if state.stack.is_empty() {
-            return Ok(None);
+            return Ok(Step::Done(false));
         }
 
         backtrack_count += 1;
         if backtrack_count > options.backtrack_limit {
-            return Err(Error::BacktrackLimitExceeded);
+            return Err(Error::RuntimeError(RuntimeError::BacktrackLimitExceeded));
         }
 
-        let (newpc, newix) = state.pop();
+        let (newpc, newix) = state
+            .pop()
+            .ok_or_else(|| internal_bug(pc, "no branch to backtrack to"))?;
         pc = newpc;
         ix = newix;
     }
 }
 
+/// The text matched by the group with its start and end saved in `slot` and `slot + 1`, or `None`
+/// if the group hasn't matched.
+fn group_text<'s, V: SlotValue>(state: &State<V>, s: &'s str, slot: usize) -> Option<&'s [u8]> {
+    let lo = state.get(slot);
+    let hi = state.get(slot + 1);
+    if lo == usize::MAX || hi == usize::MAX || hi < lo {
+        None
+    } else {
+        Some(&s.as_bytes()[lo..hi])
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use quickcheck::{quickcheck, Arbitrary, Gen};
 
+    #[test]
+    fn branch_stack_chunks() {
+        let mut stack = BranchStack::<usize>::new();
+        let n = BRANCH_CHUNK_SIZE * 2 + 10;
+        for pc in 0..n {
+            stack.push(Branch {
+                pc: pc as u32,
+                ix: 0,
+                nsave: 0,
+            });
+        }
+        assert_eq!(stack.len(), n);
+        assert_eq!(stack.rest.len(), 2);
+        assert_eq!(
+            stack[BRANCH_CHUNK_SIZE + 1].pc as usize,
+            BRANCH_CHUNK_SIZE + 1
+        );
+        assert!(stack.iter().map(|branch| branch.pc as usize).eq(0..n));
+
+        stack.truncate(BRANCH_CHUNK_SIZE * 2);
+        assert_eq!(stack.len(), BRANCH_CHUNK_SIZE * 2);
+        assert_eq!(stack.rest.len(), 1);
+        assert_eq!(stack.pop().unwrap().pc as usize, BRANCH_CHUNK_SIZE * 2 - 1);
+
+        stack.truncate(BRANCH_CHUNK_SIZE);
+        assert!(stack.rest.is_empty());
+        assert!(stack.spare.is_some());
+        stack.push(Branch {
+            pc: 42,
+            ix: 0,
+            nsave: 0,
+        });
+        assert_eq!(stack.len(), BRANCH_CHUNK_SIZE + 1);
+        assert!(stack.spare.is_none());
+        assert_eq!(stack.pop().unwrap().pc, 42);
+        assert_eq!(stack.pop().unwrap().pc as usize, BRANCH_CHUNK_SIZE - 1);
+
+        stack.clear();
+        assert!(stack.is_empty());
+        assert_eq!(stack.len(), 0);
+        assert!(stack.pop().is_none());
+    }
+
     #[test]
     fn state_push_pop() {
-        let mut state = State::new(1, MAX_STACK, 0);
+        let mut state = State::<usize>::new(1, usize::MAX, usize::MAX, 0);
 
         state.push(0, 0).unwrap();
         state.push(1, 1).unwrap();
-        assert_eq!(state.pop(), (1, 1));
-        assert_eq!(state.pop(), (0, 0));
+        assert_eq!(state.pop(), Some((1, 1)));
+        assert_eq!(state.pop(), Some((0, 0)));
         assert!(state.stack.is_empty());
 
         state.push(2, 2).unwrap();
-        assert_eq!(state.pop(), (2, 2));
+        assert_eq!(state.pop(), Some((2, 2)));
         assert!(state.stack.is_empty());
     }
 
     #[test]
     fn state_save_override() {
-        let mut state = State::new(1, MAX_STACK, 0);
+        let mut state = State::<usize>::new(1, usize::MAX, usize::MAX, 0);
         state.save(0, 10);
         state.push(0, 0).unwrap();
         state.save(0, 20);
-        assert_eq!(state.pop(), (0, 0));
+        assert_eq!(state.pop(), Some((0, 0)));
         assert_eq!(state.get(0), 10);
     }
 
     #[test]
     fn state_save_override_twice() {
-        let mut state = State::new(1, MAX_STACK, 0);
+        let mut state = State::<usize>::new(1, usize::MAX, usize::MAX, 0);
         state.save(0, 10);
         state.push(0, 0).unwrap();
         state.save(0, 20);
@@ -708,32 +2105,53 @@ mod tests {
         state.save(0, 30);
 
         assert_eq!(state.get(0), 30);
-        assert_eq!(state.pop(), (1, 1));
+        assert_eq!(state.pop(), Some((1, 1)));
         assert_eq!(state.get(0), 20);
-        assert_eq!(state.pop(), (0, 0));
+        assert_eq!(state.pop(), Some((0, 0)));
         assert_eq!(state.get(0), 10);
     }
 
     #[test]
     fn state_explicit_stack() {
-        let mut state = State::new(1, MAX_STACK, 0);
-        state.stack_push(11);
-        state.stack_push(12);
+        let mut state = State::<usize>::new(1, usize::MAX, usize::MAX, 0);
+        state.stack_push(11).unwrap();
+        state.stack_push(12).unwrap();
 
         state.push(100, 101).unwrap();
-        state.stack_push(13);
+        state.stack_push(13).unwrap();
         assert_eq!(state.stack_pop(), 13);
-        state.stack_push(14);
-        assert_eq!(state.pop(), (100, 101));
+        state.stack_push(14).unwrap();
+        assert_eq!(state.pop(), Some((100, 101)));
 
         // Note: 14 is not there because it was pushed as part of the backtrack branch
         assert_eq!(state.stack_pop(), 12);
         assert_eq!(state.stack_pop(), 11);
     }
 
+    #[test]
+    fn state_record_group() {
+        let mut state = State::<usize>::new(3, usize::MAX, usize::MAX, 0);
+        state.save(0, 1);
+        state.save(1, 2);
+        state.record_group(0, 2).unwrap();
+
+        state.push(100, 101).unwrap();
+        state.save(1, 3);
+        state.record_group(0, 2).unwrap();
+        assert_eq!(state.recorded_history(2), &[(0, 1, 2), (0, 1, 3)]);
+
+        // Backtracking undoes the iterations recorded after the branch
+        assert_eq!(state.pop(), Some((100, 101)));
+        assert_eq!(state.recorded_history(2), &[(0, 1, 2)]);
+        state.save(0, 4);
+        state.save(1, 5);
+        state.record_group(0, 2).unwrap();
+        assert_eq!(state.recorded_history(2), &[(0, 1, 2), (0, 4, 5)]);
+    }
+
     #[test]
     fn state_backtrack_cut_simple() {
-        let mut state = State::new(2, MAX_STACK, 0);
+        let mut state = State::<usize>::new(2, usize::MAX, usize::MAX, 0);
         state.save(0, 1);
         state.save(1, 2);
 
@@ -751,7 +2169,7 @@ mod tests {
 
     #[test]
     fn state_backtrack_cut_complex() {
-        let mut state = State::new(2, MAX_STACK, 0);
+        let mut state = State::<usize>::new(2, usize::MAX, usize::MAX, 0);
         state.save(0, 1);
         state.save(1, 2);
 
@@ -778,6 +2196,265 @@ mod tests {
         assert_eq!(state.get(1), 2);
     }
 
+    #[test]
+    fn state_snapshot() {
+        let mut state = State::<usize>::new(2, usize::MAX, usize::MAX, 0);
+        state.snapshot = true;
+        state.save(0, 1);
+        state.push(100, 101).unwrap();
+        state.stack_push(11).unwrap();
+        state.save(1, 2);
+
+        // Discards the branches inside, keeping the values
+        state.begin_atomic().unwrap();
+        state.push(102, 103).unwrap();
+        state.save(0, 3);
+        state.push(104, 105).unwrap();
+        state.end_atomic();
+        assert_eq!(state.backtrack_count(), 1);
+        assert_eq!((state.get(0), state.get(1)), (3, 2));
+        assert_eq!(state.stack_pop(), 11);
+
+        // Restores the saves and the explicit stack from before the push
+        assert_eq!(state.pop(), Some((100, 101)));
+        assert_eq!((state.get(0), state.get(1)), (1, usize::MAX));
+        assert!(state.snapshots.is_empty());
+        assert_eq!(state.pop(), None);
+    }
+
+    #[test]
+    fn state_atomic_rollback() {
+        let mut state = State::<usize>::new(4, usize::MAX, usize::MAX, 0);
+        state.save(0, 0);
+        state.push(10, 0).unwrap();
+
+        state.begin_atomic().unwrap();
+        state.save(2, 1);
+        state.push(11, 1).unwrap();
+        state.save(2, 2);
+        state.save(3, 3);
+        state.push(12, 3).unwrap();
+        state.save(3, 4);
+        state.end_atomic();
+
+        // The branches inside the group are gone, its captures are kept
+        assert_eq!(state.backtrack_count(), 1);
+        assert_eq!(state.get(2), 2);
+        assert_eq!(state.get(3), 4);
+
+        // Backtracking past the group rolls back everything it set
+        assert_eq!(state.pop(), Some((10, 0)));
+        assert_eq!(state.get(0), 0);
+        assert_eq!(state.get(2), usize::MAX);
+        assert_eq!(state.get(3), usize::MAX);
+    }
+
+    #[test]
+    fn state_pop_empty() {
+        let mut state = State::<usize>::new(1, usize::MAX, usize::MAX, 0);
+        assert_eq!(state.pop(), None);
+    }
+
+    #[test]
+    fn state_u32_slots() {
+        let mut state = State::<u32>::new(2, usize::MAX, usize::MAX, 0);
+        assert_eq!(state.get(0), usize::MAX);
+        state.save(0, 7);
+        state.push(3, 5).unwrap();
+        state.save(0, usize::MAX);
+        state.save(1, 9);
+        assert_eq!(state.get(0), usize::MAX);
+        assert_eq!(state.pop(), Some((3, 5)));
+        assert_eq!(state.get(0), 7);
+        assert_eq!(state.get(1), usize::MAX);
+    }
+
+    #[test]
+    fn untracked_whole_match_slots() {
+        let prog = Prog::new(
+            vec![
+                Insn::Split(3, 1),
+                Insn::Any,
+                Insn::Jmp(0),
+                Insn::Save(0),
+                Insn::Split(5, 6),
+                Insn::Lit("a".to_string()),
+                Insn::Save(1),
+                Insn::End,
+            ],
+            2,
+        );
+        assert_eq!(prog.whole_match_save_pc, Some(3));
+
+        let mut state = State::<usize>::new(2, usize::MAX, usize::MAX, 0);
+        state.prepare(&prog, 0, 0, &RegexOptions::default(), &RunConfig::new());
+        // With snapshots, the old values would be restored anyway
+        state.snapshot = false;
+        state.push(6, 0).unwrap();
+        state.save(0, 0);
+        state.save(1, 1);
+        // The old values aren't kept, so they're not restored either
+        assert!(state.oldsave.is_empty());
+        assert_eq!(state.pop(), Some((6, 0)));
+        assert_eq!((state.get(0), state.get(1)), (0, 1));
+
+        // Not if a run starts after the save of the start
+        state.prepare(&prog, 4, 0, &RegexOptions::default(), &RunConfig::new());
+        assert_eq!(state.untracked_slots, 0);
+
+        // Not if the slots are used elsewhere
+        let mut body = prog.body.clone();
+        body[5] = Insn::Backref(0);
+        assert_eq!(Prog::new(body, 2).whole_match_save_pc, None);
+        // Or if a path can skip the save of the start
+        let mut body = prog.body.clone();
+        body[0] = Insn::Split(4, 1);
+        assert_eq!(Prog::new(body, 2).whole_match_save_pc, None);
+    }
+
+    #[test]
+    fn malformed_program_is_error() {
+        let prog = Prog::new(vec![Insn::Jmp(5)], 0);
+        assert!(matches!(
+            run_default(&prog, "a", 0),
+            Err(Error::RuntimeError(RuntimeError::InternalBug { pc: 5, .. }))
+        ));
+
+        // Negative look-around without the branch for continuing after it
+        let prog = Prog::new(vec![Insn::FailNegativeLookAround, Insn::End], 0);
+        assert!(matches!(
+            run_default(&prog, "a", 0),
+            Err(Error::RuntimeError(RuntimeError::InternalBug { pc: 0, .. }))
+        ));
+    }
+
+    #[test]
+    fn one_pass() {
+        // `(a)\1` with group 1 in slots 0 and 1
+        let prog = Prog::new(
+            vec![
+                Insn::Save(0),
+                Insn::Lit("a".to_string()),
+                Insn::Save(1),
+                Insn::Backref(0),
+                Insn::End,
+            ],
+            2,
+        );
+        assert_eq!(prog.one_pass_start, 0);
+        assert_eq!(run_default(&prog, "aa", 0).unwrap(), Some(vec![0, 1]));
+        assert_eq!(run_default(&prog, "ab", 0).unwrap(), None);
+    }
+
+    #[test]
+    fn literal_folded() {
+        assert_eq!(matches_literal_folded("xABc", 1, "ab"), Some(3));
+        assert_eq!(matches_literal_folded("\u{212A}\u{17F}", 0, "ks"), Some(5));
+        assert_eq!(
+            matches_literal_folded("Stra\u{1E9E}e!", 0, "straße"),
+            Some(8)
+        );
+        assert_eq!(matches_literal_folded("strasse", 0, "straße"), None);
+        assert_eq!(matches_literal_folded("ab", 0, "abc"), None);
+        assert_eq!(matches_literal_folded("ab", 2, ""), Some(2));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-normalization")]
+    fn literal_canonical() {
+        assert_eq!(
+            matches_literal_canonical("xe\u{301}y", 1, "\u{e9}", false),
+            Some(4)
+        );
+        assert_eq!(
+            matches_literal_canonical("x\u{e9}y", 1, "e\u{301}", false),
+            Some(3)
+        );
+        assert_eq!(matches_literal_canonical("e\u{301}", 0, "e", false), None);
+        assert_eq!(
+            matches_literal_canonical("E\u{301}", 0, "\u{e9}", false),
+            None
+        );
+        assert_eq!(
+            matches_literal_canonical("E\u{301}", 0, "\u{e9}", true),
+            Some(3)
+        );
+        // Combining marks in a different order are equivalent if their classes differ
+        assert_eq!(
+            matches_literal_canonical("a\u{323}\u{301}", 0, "a\u{301}\u{323}", false),
+            Some(5)
+        );
+        assert_eq!(
+            matches_literal_canonical("\u{ac00}", 0, "\u{1100}\u{1161}", false),
+            Some(3)
+        );
+        assert_eq!(matches_literal_canonical("\u{301}", 0, "", false), Some(0));
+    }
+
+    #[test]
+    fn next_and_prev_byte() {
+        // "a\u{FFFD}\u{FFFD}" for "a", an invalid sequence of 2 bytes and one of 1 byte
+        let replacements = [(1, 2), (4, 1)];
+        let positions = [0, 1, 2, 4, 7];
+        for pair in positions.windows(2) {
+            assert_eq!(next_byte(&replacements, pair[0]), pair[1]);
+            assert_eq!(prev_byte(&replacements, pair[1]), pair[0]);
+        }
+    }
+
+    #[test]
+    fn go_back_any() {
+        // Roughly `(?<=ab|xyz)c`: go back 2 or 3 characters, match the alternation, then restore
+        // the position and match `c`.
+        fn prog(counts: Vec<usize>) -> Prog {
+            Prog::new(
+                vec![
+                    Insn::Save(0),
+                    Insn::GoBackAny(counts),
+                    Insn::Split(3, 5),
+                    Insn::Lit("ab".to_string()),
+                    Insn::Jmp(6),
+                    Insn::Lit("xyz".to_string()),
+                    Insn::Save(1),
+                    Insn::Restore(0),
+                    Insn::Lit("c".to_string()),
+                    Insn::End,
+                ],
+                2,
+            )
+        }
+        let run = |prog: &Prog, s: &str, pos: usize| run_default(prog, s, pos).unwrap();
+
+        let shortest_first = prog(vec![2, 3]);
+        let longest_first = prog(vec![3, 2]);
+        for prog in &[&shortest_first, &longest_first] {
+            assert_eq!(run(prog, "xyzc", 3), Some(vec![3, 3]));
+            assert_eq!(run(prog, "abc", 2), Some(vec![2, 2]));
+            // Going back 2 characters (not bytes) gives `äb`, going back 3 is not possible
+            assert_eq!(run(prog, "äbc", 3), None);
+            assert_eq!(run(prog, "bc", 1), None);
+        }
+        // Both widths match here, the order decides which one is used
+        let s = "abxyzab";
+        let prog_both = |counts| {
+            Prog::new(
+                vec![
+                    Insn::GoBackAny(counts),
+                    Insn::Save(0),
+                    Insn::Split(3, 5),
+                    Insn::Lit("ab".to_string()),
+                    Insn::Jmp(6),
+                    Insn::Lit("zab".to_string()),
+                    Insn::End,
+                ],
+                1,
+            )
+        };
+        assert_eq!(run(&prog_both(vec![2, 3]), s, 7), Some(vec![5]));
+        assert_eq!(run(&prog_both(vec![3, 2]), s, 7), Some(vec![4]));
+        assert_eq!(run(&prog_both(vec![8]), s, 7), None);
+    }
+
     #[derive(Clone, Debug)]
     enum Operation {
         Push,
@@ -798,7 +2475,7 @@ mod tests {
         }
     }
 
-    fn check_saves_for_operations(operations: Vec<Operation>) -> bool {
+    fn check_saves_for_operations(operations: Vec<Operation>, snapshot: bool) -> bool {
         let slots = operations
             .iter()
             .map(|o| match o {
@@ -816,7 +2493,8 @@ mod tests {
         let mut stack = Vec::new();
         let mut saves = vec![usize::MAX; slots];
 
-        let mut state = State::new(slots, MAX_STACK, 0);
+        let mut state = State::<usize>::new(slots, usize::MAX, usize::MAX, 0);
+        state.snapshot = snapshot;
 
         let mut expected = Vec::new();
         let mut actual = Vec::new();
@@ -846,10 +2524,7 @@ mod tests {
 
             // Remember state of saves for checking later
             expected.push(saves.clone());
-            let mut actual_saves = vec![usize::MAX; slots];
-            for i in 0..slots {
-                actual_saves[i] = state.get(i);
-            }
+            let actual_saves = (0..slots).map(|i| state.get(i)).collect::<Vec<_>>();
             actual.push(actual_saves);
         }
 
@@ -858,7 +2533,11 @@ mod tests {
 
     quickcheck! {
         fn state_save_quickcheck(operations: Vec<Operation>) -> bool {
-            check_saves_for_operations(operations)
+            check_saves_for_operations(operations, false)
+        }
+
+        fn state_save_snapshot_quickcheck(operations: Vec<Operation>) -> bool {
+            check_saves_for_operations(operations, true)
         }
     }
 }
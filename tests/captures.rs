@@ -1,4 +1,9 @@
-use fancy_regex::{Captures, Error, Expander, Match, Result};
+#![cfg(feature = "compiler")]
+
+use fancy_regex::{
+    Captures, CompileError, Error, Expander, Match, ParseError, RegexBuilder, RepeatedGroups,
+    Result,
+};
 use std::borrow::Cow;
 use std::ops::Index;
 
@@ -11,6 +16,31 @@ fn capture_names() {
     assert_eq!(capture_names, vec![None, Some("foo"), None, Some("bar")]);
 }
 
+#[test]
+fn group_metadata() {
+    let pattern = r"(?<foo>a(b))(?:c)(?=(d))(?P<bar>\k<foo>)";
+    let regex = common::regex(pattern);
+    assert_eq!(regex.captures_len(), 5);
+    assert_eq!(regex.group_index("foo"), Some(1));
+    assert_eq!(regex.group_index("bar"), Some(4));
+    assert_eq!(regex.group_index("baz"), None);
+
+    let spans: Vec<_> = (0..6)
+        .map(|i| regex.group_span(i).map(|span| &pattern[span]))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            Some(pattern),
+            Some("(?<foo>a(b))"),
+            Some("(b)"),
+            Some("(d)"),
+            Some(r"(?P<bar>\k<foo>)"),
+            None
+        ]
+    );
+}
+
 #[test]
 fn captures_fancy() {
     let captures = captures(r"\s*(\w+)(?=\.)", "foo bar.");
@@ -40,6 +70,23 @@ fn captures_fancy_unmatched_group() {
     assert_match(captures.get(2), "foo", 0, 3);
 }
 
+#[test]
+fn captures_char_and_utf16_ranges() {
+    let captures = captures(r"(é)(z)?(?=\S)(\S+)", "aé\u{1F600}c");
+    assert_eq!(captures.get(0).unwrap().range(), 1..8);
+
+    assert_eq!(captures.get(3).unwrap().char_range(), 2..4);
+    assert_eq!(
+        captures.char_ranges(),
+        vec![Some(1..4), Some(1..2), None, Some(2..4)]
+    );
+    assert_eq!(captures.get(3).unwrap().utf16_range(), 2..5);
+    assert_eq!(
+        captures.utf16_ranges(),
+        vec![Some(1..5), Some(1..2), None, Some(2..5)]
+    );
+}
+
 #[test]
 fn captures_after_lookbehind() {
     let captures = captures(
@@ -50,6 +97,140 @@ fn captures_after_lookbehind() {
     assert_match(captures.get(2), "(foo bar)", 9, 18);
 }
 
+#[test]
+fn captures_inside_variable_size_lookbehind() {
+    let caps = captures(r"(?<=(\d+)-(\w*))x", "a12-bcx");
+    assert_match(caps.get(0), "x", 6, 7);
+    assert_match(caps.get(1), "12", 1, 3);
+    assert_match(caps.get(2), "bc", 4, 6);
+}
+
+#[test]
+fn captures_inside_lookahead() {
+    // Capture without consuming
+    let caps = captures(r"(?=(\w+))\w", "abc");
+    assert_match(caps.get(0), "a", 0, 1);
+    assert_match(caps.get(1), "abc", 0, 3);
+
+    let caps = captures(r"(?=(\d+)x)\d", "12x");
+    assert_match(caps.get(0), "1", 0, 1);
+    assert_match(caps.get(1), "12", 0, 2);
+
+    // Repeated, the last iteration wins
+    let caps = captures(r"(?:(?=(\w))\w)+", "abc");
+    assert_match(caps.get(0), "abc", 0, 3);
+    assert_match(caps.get(1), "c", 2, 3);
+}
+
+#[test]
+fn captures_inside_lookahead_nested() {
+    let caps = captures(r"(?=(a(?=(b))))ab", "ab");
+    assert_match(caps.get(1), "a", 0, 1);
+    assert_match(caps.get(2), "b", 1, 2);
+
+    let caps = captures(r"(?=((a)(?<=(a))))", "a");
+    assert_match(caps.get(0), "", 0, 0);
+    assert_match(caps.get(1), "a", 0, 1);
+    assert_match(caps.get(2), "a", 0, 1);
+    assert_match(caps.get(3), "a", 0, 1);
+}
+
+#[test]
+fn captures_inside_lookahead_alternation() {
+    let caps = captures(r"(?=(a)|(b))\w", "b");
+    assert!(caps.get(1).is_none());
+    assert_match(caps.get(2), "b", 0, 1);
+
+    let caps = captures(r"x(?=((a)|b)c)", "xac");
+    assert_match(caps.get(0), "x", 0, 1);
+    assert_match(caps.get(1), "a", 1, 2);
+    assert_match(caps.get(2), "a", 1, 2);
+
+    // Groups of an alternative that failed after the look-ahead are unset
+    let caps = captures(r"(?=(a))ab|(?=(\w))\w+", "ac");
+    assert!(caps.get(1).is_none());
+    assert_match(caps.get(2), "a", 0, 1);
+}
+
+#[test]
+fn captures_inside_negative_lookaround() {
+    // A negative look-around only succeeds if its groups didn't match
+    let caps = captures(r"(?!(a))\w", "b");
+    assert_eq!(caps.len(), 2);
+    assert!(caps.get(1).is_none());
+
+    let caps = captures(r"(?<!(a))b", "cb");
+    assert!(caps.get(1).is_none());
+}
+
+#[test]
+fn captures_inside_atomic_group() {
+    // Captures from inside the group are kept after it matched
+    let caps = captures(r"(?>(a+))b", "aab");
+    assert_match(caps.get(1), "aa", 0, 2);
+
+    // An alternative inside the group that failed doesn't leave its captures
+    let caps = captures(r"(?>(a)x|ab)", "ab");
+    assert!(caps.get(1).is_none());
+
+    // Backtracking past the group rolls back all of its captures
+    let caps = captures(r"(?:(?>(a+))b|a+c)", "aac");
+    assert_match(caps.get(0), "aac", 0, 3);
+    assert!(caps.get(1).is_none());
+    let caps = captures(r"^(?:(?>(a)(b)?)c|ab)", "ab");
+    assert!(caps.get(1).is_none());
+    assert!(caps.get(2).is_none());
+
+    // In a repeat, the captures of the last iteration that matched are kept
+    let caps = captures(r"(?>(a)|b)+c", "abac");
+    assert_match(caps.get(1), "a", 2, 3);
+    let caps = captures(r"(?:(?>(a)|(b))c)+", "acbc");
+    assert_match(caps.get(1), "a", 0, 1);
+    assert_match(caps.get(2), "b", 2, 3);
+}
+
+#[test]
+fn captures_inside_repeat() {
+    // Groups keep what they captured in an earlier iteration
+    let caps = captures(r"(?:(a)|b)+", "ab");
+    assert_match(caps.get(1), "a", 0, 1);
+
+    // A backref inside its group refers to the previous iteration
+    let caps = captures(r"^(a|b\1)+$", "aba");
+    assert_match(caps.get(1), "ba", 1, 3);
+    assert!(common::regex(r"^(a|b\1)+$")
+        .captures("ab")
+        .unwrap()
+        .is_none());
+    let caps = captures(r"(?:(a|b\1)x)+", "axbax");
+    assert_match(caps.get(0), "axbax", 0, 5);
+    assert_match(caps.get(1), "ba", 2, 4);
+}
+
+#[test]
+fn captures_inside_repeat_javascript() {
+    let regex = |re: &str| {
+        RegexBuilder::new(re)
+            .repeated_groups(RepeatedGroups::JavaScript)
+            .build()
+            .unwrap()
+    };
+
+    // Groups are reset in each iteration, also for repeats that could be delegated otherwise
+    let caps = regex(r"(?:(a)|b)+").captures("ab").unwrap().unwrap();
+    assert_match(caps.get(0), "ab", 0, 2);
+    assert!(caps.get(1).is_none());
+    let caps = regex(r"(?:(a)|(b)){2}").captures("ab").unwrap().unwrap();
+    assert!(caps.get(1).is_none());
+    assert_match(caps.get(2), "b", 1, 2);
+
+    // A backref to a group that didn't match in the iteration matches the empty string
+    let caps = regex(r"^(a|b\1)+$").captures("ab").unwrap().unwrap();
+    assert_match(caps.get(1), "b", 1, 2);
+    let caps = regex(r"^(?:(a)|b\1)+$").captures("ab").unwrap().unwrap();
+    assert!(caps.get(1).is_none());
+}
+
 #[test]
 fn captures_iter() {
     let text = "11 21 33";
@@ -92,6 +273,37 @@ fn captures_iter_attributes() {
     assert_eq!(regex.as_str(), all_captures.regex().as_str());
 }
 
+#[test]
+fn captures_index_and_iter() {
+    for pattern in &[r"(?<first>\w+) (\w+)(x)?", r"(?<first>\w+) (\w+)(x)?(?=!)"] {
+        let captures = captures(pattern, "hello world!");
+        assert_eq!(&captures[0], "hello world");
+        assert_eq!(&captures[2], "world");
+        assert_eq!(&captures["first"], "hello");
+
+        let mut iter = captures.iter();
+        assert_eq!(iter.len(), 4);
+        iter.next();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+
+        let groups: Vec<_> = (&captures)
+            .into_iter()
+            .map(|m| m.map(|m| m.as_str()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![Some("hello world"), Some("hello"), Some("world"), None]
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "no group at index '3'")]
+fn captures_index_unmatched_group() {
+    let captures = captures(r"(\w+) (\w+)(x)?", "hello world");
+    let _ = &captures[3];
+}
+
 #[test]
 fn captures_from_pos() {
     let text = "11 21 33";
@@ -151,6 +363,59 @@ fn captures_from_pos_looking_left() {
     assert_match(captures.get(1), "x", 1, 2);
 }
 
+#[test]
+fn captures_at() {
+    // Wrapped
+    let regex = common::regex(r"\b(\w)(\w)?");
+    assert!(regex.captures_at("ax", 1).unwrap().is_none());
+    assert!(regex.captures_at(".x a", 0).unwrap().is_none());
+    let captures = assert_captures(regex.captures_at(".x a", 1));
+    assert_eq!(captures.len(), 3);
+    assert_match(captures.get(0), "x", 1, 2);
+    assert_match(captures.get(1), "x", 1, 2);
+    assert!(captures.get(2).is_none());
+
+    // Fancy
+    let regex = common::regex(r"(?<=(\d))(\w)\2");
+    assert!(regex.captures_at("1aab", 0).unwrap().is_none());
+    assert!(regex.captures_at("1abb", 1).unwrap().is_none());
+    let captures = assert_captures(regex.captures_at("1aab", 1));
+    assert_eq!(captures.len(), 3);
+    assert_match(captures.get(0), "aa", 1, 3);
+    assert_match(captures.get(1), "1", 0, 1);
+    assert_match(captures.get(2), "a", 1, 2);
+}
+
+#[test]
+fn all_matches_at() {
+    fn ways<'t>(re: &str, text: &'t str, pos: usize, limit: usize) -> Vec<Vec<Option<&'t str>>> {
+        common::regex(re)
+            .all_matches_at(text, pos, limit)
+            .unwrap()
+            .iter()
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.as_str())).collect())
+            .collect()
+    }
+
+    // Ambiguous: two ways to split the same match
+    assert_eq!(
+        ways(r"(a+)(a+)", "aaa", 0, 10),
+        vec![
+            vec![Some("aaa"), Some("aa"), Some("a")],
+            vec![Some("aaa"), Some("a"), Some("aa")],
+            vec![Some("aa"), Some("a"), Some("a")],
+        ]
+    );
+    // The same groups from different paths are only reported once
+    assert_eq!(ways(r"(?:a|a)b", "ab", 0, 10), vec![vec![Some("ab")]]);
+    assert_eq!(ways(r"(a+)(a+)", "aaa", 0, 2).len(), 2);
+    assert!(ways(r"(a+)(a+)", "aaa", 0, 0).is_empty());
+    assert!(ways(r"a", "ba", 0, 10).is_empty());
+    assert_eq!(ways(r"a|(b)", "ba", 1, 10), vec![vec![Some("a"), None]]);
+    // Atomic groups only match once
+    assert_eq!(ways(r"(?>a+)", "aa", 0, 10), vec![vec![Some("aa")]]);
+}
+
 #[cfg_attr(feature = "track_caller", track_caller)]
 fn captures<'a>(re: &str, text: &'a str) -> Captures<'a> {
     let regex = common::regex(re);
@@ -277,30 +542,254 @@ fn expander_errors() {
     }
 
     // Substitution char at end of template.
-    assert_err!(exp.check("$", &with_names), Error::ParseError);
+    assert_err!(
+        exp.check("$", &with_names),
+        Error::ParseError(0, ParseError::GeneralParseError(_))
+    );
 
     // Substitution char not followed by a name or number.
-    assert_err!(exp.check("$.", &with_names), Error::ParseError);
+    assert_err!(
+        exp.check("$.", &with_names),
+        Error::ParseError(0, ParseError::GeneralParseError(_))
+    );
 
     // Empty delimiter pair.
-    assert_err!(exp.check("${}", &with_names), Error::ParseError);
+    assert_err!(
+        exp.check("${}", &with_names),
+        Error::ParseError(0, ParseError::GeneralParseError(_))
+    );
 
     // Unterminated delimiter pair.
-    assert_err!(exp.check("${", &with_names), Error::ParseError);
+    assert_err!(
+        exp.check("${", &with_names),
+        Error::ParseError(0, ParseError::GeneralParseError(_))
+    );
 
     // Group 0 is always OK.
     assert!(exp.check("$0", &with_names).is_ok());
     assert!(exp.check("$0", &without_names).is_ok());
 
     // Can't use numbers with named groups.
-    assert_err!(exp.check("$1", &with_names), Error::NamedBackrefOnly);
-    assert_err!(exp.check("${1}", &with_names), Error::NamedBackrefOnly);
+    assert_err!(
+        exp.check("$1", &with_names),
+        Error::ParseError(0, ParseError::NamedBackrefOnly)
+    );
+    assert_err!(
+        exp.check("${1}", &with_names),
+        Error::ParseError(0, ParseError::NamedBackrefOnly)
+    );
 
     // Unmatched group number.
-    assert_err!(exp.check("$2", &without_names), Error::InvalidBackref);
-    assert_err!(exp.check("${2}", &without_names), Error::InvalidBackref);
+    assert_err!(
+        exp.check("$2", &without_names),
+        Error::ParseError(0, ParseError::InvalidBackref)
+    );
+    assert_err!(
+        exp.check("${2}", &without_names),
+        Error::ParseError(0, ParseError::InvalidBackref)
+    );
 
     // Unmatched group name.
-    assert_err!(exp.check("$xx", &with_names), Error::InvalidBackref);
-    assert_err!(exp.check("${xx}", &with_names), Error::InvalidBackref);
+    assert_err!(
+        exp.check("$xx", &with_names),
+        Error::ParseError(0, ParseError::InvalidBackref)
+    );
+    assert_err!(
+        exp.check("${xx}", &with_names),
+        Error::ParseError(0, ParseError::InvalidBackref)
+    );
+}
+
+#[test]
+fn captures_branch() {
+    let branch = |re: &str, text: &str| {
+        let regex = RegexBuilder::new(re).record_branch(true).build().unwrap();
+        regex
+            .captures(text)
+            .unwrap()
+            .map(|caps| caps.branch().unwrap())
+    };
+    assert_eq!(branch(r"\d+|[a-z]+|\s+", "abc 123"), Some(1));
+    assert_eq!(branch(r"\d+|[a-z]+|\s+", " abc"), Some(2));
+    assert_eq!(branch(r"(\w)\1|(?<=a)b|b", "ab"), Some(1));
+    assert_eq!(branch(r"(\w)\1|(?<=a)b|b", "xbb"), Some(0));
+    assert_eq!(branch(r"(\w)\1|(?<=a)b|b", "xb"), Some(2));
+    assert_eq!(branch(r"a(?:b|c)|ac", "ac"), Some(0));
+    assert_eq!(branch(r"a(b|c)", "ac"), Some(0));
+    assert_eq!(branch(r"x|y", "abc"), None);
+
+    let regex = RegexBuilder::new(r"(?P<num>\d+)|(?P<word>[a-z]+)")
+        .record_branch(true)
+        .build()
+        .unwrap();
+    let branches: Vec<_> = regex
+        .captures_iter("ab 12 c")
+        .map(|caps| caps.unwrap().branch())
+        .collect();
+    assert_eq!(branches, vec![Some(1), Some(0), Some(1)]);
+    let caps = regex.captures_at("ab 12 c", 3).unwrap().unwrap();
+    assert_eq!(caps.branch(), Some(0));
+    assert_eq!(caps.name("num").unwrap().as_str(), "12");
+
+    let caps = common::regex(r"\d+|[a-z]+")
+        .captures("abc")
+        .unwrap()
+        .unwrap();
+    assert_eq!(caps.branch(), None);
+}
+
+#[test]
+fn captured_groups() {
+    let build = |re: &str, groups: &[&str]| {
+        RegexBuilder::new(re)
+            .captured_groups(groups)
+            .build()
+            .unwrap()
+    };
+
+    let regex = build(r"(a)(b)(?<c>c)", &["2"]);
+    let caps = regex.captures("abc").unwrap().unwrap();
+    assert_eq!(caps.len(), 4);
+    assert_eq!(regex.captures_len(), 4);
+    assert_eq!(caps.get(0).unwrap().as_str(), "abc");
+    assert_eq!(caps.get(1), None);
+    assert_eq!(caps.get(2).unwrap().as_str(), "b");
+    assert_eq!(caps.name("c"), None);
+    assert_eq!(caps.get(4), None);
+
+    // Fancy, with a backref to a group that isn't asked for
+    let regex = build(r"(\w)(x)?(?<=(\w))\1(.)", &["4"]);
+    let caps = regex.captures("-aab").unwrap().unwrap();
+    assert_eq!(caps.get(0).unwrap().as_str(), "aab");
+    assert_eq!(caps.get(1).unwrap().as_str(), "a");
+    assert_eq!(caps.get(2), None);
+    assert_eq!(caps.get(3), None);
+    assert_eq!(&caps[4], "b");
+    assert_eq!(regex.replace("-aab", "$1$2$4"), "-ab");
+
+    // Repeated groups that are not captured
+    let regex = build(r"(?:(ab)+|(c)*)()*(d)", &["3"]);
+    let caps = regex.captures("ababd").unwrap().unwrap();
+    assert_eq!(caps.get(0).unwrap().as_str(), "ababd");
+    assert_eq!(caps.get(3).unwrap().as_str(), "");
+    assert_eq!(caps.get(4), None);
+
+    assert!(matches!(
+        RegexBuilder::new(r"(a)(?<b>b)")
+            .captured_groups(&["x"])
+            .build(),
+        Err(Error::CompileError(CompileError::UnknownGroup(ref group))) if group == "x"
+    ));
+    assert!(matches!(
+        RegexBuilder::new(r"(a)").captured_groups(&["2"]).build(),
+        Err(Error::CompileError(CompileError::UnknownGroup(_)))
+    ));
+}
+
+#[test]
+fn group_iterations() {
+    let iterations = |re: &str, text: &str, group: usize| {
+        let regex = RegexBuilder::new(re).capture_history(true).build().unwrap();
+        let caps = regex.captures(text).unwrap().unwrap();
+        caps.group_iterations(group)
+            .map(|m| m.as_str().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(iterations(r"(\w+,)+", "a,bc,d,", 1), ["a,", "bc,", "d,"]);
+    assert_eq!(iterations(r"(?:(\d)(\w))+", "1a2b3", 2), ["a", "b"]);
+    assert_eq!(iterations(r"(x)?(\w)+", "abc", 1), Vec::<&str>::new());
+    assert_eq!(iterations(r"(\w)+", "abc", 0), ["abc"]);
+    // Iterations that were backtracked are dropped
+    assert_eq!(
+        iterations(r"^(?:(\d)+x|(\d)+y)$", "12y", 1),
+        Vec::<&str>::new()
+    );
+    assert_eq!(iterations(r"^(?:(\d)+x|(\d)+y)$", "12y", 2), ["1", "2"]);
+    assert_eq!(iterations(r"(?:(\w)(?!\1))+", "abb", 1), ["a"]);
+    assert_eq!(iterations(r"(?:(a)|b)+(?<=(\w)(\w))", "aba", 1), ["a", "a"]);
+
+    // Each match has its own history
+    let regex = RegexBuilder::new(r"(?:(\d)-?)+")
+        .capture_history(true)
+        .build()
+        .unwrap();
+    let all: Vec<Vec<_>> = regex
+        .captures_iter("1-2 3")
+        .map(|caps| {
+            caps.unwrap()
+                .group_iterations(1)
+                .map(|m| m.range())
+                .collect()
+        })
+        .collect();
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0], [0..1, 2..3]);
+    assert_eq!(all[1].len(), 1);
+    assert_eq!(all[1][0], 4..5);
+
+    // Named groups and groups that are selected by `captured_groups`
+    let regex = RegexBuilder::new(r"(a)(?:(?<x>\w)\.)*")
+        .captured_groups(&["x"])
+        .capture_history(true)
+        .build()
+        .unwrap();
+    let caps = regex.captures("ab.c.").unwrap().unwrap();
+    let x: Vec<_> = caps.group_iterations(2).map(|m| m.as_str()).collect();
+    assert_eq!(x, ["b", "c"]);
+    assert_eq!(caps.group_iterations(1).count(), 0);
+
+    // Without the option, only the last iteration is returned
+    let caps = common::regex(r"(\w,)+").captures("a,b,").unwrap().unwrap();
+    let last: Vec<_> = caps.group_iterations(1).map(|m| m.as_str()).collect();
+    assert_eq!(last, ["b,"]);
+}
+
+#[cfg(feature = "derive")]
+mod derive {
+    use fancy_regex::{Error, FromCaptures};
+
+    use crate::common;
+
+    #[derive(Debug, PartialEq, FromCaptures)]
+    struct Entry {
+        level: String,
+        line: u32,
+        #[capture(name = "msg")]
+        message: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, FromCaptures)]
+    struct Pair(char, i64);
+
+    #[test]
+    fn named_fields() {
+        let regex = common::regex(r"(?<level>[A-Z]+):(?<line>\d+)(?: (?<msg>.+))?");
+        assert_eq!(
+            regex.captures_parse("WARN:12 disk full").unwrap(),
+            Some(Entry {
+                level: "WARN".to_string(),
+                line: 12,
+                message: Some("disk full".to_string()),
+            })
+        );
+        assert_eq!(
+            regex
+                .captures_parse::<Entry>("INFO:3")
+                .unwrap()
+                .unwrap()
+                .message,
+            None
+        );
+        assert_eq!(regex.captures_parse::<Entry>("info").unwrap(), None);
+    }
+
+    #[test]
+    fn tuple_fields() {
+        let regex = common::regex(r"(\w)=(-?\d+)");
+        assert_eq!(regex.captures_parse("x=-5").unwrap(), Some(Pair('x', -5)));
+        match regex.captures_parse::<Pair>("x=99999999999999999999") {
+            Err(Error::CaptureConversion { group, .. }) => assert_eq!(group, "2"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
 }
@@ -1,6 +1,8 @@
+#![cfg(feature = "compiler")]
+
 mod common;
 
-use fancy_regex::{Match, Regex};
+use fancy_regex::{EmptyMatchPolicy, Match, Progress, RangeAnchor, Regex, RegexBuilder};
 use std::ops::Range;
 
 #[test]
@@ -17,6 +19,25 @@ fn find_wrap() {
     assert_eq!(find(r"(?m)^yes$", "foo\nyes\n"), Some((4, 7)));
 }
 
+#[test]
+fn find_at() {
+    for pattern in &[r"\d+", r"\d+(?=x)|\d+"] {
+        let regex = common::regex(pattern);
+        assert_eq!(regex.find_at("a12", 0).unwrap(), None);
+        let m = regex.find_at("a12", 1).unwrap().unwrap();
+        assert_eq!((m.start(), m.end()), (1, 3));
+        let m = regex.find_at("a12", 2).unwrap().unwrap();
+        assert_eq!((m.start(), m.end()), (2, 3));
+        assert_eq!(regex.find_at("a12", 3).unwrap(), None);
+    }
+
+    // Text before the position is used for `^` and `\b`
+    let regex = common::regex(r"^a|\bb");
+    assert_eq!(regex.find_at("aab", 1).unwrap(), None);
+    assert_eq!(regex.find_at("aab", 2).unwrap(), None);
+    assert!(regex.find_at("a b", 2).unwrap().is_some());
+}
+
 #[test]
 fn find_fancy_case_insensitive() {
     assert_eq!(find(r"(x|xy)\1", "XX"), None);
@@ -56,10 +77,33 @@ fn lookbehind_variable_sized_alt() {
     assert_eq!(find(r"(?<=a|bc)", "xxb"), None);
     assert_eq!(find(r"(?<=a|bc)", "xxc"), None);
 
-    assert!(Regex::new(r"(?<=a(?:b|cd))").is_err());
     assert!(Regex::new(r"(?<=a+b+))").is_err());
 }
 
+#[test]
+fn lookbehind_variable_size() {
+    assert_eq!(find(r"(?<=a(?:b|cd))x", "abx"), Some((2, 3)));
+    assert_eq!(find(r"(?<=a(?:b|cd))x", "acdx"), Some((3, 4)));
+    assert_eq!(find(r"(?<=a(?:b|cd))x", "adx"), None);
+
+    assert_eq!(find(r"(?<=\d+)x", "a123x"), Some((4, 5)));
+    assert_eq!(find(r"(?<=\d+)x", "a x"), None);
+    assert_eq!(find(r"(?<=^a+)b", "aaab"), Some((3, 4)));
+    assert_eq!(find(r"(?<=^a+)b", "caab"), None);
+    assert_eq!(find(r"(?<=\ba.*c)d", "xa yzcd"), None);
+    assert_eq!(find(r"(?<=\ba.*c)d", "x ayzcd"), Some((6, 7)));
+    assert_eq!(find(r"(?<=a\w{2,3}?)!", "abcd!"), Some((4, 5)));
+    assert_eq!(find(r"(?<=a\w{2,3}?)!", "abcde!"), None);
+
+    // Look-around inside
+    assert_eq!(find(r"(?<=(?=ab)\w+)c", "abc"), Some((2, 3)));
+    assert_eq!(find(r"(?<=(?<!x)a+)b", "xaab"), Some((3, 4)));
+    assert_eq!(find(r"(?<=(?<!x)a+)b", "xab"), None);
+
+    // Backrefs are not supported
+    assert!(Regex::new(r"(a)(?<=\1+)").is_err());
+}
+
 #[test]
 fn negative_lookbehind_variable_sized_alt() {
     assert_eq!(find(r"(?<!a|bc)x", "axx"), Some((2, 3)));
@@ -70,8 +114,32 @@ fn negative_lookbehind_variable_sized_alt() {
     assert_eq!(find(r"(?<!a|bc)x", "ax"), None);
     assert_eq!(find(r"(?<!a|bc)x", "bcx"), None);
 
-    assert!(Regex::new(r"(?<!a(?:b|cd))").is_err());
-    assert!(Regex::new(r"(?<!a+b+)").is_err());
+    assert_eq!(find(r"(?<!a(?:b|cd))x", "acdx"), None);
+    assert_eq!(find(r"(?<!a(?:b|cd))x", "adx"), Some((2, 3)));
+    assert_eq!(find(r"(?<!a+b+)x", "aabbx"), None);
+    assert_eq!(find(r"(?<!a+b+)x", "aabax"), Some((4, 5)));
+}
+
+#[test]
+fn anchors_in_lookarounds() {
+    // Anchors see the whole text, not only the part that the look-around body is matched against
+    assert_eq!(find(r"(?<=^ab)c", "abc"), Some((2, 3)));
+    assert_eq!(find(r"(?<=^ab)c", "xabc"), None);
+    assert_eq!(find(r"(?<=^ab)c", "ab\nabc"), None);
+    assert_eq!(find(r"(?m)(?<=^ab)c", "x\nabc"), Some((4, 5)));
+    assert_eq!(find(r"(?<=\Aab)c", "abc abc"), Some((2, 3)));
+    assert_eq!(find(r"(?<=^)a", "aa"), Some((0, 1)));
+    assert_eq!(find(r"(?<=b|^)a", "ca ba"), Some((4, 5)));
+    // Variable size
+    assert_eq!(find(r"(?<=^a+)b", "aab"), Some((2, 3)));
+    assert_eq!(find(r"(?<=^a+)b", "xaab"), None);
+
+    assert_eq!(find(r"a(?=b$)", "abab"), Some((2, 3)));
+    assert_eq!(find(r"a(?=b$)", "abx"), None);
+    assert_eq!(find(r"(?m)a(?=b$)", "abx\nab\nx"), Some((4, 5)));
+    assert_eq!(find(r"a(?=b\z)", "ab\nab"), Some((3, 4)));
+    assert_eq!(find(r"a(?!b$)", "abab"), Some((0, 1)));
+    assert_eq!(find(r"a(?=\w*$)", "a- ab"), Some((3, 4)));
 }
 
 #[test]
@@ -134,6 +202,38 @@ fn delegates_match_unicode_scalar_value() {
     assert_eq!(find(r".(?=\ba+)", "\u{1F60A}a"), Some((0, 4)));
 }
 
+#[test]
+fn word_boundary_in_sized_delegate() {
+    assert_eq!(find(r"(?=f)\bfoo", "xfoo foo"), Some((5, 8)));
+    assert_eq!(find(r"(?=f)\bfoo", "foo"), Some((0, 3)));
+    assert_eq!(find(r"(?=f)\Bfoo", "foo xfoo"), Some((5, 8)));
+    assert_eq!(find(r"(?<=\bab)c", "xabc abc"), Some((7, 8)));
+}
+
+#[test]
+fn generic_haystacks() {
+    use std::borrow::Cow;
+    use std::rc::Rc;
+
+    let regex = common::regex(r"(\w)\1");
+    let string = String::from("abba");
+    let cow: Cow<str> = Cow::Owned(string.clone());
+    let rc: Rc<str> = Rc::from("abba");
+
+    assert!(regex.is_match(&string).unwrap());
+    assert_eq!(regex.find(&string).unwrap().unwrap().range(), 1..3);
+    assert_eq!(regex.find(&cow).unwrap().unwrap().range(), 1..3);
+    assert_eq!(&regex.captures(&rc).unwrap().unwrap()[1], "b");
+    assert_eq!(regex.find_iter(&cow).count(), 1);
+    assert_eq!(regex.captures_iter(&rc).count(), 1);
+
+    fn count<T: AsRef<str>>(regex: &Regex, texts: &[T]) -> usize {
+        texts.iter().map(|text| regex.find_iter(text).count()).sum()
+    }
+    assert_eq!(count(&regex, &["aa", "bbcc"]), 3);
+    assert_eq!(count(&regex, &[string]), 1);
+}
+
 #[test]
 fn find_iter() {
     let text = "11 22 33";
@@ -199,6 +299,117 @@ fn find_iter_zero_length_longer_codepoint() {
     }
 }
 
+#[test]
+fn find_iter_empty_match_policy() {
+    let ranges = |re: &str, text: &str, policy: EmptyMatchPolicy| -> Vec<Range<usize>> {
+        let regex = RegexBuilder::new(re)
+            .empty_match_policy(policy)
+            .build()
+            .unwrap();
+        regex.find_iter(text).map(|m| m.unwrap().range()).collect()
+    };
+
+    assert_eq!(
+        ranges("a*", "baaa", EmptyMatchPolicy::SkipChar),
+        vec![0..0, 1..4]
+    );
+    assert_eq!(
+        ranges("a*", "baaa", EmptyMatchPolicy::RetryNonEmpty),
+        vec![0..0, 1..4, 4..4]
+    );
+    // The regex crate prefers the empty match of a lazy repeat
+    assert_eq!(
+        ranges("a*?", "baa", EmptyMatchPolicy::RetryNonEmpty),
+        vec![0..0, 1..1, 1..2, 2..2, 2..3, 3..3]
+    );
+
+    // Needs backtracking
+    assert_eq!(
+        ranges("a*?(?!b)", "aab", EmptyMatchPolicy::SkipChar),
+        vec![0..0, 1..1, 3..3]
+    );
+    assert_eq!(
+        ranges("a*?(?!b)", "aab", EmptyMatchPolicy::RetryNonEmpty),
+        vec![0..0, 0..1, 1..1, 3..3]
+    );
+
+    // `find_last` gives the last of them
+    let regex = RegexBuilder::new("a?")
+        .empty_match_policy(EmptyMatchPolicy::RetryNonEmpty)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find_last("baa").unwrap().unwrap().range(), 3..3);
+}
+
+#[test]
+fn count_matches() {
+    let texts = ["", "aab abb\nba a", "ÿaa äaä b", "bbbb"];
+    for re in &[
+        r"a",
+        r"(\w)\1",
+        r"a*",
+        r"a*?(?!b)",
+        r"(?<=a)b|\bb",
+        r"\b",
+        r"a(?=b)",
+    ] {
+        for &policy in &[EmptyMatchPolicy::SkipChar, EmptyMatchPolicy::RetryNonEmpty] {
+            let regex = RegexBuilder::new(re)
+                .empty_match_policy(policy)
+                .build()
+                .unwrap();
+            for text in &texts {
+                assert_eq!(
+                    regex.count_matches(text).unwrap(),
+                    regex.find_iter(text).count(),
+                    "{:?} in {:?} with {:?}",
+                    re,
+                    text,
+                    policy
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn captures_iter_empty_match_policy() {
+    let regex = RegexBuilder::new(r"(a*?)(?!b)")
+        .empty_match_policy(EmptyMatchPolicy::RetryNonEmpty)
+        .build()
+        .unwrap();
+    let groups: Vec<_> = regex
+        .captures_iter("aab")
+        .map(|caps| caps.unwrap().get(1).unwrap().range())
+        .collect();
+    assert_eq!(groups, vec![0..0, 0..1, 1..1, 3..3]);
+}
+
+#[cfg(feature = "unicode-segmentation")]
+#[test]
+fn find_iter_skip_grapheme() {
+    let text = "e\u{301}x";
+    let ranges = |policy: EmptyMatchPolicy| -> Vec<Range<usize>> {
+        let regex = RegexBuilder::new("x*")
+            .empty_match_policy(policy)
+            .build()
+            .unwrap();
+        regex.find_iter(text).map(|m| m.unwrap().range()).collect()
+    };
+
+    assert_eq!(ranges(EmptyMatchPolicy::SkipChar), vec![0..0, 1..1, 3..4]);
+    assert_eq!(ranges(EmptyMatchPolicy::SkipGrapheme), vec![0..0, 3..4]);
+
+    // Skipping the grapheme cluster after the empty match at the start also skips the match at
+    // the end, even though no match spans it
+    let text = "e\u{301}\u{301}\u{301}";
+    let regex = RegexBuilder::new("^|\u{301}$")
+        .empty_match_policy(EmptyMatchPolicy::SkipGrapheme)
+        .build()
+        .unwrap();
+    assert_eq!(regex.find_last(text).unwrap().unwrap().range(), 0..0);
+}
+
 #[test]
 fn find_iter_attributes() {
     let text = "ab1c2";
@@ -232,3 +443,420 @@ fn incomplete_escape_sequences() {
     assert!(Regex::new("\\U").is_err());
     assert!(Regex::new("\\x").is_err());
 }
+
+#[test]
+fn find_iter_offset_and_resume() {
+    for pattern in &[r"\d+", r"(?<!x)\d+"] {
+        let regex = common::regex(pattern);
+        let text = "1 22 333";
+        let mut matches = regex.find_iter(text);
+        assert_eq!(matches.offset(), 0);
+        assert_eq!(matches.size_hint(), (0, Some(9)));
+        assert_eq!(matches.next().unwrap().unwrap().as_str(), "1");
+        assert_eq!(matches.offset(), 1);
+
+        let rest: Vec<_> = regex
+            .find_iter_from_pos(text, matches.offset())
+            .map(|m| m.unwrap().start())
+            .collect();
+        assert_eq!(rest, vec![2, 5]);
+        let rest: Vec<_> = regex
+            .captures_iter_from_pos(text, 3)
+            .map(|c| c.unwrap().get(0).unwrap().start())
+            .collect();
+        assert_eq!(rest, vec![3, 5]);
+
+        // Fused
+        matches.next();
+        matches.next();
+        assert!(matches.next().is_none());
+        assert!(matches.next().is_none());
+        assert_eq!(matches.size_hint(), (0, Some(0)));
+        assert_eq!(matches.offset(), text.len());
+    }
+}
+
+#[test]
+fn grep_lines() {
+    fn grep(re: &str, text: &str) -> Vec<(usize, Range<usize>, Range<usize>)> {
+        common::regex(re)
+            .grep_lines(text)
+            .map(|line| {
+                let (number, span, m) = line.unwrap();
+                (number, span, m.range())
+            })
+            .collect()
+    }
+
+    let text = "a1 b2\nc\n\nd3\n";
+    assert_eq!(grep(r"\d", text), vec![(1, 0..5, 1..2), (4, 9..11, 10..11)]);
+    // Look-behind sees the previous line
+    assert_eq!(
+        grep(r"(?<=\n)\w", text),
+        vec![(2, 6..7, 6..7), (4, 9..11, 9..10)]
+    );
+    // A match spanning lines is reported for the line it starts in
+    assert_eq!(grep(r"c\n\nd", text), vec![(2, 6..7, 6..10)]);
+    // No extra line after the trailing newline
+    assert_eq!(
+        grep(r"(?m)^", "a\n\n"),
+        vec![(1, 0..1, 0..0), (2, 2..2, 2..2)]
+    );
+    assert_eq!(grep(r"(?m)^", "a"), vec![(1, 0..1, 0..0)]);
+    assert_eq!(grep(r"x", "a\nb"), vec![]);
+}
+
+#[test]
+fn lines_matching() {
+    fn lines<'t>(re: &str, text: &'t str) -> Vec<&'t str> {
+        common::regex(re)
+            .lines_matching(text)
+            .map(|line| line.unwrap())
+            .collect()
+    }
+
+    let text = "ab\nabc\r\n\nb\n";
+    assert_eq!(lines(r"a|ab", text), vec!["ab"]);
+    assert_eq!(lines(r"\w*", text), vec!["ab", "abc", "", "b"]);
+    // Hard patterns are anchored too
+    assert_eq!(lines(r"(\w)(?!\1)\w", text), vec!["ab"]);
+    assert_eq!(lines(r"(?<=a)b", text), Vec::<&str>::new());
+    // Each line is matched on its own
+    assert_eq!(lines(r"(?m)^$\n?", text), vec![""]);
+}
+
+#[test]
+fn split_captures() {
+    let regex = common::regex(r"(?<=\w)([-+])(?=\w)");
+    let fields = regex
+        .split_captures("a-b+c-+d")
+        .map(|field| {
+            let (field, captures) = field.unwrap();
+            (field, captures.map(|c| c[1].to_string()))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        vec![
+            ("a", Some("-".to_string())),
+            ("b", Some("+".to_string())),
+            ("c-+d", None),
+        ]
+    );
+}
+
+#[test]
+fn split_captures_edges() {
+    let regex = common::regex(r",");
+    let fields = |text| {
+        regex
+            .split_captures(text)
+            .map(|field| field.unwrap().0)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(fields(""), vec![""]);
+    assert_eq!(fields(","), vec!["", ""]);
+    assert_eq!(fields("a,,b,"), vec!["a", "", "b", ""]);
+}
+
+#[test]
+fn splitn_captures() {
+    let regex = common::regex(r"(\d)(?!\d)");
+    let fields = |text, limit| {
+        regex
+            .splitn_captures(text, limit)
+            .map(|field| field.unwrap())
+            .map(|(field, captures)| (field, captures.map(|c| c[1].to_string())))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(fields("a1b22c3", 0), vec![]);
+    assert_eq!(fields("a1b22c3", 1), vec![("a1b22c3", None)]);
+    assert_eq!(
+        fields("a1b22c3", 2),
+        vec![("a", Some("1".to_string())), ("b22c3", None)]
+    );
+    assert_eq!(
+        fields("a1b22c3", 10),
+        vec![
+            ("a", Some("1".to_string())),
+            ("b2", Some("2".to_string())),
+            ("c", Some("3".to_string())),
+            ("", None),
+        ]
+    );
+}
+
+#[test]
+fn split_inclusive() {
+    let regex = common::regex(r"\n(?!\s)");
+    let parts = |text| {
+        regex
+            .split_inclusive(text)
+            .map(|part| part.unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(parts("a\n b\nc\n"), vec!["a\n b\n", "c\n"]);
+    assert_eq!(parts("a\nb"), vec!["a\n", "b"]);
+    assert_eq!(parts("ab"), vec!["ab"]);
+    assert!(parts("").is_empty());
+}
+
+#[test]
+fn find_last() {
+    fn find_last(re: &str, text: &str) -> Option<(usize, usize)> {
+        let regex = common::regex(re);
+        let last = regex.find_last(text).unwrap().map(|m| (m.start(), m.end()));
+        let iterated = regex.find_iter(text).last().map(|m| {
+            let m = m.unwrap();
+            (m.start(), m.end())
+        });
+        assert_eq!(last, iterated, "{} in {:?}", re, text);
+        last
+    }
+    assert_eq!(find_last(r"\d+", "a1b22c333d"), Some((6, 9)));
+    assert_eq!(find_last(r"\d{1,2}", "a1b22c333d"), Some((8, 9)));
+    assert_eq!(find_last(r"\w+ing", "singing along"), Some((0, 7)));
+    assert_eq!(find_last(r"\w+", "hello world"), Some((6, 11)));
+    assert_eq!(find_last(r"(\w)+", "abbsc"), Some((0, 5)));
+    assert_eq!(find_last("(?:[^k]){2}", "\nßK"), Some((0, 3)));
+    assert_eq!(find_last("(?:b|(?:))", "ab"), Some((1, 2)));
+    assert_eq!(find_last("aa", "aaa"), Some((0, 2)));
+    assert_eq!(find_last("aa", "aaaaa"), Some((2, 4)));
+    assert_eq!(find_last("x*", "axx"), Some((1, 3)));
+    assert_eq!(find_last("x?", "axx"), Some((2, 3)));
+    assert_eq!(find_last("x?", "xxa"), Some((3, 3)));
+    assert_eq!(find_last(r"\b", "ab cd"), Some((5, 5)));
+    assert_eq!(find_last(r"(?<=a)b", "abcb ab"), Some((6, 7)));
+    assert_eq!(find_last(r"(?<!a)b", "abcb ab"), Some((3, 4)));
+    assert_eq!(find_last(r"é\w", "éa éb é"), Some((4, 7)));
+    assert_eq!(find_last(r"\d+", "abc"), None);
+    assert_eq!(find_last(r"\w+z", "abc"), None);
+    assert_eq!(find_last(r"\w+z", ""), None);
+}
+
+#[test]
+fn find_in() {
+    fn find_in(
+        re: &str,
+        text: &str,
+        range: Range<usize>,
+        anchor: RangeAnchor,
+    ) -> Option<Range<usize>> {
+        let regex = common::regex(re);
+        regex
+            .find_in(text, range, anchor)
+            .unwrap()
+            .map(|m| m.range())
+    }
+    use RangeAnchor::*;
+
+    // Delegated and backtracking regexes, matches are shortened to fit into the range
+    for re in &[r"a+", r"(?:a(?!x))+", r"a+(?=a*)"] {
+        assert_eq!(find_in(re, "baaaa", 0..5, Unanchored), Some(1..5), "{}", re);
+        assert_eq!(find_in(re, "baaaa", 0..3, Unanchored), Some(1..3), "{}", re);
+        assert_eq!(find_in(re, "baaaa", 0..3, Start), None, "{}", re);
+        assert_eq!(find_in(re, "baaaa", 2..4, Both), Some(2..4), "{}", re);
+        assert_eq!(find_in(re, "baaaa", 0..5, End), Some(1..5), "{}", re);
+        assert_eq!(find_in(re, "baaab", 0..5, End), None, "{}", re);
+    }
+
+    // Look-around and `\b` see the text outside of the range, `^` and `$` don't use the range
+    assert_eq!(find_in(r"(?<=a)b", "ab", 1..2, Both), Some(1..2));
+    assert_eq!(find_in(r"b(?!c)", "bc", 0..1, Both), None);
+    assert_eq!(find_in(r"\bb", "ab", 1..2, Unanchored), None);
+    assert_eq!(find_in(r"^b", "ab", 1..2, Unanchored), None);
+    assert_eq!(find_in(r"b$", "abc", 1..2, Unanchored), None);
+
+    // Lazy and alternations try longer ways when the end is anchored
+    assert_eq!(find_in(r"\w+?", "abc", 0..3, Both), Some(0..3));
+    assert_eq!(find_in(r"a|ab|abc", "abcd", 0..2, Both), Some(0..2));
+    assert_eq!(find_in(r"x*", "abc", 1..2, Unanchored), Some(1..1));
+
+    // Invalid ranges
+    let (start, end) = (1, 0);
+    assert_eq!(find_in(r"a", "a", start..end, Unanchored), None);
+    assert_eq!(find_in(r"a", "a", 0..2, Unanchored), None);
+    assert_eq!(find_in(r"\w", "é", 0..1, Unanchored), None);
+
+    // Over the whole text, it's the same match as `find`, also when an iteration of a repeat
+    // can be empty
+    for &(re, text) in &[
+        (r"(?:^|ß)+", "ß"),
+        (r"((?:(?:(?:$|^)|\W[^k])|ß))+", "ßk ǆKK"),
+        (r"(?:|a)*", "aa"),
+        (r"(?:|a)+?b", "aab"),
+        (r"(?:|a)*(?=)", "aa"),
+        (r"(?:a|)+\w", "aab"),
+        (r"(a|b\1)*", "ab"),
+    ] {
+        let regex = common::regex(re);
+        assert_eq!(
+            find_in(re, text, 0..text.len(), Unanchored),
+            regex.find(text).unwrap().map(|m| m.range()),
+            "{} in {:?}",
+            re,
+            text
+        );
+    }
+
+    let regex = common::regex(r"(\w)(\w+)");
+    let captures = regex.captures_in("abcd", 1..3, Both).unwrap().unwrap();
+    assert_eq!(captures.get(1).unwrap().range(), 1..2);
+    assert_eq!(captures.get(2).unwrap().range(), 2..3);
+}
+
+#[test]
+fn find_with_literal_suffix() {
+    let regex = common::regex(r".*\.tar\.gz(?<!partial\.tar\.gz)");
+    assert_eq!(
+        find(
+            r".*\.tar\.gz(?<!partial\.tar\.gz)",
+            "a.tar.gz b.partial.tar.gz"
+        ),
+        Some((0, 8))
+    );
+    assert_eq!(regex.find("b.partial.tar.gz").unwrap(), None);
+    assert_eq!(regex.find_from_pos("a.tar.gz b", 3).unwrap(), None);
+    assert!(regex.captures("x.tar.gz").unwrap().is_some());
+
+    // Without the suffix in the text, there is no need to backtrack from every position
+    let text = "x".repeat(10_000);
+    assert!(!regex.is_match(&text).unwrap());
+    assert_eq!(regex.captures(&text).unwrap().map(|_| ()), None);
+
+    // The suffix has to leave room for the rest of the match before it
+    assert_eq!(find(r"(?<=a)\w{3}ab", "abab"), None);
+    assert_eq!(find(r"(?<=a)\w{3}ab", "ababab"), Some((1, 6)));
+}
+
+#[test]
+fn find_with_literal_alternation() {
+    let regex = common::regex(r"(?<=\$)(foo|bar|baz|foobar)\b");
+    assert_eq!(
+        find(r"(?<=\$)(foo|bar|baz|foobar)\b", "foo $bar $baz"),
+        Some((5, 8))
+    );
+    assert_eq!(regex.find("foo bar baz").unwrap(), None);
+    let caps = regex.captures("$qux $foobar").unwrap().unwrap();
+    assert_eq!(caps.get(1).unwrap().as_str(), "foobar");
+    let found: Vec<_> = regex
+        .find_iter("$foo$bar x$baz $foox")
+        .map(|m| m.unwrap().as_str())
+        .collect();
+    assert_eq!(found, vec!["foo", "bar", "baz"]);
+
+    // Overlapping candidates: the match starting earliest wins
+    assert_eq!(find(r"(?=\w)(abc|bc)d", "abcbcd"), Some((3, 6)));
+    assert_eq!(find(r"(?=\w)(bc|abc)d", "xabcd"), Some((1, 5)));
+}
+
+#[test]
+fn find_script_run() {
+    assert_eq!(find(r"(*sr:\w+)", "abcбв"), Some((0, 3)));
+    assert_eq!(find(r"(*sr:\w+)\w", "abcбв"), Some((0, 5)));
+    assert_eq!(find(r"(*sr:\w+)", "бв abc"), Some((0, 4)));
+}
+
+#[test]
+fn find_resumable() {
+    let text = "ab aab abab xyzxyz xyz abcabc";
+    for re in &[r"(\w+)\1", r"(?<=x)y", r"\b(a|ab)+(?!c)\b", r"z{2}", r"q"] {
+        let regex = common::regex(re);
+        let expected = regex.find(text).unwrap();
+        for &fuel in &[1, 7, 50, std::usize::MAX] {
+            let mut search = regex.find_resumable(text);
+            let mut calls = 0;
+            let found = loop {
+                calls += 1;
+                match search.resume(fuel).unwrap() {
+                    Progress::Done(m) => break m,
+                    Progress::Suspended => assert!(calls < 10_000, "{} doesn't progress", re),
+                }
+            };
+            assert_eq!(found, expected, "{} with fuel {}", re, fuel);
+            if fuel == std::usize::MAX {
+                assert_eq!(calls, 1);
+            }
+            // The result stays available
+            assert_eq!(search.resume(1).unwrap(), Progress::Done(expected));
+        }
+    }
+}
+
+#[test]
+fn find_resumable_limits() {
+    let regex = RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
+        .backtrack_limit(100)
+        .build()
+        .unwrap();
+    let mut search = regex.find_resumable("abababababababababab");
+    let result = loop {
+        match search.resume(10) {
+            Ok(Progress::Suspended) => {}
+            result => break result,
+        }
+    };
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn find_async() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    /// Future that is pending the first time it's polled, like `yield_now` of executors.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Poll the future until it's ready, returning the result and the number of polls.
+    fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+        fn raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(raw_waker()) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        let mut polls = 0;
+        loop {
+            polls += 1;
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return (output, polls);
+            }
+        }
+    }
+
+    let regex = common::regex(r"(\w+)\s+\1");
+    let text = "the quick brown fox jumps over the the lazy dog";
+    let mut yields = 0;
+    let (result, polls) = block_on(regex.find_async(text, 20, || {
+        yields += 1;
+        YieldOnce(false)
+    }));
+    assert_eq!(result.unwrap().unwrap().as_str(), "the the");
+    assert!(yields > 1);
+    assert_eq!(polls, yields + 1);
+
+    let (result, polls) = block_on(regex.find_async(text, std::usize::MAX, || YieldOnce(false)));
+    assert_eq!(result.unwrap().unwrap().as_str(), "the the");
+    assert_eq!(polls, 1);
+}
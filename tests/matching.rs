@@ -1,4 +1,11 @@
-use fancy_regex::{Error, RegexBuilder};
+#![cfg(feature = "compiler")]
+
+use fancy_regex::{
+    CompileError, Error, Fragments, LargeRepeatPolicy, NumberedBackrefs, ParseError, Regex,
+    RegexBuilder, RunConfig, RuntimeError,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 mod common;
 
@@ -11,6 +18,15 @@ fn control_character_escapes() {
     assert_match(r"\r", "\x0D");
     assert_match(r"\t", "\x09");
     assert_match(r"\v", "\x0B");
+    assert_match(r"\cI", "\x09");
+    assert_match(r"\cm", "\x0D");
+    assert_match(r"\0", "\x00");
+    assert_match(r"\033", "\x1B");
+    // Only up to three octal digits
+    assert_match(r"^\0101$", "\x081");
+    // Also in hard expressions
+    assert_match(r"(?<=\0)\cA(.)\1", "\x00\x01xx");
+    assert_no_match(r"(?<=\0)\cA(.)\1", "\x01xx");
 }
 
 #[test]
@@ -31,6 +47,12 @@ fn character_class_escapes() {
     // Control characters
     assert_match(r"[\e]", "\x1B");
     assert_match(r"[\n]", "\x0A");
+    assert_match(r"[\cI]", "\x09");
+    assert_match(r"[\0]", "\x00");
+    assert_match(r"[\0-\037]", "\x1F");
+    assert_no_match(r"[\0-\037]", " ");
+    // Backrefs aren't possible in classes, so any digit starts an octal escape
+    assert_match(r"[\44]", "$");
 
     // `]` can be unescaped if it's right after `[`
     assert_match(r"[]]", "]");
@@ -66,6 +88,24 @@ fn alternation_with_empty_arm() {
     assert_no_match(r"^(a|)$", "b");
 }
 
+#[test]
+fn empty_iteration_ends_repeat() {
+    // The same with the regex crate and with backtracking
+    for re in &[
+        r"(?:|a)*",
+        r"(?:|a)*(?=)",
+        r"(?:|a)+",
+        r"(?:|a)+(?=)",
+        r"(?:|a)*?(?=)",
+    ] {
+        let m = common::regex(re).find("aa").unwrap().unwrap();
+        assert_eq!(m.range(), 0..0, "{}", re);
+    }
+    // The iteration is still backtracked into when what follows doesn't match
+    assert_match(r"^(?:|a)*(?=)a$", "a");
+    assert_match(r"^(?:|a)*(?=)$", "a");
+}
+
 #[test]
 fn case_insensitive_character_class() {
     assert_match(r"^(?i)[a-z]+$", "aB");
@@ -80,6 +120,30 @@ fn case_insensitive_escape() {
     assert_match(r"(?i)\p{Ll}", "A");
 }
 
+#[test]
+fn flags_in_group_are_scoped_to_group() {
+    assert_match(r"((?i)a)b", "Ab");
+    assert_no_match(r"((?i)a)b", "AB");
+    assert_no_match(r"(?:(?i)a)b", "AB");
+}
+
+#[test]
+fn case_insensitive_backref() {
+    assert_match(r"((?i)abc)\1", "abcABC");
+    assert_match(r"((?i)abc)\1", "ABCaBc");
+    assert_no_match(r"((?i)abc)d\1", "abcDabc");
+    assert_match(r"(?i)(abc)\1", "abcABC");
+    // Only the flags of the group count, not the ones at the backref
+    assert_no_match(r"(abc)(?i:\1)", "abcABC");
+    assert_no_match(r"(a)(?i:\1)", "aA");
+    assert_match(r"(a)(?i:\1)", "aa");
+    assert_no_match(r"(abc)\1", "abcABC");
+    assert_match(r"(?i)(ǆ)\1", "ǆǄ");
+    // Simple case folding like for literals, e.g. final sigma folds to sigma
+    assert_match(r"(?i)(σ)\1", "σς");
+    assert_match(r"(?i)(ς)\1", "ςΣ");
+}
+
 #[test]
 fn atomic_group() {
     assert_match(r"^a(?>bc|b)c$", "abcc");
@@ -90,6 +154,87 @@ fn atomic_group() {
     assert_no_match(r"^a(?>bc(?=d)|b)cd$", "abcd");
 }
 
+#[test]
+fn script_run() {
+    assert_match(r"^(*sr:\w+)$", "hello");
+    assert_match(r"^(*sr:\w+)$", "привет");
+    // Cyrillic `е` in Latin text
+    assert_no_match(r"^(*sr:\w+)$", "h\u{435}llo");
+    // Digits and punctuation belong to any script
+    assert_match(r"^(*script_run:[\w.]+)$", "пример.123");
+
+    // Backtracks into the run, unless it's atomic
+    assert_match(r"^(*sr:\w+)c", "abc");
+    assert_no_match(r"^(*asr:\w+)c", "abc");
+    assert_no_match(r"^(*atomic_script_run:\w+)c", "abc");
+
+    // In look-behind, also without constant size
+    assert_match(r"(?<=(*sr:\w\w))!", "ab!");
+    assert_no_match(r"(?<=(*sr:\w\w))!", "aб!");
+    assert_match(r"(?<=(*sr:\w+))!", "abc!");
+    assert_no_match(r"(?<=^(*sr:\w+))!", "abб!");
+}
+
+#[test]
+fn quantified_lookarounds() {
+    // Matched once when the repeat requires it
+    assert_match(r"^(?=a)+a$", "a");
+    assert_no_match(r"^(?=a)+b$", "b");
+    assert_no_match(r"^(?!a){2,3}a$", "a");
+    assert_match(r"^a(?<=a)*$", "a");
+    assert_no_match(r"^b(?<=a){1,}$", "b");
+
+    // Optional when the repeat allows zero times
+    assert_match(r"^(?=a)?b$", "b");
+    assert_match(r"^(?!b)*b$", "b");
+    assert_match(r"^b(?<!b){0,2}$", "b");
+
+    // Not matched at all for `{0}`, but groups inside still count
+    assert_match(r"^(?=(a)){0}b$", "b");
+    let regex = common::regex(r"^(?=(a))?\w");
+    let captures = regex.captures("a").unwrap().unwrap();
+    assert_eq!(captures.get(1).map(|m| m.as_str()), Some("a"));
+    let captures = regex.captures("b").unwrap().unwrap();
+    assert!(captures.get(1).is_none());
+}
+
+#[test]
+fn repeated_backref() {
+    assert_match(r"(\w+)\s+\1{2,}", "go gogo");
+    assert_no_match(r"(\w+)\s+\1{2,}", "go go");
+    assert_match(r"^(ab)\1{1,2}$", "ababab");
+    assert_no_match(r"^(ab)\1{1,2}$", "abababab");
+
+    // The group matching empty doesn't make the repeat loop forever
+    assert_match(r"^(a*)\1{2,}b$", "b");
+    assert_match(r"^(a*)\1{2,}$", "aaa");
+    assert_no_match(r"^(a*)\1{2,}$", "aa");
+    assert_match(r"^(a*)\1*?b$", "b");
+
+    let regex = common::regex(r"(\w+)\s+\1{2,}");
+    let m = regex.find("say go gogogo!").unwrap().unwrap();
+    assert_eq!(m.as_str(), "go gogogo");
+    let regex = common::regex(r"(a)\1{1,}?");
+    let m = regex.find("aaaa").unwrap().unwrap();
+    assert_eq!(m.range(), 0..2);
+}
+
+#[test]
+fn simplified_alternatives() {
+    // Common prefixes are hoisted, the order of the alternatives stays the same
+    assert_match(r"^(?:ab|abc)(?=c)", "abc");
+    assert_match(r"^(?:abc|ab)c$", "abc");
+    assert_no_match(r"^(?>ab|abc)$", "abc");
+    assert_match(r"(?<=abc|abd|ab)x", "abdx");
+    assert_match(r"^(?:a+)*(?=b)", "aab");
+    // Duplicate alternatives with groups are kept
+    assert_match(r"^(?:(a)|(a))\2", "aa");
+    assert_no_match(r"^(?:(a)|(a))\2", "a");
+    // Repeats of alternatives that are all empty
+    assert_match(r"^(?:|)*b", "b");
+    assert_match(r"^(?:|){1,2}$", "");
+}
+
 #[test]
 fn backtrack_limit() {
     let re = RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
@@ -100,11 +245,99 @@ fn backtrack_limit() {
     let result = re.is_match(s);
     assert!(result.is_err());
     match result.err() {
-        Some(Error::BacktrackLimitExceeded) => {}
-        _ => panic!("Expected Error::BacktrackLimitExceeded"),
+        Some(Error::RuntimeError(RuntimeError::BacktrackLimitExceeded)) => {}
+        _ => panic!("Expected RuntimeError::BacktrackLimitExceeded"),
     }
 }
 
+#[test]
+fn memory_limit() {
+    let re = RegexBuilder::new(r"(?:(?>a)|b)*(?=c)")
+        .memory_limit(1_000)
+        .build()
+        .unwrap();
+    let s = "ab".repeat(1_000);
+    match re.is_match(&s) {
+        Err(Error::RuntimeError(RuntimeError::MemoryLimitExceeded)) => {}
+        result => panic!(
+            "Expected RuntimeError::MemoryLimitExceeded, got {:?}",
+            result
+        ),
+    }
+    assert!(re.is_match("abc").unwrap());
+}
+
+#[test]
+fn delegate_size_limit() {
+    let build = |re: &str| {
+        let regex = RegexBuilder::new(re).delegate_size_limit(10_000).build()?;
+        regex.compile_all()?;
+        Ok::<_, Error>(regex)
+    };
+    match build(r"(a)\1[a-z]{1000}") {
+        Err(Error::CompileError(CompileError::DelegateError {
+            delegated, span, ..
+        })) => {
+            assert_eq!(delegated, "^[a-z]{1000}");
+            assert_eq!(span, Some(5..16));
+        }
+        result => panic!("Expected CompileError::DelegateError, got {:?}", result),
+    }
+    // Delegated entirely
+    match build(r"[a-z]{1000}") {
+        Err(Error::CompileError(CompileError::DelegateError { span, .. })) => {
+            assert_eq!(span, Some(0..11))
+        }
+        result => panic!("Expected CompileError::DelegateError, got {:?}", result),
+    }
+    assert!(build(r"(a)\1[a-z]{10}").is_ok());
+    assert!(Regex::new(r"(a)\1[a-z]{1000}").is_ok());
+}
+
+#[test]
+fn delegates_are_compiled_lazily() {
+    let re = RegexBuilder::new(r"(a)\1[a-z]{1000}")
+        .delegate_size_limit(10_000)
+        .build()
+        .unwrap();
+    // The delegate isn't needed when there's no match before it
+    assert!(!re.is_match("b").unwrap());
+    match re.is_match("aaa") {
+        Err(Error::CompileError(CompileError::DelegateError { delegated, .. })) => {
+            assert_eq!(delegated, "^[a-z]{1000}");
+        }
+        result => panic!("Expected CompileError::DelegateError, got {:?}", result),
+    }
+    assert!(re.compile_all().is_err());
+
+    let re = common::regex(r"(a)\1[a-z]{3}");
+    re.compile_all().unwrap();
+    assert!(re.is_match("aabcd").unwrap());
+}
+
+#[test]
+fn delegated_regexes() {
+    let re = common::regex(r"\d+-\w");
+    let delegated = re.delegated_regexes();
+    assert_eq!(delegated.len(), 1);
+    assert_eq!(delegated[0].span(), Some(0..6));
+    assert!(delegated[0].is_compiled());
+
+    let re = common::regex(r"(a)\1[a-z]{3}");
+    let delegated = re.delegated_regexes();
+    let suffix = delegated
+        .iter()
+        .find(|delegate| delegate.as_str() == "^[a-z]{3}")
+        .unwrap();
+    assert_eq!(suffix.span(), Some(5..13));
+    assert!(!suffix.is_compiled());
+    re.compile_all().unwrap();
+    assert!(re
+        .delegated_regexes()
+        .iter()
+        .all(|delegate| delegate.is_compiled()));
+}
+
 #[test]
 fn end_of_hard_expression_cannot_be_delegated() {
     assert_match(r"(?!x)(?:a|ab)c", "abc");
@@ -112,21 +345,405 @@ fn end_of_hard_expression_cannot_be_delegated() {
     assert_match(r"((?!x)(?:a|ab))c", "abc");
 }
 
+#[test]
+fn run_config() {
+    let re = Regex::new("(?i)(a|b|ab)*(?=c)").unwrap();
+    let s = "ab".repeat(10);
+
+    let mut config = RunConfig::new();
+    config.step_limit(1_000);
+    match re.is_match_with_config(&s, &config) {
+        Err(Error::RuntimeError(RuntimeError::StepLimitExceeded)) => {}
+        result => panic!("Expected RuntimeError::StepLimitExceeded, got {:?}", result),
+    }
+    assert!(re.find_with_config("abc", &config).unwrap().is_some());
+    // The same regex without the limit
+    assert!(!re.is_match(&s).unwrap());
+
+    let mut config = RunConfig::new();
+    config.max_stack(10);
+    match re.captures_with_config(&s, &config) {
+        Err(Error::RuntimeError(RuntimeError::StackOverflow)) => {}
+        result => panic!(
+            "Expected RuntimeError::StackOverflow, got {:?}",
+            result.map(|_| ())
+        ),
+    }
+
+    let mut config = RunConfig::new();
+    config.deadline(Instant::now());
+    match re.find_with_config(&s, &config) {
+        Err(Error::RuntimeError(RuntimeError::DeadlineExceeded)) => {}
+        result => panic!("Expected RuntimeError::DeadlineExceeded, got {:?}", result),
+    }
+
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let sink = lines.clone();
+    let mut config = RunConfig::new();
+    config.trace_sink(move |line| sink.lock().unwrap().push(line.to_string()));
+    let caps = re.captures_with_config("abc", &config).unwrap().unwrap();
+    assert_eq!(caps.get(1).unwrap().as_str(), "b");
+    let lines = lines.lock().unwrap();
+    assert_eq!(lines[0], "pos\tinstruction");
+    assert!(lines.len() > 10);
+
+    // Regexes that don't need backtracking ignore the limits
+    let re = Regex::new("(a|b|ab)*c").unwrap();
+    let mut config = RunConfig::new();
+    config.step_limit(1).deadline(Instant::now());
+    assert!(re.is_match_with_config(&s, &config).is_ok());
+}
+
+#[test]
+fn trace_sink_runs_regexes() {
+    let re = Arc::new(Regex::new("(?i)(a|b|ab)*(?=c)").unwrap());
+    let pushes = Arc::new(Mutex::new(0));
+    let mut config = RunConfig::new();
+    {
+        let re = re.clone();
+        let pushes = pushes.clone();
+        config.trace_sink(move |line| {
+            // Runs the regex that is being traced, from inside the run
+            if re.is_match(line).unwrap() && line.starts_with("stack after push") {
+                *pushes.lock().unwrap() += 1;
+            }
+        });
+    }
+    assert!(re.is_match_with_config("abc", &config).unwrap());
+    assert!(*pushes.lock().unwrap() > 0);
+}
+
+#[test]
+fn deep_backtrack_stack() {
+    // Needs more than a million backtrack branches, which used to be a fixed limit
+    let re = RegexBuilder::new(r"(?:(?>a)|b)*(?=c)")
+        .memory_limit(1 << 30)
+        .build()
+        .unwrap();
+    let s = format!("{}c", "ab".repeat(600_000));
+    assert_eq!(re.find(&s).unwrap().unwrap().end(), s.len() - 1);
+
+    // The memory limit still applies
+    let re = RegexBuilder::new(r"(?:(?>a)|b)*(?=c)")
+        .memory_limit(1 << 20)
+        .build()
+        .unwrap();
+    match re.find(&s) {
+        Err(Error::RuntimeError(RuntimeError::MemoryLimitExceeded)) => {}
+        result => panic!(
+            "Expected RuntimeError::MemoryLimitExceeded, got {:?}",
+            result
+        ),
+    }
+}
+
+#[test]
+fn auto_step_limit() {
+    let re = Regex::new("(?i)(a|b|ab)*(?=c)").unwrap();
+    let mut config = RunConfig::new();
+    config.auto_step_limit(2);
+
+    // Linear amounts of work stay within the limit, however long the text is
+    let long = format!("{}c", "ab".repeat(1_000));
+    assert!(re.is_match_with_config(&long, &config).unwrap());
+
+    let s = "ab".repeat(12);
+    match re.is_match_with_config(&s, &config) {
+        Err(Error::RuntimeError(RuntimeError::AutoStepLimitExceeded {
+            limit,
+            text_len,
+            instructions,
+            backtracks,
+        })) => {
+            assert_eq!(text_len, 24);
+            assert_eq!(limit, 2 * 25 * instructions);
+            assert!(backtracks > 0);
+        }
+        result => panic!(
+            "Expected RuntimeError::AutoStepLimitExceeded, got {:?}",
+            result
+        ),
+    }
+
+    // A fixed step limit that is smaller takes precedence
+    config.step_limit(10);
+    match re.is_match_with_config(&s, &config) {
+        Err(Error::RuntimeError(RuntimeError::StepLimitExceeded)) => {}
+        result => panic!("Expected RuntimeError::StepLimitExceeded, got {:?}", result),
+    }
+}
+
+#[test]
+fn instruction_spans() {
+    let parts = |re: &Regex, spans: &[Option<std::ops::Range<usize>>]| -> Vec<String> {
+        spans
+            .iter()
+            .flatten()
+            .map(|span| re.as_str()[span.clone()].to_string())
+            .collect()
+    };
+
+    let re = Regex::new(r"(\w+)\s\1").unwrap();
+    let spans = re.instruction_spans().unwrap();
+    let captures = parts(&re, spans.captures());
+    for part in &[r"(\w+)\s\1", r"(\w+)", r"\w+", r"\s", r"\1"] {
+        assert!(captures.iter().any(|p| p == part), "{:?}", captures);
+    }
+    // The loop for searching isn't part of the pattern
+    assert_eq!(spans.captures()[0], None);
+    // Without groups other than the one referenced
+    let find = parts(&re, spans.find());
+    assert!(find.iter().any(|p| p == r"\1"), "{:?}", find);
+    assert!(!spans.is_match().is_empty());
+
+    // Matched right-to-left
+    let re = Regex::new(r"(?<=a(?:b|cd)+)x").unwrap();
+    let captures = parts(&re, re.instruction_spans().unwrap().captures());
+    assert!(captures.iter().any(|p| p == "b|cd"), "{:?}", captures);
+
+    assert!(Regex::new(r"\w+").unwrap().instruction_spans().is_none());
+}
+
+#[test]
+fn cached() {
+    let re = Regex::cached(r"(\w)\1").unwrap();
+    assert!(re.is_match("foo").unwrap());
+    let again = Regex::cached(r"(\w)\1").unwrap();
+    assert_eq!(again.as_str(), r"(\w)\1");
+    assert!(again.is_match("foo").unwrap());
+
+    // Options are part of the cache key
+    let limited = RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
+        .backtrack_limit(10)
+        .build_cached()
+        .unwrap();
+    let unlimited = Regex::cached("(?i)(a|b|ab)*(?=c)").unwrap();
+    let s = "abababab";
+    assert!(limited.is_match(s).is_err());
+    assert!(unlimited.is_match(s).is_ok());
+
+    assert!(Regex::cached("(").is_err());
+}
+
+#[test]
+fn swap_greed() {
+    let find = |re: &str, text| {
+        let regex = RegexBuilder::new(re).swap_greed(true).build().unwrap();
+        assert!(regex.options().swap_greed());
+        regex.find(text).unwrap().unwrap().as_str().to_string()
+    };
+    // Delegated to the regex crate
+    assert_eq!(find("a+", "aaa"), "a");
+    assert_eq!(find("a+?", "aaa"), "aaa");
+    assert_eq!(find("<.*>", "<a><b>"), "<a>");
+    // Compiled for the VM
+    assert_eq!(find(r"(\w)<.+>\1", "x<a>x<b>x"), "x<a>x");
+    assert_eq!(find(r"(\w)<.+?>\1", "x<a>x<b>x"), "x<a>x<b>x");
+    assert_eq!(find(r"(?=a)(a{2,})", "aaaa"), "aa");
+    // Turned off with the flag
+    assert_eq!(find(r"(?-U)(\w)<.+>\1", "x<a>x<b>x"), "x<a>x<b>x");
+    assert_eq!(find(r"(?-U:a+)b*", "aabb"), "aa");
+
+    let re = Regex::new(r"(\w)<.+>\1").unwrap();
+    assert!(!re.options().swap_greed());
+    assert_eq!(re.find("x<a>x<b>x").unwrap().unwrap().as_str(), "x<a>x<b>x");
+}
+
+#[test]
+fn strict() {
+    // Accepted by default
+    assert_match(r"a{b}]", "a{b}]");
+    assert_match(r"(a)\12", "a\n");
+    assert_match(r"(a)(?=A)\101", "aA");
+
+    for re in &[r"a{b}", r"a]", r"(a)\12"] {
+        let result = RegexBuilder::new(re).strict(true).build();
+        assert!(
+            matches!(result, Err(Error::ParseError(..))),
+            "Expected {} to be rejected in strict mode",
+            re
+        );
+    }
+    let re = RegexBuilder::new(r"a\{b\}\]{2}")
+        .strict(true)
+        .build()
+        .unwrap();
+    assert!(re.options().strict());
+    assert!(re.is_match("a{b}]]").unwrap());
+}
+
+#[test]
+fn large_repeats() {
+    let result = RegexBuilder::new(r"x(?:a|b){3,5000}")
+        .max_repeat(1000)
+        .build();
+    match result {
+        Err(Error::ParseError(
+            8,
+            ParseError::RepeatTooLarge {
+                count: 5000,
+                max: 1000,
+            },
+        )) => {}
+        result => panic!("Expected ParseError::RepeatTooLarge, got {:?}", result),
+    }
+
+    for re in &[r"x(?:a|b){3,5000}", r"(\w)(?:a|\1){2000}", r"^[ab]{1001,}$"] {
+        let counted = RegexBuilder::new(re)
+            .max_repeat(1000)
+            .large_repeat_policy(LargeRepeatPolicy::Count)
+            .build()
+            .unwrap();
+        assert_eq!(counted.options().max_repeat(), Some(1000));
+        let expanded = Regex::new(re).unwrap();
+        for text in &[
+            "xab".to_string(),
+            format!("x{}", "ab".repeat(1000)),
+            format!("x{}", "ab".repeat(3000)),
+            "a".repeat(2001),
+            "ab".repeat(500),
+            "ab".repeat(501),
+        ] {
+            assert_eq!(
+                counted.find(text).unwrap().map(|m| m.range()),
+                expanded.find(text).unwrap().map(|m| m.range()),
+                "{} on {:?}",
+                re,
+                text
+            );
+        }
+    }
+}
+
+#[test]
+fn numbered_backrefs() {
+    let groups = "(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)";
+    let re = common::regex(&format!("^{}\\g{{10}}\\g{{-1}}\\g10$", groups));
+    assert!(re.is_match("abcdefghijjjj").unwrap());
+    let re = common::regex(r"^(?<x>a)\g{x}$");
+    assert!(re.is_match("aa").unwrap());
+
+    let build = |re: &str, rule| {
+        RegexBuilder::new(re)
+            .numbered_backrefs(rule)
+            .build()
+            .unwrap()
+    };
+    let re = build(r"^(a)\10$", NumberedBackrefs::Pcre);
+    assert!(re.is_match("a\x08").unwrap());
+    let re = build(r"^(a)\10$", NumberedBackrefs::Java);
+    assert!(re.is_match("aa0").unwrap());
+    let re = build(&format!("^{}\\100$", groups), NumberedBackrefs::Python);
+    assert!(re.is_match("abcdefghij@").unwrap());
+    let re = build(&format!("^{}\\108$", groups), NumberedBackrefs::Python);
+    assert!(re.is_match("abcdefghijj8").unwrap());
+}
+
+#[test]
+fn casei_literal_in_fancy_expression() {
+    // Literals next to look-around are matched by the VM instead of the regex crate
+    assert_match(r"(?i)(?<=x)straße(?=y)", "XSTRAßEY");
+    assert_match(r"(?i)(?<=x)straße(?=y)", "xStra\u{1E9E}ey");
+    assert_no_match(r"(?i)(?<=x)straße(?=y)", "xstrasseY");
+    assert_match(r"(?i)(?!a)kelvin(?=!)", "\u{212A}ELVIN!");
+    assert_match("(?i)(?!a)\u{212A}elvin(?=!)", "Kelvin!");
+    assert_match("(?i)(?!a)\u{3A3}\u{3C3}", "\u{3C2}\u{3A3}");
+    assert_no_match("(?i)(?!a)\u{130}", "i");
+    assert_no_match(r"(?i)(?!a)abc", "ab");
+}
+
+#[test]
+#[cfg(feature = "unicode-normalization")]
+fn normalization_insensitive() {
+    let build = |re: &str| {
+        RegexBuilder::new(re)
+            .normalization_insensitive(true)
+            .build()
+            .unwrap()
+    };
+    let composed = "r\u{e9}sum\u{e9}";
+    let decomposed = "re\u{301}sume\u{301}";
+    for re in &[composed, decomposed] {
+        let re = build(re);
+        assert!(re.is_match(composed).unwrap());
+        assert!(re.is_match(decomposed).unwrap());
+        assert!(!re.is_match("resume").unwrap());
+    }
+
+    // Also in hard expressions and case-insensitive
+    let re = build("(?i)(\u{c9})\\1");
+    assert_eq!(re.find("e\u{301}E\u{301}").unwrap().unwrap().end(), 6);
+
+    let re = Regex::new(composed).unwrap();
+    assert!(!re.is_match(decomposed).unwrap());
+}
+
+#[test]
+#[cfg(feature = "unicode-normalization")]
+fn canonical_backrefs() {
+    let build = |re: &str| {
+        RegexBuilder::new(re)
+            .canonical_backrefs(true)
+            .build()
+            .unwrap()
+    };
+    let re = build(r"(\w+) \1");
+    let text = "r\u{e9}sum\u{e9} re\u{301}sume\u{301}!";
+    assert_eq!(
+        re.find(text).unwrap().unwrap().as_str(),
+        &text[..text.len() - 1]
+    );
+    let text = "re\u{301}sume\u{301} r\u{e9}sum\u{e9}";
+    assert_eq!(re.find(text).unwrap().unwrap().as_str(), text);
+    // Doesn't split a character from its combining marks
+    assert!(!re.is_match("e e\u{301}").unwrap());
+    assert!(re.is_match("e\u{301} e\u{301}").unwrap());
+
+    let re = build(r"(?i)(\w+) \1");
+    assert!(re.is_match("\u{c9}t\u{e9} e\u{301}T\u{c9}").unwrap());
+
+    let re = Regex::new(r"(\w+) \1").unwrap();
+    assert!(!re
+        .is_match("r\u{e9}sum\u{e9} re\u{301}sume\u{301}")
+        .unwrap());
+}
+
+#[test]
+fn fragments() {
+    let mut defs = Fragments::new();
+    defs.insert("word", r"(\w+)").unwrap();
+    defs.insert("pair", r"(?&word)=(?&word)").unwrap();
+    let build = |re: &str| RegexBuilder::new(re).fragments(&defs).build();
+
+    // Groups in fragments don't capture, so `\1` refers to the group around the call
+    let re = build(r"^((?&pair))(?:,\1)*$").unwrap();
+    assert_eq!(re.captures_len(), 2);
+    assert!(re.is_match("a=b,a=b").unwrap());
+    assert!(!re.is_match("a=b,a=c").unwrap());
+
+    // A call can be repeated and used in look-around
+    let re = build(r"(?:(?&pair),?)+(?=;)").unwrap();
+    assert_eq!(re.find("x:a=b,c=d;").unwrap().unwrap().as_str(), "a=b,c=d");
+    let re = build(r"(?i)(?&word)").unwrap();
+    assert_eq!(re.options().fragments().len(), 2);
+
+    match build("a(?&missing)") {
+        Err(Error::ParseError(1, ParseError::UnknownFragment(name))) => assert_eq!(name, "missing"),
+        result => panic!("Expected unknown fragment, got {:?}", result),
+    }
+}
+
 #[cfg_attr(feature = "track_caller", track_caller)]
 fn assert_match(re: &str, text: &str) {
     let result = match_text(re, text);
-    assert_eq!(
-        result, true,
-        "Expected regex '{}' to match text '{}'",
-        re, text
-    );
+    assert!(result, "Expected regex '{}' to match text '{}'", re, text);
 }
 
 #[cfg_attr(feature = "track_caller", track_caller)]
 fn assert_no_match(re: &str, text: &str) {
     let result = match_text(re, text);
-    assert_eq!(
-        result, false,
+    assert!(
+        !result,
         "Expected regex '{}' to not match text '{}'",
         re, text
     );
@@ -143,3 +760,13 @@ fn match_text(re: &str, text: &str) -> bool {
     );
     result.unwrap()
 }
+
+#[test]
+fn is_match_with_groups() {
+    assert_match(r"(a+)(?=b)(?<x>\w)", "aab");
+    assert_match(r"(\w+)-(\d)-\1", "ab-1-ab");
+    assert_no_match(r"(\w+)-(\d)-\1", "ab-1-ca");
+    assert_match(r"(?<=(a|bc))(x)\2", "bcxx");
+    assert_no_match(r"(?<=(a|bc))(x)\2", "cxx");
+    assert_match(r"(?P<w>\w)(?P=w)(?!(c))", "aab");
+}
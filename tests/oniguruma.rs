@@ -1,4 +1,5 @@
 //! Run tests from Oniguruma's test suite, see `oniguruma/README.md`
+#![cfg(feature = "compiler")]
 
 use std::collections::HashMap;
 use std::panic;
@@ -151,10 +152,8 @@ fn run_test(test: &Test) -> Option<String> {
         ..
     } = test;
 
-    let compile_result = FancyRegex::new(&pattern);
-    if compile_result.is_err() {
-        let error = format!("{:?}", compile_result.unwrap_err());
-        return Some(format!("Compile failed: {}", error));
+    if let Err(error) = FancyRegex::new(pattern) {
+        return Some(format!("Compile failed: {:?}", error));
     }
 
     match *assertion {
@@ -162,8 +161,8 @@ fn run_test(test: &Test) -> Option<String> {
             let result = panic::catch_unwind(|| {
                 // compile regex again instead of using above, otherwise:
                 // "may not be safely transferrable across a catch_unwind boundary"
-                let regex = FancyRegex::new(&pattern).unwrap();
-                regex.captures(&text).unwrap()
+                let regex = FancyRegex::new(pattern).unwrap();
+                regex.captures(text).unwrap()
             });
 
             if let Ok(captures_result) = result {
@@ -188,8 +187,8 @@ fn run_test(test: &Test) -> Option<String> {
             }
         }
         Assertion::NoMatch => {
-            let regex = FancyRegex::new(&pattern).unwrap();
-            let result = regex.find(&text).unwrap();
+            let regex = FancyRegex::new(pattern).unwrap();
+            let result = regex.find(text).unwrap();
             if result.is_some() {
                 Some("Match found".to_string())
             } else {
@@ -232,7 +231,7 @@ fn oniguruma() {
                 // content for the ignore file. To do that, disable the assert and enable the print:
 
                 // println!("  // {}\n  {}\n", failure, test.source);
-                assert!(false, "Test {} failed: {}", &test.source, failure);
+                panic!("Test {} failed: {}", &test.source, failure);
             } else {
                 // println!("Success: {}", test.source);
                 success += 1;
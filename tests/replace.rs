@@ -1,5 +1,8 @@
-use fancy_regex::{Captures, NoExpand};
+#![cfg(feature = "compiler")]
+
+use fancy_regex::{Captures, Error, NoExpand, RegexBuilder, Replacer, RuntimeError};
 use std::borrow::Cow;
+use std::io::{self, Read};
 
 mod common;
 
@@ -80,3 +83,273 @@ fn replacen() {
     let regex = common::regex("bla");
     assert_eq!(regex.replacen("blablabla", 2, "foo"), "foofoobla");
 }
+
+/// `replace_all_edits()` returns the matches and their replacements instead of the new text
+#[test]
+fn replace_all_edits() {
+    let regex = common::regex(r"(\w)\1");
+    let edits = regex.replace_all_edits("aab cc dd", "<$1>").unwrap();
+    assert_eq!(
+        edits,
+        vec![
+            (0..2, "<a>".to_string()),
+            (4..6, "<c>".to_string()),
+            (7..9, "<d>".to_string())
+        ]
+    );
+
+    let edits = regex.replace_all_edits("aab cc", NoExpand("$1")).unwrap();
+    assert_eq!(
+        edits,
+        vec![(0..2, "$1".to_string()), (4..6, "$1".to_string())]
+    );
+
+    assert_eq!(regex.replace_all_edits("abc", "x").unwrap(), vec![]);
+}
+
+/// Applying the edits from the last to the first gives the same result as `replacen()`
+#[test]
+fn replacen_edits_same_as_replacen() {
+    let regex = common::regex(r"(?<=\s)(\w+)(?=\s)");
+    let text = "one two three four five six";
+    for &limit in &[0, 1, 2, 10] {
+        for rep in &["[$1]", "-"] {
+            let mut edited = text.to_string();
+            for (range, replacement) in regex
+                .replacen_edits(text, limit, *rep)
+                .unwrap()
+                .into_iter()
+                .rev()
+            {
+                edited.replace_range(range, &replacement);
+            }
+            assert_eq!(edited, regex.replacen(text, limit, *rep));
+        }
+    }
+}
+
+/// `replace_all_edits()` returns errors instead of panicking
+#[test]
+fn replace_all_edits_error() {
+    let regex = RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
+        .backtrack_limit(10)
+        .build()
+        .unwrap();
+    assert!(regex.replace_all_edits("abababab", "z").is_err());
+}
+
+/// Reader that returns at most `size` bytes per read, to exercise chunk boundaries.
+struct SmallReads<'a> {
+    data: &'a [u8],
+    size: usize,
+}
+
+impl<'a> Read for SmallReads<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.size.min(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+fn replace_all_stream<R: Replacer>(regex: &str, text: &str, size: usize, rep: R) -> String {
+    let regex = common::regex(regex);
+    let mut output = Vec::new();
+    let reader = SmallReads {
+        data: text.as_bytes(),
+        size,
+    };
+    regex.replace_all_stream(reader, &mut output, rep).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn replace_borrows_without_match() {
+    // Wrapped and fancy regexes, with and without expansion in the replacement
+    for re in &[r"b+", r"(b)\1"] {
+        let regex = common::regex(re);
+        assert!(matches!(regex.replace("aaa", "x"), Cow::Borrowed("aaa")));
+        assert!(matches!(
+            regex.replace_all("aaa", "$1"),
+            Cow::Borrowed("aaa")
+        ));
+        assert!(matches!(
+            regex.replacen("aaa", 3, NoExpand("$")),
+            Cow::Borrowed("aaa")
+        ));
+        assert!(matches!(regex.replace_all("", "x"), Cow::Borrowed("")));
+        assert!(matches!(regex.replace_all("abba", "x"), Cow::Owned(_)));
+    }
+}
+
+#[test]
+fn replace_all_with_groups() {
+    let regex = common::regex(r"(\w+)@(\w+)\.com(?!\.)");
+    let mut calls = 0;
+    let result = regex.replace_all_with_groups("a@b.com, cd@ef.com; x@y.com.au", |caps, dst| {
+        calls += 1;
+        dst.push_str(&caps[1][..1]);
+        dst.push_str("*@");
+        dst.push_str(&caps[2]);
+        dst.push_str(".com");
+    });
+    assert_eq!(result, "a*@b.com, c*@ef.com; x@y.com.au");
+    assert_eq!(calls, 2);
+
+    let result = regex.replace_all_with_groups("no match", |_, _| unreachable!());
+    assert!(matches!(result, Cow::Borrowed("no match")));
+}
+
+#[test]
+fn replacer_closure_runs_regexes() {
+    let regex = common::regex(r"(\w+)(?=:)|(?<=-)(\d+)");
+    let inner = common::regex(r"(.)\1");
+
+    // The closure runs the same regex and another fancy regex, and compiles a new one
+    let result = regex.replace_all("book: -123 apple: -44", |caps: &Captures<'_>| {
+        let m = caps.get(0).unwrap().as_str();
+        let doubled = inner.replace_all(m, "<$1>");
+        let nested = regex.find(m).unwrap().map_or(0, |m| m.start());
+        let digits = common::regex(r"(?<!x)\d").find_iter(m).count();
+        format!("{}@{}#{}", doubled, nested, digits)
+    });
+    assert_eq!(result, "b<o>k@0#0: -123@0#3 a<p>le@0#0: -<4>@0#2");
+
+    // Replacing within a replacement, where the outer captures find their groups lazily
+    let result = regex.replace_all("ab: -12", |caps: &Captures<'_>| {
+        let text = format!("{}:", caps.get(0).unwrap().as_str());
+        let replaced = regex.replace_all(&text, "[$0]");
+        match (caps.get(1), caps.get(2)) {
+            (Some(word), None) => format!("{}{}", replaced, word.as_str().len()),
+            (None, Some(number)) => format!("{}{}", replaced, number.as_str().len()),
+            _ => unreachable!(),
+        }
+    });
+    assert_eq!(result, "[ab]:2: -[12]:2");
+}
+
+#[test]
+fn replace_all_stream_same_as_replace_all() {
+    let cases = [
+        (r"\bfoo\b", "foo food foo ufoo foo"),
+        (r"(?<=a)b(?!c)", "ab abc bab abab"),
+        (r"^x|x$", "xaxbx"),
+        (r"(\w)\1", "aabbcdde aa"),
+        (r"\d+", "a1b22c333d4444"),
+        (r"", "héllo"),
+        (r"(?m)^\s*", "a\n  b\n\tc"),
+        (r"é|ü", "aéüöé"),
+    ];
+    for &(regex, text) in cases.iter() {
+        let expected = common::regex(regex).replace_all(text, "<$0>").into_owned();
+        for size in 1..6 {
+            assert_eq!(
+                replace_all_stream(regex, text, size, "<$0>"),
+                expected,
+                "regex {:?} with text {:?} and read size {}",
+                regex,
+                text,
+                size
+            );
+        }
+    }
+}
+
+#[test]
+fn replace_all_stream_long_input() {
+    let text = "The quick brown fox. ".repeat(5000);
+    let expected = text.replace("fox", "cat");
+    assert_eq!(
+        replace_all_stream(r"(?<=brown )fox", &text, 1000, "cat"),
+        expected
+    );
+}
+
+#[test]
+fn replace_all_stream_limited() {
+    let text = "a1b22c333d4444 ".repeat(100);
+    for re in &[r"\d+", r"\d+?(?=\D)", r"(\d)\d*\1"] {
+        let regex = common::regex(re);
+        let expected = regex.replace_all(&text, "<$0>").into_owned();
+        for size in 1..6 {
+            let mut output = Vec::new();
+            let reader = SmallReads {
+                data: text.as_bytes(),
+                size,
+            };
+            regex
+                .replace_all_stream_limited(reader, &mut output, "<$0>", 32)
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                expected,
+                "regex {:?} with read size {}",
+                re,
+                size
+            );
+        }
+    }
+}
+
+#[test]
+fn replace_all_stream_limited_exceeded() {
+    let regex = common::regex(r"<[^>]*>");
+    // The first read ends right after the long match
+    let text = format!("a <b> <{}> c", "b".repeat(192));
+    let reader = SmallReads {
+        data: text.as_bytes(),
+        size: 200,
+    };
+    let error = regex
+        .replace_all_stream_limited(reader, io::sink(), "", 64)
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::Other);
+    let error = error.into_inner().unwrap().downcast::<Error>().unwrap();
+    assert!(matches!(
+        *error,
+        Error::RuntimeError(RuntimeError::StreamBufferExceeded { limit: 64 })
+    ));
+
+    // A limit that is larger than the longest match is like no limit
+    let mut output = Vec::new();
+    regex
+        .replace_all_stream_limited(text.as_bytes(), &mut output, "", 1000)
+        .unwrap();
+    assert_eq!(output, regex.replace_all(&text, "").as_bytes());
+}
+
+#[test]
+fn replace_all_stream_invalid_utf8() {
+    let regex = common::regex("a");
+    let mut output = Vec::new();
+    let result = regex.replace_all_stream(&b"ab\xffc"[..], &mut output, "x");
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn validate_template() {
+    let invalid = |re: &str, template: &str| match common::regex(re).validate_template(template) {
+        Ok(()) => None,
+        Err(Error::InvalidTemplate { span, reason }) => Some((span, reason)),
+        Err(error) => panic!("unexpected error: {:?}", error),
+    };
+    let span = |re: &str, template: &str| invalid(re, template).map(|(span, _)| span);
+
+    assert_eq!(span(r"(a)(?<b>b)", "$0 $1 ${1}$2 $b ${b} $$ x"), None);
+    assert_eq!(span(r"a", "$0$$"), None);
+    assert_eq!(span(r"(a)(?<b>b)", "x $3"), Some(2..4));
+    assert_eq!(span(r"(a)(?<b>b)", "é${10}"), Some(2..7));
+    assert_eq!(span(r"(a)(?<b>b)", "$b $c"), Some(3..5));
+    assert_eq!(span(r"(a)(?<b>b)", "${bb}"), Some(0..5));
+    assert_eq!(span(r"(a)", "a$"), Some(1..2));
+    assert_eq!(span(r"(a)", "$ "), Some(0..1));
+    assert_eq!(span(r"(a)", "x${a b}y"), Some(1..7));
+    assert_eq!(span(r"(a)", "x${1"), Some(1..4));
+
+    let (span, reason) = invalid(r"(\d)", "$1st").unwrap();
+    assert_eq!(span, 0..4);
+    assert!(reason.contains("${1}st"), "{}", reason);
+    let (_, reason) = invalid(r"(\d)", "$2").unwrap();
+    assert_eq!(reason, "no group 2 in the regex, which has groups 0 to 1");
+}